@@ -0,0 +1,389 @@
+use std::fmt::Write as _;
+
+use bytes::{BufMut, BytesMut};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use tokio_util::codec::Encoder;
+use vector_core::{
+    config::DataType,
+    event::{
+        metric::{MetricSketch, Sample},
+        Event, Metric, MetricValue,
+    },
+    schema,
+};
+
+/// Errors that can occur during line protocol serialization.
+#[derive(Debug, Snafu)]
+pub enum LineProtocolSerializerError {
+    #[snafu(display("Only metric events can be serialized to line protocol"))]
+    NotAMetric,
+    #[snafu(display(
+        r#"Metric "{}" has no value that can be serialized to line protocol"#,
+        name
+    ))]
+    NoFields { name: String },
+    #[snafu(display(
+        r#"Metric "{}" contains a line break, which line protocol can't represent"#,
+        name
+    ))]
+    LineBreak { name: String },
+}
+
+/// Config used to build a `LineProtocolSerializer`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LineProtocolSerializerConfig;
+
+impl LineProtocolSerializerConfig {
+    /// Creates a new `LineProtocolSerializerConfig`.
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Build the `LineProtocolSerializer` from this configuration.
+    pub const fn build(&self) -> LineProtocolSerializer {
+        LineProtocolSerializer
+    }
+
+    /// The data type of events that are accepted by `LineProtocolSerializer`.
+    pub fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    /// The schema required by the serializer.
+    pub fn schema_requirement(&self) -> schema::Requirement {
+        // The serializer only accepts metrics, which have no schema.
+        schema::Requirement::empty()
+    }
+}
+
+/// Serializer that converts a metric `Event` to bytes using the InfluxDB line protocol.
+///
+/// Each metric becomes a single point laid out as the `influxdb_metrics` sink writes it: the
+/// measurement is the namespace and name of the metric joined by a dot, its tags are completed by
+/// a `metric_type` tag, and the point is timestamped in nanoseconds if the metric has a timestamp.
+#[derive(Debug, Clone)]
+pub struct LineProtocolSerializer;
+
+impl LineProtocolSerializer {
+    /// Creates a new `LineProtocolSerializer`.
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl Encoder<Event> for LineProtocolSerializer {
+    type Error = vector_core::Error;
+
+    fn encode(&mut self, event: Event, buffer: &mut BytesMut) -> Result<(), Self::Error> {
+        let metric = match event {
+            Event::Metric(metric) => metric,
+            _ => return Err(LineProtocolSerializerError::NotAMetric.into()),
+        };
+        let measurement = match metric.namespace() {
+            Some(namespace) => format!("{}.{}", namespace, metric.name()),
+            None => metric.name().to_owned(),
+        };
+        let (metric_type, fields) = type_and_fields(&metric);
+        if fields.is_empty() {
+            return Err(LineProtocolSerializerError::NoFields { name: measurement }.into());
+        }
+
+        let mut tags = metric
+            .tags()
+            .map(|tags| {
+                tags.iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str()))
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new);
+        tags.retain(|(key, value)| !key.is_empty() && !value.is_empty() && *key != "metric_type");
+        tags.push(("metric_type", metric_type));
+        tags.sort_unstable();
+
+        let has_line_break = std::iter::once(measurement.as_str())
+            .chain(tags.iter().flat_map(|(key, value)| [*key, *value]))
+            .any(|string| string.contains('\n'));
+        if has_line_break {
+            return Err(LineProtocolSerializerError::LineBreak { name: measurement }.into());
+        }
+
+        let mut line = String::new();
+        escape(&measurement, ", ", &mut line);
+        for (key, value) in tags {
+            line.push(',');
+            escape(key, ",= ", &mut line);
+            line.push('=');
+            escape(value, ",= ", &mut line);
+        }
+        for (index, (key, value)) in fields.iter().enumerate() {
+            line.push(if index == 0 { ' ' } else { ',' });
+            escape(key, ",= ", &mut line);
+            line.push('=');
+            match value {
+                Field::Float(value) => write!(line, "{}", value),
+                Field::Integer(value) => write!(line, "{}i", value),
+            }
+            .expect("writing to a string can't fail");
+        }
+        if let Some(timestamp) = metric.timestamp() {
+            write!(line, " {}", timestamp.timestamp_nanos())
+                .expect("writing to a string can't fail");
+        }
+
+        buffer.put_slice(line.as_bytes());
+        Ok(())
+    }
+}
+
+enum Field {
+    Float(f64),
+    Integer(u64),
+}
+
+/// Returns the `metric_type` tag of the metric and its fields, in the order they are written.
+///
+/// Fields whose value isn't finite are left out, as line protocol has no way to represent them.
+fn type_and_fields(metric: &Metric) -> (&'static str, Vec<(String, Field)>) {
+    let (metric_type, fields) = match metric.value() {
+        MetricValue::Counter { value } => {
+            ("counter", vec![("value".to_owned(), Field::Float(*value))])
+        }
+        MetricValue::Gauge { value } => ("gauge", vec![("value".to_owned(), Field::Float(*value))]),
+        MetricValue::Set { values } => (
+            "set",
+            vec![("value".to_owned(), Field::Float(values.len() as f64))],
+        ),
+        MetricValue::Distribution { samples, .. } => ("distribution", distribution_fields(samples)),
+        MetricValue::AggregatedHistogram {
+            buckets,
+            count,
+            sum,
+        } => {
+            let mut fields = buckets
+                .iter()
+                .map(|bucket| {
+                    (
+                        format!("bucket_{}", bucket.upper_limit),
+                        Field::Integer(bucket.count),
+                    )
+                })
+                .collect::<Vec<_>>();
+            fields.push(("count".to_owned(), Field::Integer(*count)));
+            fields.push(("sum".to_owned(), Field::Float(*sum)));
+            ("histogram", fields)
+        }
+        MetricValue::AggregatedSummary {
+            quantiles,
+            count,
+            sum,
+        } => {
+            let mut fields = quantiles
+                .iter()
+                .map(|quantile| {
+                    (
+                        format!("quantile_{}", quantile.quantile),
+                        Field::Float(quantile.value),
+                    )
+                })
+                .collect::<Vec<_>>();
+            fields.push(("count".to_owned(), Field::Integer(*count)));
+            fields.push(("sum".to_owned(), Field::Float(*sum)));
+            ("summary", fields)
+        }
+        MetricValue::Sketch { sketch } => match sketch {
+            MetricSketch::AgentDDSketch(ddsketch) => {
+                let mut fields = vec![(
+                    "count".to_owned(),
+                    Field::Integer(u64::from(ddsketch.count())),
+                )];
+                let stats = [
+                    ("min", ddsketch.min()),
+                    ("max", ddsketch.max()),
+                    ("sum", ddsketch.sum()),
+                    ("avg", ddsketch.avg()),
+                ];
+                fields.extend(
+                    stats
+                        .into_iter()
+                        .filter_map(|(key, value)| Some((key.to_owned(), Field::Float(value?)))),
+                );
+                ("sketch", fields)
+            }
+        },
+    };
+
+    let fields = fields
+        .into_iter()
+        .filter(|(_, value)| match value {
+            Field::Float(value) => value.is_finite(),
+            Field::Integer(_) => true,
+        })
+        .collect();
+    (metric_type, fields)
+}
+
+/// Summarizes the samples of a distribution, weighting each sample by its rate.
+fn distribution_fields(samples: &[Sample]) -> Vec<(String, Field)> {
+    let count = samples
+        .iter()
+        .map(|sample| u64::from(sample.rate))
+        .sum::<u64>();
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let sum = samples
+        .iter()
+        .map(|sample| sample.value * f64::from(sample.rate))
+        .sum::<f64>();
+    let values = samples
+        .iter()
+        .filter(|sample| sample.rate > 0)
+        .map(|sample| sample.value);
+    let min = values.clone().fold(f64::INFINITY, f64::min);
+    let max = values.fold(f64::NEG_INFINITY, f64::max);
+
+    vec![
+        ("count".to_owned(), Field::Integer(count)),
+        ("min".to_owned(), Field::Float(min)),
+        ("max".to_owned(), Field::Float(max)),
+        ("sum".to_owned(), Field::Float(sum)),
+        ("avg".to_owned(), Field::Float(sum / count as f64)),
+    ]
+}
+
+/// Writes `string` to `output`, escaping the `special` characters with a backslash.
+fn escape(string: &str, special: &str, output: &mut String) {
+    for c in string.chars() {
+        if special.contains(c) {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+    use chrono::{TimeZone, Utc};
+    use vector_common::btreemap;
+    use vector_core::event::{LogEvent, MetricKind};
+
+    fn serialize(event: impl Into<Event>) -> Result<String, vector_core::Error> {
+        let mut serializer = LineProtocolSerializer::new();
+        let mut bytes = BytesMut::new();
+        serializer.encode(event.into(), &mut bytes)?;
+        Ok(String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    fn timestamp() -> chrono::DateTime<Utc> {
+        Utc.ymd(2018, 11, 14).and_hms_nano(8, 9, 10, 11)
+    }
+
+    #[test]
+    fn serialize_counter() {
+        let metric = Metric::new(
+            "total",
+            MetricKind::Incremental,
+            MetricValue::Counter { value: 1.5 },
+        )
+        .with_namespace(Some("ns"))
+        .with_timestamp(Some(timestamp()));
+
+        assert_eq!(
+            serialize(metric).unwrap(),
+            "ns.total,metric_type=counter value=1.5 1542182950000000011"
+        );
+    }
+
+    #[test]
+    fn serialize_gauge() {
+        let metric = Metric::new(
+            "meter",
+            MetricKind::Absolute,
+            MetricValue::Gauge { value: -1.0 },
+        );
+
+        assert_eq!(
+            serialize(metric).unwrap(),
+            "meter,metric_type=gauge value=-1"
+        );
+    }
+
+    #[test]
+    fn serialize_tagged_metric() {
+        let metric = Metric::new(
+            "requests",
+            MetricKind::Incremental,
+            MetricValue::Counter { value: 3.0 },
+        )
+        .with_tags(Some(btreemap! {
+            "status" => "200",
+            "host" => "web-1",
+            "metric_type" => "overridden",
+            "empty" => "",
+        }))
+        .with_timestamp(Some(timestamp()));
+
+        assert_eq!(
+            serialize(metric).unwrap(),
+            "requests,host=web-1,metric_type=counter,status=200 value=3 1542182950000000011"
+        );
+    }
+
+    #[test]
+    fn serialize_escapes_special_characters() {
+        let metric = Metric::new(
+            "disk usage,total",
+            MetricKind::Absolute,
+            MetricValue::Gauge { value: 0.5 },
+        )
+        .with_tags(Some(btreemap! {
+            "mount point" => "C:\\Program Files,x86=1",
+        }));
+
+        assert_eq!(
+            serialize(metric).unwrap(),
+            r#"disk\ usage\,total,metric_type=gauge,mount\ point=C:\Program\ Files\,x86\=1 value=0.5"#
+        );
+    }
+
+    #[test]
+    fn serialize_aggregated_histogram() {
+        let metric = Metric::new(
+            "latency",
+            MetricKind::Absolute,
+            MetricValue::AggregatedHistogram {
+                buckets: vector_core::buckets![1.0 => 2, 2.5 => 5],
+                count: 7,
+                sum: 12.5,
+            },
+        );
+
+        assert_eq!(
+            serialize(metric).unwrap(),
+            "latency,metric_type=histogram bucket_1=2i,bucket_2.5=5i,count=7i,sum=12.5"
+        );
+    }
+
+    #[test]
+    fn serialize_rejects_logs() {
+        let event = LogEvent::from("message");
+
+        assert!(serialize(event).is_err());
+    }
+
+    #[test]
+    fn serialize_rejects_line_breaks() {
+        let metric = Metric::new(
+            "total",
+            MetricKind::Incremental,
+            MetricValue::Counter { value: 1.0 },
+        )
+        .with_tags(Some(btreemap! { "path" => "a\nb" }));
+
+        assert!(serialize(metric).is_err());
+    }
+}
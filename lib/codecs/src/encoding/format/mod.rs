@@ -6,6 +6,7 @@
 mod avro;
 mod gelf;
 mod json;
+mod line_protocol;
 mod logfmt;
 mod native;
 mod native_json;
@@ -18,6 +19,7 @@ pub use avro::{AvroSerializer, AvroSerializerConfig, AvroSerializerOptions};
 use dyn_clone::DynClone;
 pub use gelf::{GelfSerializer, GelfSerializerConfig};
 pub use json::{JsonSerializer, JsonSerializerConfig};
+pub use line_protocol::{LineProtocolSerializer, LineProtocolSerializerConfig};
 pub use logfmt::{LogfmtSerializer, LogfmtSerializerConfig};
 pub use native::{NativeSerializer, NativeSerializerConfig};
 pub use native_json::{NativeJsonSerializer, NativeJsonSerializerConfig};
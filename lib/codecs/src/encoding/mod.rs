@@ -9,10 +9,10 @@ use std::fmt::Debug;
 use bytes::BytesMut;
 pub use format::{
     AvroSerializer, AvroSerializerConfig, AvroSerializerOptions, GelfSerializer,
-    GelfSerializerConfig, JsonSerializer, JsonSerializerConfig, LogfmtSerializer,
-    LogfmtSerializerConfig, NativeJsonSerializer, NativeJsonSerializerConfig, NativeSerializer,
-    NativeSerializerConfig, RawMessageSerializer, RawMessageSerializerConfig, TextSerializer,
-    TextSerializerConfig,
+    GelfSerializerConfig, JsonSerializer, JsonSerializerConfig, LineProtocolSerializer,
+    LineProtocolSerializerConfig, LogfmtSerializer, LogfmtSerializerConfig, NativeJsonSerializer,
+    NativeJsonSerializerConfig, NativeSerializer, NativeSerializerConfig, RawMessageSerializer,
+    RawMessageSerializerConfig, TextSerializer, TextSerializerConfig,
 };
 pub use framing::{
     BoxedFramer, BoxedFramingError, BytesEncoder, BytesEncoderConfig, CharacterDelimitedEncoder,
@@ -202,6 +202,11 @@ pub enum SerializerConfig {
     /// JSON serialization.
     Json,
 
+    /// InfluxDB line protocol serialization.
+    ///
+    /// This encoding only accepts metric events, each of which is written as a single point.
+    LineProtocol,
+
     /// Logfmt serialization.
     Logfmt,
 
@@ -246,6 +251,12 @@ impl From<JsonSerializerConfig> for SerializerConfig {
     }
 }
 
+impl From<LineProtocolSerializerConfig> for SerializerConfig {
+    fn from(_: LineProtocolSerializerConfig) -> Self {
+        Self::LineProtocol
+    }
+}
+
 impl From<LogfmtSerializerConfig> for SerializerConfig {
     fn from(_: LogfmtSerializerConfig) -> Self {
         Self::Logfmt
@@ -285,6 +296,9 @@ impl SerializerConfig {
             )),
             SerializerConfig::Gelf => Ok(Serializer::Gelf(GelfSerializerConfig::new().build())),
             SerializerConfig::Json => Ok(Serializer::Json(JsonSerializerConfig.build())),
+            SerializerConfig::LineProtocol => Ok(Serializer::LineProtocol(
+                LineProtocolSerializerConfig.build(),
+            )),
             SerializerConfig::Logfmt => Ok(Serializer::Logfmt(LogfmtSerializerConfig.build())),
             SerializerConfig::Native => Ok(Serializer::Native(NativeSerializerConfig.build())),
             SerializerConfig::NativeJson => {
@@ -305,6 +319,7 @@ impl SerializerConfig {
             }
             SerializerConfig::Gelf { .. } => GelfSerializerConfig::input_type(),
             SerializerConfig::Json => JsonSerializerConfig.input_type(),
+            SerializerConfig::LineProtocol => LineProtocolSerializerConfig.input_type(),
             SerializerConfig::Logfmt => LogfmtSerializerConfig.input_type(),
             SerializerConfig::Native => NativeSerializerConfig.input_type(),
             SerializerConfig::NativeJson => NativeJsonSerializerConfig.input_type(),
@@ -321,6 +336,7 @@ impl SerializerConfig {
             }
             SerializerConfig::Gelf { .. } => GelfSerializerConfig::schema_requirement(),
             SerializerConfig::Json => JsonSerializerConfig.schema_requirement(),
+            SerializerConfig::LineProtocol => LineProtocolSerializerConfig.schema_requirement(),
             SerializerConfig::Logfmt => LogfmtSerializerConfig.schema_requirement(),
             SerializerConfig::Native => NativeSerializerConfig.schema_requirement(),
             SerializerConfig::NativeJson => NativeJsonSerializerConfig.schema_requirement(),
@@ -339,6 +355,8 @@ pub enum Serializer {
     Gelf(GelfSerializer),
     /// Uses a `JsonSerializer` for serialization.
     Json(JsonSerializer),
+    /// Uses a `LineProtocolSerializer` for serialization.
+    LineProtocol(LineProtocolSerializer),
     /// Uses a `LogfmtSerializer` for serialization.
     Logfmt(LogfmtSerializer),
     /// Uses a `NativeSerializer` for serialization.
@@ -357,6 +375,7 @@ impl Serializer {
         match self {
             Serializer::Json(_) | Serializer::NativeJson(_) | Serializer::Gelf(_) => true,
             Serializer::Avro(_)
+            | Serializer::LineProtocol(_)
             | Serializer::Logfmt(_)
             | Serializer::Text(_)
             | Serializer::Native(_)
@@ -376,6 +395,7 @@ impl Serializer {
             Serializer::Json(serializer) => serializer.to_json_value(event),
             Serializer::NativeJson(serializer) => serializer.to_json_value(event),
             Serializer::Avro(_)
+            | Serializer::LineProtocol(_)
             | Serializer::Logfmt(_)
             | Serializer::Text(_)
             | Serializer::Native(_)
@@ -404,6 +424,12 @@ impl From<JsonSerializer> for Serializer {
     }
 }
 
+impl From<LineProtocolSerializer> for Serializer {
+    fn from(serializer: LineProtocolSerializer) -> Self {
+        Self::LineProtocol(serializer)
+    }
+}
+
 impl From<LogfmtSerializer> for Serializer {
     fn from(serializer: LogfmtSerializer) -> Self {
         Self::Logfmt(serializer)
@@ -442,6 +468,7 @@ impl tokio_util::codec::Encoder<Event> for Serializer {
             Serializer::Avro(serializer) => serializer.encode(event, buffer),
             Serializer::Gelf(serializer) => serializer.encode(event, buffer),
             Serializer::Json(serializer) => serializer.encode(event, buffer),
+            Serializer::LineProtocol(serializer) => serializer.encode(event, buffer),
             Serializer::Logfmt(serializer) => serializer.encode(event, buffer),
             Serializer::Native(serializer) => serializer.encode(event, buffer),
             Serializer::NativeJson(serializer) => serializer.encode(event, buffer),
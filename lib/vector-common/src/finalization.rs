@@ -86,6 +86,57 @@ impl EventFinalizers {
         }
     }
 
+    /// Updates the status of all event finalizers in the collection and releases them, in a single
+    /// pass.
+    ///
+    /// The batches of the event finalizers that aren't shared with any other copy of their event
+    /// are updated as they are released.
+    pub fn resolve(self, status: EventStatus) {
+        for finalizer in self.0 {
+            finalizer.update_status(status);
+        }
+    }
+
+    /// Merges the event finalizers of the collection that aren't shared with any other copy of
+    /// their event into a single event finalizer for each batch, holding their combined status.
+    ///
+    /// Resolving the collection then takes one update for each batch rather than one for each
+    /// event. The batches are still only updated once the merged event finalizers are.
+    pub fn compact(&mut self) {
+        let mut merged: Vec<Arc<EventFinalizer>> = Vec::new();
+        let mut shared = Vec::new();
+        for finalizer in mem::take(&mut self.0) {
+            let finalizer = match Arc::try_unwrap(finalizer) {
+                Ok(finalizer) => finalizer,
+                Err(finalizer) => {
+                    shared.push(finalizer);
+                    continue;
+                }
+            };
+            // Recording the status keeps the finalizer from updating its batch when dropped, the
+            // status being carried over to the merged finalizer instead.
+            let status = finalizer.status.swap(EventStatus::Recorded);
+            if status == EventStatus::Recorded {
+                continue;
+            }
+            let index = match merged
+                .iter()
+                .position(|other| Arc::ptr_eq(&other.batch.0, &finalizer.batch.0))
+            {
+                Some(index) => index,
+                None => {
+                    merged.push(Arc::new(EventFinalizer::new(finalizer.batch.clone())));
+                    merged.len() - 1
+                }
+            };
+            if status != EventStatus::Dropped {
+                merged[index].update_status(status);
+            }
+        }
+        shared.extend(merged);
+        self.0 = shared;
+    }
+
     /// Consumes all event finalizers and updates their underlying batches immediately.
     pub fn update_sources(&mut self) {
         let finalizers = mem::take(&mut self.0);
@@ -501,6 +552,49 @@ mod tests {
         assert_eq!(receiver.try_recv(), Ok(BatchStatus::Delivered));
     }
 
+    #[test]
+    fn compact_events() {
+        let (batch1, mut receiver1) = BatchNotifier::new_with_receiver();
+        let (batch2, mut receiver2) = BatchNotifier::new_with_receiver();
+        let mut finalizers = EventFinalizers::default();
+        for _ in 0..100 {
+            finalizers.add(EventFinalizer::new(batch1.clone()));
+            finalizers.add(EventFinalizer::new(batch2.clone()));
+        }
+        let shared = EventFinalizers::new(EventFinalizer::new(batch1.clone()));
+        finalizers.merge(shared.clone());
+        drop(batch1);
+        drop(batch2);
+
+        // The finalizer shared with another copy of its event is kept as is.
+        finalizers.compact();
+        assert_eq!(finalizers.len(), 3);
+        assert_eq!(receiver1.try_recv(), Err(Empty));
+        assert_eq!(receiver2.try_recv(), Err(Empty));
+
+        finalizers.resolve(EventStatus::Delivered);
+        assert_eq!(receiver1.try_recv(), Err(Empty));
+        assert_eq!(receiver2.try_recv(), Ok(BatchStatus::Delivered));
+        shared.update_status(EventStatus::Rejected);
+        drop(shared);
+        assert_eq!(receiver1.try_recv(), Ok(BatchStatus::Rejected));
+    }
+
+    #[test]
+    fn compact_keeps_status() {
+        let (batch, mut receiver) = BatchNotifier::new_with_receiver();
+        let mut finalizers = EventFinalizers::default();
+        let errored = EventFinalizers::new(EventFinalizer::new(batch.clone()));
+        errored.update_status(EventStatus::Errored);
+        finalizers.merge(errored);
+        finalizers.add(EventFinalizer::new(batch));
+
+        finalizers.compact();
+        assert_eq!(finalizers.len(), 1);
+        finalizers.resolve(EventStatus::Delivered);
+        assert_eq!(receiver.try_recv(), Ok(BatchStatus::Errored));
+    }
+
     fn make_finalizer() -> (EventFinalizers, BatchStatusReceiver) {
         let (batch, receiver) = BatchNotifier::new_with_receiver();
         let finalizer = EventFinalizers::new(EventFinalizer::new(batch));
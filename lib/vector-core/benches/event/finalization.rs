@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use criterion::{
+    criterion_group, measurement::WallTime, BatchSize, BenchmarkGroup, Criterion, SamplingMode,
+};
+use vector_core::event::{BatchNotifier, EventFinalizer, EventFinalizers, EventStatus};
+
+/// The number of events of the batches resolved, which come from a handful of source batches.
+const EVENTS: usize = 10_000;
+const SOURCE_BATCHES: usize = 4;
+
+fn finalizers() -> EventFinalizers {
+    let batches = (0..SOURCE_BATCHES)
+        .map(|_| BatchNotifier::new_with_receiver().0)
+        .collect::<Vec<_>>();
+    let mut finalizers = EventFinalizers::default();
+    for i in 0..EVENTS {
+        finalizers.add(EventFinalizer::new(batches[i % SOURCE_BATCHES].clone()));
+    }
+    finalizers
+}
+
+fn resolve(c: &mut Criterion) {
+    let mut group: BenchmarkGroup<WallTime> =
+        c.benchmark_group("vector_core::event::finalization::EventFinalizers::resolve");
+    group.sampling_mode(SamplingMode::Auto);
+
+    group.bench_function("update_status then drop", move |b| {
+        b.iter_batched(
+            finalizers,
+            |finalizers| {
+                finalizers.update_status(EventStatus::Delivered);
+                drop(finalizers);
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("resolve", move |b| {
+        b.iter_batched(
+            finalizers,
+            |finalizers| finalizers.resolve(EventStatus::Delivered),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("compact then resolve", move |b| {
+        b.iter_batched(
+            finalizers,
+            |mut finalizers| {
+                finalizers.compact();
+                finalizers.resolve(EventStatus::Delivered);
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    // Compacting happens while the request is built, so only resolving is left once it's written.
+    group.bench_function("resolve compacted", move |b| {
+        b.iter_batched(
+            || {
+                let mut finalizers = finalizers();
+                finalizers.compact();
+                finalizers
+            },
+            |finalizers| finalizers.resolve(EventStatus::Delivered),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_secs(5))
+        .measurement_time(Duration::from_secs(30))
+        // degree of noise to ignore in measurements, here 1%
+        .noise_threshold(0.01)
+        // likelihood of noise registering as difference, here 5%
+        .significance_level(0.05)
+        // likelihood of capturing the true runtime, here 95%
+        .confidence_level(0.95);
+    targets = resolve
+);
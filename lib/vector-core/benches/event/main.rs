@@ -1,5 +1,6 @@
 use criterion::criterion_main;
 
+mod finalization;
 mod log_event;
 
-criterion_main!(log_event::benches);
+criterion_main!(log_event::benches, finalization::benches);
@@ -108,6 +108,7 @@ impl EncodingConfigWithFraming {
             (
                 None,
                 Serializer::Gelf(_)
+                | Serializer::LineProtocol(_)
                 | Serializer::Logfmt(_)
                 | Serializer::NativeJson(_)
                 | Serializer::RawMessage(_)
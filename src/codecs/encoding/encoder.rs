@@ -118,6 +118,7 @@ impl Encoder<Framer> {
                 Serializer::Avro(_)
                 | Serializer::Gelf(_)
                 | Serializer::Json(_)
+                | Serializer::LineProtocol(_)
                 | Serializer::Logfmt(_)
                 | Serializer::NativeJson(_)
                 | Serializer::RawMessage(_)
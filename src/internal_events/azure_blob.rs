@@ -1,7 +1,17 @@
-use metrics::counter;
+use std::time::Duration;
+
+use chrono::SecondsFormat;
+use metrics::{counter, gauge, histogram};
 use vector_core::internal_event::InternalEvent;
 
 use super::prelude::{error_stage, error_type};
+use crate::sinks::{
+    azure_common::{
+        receipt::{BlobProvenance, WriteReceipt, RECEIPT_VERSION},
+        summary::BatchTotals,
+    },
+    util::retries::BackoffSource,
+};
 
 #[derive(Debug)]
 pub struct AzureBlobResponseError {
@@ -58,3 +68,953 @@ impl InternalEvent for AzureBlobHttpError {
         counter!("http_request_errors_total", 1);
     }
 }
+
+#[derive(Debug)]
+pub struct AzureBlobJanitorSweep<'a> {
+    pub prefix: &'a str,
+    pub deleted: usize,
+}
+
+impl<'a> InternalEvent for AzureBlobJanitorSweep<'a> {
+    fn emit(self) {
+        debug!(
+            message = "Deleted expired objects.",
+            prefix = %self.prefix,
+            deleted = %self.deleted,
+        );
+        counter!(
+            "azure_blob_janitor_deleted_objects_total",
+            self.deleted as u64
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobJanitorError {
+    pub path: String,
+    pub error: String,
+}
+
+impl InternalEvent for AzureBlobJanitorError {
+    fn emit(self) {
+        error!(
+            message = "Failed to clean up expired objects.",
+            path = %self.path,
+            error = %self.error,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSidecarWriteError<'a> {
+    pub blob: &'a str,
+    pub error: String,
+}
+
+impl<'a> InternalEvent for AzureBlobSidecarWriteError<'a> {
+    fn emit(self) {
+        error!(
+            message = "Failed to write sidecar blob; its data blob was written.",
+            blob = %self.blob,
+            error = %self.error,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "sidecar_write_failed",
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobWriteVerificationError<'a> {
+    pub blob: &'a str,
+    pub error: String,
+}
+
+impl<'a> InternalEvent for AzureBlobWriteVerificationError<'a> {
+    fn emit(self) {
+        error!(
+            message = "Written blob doesn't match what was uploaded; retrying.",
+            blob = %self.blob,
+            error = %self.error,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "write_verification_failed",
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobChecksumMismatch<'a> {
+    pub blob: &'a str,
+    pub stored_md5: String,
+    pub expected_md5: &'a str,
+}
+
+impl<'a> InternalEvent for AzureBlobChecksumMismatch<'a> {
+    fn emit(self) {
+        error!(
+            message = "Content-MD5 of the written blob doesn't match the uploaded payload; deleting it and retrying.",
+            blob = %self.blob,
+            stored_md5 = %self.stored_md5,
+            expected_md5 = %self.expected_md5,
+            error_code = "checksum_mismatch",
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "checksum_mismatch",
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobPayloadTooLarge<'a> {
+    pub blob: &'a str,
+    pub count: usize,
+    pub byte_size: usize,
+}
+
+impl<'a> InternalEvent for AzureBlobPayloadTooLarge<'a> {
+    fn emit(self) {
+        error!(
+            message = "Blob exceeds the request size limit of the storage account; dropping the request. Reduce `batch.max_bytes` or enable `compression` to write smaller blobs.",
+            blob = %self.blob,
+            count = %self.count,
+            byte_size = %self.byte_size,
+            error_code = "payload_too_large",
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "payload_too_large",
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobOversizedBatch<'a> {
+    pub blob: &'a str,
+    pub count: usize,
+    pub byte_size: usize,
+    pub limit: usize,
+    pub blob_type: &'static str,
+}
+
+impl<'a> InternalEvent for AzureBlobOversizedBatch<'a> {
+    fn emit(self) {
+        error!(
+            message = "Blob exceeds the size Azure accepts in a single write; dropping the request without sending it. Reduce `batch.max_bytes` or enable `compression` to write smaller blobs.",
+            blob = %self.blob,
+            count = %self.count,
+            byte_size = %self.byte_size,
+            limit = %self.limit,
+            blob_type = %self.blob_type,
+            error_code = "oversized_batch",
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "oversized_batch",
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+        counter!(
+            "azure_blob_oversized_batch_total", 1,
+            "blob_type" => self.blob_type,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobBatchSummary<'a> {
+    pub container: &'a str,
+    pub totals: BatchTotals,
+    pub interval: Duration,
+}
+
+impl<'a> InternalEvent for AzureBlobBatchSummary<'a> {
+    fn emit(self) {
+        info!(
+            message = "Batch summary.",
+            container = %self.container,
+            events = %self.totals.events,
+            logical_bytes = %self.totals.logical_bytes,
+            compressed_bytes = %self.totals.compressed_bytes,
+            blobs = %self.totals.blobs,
+            interval_secs = %self.interval.as_secs(),
+        );
+        if let Some(average) = self.totals.average_blob_bytes() {
+            gauge!(
+                "azure_blob_average_blob_size_bytes", average,
+                "container" => self.container.to_owned(),
+            );
+        }
+        if let Some(ratio) = self.totals.blobs_per_event() {
+            gauge!(
+                "azure_blob_blobs_per_event", ratio,
+                "container" => self.container.to_owned(),
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobCredentialFallback {
+    pub error: String,
+}
+
+impl InternalEvent for AzureBlobCredentialFallback {
+    fn emit(self) {
+        warn!(
+            message = "Failed to acquire a token for `storage_account`; falling back to `connection_string`.",
+            error = %self.error,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobHealthcheckThrottled {
+    pub attempt: usize,
+    pub delay: Duration,
+}
+
+impl InternalEvent for AzureBlobHealthcheckThrottled {
+    fn emit(self) {
+        warn!(
+            message = "Healthcheck throttled by the storage account; retrying.",
+            attempt = %self.attempt,
+            delay_ms = %self.delay.as_millis(),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobTokenRefreshError {
+    pub error: String,
+}
+
+impl InternalEvent for AzureBlobTokenRefreshError {
+    fn emit(self) {
+        error!(
+            message = "Failed to refresh the token of `storage_account` ahead of its expiry.",
+            error = %self.error,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobOrphanedBlob<'a> {
+    pub blob: &'a str,
+    pub handling: &'static str,
+    pub error: String,
+    pub handling_error: Option<String>,
+}
+
+impl<'a> InternalEvent for AzureBlobOrphanedBlob<'a> {
+    fn emit(self) {
+        error!(
+            message = "Failed to write the index entry of blob; its events are rejected.",
+            blob = %self.blob,
+            handling = %self.handling,
+            error = %self.error,
+            handling_error = ?self.handling_error,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "index_write_failed",
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+        counter!(
+            "azure_blob_orphaned_blobs_total", 1,
+            "handling" => self.handling,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobPathNormalized<'a> {
+    pub partition: &'a str,
+    pub normalized: &'a str,
+}
+
+impl<'a> InternalEvent for AzureBlobPathNormalized<'a> {
+    fn emit(self) {
+        debug!(
+            message = "Normalized malformed blob path.",
+            partition = %self.partition,
+            normalized = %self.normalized,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "azure_blob_malformed_paths_total", 1,
+            "outcome" => "normalized",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobPathRejected<'a> {
+    pub partition: &'a str,
+    pub error: &'static str,
+    pub count: usize,
+}
+
+impl<'a> InternalEvent for AzureBlobPathRejected<'a> {
+    fn emit(self) {
+        warn!(
+            message = "Rejected events with a malformed blob path.",
+            partition = %self.partition,
+            error = %self.error,
+            count = %self.count,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "azure_blob_malformed_paths_total", 1,
+            "outcome" => "rejected",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobReservedBlobRejected<'a> {
+    pub blob: &'a str,
+    pub count: usize,
+}
+
+impl<'a> InternalEvent for AzureBlobReservedBlobRejected<'a> {
+    fn emit(self) {
+        error!(
+            message = "Rejected events whose blob would be written under the prefix reserved for the janitor.",
+            blob = %self.blob,
+            count = %self.count,
+            error_code = "reserved_prefix",
+            error_type = error_type::CONDITION_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "reserved_prefix",
+            "error_type" => error_type::CONDITION_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobRequestDelivered<'a> {
+    pub blob: &'a str,
+    pub attempts: usize,
+    pub elapsed: Duration,
+}
+
+impl<'a> InternalEvent for AzureBlobRequestDelivered<'a> {
+    fn emit(self) {
+        debug!(
+            message = "Blob delivered.",
+            blob = %self.blob,
+            attempts = %self.attempts,
+            elapsed_ms = %self.elapsed.as_millis(),
+        );
+        counter!("azure_blob_objects_written_total", 1);
+        histogram!("azure_blob_delivery_attempts", self.attempts as f64);
+        histogram!("azure_blob_delivery_duration_seconds", self.elapsed);
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobUnresolvablePartition {
+    pub mode: &'static str,
+    pub count: usize,
+}
+
+impl InternalEvent for AzureBlobUnresolvablePartition {
+    fn emit(self) {
+        debug!(
+            message = "Handled events with an unresolvable partition.",
+            mode = %self.mode,
+            count = %self.count,
+        );
+        counter!(
+            "azure_blob_unresolvable_partition_events_total", self.count as u64,
+            "mode" => self.mode,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobEventsShed {
+    pub count: usize,
+}
+
+impl InternalEvent for AzureBlobEventsShed {
+    fn emit(self) {
+        debug!(
+            message = "Shed low-priority events while the sink is falling behind.",
+            count = %self.count,
+            internal_log_rate_secs = 10,
+        );
+        counter!("azure_blob_shed_events_total", self.count as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobEncodingError {
+    pub policy: &'static str,
+    pub count: usize,
+    pub error: String,
+}
+
+impl InternalEvent for AzureBlobEncodingError {
+    fn emit(self) {
+        error!(
+            message = "Failed to encode events.",
+            policy = %self.policy,
+            count = %self.count,
+            error = %self.error,
+            error_code = "encoding_failed",
+            error_type = error_type::ENCODER_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_secs = 10,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "encoding_failed",
+            "error_type" => error_type::ENCODER_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+        counter!(
+            "component_discarded_events_total", self.count as u64,
+            "error_code" => "encoding_failed",
+            "error_type" => error_type::ENCODER_FAILED,
+            "stage" => error_stage::PROCESSING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobNameCollision<'a> {
+    pub blob: &'a str,
+    pub policy: &'static str,
+}
+
+impl<'a> InternalEvent for AzureBlobNameCollision<'a> {
+    fn emit(self) {
+        warn!(
+            message = "Blob already exists under the name being written; not overwriting it.",
+            blob = %self.blob,
+            policy = %self.policy,
+            internal_log_rate_secs = 10,
+        );
+        counter!(
+            "azure_blob_name_collisions_total", 1,
+            "policy" => self.policy,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobPriorBlobDeleted<'a> {
+    pub blob: &'a str,
+    pub policy: &'static str,
+}
+
+impl<'a> InternalEvent for AzureBlobPriorBlobDeleted<'a> {
+    fn emit(self) {
+        debug!(
+            message = "Permanently deleted the blob existing under the name being written.",
+            blob = %self.blob,
+        );
+        counter!(
+            "azure_blob_name_collisions_total", 1,
+            "policy" => self.policy,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSoftDeleteEnabled {
+    pub retention_days: u32,
+    pub policy: &'static str,
+    /// Whether the previous content of the blobs overwritten is retained.
+    pub retained: bool,
+}
+
+impl InternalEvent for AzureBlobSoftDeleteEnabled {
+    fn emit(self) {
+        if self.retained {
+            warn!(
+                message = "Soft delete is enabled on the storage account; overwritten blobs keep their previous content until the retention period elapses.",
+                retention_days = %self.retention_days,
+                policy = %self.policy,
+            );
+        } else {
+            info!(
+                message = "Soft delete is enabled on the storage account.",
+                retention_days = %self.retention_days,
+                policy = %self.policy,
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSoftDeleteUnknown {
+    pub error: String,
+}
+
+impl InternalEvent for AzureBlobSoftDeleteUnknown {
+    fn emit(self) {
+        warn!(
+            message = "Failed to read the soft delete policy of the storage account.",
+            error = %self.error,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobWriteReceipt<'a> {
+    pub receipt: &'a WriteReceipt,
+}
+
+impl<'a> InternalEvent for AzureBlobWriteReceipt<'a> {
+    fn emit(self) {
+        let (event_time_min, event_time_max) = match self.receipt.event_time_range {
+            Some((min, max)) => (
+                min.to_rfc3339_opts(SecondsFormat::Millis, true),
+                max.to_rfc3339_opts(SecondsFormat::Millis, true),
+            ),
+            None => (String::new(), String::new()),
+        };
+        // Receipts are meant to be consumed through the `internal_logs` source, so none of them
+        // is rate limited away.
+        info!(
+            message = "Blob write receipt.",
+            receipt_version = RECEIPT_VERSION,
+            container = %self.receipt.container,
+            blob = %self.receipt.blob,
+            etag = %self.receipt.etag,
+            content_md5 = %self.receipt.content_md5,
+            count = self.receipt.count,
+            byte_size = self.receipt.byte_size,
+            event_time_min = %event_time_min,
+            event_time_max = %event_time_max,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobAcknowledgementProvenance<'a> {
+    pub provenance: &'a BlobProvenance,
+    pub count: usize,
+}
+
+impl<'a> InternalEvent for AzureBlobAcknowledgementProvenance<'a> {
+    fn emit(self) {
+        // Like the receipts, these are meant to be consumed through the `internal_logs` source.
+        info!(
+            message = "Acknowledging events written to blob.",
+            container = %self.provenance.container,
+            blob = %self.provenance.blob,
+            etag = %self.provenance.etag,
+            written_at = %self
+                .provenance
+                .written_at
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            count = self.count,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobContainerLost<'a> {
+    pub container: &'a str,
+}
+
+impl<'a> InternalEvent for AzureBlobContainerLost<'a> {
+    fn emit(self) {
+        error!(
+            message = "Container no longer exists; stopping the sink. Set `create_missing_container` to recreate it instead.",
+            container = %self.container,
+            error_code = "container_not_found",
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "container_not_found",
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobContainerRecreated<'a> {
+    pub container: &'a str,
+}
+
+impl<'a> InternalEvent for AzureBlobContainerRecreated<'a> {
+    fn emit(self) {
+        warn!(
+            message = "Recreated the container, which no longer existed.",
+            container = %self.container,
+        );
+        counter!("azure_blob_containers_recreated_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobFanOutTargetError<'a> {
+    pub container: &'a str,
+    pub error: &'a str,
+}
+
+impl<'a> InternalEvent for AzureBlobFanOutTargetError<'a> {
+    fn emit(self) {
+        warn!(
+            message = "Failed to write the batch to one of the containers it is fanned out to.",
+            container = %self.container,
+            error = %self.error,
+        );
+        counter!(
+            "azure_blob_fan_out_target_errors_total", 1,
+            "container" => self.container.to_owned(),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobInFlightShed {
+    pub count: usize,
+    pub byte_size: usize,
+}
+
+impl InternalEvent for AzureBlobInFlightShed {
+    fn emit(self) {
+        warn!(
+            message = "Shed a batch as the requests in flight are over the limit.",
+            count = %self.count,
+            byte_size = %self.byte_size,
+            internal_log_rate_secs = 10,
+        );
+        counter!("azure_blob_in_flight_shed_events_total", self.count as u64);
+        counter!(
+            "azure_blob_in_flight_shed_bytes_total",
+            self.byte_size as u64
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobFlushTriggered<'a> {
+    pub signal: &'a str,
+}
+
+impl<'a> InternalEvent for AzureBlobFlushTriggered<'a> {
+    fn emit(self) {
+        info!(
+            message = "Flushing all pending batches on signal.",
+            signal = %self.signal,
+        );
+        counter!("azure_blob_flushes_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSuccessMarkerWritten<'a> {
+    pub marker: &'a str,
+}
+
+impl<'a> InternalEvent for AzureBlobSuccessMarkerWritten<'a> {
+    fn emit(self) {
+        debug!(
+            message = "Wrote the success marker of a closed partition.",
+            marker = %self.marker,
+        );
+        counter!("azure_blob_success_markers_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSuccessMarkerSkipped<'a> {
+    pub partition: &'a str,
+}
+
+impl<'a> InternalEvent for AzureBlobSuccessMarkerSkipped<'a> {
+    fn emit(self) {
+        warn!(
+            message = "Not writing the success marker of a closed partition whose last blob failed to be written.",
+            partition = %self.partition,
+            internal_log_rate_secs = 10,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSuccessMarkerError<'a> {
+    pub marker: &'a str,
+    pub error: String,
+}
+
+impl<'a> InternalEvent for AzureBlobSuccessMarkerError<'a> {
+    fn emit(self) {
+        error!(
+            message = "Failed to write success marker; retrying on the next check.",
+            marker = %self.marker,
+            error = %self.error,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_secs = 10
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => "success_marker_write_failed",
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobBatchExpired<'a> {
+    pub blob: &'a str,
+    pub count: usize,
+    pub age_secs: i64,
+}
+
+impl<'a> InternalEvent for AzureBlobBatchExpired<'a> {
+    fn emit(self) {
+        warn!(
+            message = "Dropping batch whose oldest event is older than `max_batch_age_before_send_secs`.",
+            blob = %self.blob,
+            count = %self.count,
+            age_secs = %self.age_secs,
+            internal_log_rate_secs = 10,
+        );
+        counter!("azure_blob_expired_events_total", self.count as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobRetryBackoff {
+    pub delay: Duration,
+    pub source: BackoffSource,
+}
+
+impl InternalEvent for AzureBlobRetryBackoff {
+    fn emit(self) {
+        debug!(
+            message = "Backing off before retrying request.",
+            delay_ms = %self.delay.as_millis(),
+            source = self.source.as_str(),
+        );
+        histogram!(
+            "azure_blob_retry_backoff_seconds", self.delay,
+            "source" => self.source.as_str(),
+        );
+    }
+}
+
+/// The body of an upload the storage account answered, whether it accepted it or not.
+#[derive(Debug)]
+pub struct AzureBlobRequestBodySent {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for AzureBlobRequestBodySent {
+    fn emit(self) {
+        counter!("azure_blob_request_body_bytes_total", self.byte_size as u64);
+    }
+}
+
+/// The finalizers of a batch resolved once its write is done, when acknowledgements are batched.
+#[derive(Debug)]
+pub struct AzureBlobFinalizersResolved {
+    pub count: usize,
+    pub elapsed: Duration,
+}
+
+impl InternalEvent for AzureBlobFinalizersResolved {
+    fn emit(self) {
+        trace!(
+            message = "Resolved finalizers.",
+            count = %self.count,
+            elapsed_us = %self.elapsed.as_micros(),
+        );
+        histogram!("azure_blob_finalizer_resolution_seconds", self.elapsed);
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobFinalizerDoubleTake<'a> {
+    pub blob: &'a str,
+    pub count: usize,
+}
+
+impl<'a> InternalEvent for AzureBlobFinalizerDoubleTake<'a> {
+    fn emit(self) {
+        error!(
+            message = "Finalizers of a request taken twice; the events of the request may never be acknowledged.",
+            blob = %self.blob,
+            count = %self.count,
+        );
+        counter!("azure_blob_finalizer_double_take_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobRequestCancelled {
+    pub count: usize,
+    /// Whether the request was `queued` or `in_flight` when cancelled.
+    pub state: &'static str,
+}
+
+impl InternalEvent for AzureBlobRequestCancelled {
+    fn emit(self) {
+        warn!(
+            message = "Cancelled a request as the sink is stopping; its events are rejected.",
+            count = %self.count,
+            state = %self.state,
+            internal_log_rate_secs = 10,
+        );
+        counter!(
+            "azure_blob_cancelled_events_total", self.count as u64,
+            "state" => self.state,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSourceArchivedBlob<'a> {
+    pub blob: &'a str,
+    /// Whether the rehydration of the blob was requested, rather than the blob skipped.
+    pub rehydrating: bool,
+}
+
+impl<'a> InternalEvent for AzureBlobSourceArchivedBlob<'a> {
+    fn emit(self) {
+        let action = if self.rehydrating {
+            "rehydrating"
+        } else {
+            "skipped"
+        };
+        info!(
+            message = "Not reading blob in the archive tier.",
+            blob = %self.blob,
+            action,
+            internal_log_rate_secs = 10,
+        );
+        counter!("azure_blob_source_archived_blobs_total", 1, "action" => action);
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSourceReadError<'a> {
+    pub blob: &'a str,
+    pub error: String,
+}
+
+impl<'a> InternalEvent for AzureBlobSourceReadError<'a> {
+    fn emit(self) {
+        error!(
+            message = "Failed to read blob.",
+            blob = %self.blob,
+            error = %self.error,
+            error_type = error_type::READER_FAILED,
+            stage = error_stage::RECEIVING,
+            internal_log_rate_secs = 10,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::READER_FAILED,
+            "stage" => error_stage::RECEIVING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSourceListError {
+    pub error: String,
+}
+
+impl InternalEvent for AzureBlobSourceListError {
+    fn emit(self) {
+        error!(
+            message = "Failed to list blobs.",
+            error = %self.error,
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::RECEIVING,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::RECEIVING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AzureBlobSourceCheckpointError {
+    pub error: std::io::Error,
+}
+
+impl InternalEvent for AzureBlobSourceCheckpointError {
+    fn emit(self) {
+        error!(
+            message = "Failed to write checkpoint, the blobs read since the last one will be read again after a restart.",
+            error = %self.error,
+            error_code = super::prelude::io_error_code(&self.error),
+            error_type = error_type::WRITER_FAILED,
+            stage = error_stage::RECEIVING,
+        );
+        counter!(
+            "component_errors_total", 1,
+            "error_code" => super::prelude::io_error_code(&self.error),
+            "error_type" => error_type::WRITER_FAILED,
+            "stage" => error_stage::RECEIVING,
+        );
+    }
+}
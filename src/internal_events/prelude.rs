@@ -45,7 +45,7 @@ pub mod error_type {
 }
 
 #[cfg(any(
-    feature = "sinks-azure_blob",
+    feature = "azure_common",
     feature = "sinks-elasticsearch",
     feature = "sinks-prometheus",
     feature = "sources-apache_metrics",
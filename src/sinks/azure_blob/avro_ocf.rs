@@ -0,0 +1,296 @@
+use std::{collections::BTreeMap, io};
+
+use avro_rs::{types::Value as AvroValue, Codec, Schema, Writer};
+use codecs::{
+    encoding::{AvroSerializer, Framer},
+    BytesEncoder,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    codecs::{Encoder, Transformer},
+    event::{Event, LogEvent, Value},
+};
+
+pub const AVRO_CONTENT_TYPE: &str = "application/avro";
+
+fn default_record_name() -> String {
+    "Event".to_owned()
+}
+
+/// Compression of the data blocks of the files.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AvroOcfCodec {
+    None,
+    #[derivative(Default)]
+    Deflate,
+    Snappy,
+}
+
+impl From<AvroOcfCodec> for Codec {
+    fn from(codec: AvroOcfCodec) -> Self {
+        match codec {
+            AvroOcfCodec::None => Self::Null,
+            AvroOcfCodec::Deflate => Self::Deflate,
+            AvroOcfCodec::Snappy => Self::Snappy,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AvroOcfConfig {
+    /// The schema of the records, which is inferred from the events of each batch when unset.
+    pub schema: Option<String>,
+    /// The name of the inferred records.
+    #[serde(default = "default_record_name")]
+    pub record_name: String,
+    #[serde(default)]
+    pub codec: AvroOcfCodec,
+}
+
+impl AvroOcfConfig {
+    pub fn build(&self) -> crate::Result<AvroOcf> {
+        let schema = match &self.schema {
+            Some(schema) => Some(
+                Schema::parse_str(schema)
+                    .map_err(|error| format!("Invalid `avro_ocf.schema`: {}", error))?,
+            ),
+            None => {
+                if !is_avro_name(&self.record_name) {
+                    return Err(format!(
+                        "`avro_ocf.record_name` {:?} isn't a valid Avro name",
+                        self.record_name
+                    )
+                    .into());
+                }
+                None
+            }
+        };
+        Ok(AvroOcf {
+            schema,
+            record_name: self.record_name.clone(),
+            codec: self.codec,
+        })
+    }
+}
+
+/// Writes each batch as an Avro Object Container File, embedding the schema of its records.
+#[derive(Clone, Debug)]
+pub struct AvroOcf {
+    schema: Option<Schema>,
+    record_name: String,
+    codec: AvroOcfCodec,
+}
+
+impl AvroOcf {
+    /// The encoder telling apart the events that don't conform to the configured schema.
+    ///
+    /// Inferred schemas fit every event of their batch, so there's nothing to tell apart then.
+    pub fn conformance_encoder(&self) -> Option<Encoder<Framer>> {
+        self.schema.as_ref().map(|schema| {
+            Encoder::<Framer>::new(
+                BytesEncoder::new().into(),
+                AvroSerializer::new(schema.clone()).into(),
+            )
+        })
+    }
+
+    /// Writes the file holding the events.
+    pub fn write(&self, events: Vec<Event>, transformer: &Transformer) -> io::Result<Vec<u8>> {
+        let logs = events
+            .into_iter()
+            .map(|mut event| {
+                transformer.transform(&mut event);
+                event.into_log()
+            })
+            .collect::<Vec<_>>();
+
+        let (schema, values) = match &self.schema {
+            Some(schema) => {
+                let values = logs
+                    .into_iter()
+                    .map(|log| {
+                        avro_rs::to_value(log)
+                            .and_then(|value| value.resolve(schema))
+                            .map_err(invalid_data)
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                (schema.clone(), values)
+            }
+            None => {
+                let inferred = InferredSchema::new(&logs);
+                let schema = inferred.schema(&self.record_name)?;
+                let values = logs.iter().map(|log| inferred.record(log)).collect();
+                (schema, values)
+            }
+        };
+
+        let mut writer = Writer::with_codec(&schema, Vec::new(), self.codec.into());
+        for value in values {
+            writer.append(value).map_err(invalid_data)?;
+        }
+        writer.into_inner().map_err(invalid_data)
+    }
+}
+
+fn invalid_data(error: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+/// Whether `name` can be used as the name of an Avro record or field.
+fn is_avro_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The type of a field inferred from the values it holds across a batch.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum FieldType {
+    Null,
+    Boolean,
+    Long,
+    Double,
+    String,
+    Timestamp,
+    /// Objects, arrays and fields holding values of different types are written as JSON.
+    Json,
+}
+
+impl FieldType {
+    const fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Boolean(_) => Self::Boolean,
+            Value::Integer(_) => Self::Long,
+            Value::Float(_) => Self::Double,
+            Value::Bytes(_) => Self::String,
+            Value::Timestamp(_) => Self::Timestamp,
+            Value::Regex(_) => Self::String,
+            Value::Object(_) | Value::Array(_) => Self::Json,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Null, other) => other,
+            (this, Self::Null) => this,
+            (Self::Long, Self::Double) | (Self::Double, Self::Long) => Self::Double,
+            (this, other) if this == other => this,
+            _ => Self::Json,
+        }
+    }
+
+    fn schema(self) -> serde_json::Value {
+        match self {
+            Self::Null => json!("null"),
+            Self::Boolean => json!("boolean"),
+            Self::Long => json!("long"),
+            Self::Double => json!("double"),
+            Self::String | Self::Json => json!("string"),
+            Self::Timestamp => json!({"type": "long", "logicalType": "timestamp-micros"}),
+        }
+    }
+
+    fn value(self, value: &Value) -> AvroValue {
+        match (self, value) {
+            (_, Value::Null) => AvroValue::Null,
+            (Self::Boolean, Value::Boolean(value)) => AvroValue::Boolean(*value),
+            (Self::Long, Value::Integer(value)) => AvroValue::Long(*value),
+            (Self::Double, Value::Integer(value)) => AvroValue::Double(*value as f64),
+            (Self::Double, Value::Float(value)) => AvroValue::Double(value.into_inner()),
+            (Self::Timestamp, Value::Timestamp(value)) => {
+                AvroValue::TimestampMicros(value.timestamp_nanos() / 1_000)
+            }
+            (Self::String, value) => AvroValue::String(value.to_string_lossy()),
+            (_, value) => AvroValue::String(
+                serde_json::to_string(value).expect("values always serialize to JSON"),
+            ),
+        }
+    }
+}
+
+/// A record schema fitting every event of a batch.
+///
+/// Each top level field becomes an optional field of the record, so events lacking some of the
+/// fields of the others still fit. Field names that aren't valid Avro names are sanitized.
+struct InferredSchema {
+    /// The fields by name in the events, along with their Avro name and type.
+    fields: BTreeMap<String, (String, FieldType)>,
+}
+
+impl InferredSchema {
+    fn new(logs: &[LogEvent]) -> Self {
+        let mut types = BTreeMap::<String, FieldType>::new();
+        for log in logs {
+            if let Some(fields) = log.as_map() {
+                for (name, value) in fields {
+                    let field_type = types.entry(name.clone()).or_insert(FieldType::Null);
+                    *field_type = field_type.merge(FieldType::of(value));
+                }
+            }
+        }
+
+        let mut avro_names = BTreeMap::new();
+        let fields = types
+            .into_iter()
+            .map(|(name, field_type)| {
+                let mut avro_name = name
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .collect::<String>();
+                if !avro_name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                    avro_name.insert(0, '_');
+                }
+                let collisions = avro_names.entry(avro_name.clone()).or_insert(0);
+                if *collisions > 0 {
+                    avro_name = format!("{}_{}", avro_name, collisions);
+                }
+                *collisions += 1;
+                (name, (avro_name, field_type))
+            })
+            .collect();
+        Self { fields }
+    }
+
+    fn schema(&self, record_name: &str) -> io::Result<Schema> {
+        let fields = self
+            .fields
+            .values()
+            .map(|(avro_name, field_type)| {
+                let field_type = match field_type {
+                    FieldType::Null => json!("null"),
+                    field_type => json!(["null", field_type.schema()]),
+                };
+                json!({"name": avro_name, "type": field_type, "default": null})
+            })
+            .collect::<Vec<_>>();
+        let schema = json!({"type": "record", "name": record_name, "fields": fields});
+        Schema::parse(&schema).map_err(invalid_data)
+    }
+
+    fn record(&self, log: &LogEvent) -> AvroValue {
+        AvroValue::Record(
+            self.fields
+                .iter()
+                .map(|(name, (avro_name, field_type))| {
+                    let value = match log.as_map().and_then(|fields| fields.get(name)) {
+                        Some(value) => field_type.value(value),
+                        None => AvroValue::Null,
+                    };
+                    let value = match field_type {
+                        FieldType::Null => value,
+                        _ => AvroValue::Union(Box::new(value)),
+                    };
+                    (avro_name.clone(), value)
+                })
+                .collect(),
+        )
+    }
+}
@@ -1,21 +1,78 @@
-use std::{convert::TryInto, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+    num::{NonZeroU32, NonZeroUsize},
+    sync::Arc,
+    time::Duration,
+};
 
 use azure_storage_blobs::prelude::*;
-use codecs::{encoding::Framer, JsonSerializerConfig, NewlineDelimitedEncoderConfig};
+use codecs::{
+    encoding::{Framer, Serializer, SerializerConfig},
+    JsonSerializerConfig, NewlineDelimitedEncoderConfig,
+};
+use futures::{FutureExt, TryFutureExt};
+use http::{HeaderMap, HeaderValue, Uri};
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 use tower::ServiceBuilder;
+use vector_common::TimeZone;
 
-use super::request_builder::AzureBlobRequestOptions;
+use super::{
+    avro_ocf::AvroOcfConfig,
+    csv_output::{Csv, CsvConfig, CsvHeader, CSV_CONTENT_TYPE, CSV_SCHEMA_SIDECAR},
+    envelope::EnvelopeConfig,
+    ordering::OrderingKeys,
+    passthrough::PassthroughConfig,
+    request_builder::AzureBlobRequestOptions,
+    sniff::ContentTypeSniffingConfig,
+    time_window::TimeWindowConfig,
+    zstd_dictionary::ZstdConfig,
+};
 use crate::{
     codecs::{Encoder, EncodingConfigWithFraming, SinkType},
     config::{AcknowledgementsConfig, DataType, GenerateConfig, Input, SinkConfig, SinkContext},
     sinks::{
         azure_common::{
-            self, config::AzureBlobRetryLogic, service::AzureBlobService, sink::AzureBlobSink,
+            self,
+            acknowledgements::AcknowledgementService,
+            blob_path::BlobPathPolicy,
+            blob_type::{AppendOrdering, BlobTypeConfig},
+            broker::{TokenBroker, TokenBrokerConfig},
+            bucket::HashBucketConfig,
+            cancellation::CancellationService,
+            coalesce::CoalesceConfig,
+            computed_metadata::ComputedMetadata,
+            concurrency::{ByteLimiter, ConcurrencyMode, PartitionLimiter},
+            config::{
+                AzureBlobRetryLogic, ChecksumAlgorithm, ClientOptions, EncodingErrorPolicy,
+                OverwritePolicy, TokenWarmUp, TokenWarmUpConfig, UnresolvablePartition,
+            },
+            container::ContainerGuard,
+            content_language::ContentLanguageConfig,
+            encoding::EncodingErrorHandler,
+            endpoint::Endpoint,
+            fan_out::{FanOutConfig, FanOutService, FanOutTarget},
+            fingerprint::SchemaFingerprintConfig,
+            flush::FlushSignal,
+            imds,
+            in_flight::{InFlightLimitConfig, InFlightLimitService},
+            index::SealedBatchConfig,
+            janitor::{JanitorConfig, ReservedPrefixGuard, RESERVED_PREFIX},
+            marker::{SuccessMarkerConfig, SuccessMarkers},
+            precompressed::PrecompressedConfig,
+            schema::SchemaSidecar,
+            schema_version::SchemaVersionConfig,
+            service::AzureBlobService,
+            shedding::LoadSheddingConfig,
+            sink::AzureBlobSink,
+            slow_start::{SlowStartConfig, SlowStartService},
+            soft_delete::check_soft_delete,
+            summary::BatchSummary,
         },
         util::{
             partitioner::KeyPartitioner, BatchConfig, BulkSizeBasedDefaultBatchSettings,
-            Compression, ServiceBuilderExt, TowerRequestConfig,
+            Compression, CompressionLevel, ServiceBuilderExt, TowerRequestConfig,
         },
         Healthcheck, VectorSink,
     },
@@ -27,18 +84,86 @@ use crate::{
 pub struct AzureBlobSinkConfig {
     pub connection_string: Option<String>,
     pub storage_account: Option<String>,
+    pub imds_endpoint: Option<String>,
     pub(super) container_name: String,
     pub blob_prefix: Option<String>,
     pub blob_time_format: Option<String>,
     pub blob_append_uuid: Option<bool>,
+    pub timezone: Option<TimeZone>,
     #[serde(flatten)]
     pub encoding: EncodingConfigWithFraming,
     #[serde(default = "Compression::gzip_default")]
     pub compression: Compression,
+    pub gzip_level: Option<u32>,
     #[serde(default)]
     pub batch: BatchConfig<BulkSizeBasedDefaultBatchSettings>,
     #[serde(default)]
     pub request: TowerRequestConfig,
+    pub janitor: Option<JanitorConfig>,
+    pub sidecar_checksum: Option<ChecksumAlgorithm>,
+    pub batch_summary_interval_secs: Option<u64>,
+    pub coalesce: Option<CoalesceConfig>,
+    pub unresolvable_partition: Option<UnresolvablePartition>,
+    #[serde(default)]
+    pub force_http1: bool,
+    #[serde(default)]
+    pub event_time_metadata: bool,
+    pub content_type_sniffing: Option<ContentTypeSniffingConfig>,
+    pub load_shedding: Option<LoadSheddingConfig>,
+    #[serde(default = "crate::serde::default_true")]
+    pub source_type_metadata: bool,
+    #[serde(default)]
+    pub verify_write: bool,
+    #[serde(default)]
+    pub encoding_on_error: EncodingErrorPolicy,
+    #[serde(default)]
+    pub overwrite: OverwritePolicy,
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub extra_headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub concurrency_mode: ConcurrencyMode,
+    pub passthrough: Option<PassthroughConfig>,
+    pub time_window: Option<TimeWindowConfig>,
+    pub token_warm_up: Option<TokenWarmUpConfig>,
+    pub avro_ocf: Option<AvroOcfConfig>,
+    pub hash_bucket: Option<HashBucketConfig>,
+    pub token_broker: Option<TokenBrokerConfig>,
+    #[serde(default)]
+    pub write_receipts: bool,
+    #[serde(default)]
+    pub create_missing_container: bool,
+    pub fan_out: Option<FanOutConfig>,
+    pub zstd: Option<ZstdConfig>,
+    pub in_flight_limit: Option<InFlightLimitConfig>,
+    #[serde(default)]
+    pub batch_metadata: BTreeMap<String, String>,
+    pub flush_signal: Option<FlushSignal>,
+    pub success_marker: Option<SuccessMarkerConfig>,
+    pub write_endpoint: Option<String>,
+    pub read_endpoint: Option<String>,
+    pub max_batch_age_before_send_secs: Option<u64>,
+    pub envelope: Option<EnvelopeConfig>,
+    pub slow_start: Option<SlowStartConfig>,
+    pub blob_type: Option<BlobTypeConfig>,
+    #[serde(default)]
+    pub ordering_key: bool,
+    pub precompressed: Option<PrecompressedConfig>,
+    #[serde(default)]
+    pub batch_acknowledgements: bool,
+    pub csv: Option<CsvConfig>,
+    #[serde(default = "crate::serde::default_true")]
+    pub drain_on_reload: bool,
+    pub schema_fingerprint: Option<SchemaFingerprintConfig>,
+    pub schema_version: Option<SchemaVersionConfig>,
+    pub encoding_pool_size: Option<NonZeroUsize>,
+    #[serde(default)]
+    pub acknowledgement_provenance: bool,
+    pub content_language: Option<ContentLanguageConfig>,
+    pub sealed_batch: Option<SealedBatchConfig>,
+    pub max_inflight_bytes: Option<NonZeroU32>,
+    #[serde(default)]
+    pub blob_path_policy: BlobPathPolicy,
     #[serde(
         default,
         deserialize_with = "crate::serde::bool_or_struct",
@@ -52,14 +177,66 @@ impl GenerateConfig for AzureBlobSinkConfig {
         toml::Value::try_from(Self {
             connection_string: Some(String::from("DefaultEndpointsProtocol=https;AccountName=some-account-name;AccountKey=some-account-key;")),
             storage_account: Some(String::from("some-account-name")),
+            imds_endpoint: None,
             container_name: String::from("logs"),
             blob_prefix: Some(String::from("blob")),
             blob_time_format: Some(String::from("%s")),
             blob_append_uuid: Some(true),
+            timezone: None,
             encoding: (Some(NewlineDelimitedEncoderConfig::new()), JsonSerializerConfig::new()).into(),
             compression: Compression::gzip_default(),
+            gzip_level: None,
             batch: BatchConfig::default(),
             request: TowerRequestConfig::default(),
+            janitor: None,
+            sidecar_checksum: None,
+            batch_summary_interval_secs: None,
+            coalesce: None,
+            unresolvable_partition: None,
+            force_http1: false,
+            event_time_metadata: false,
+            content_type_sniffing: None,
+            load_shedding: None,
+            source_type_metadata: true,
+            verify_write: false,
+            encoding_on_error: EncodingErrorPolicy::DropBatch,
+            overwrite: OverwritePolicy::Allow,
+            user_agent: None,
+            extra_headers: BTreeMap::new(),
+            concurrency_mode: ConcurrencyMode::Global,
+            passthrough: None,
+            time_window: None,
+            token_warm_up: None,
+            avro_ocf: None,
+            hash_bucket: None,
+            token_broker: None,
+            write_receipts: false,
+            create_missing_container: false,
+            fan_out: None,
+            zstd: None,
+            in_flight_limit: None,
+            batch_metadata: BTreeMap::new(),
+            flush_signal: None,
+            success_marker: None,
+            write_endpoint: None,
+            read_endpoint: None,
+            max_batch_age_before_send_secs: None,
+            envelope: None,
+            slow_start: None,
+            blob_type: None,
+            ordering_key: false,
+            precompressed: None,
+            batch_acknowledgements: false,
+            csv: None,
+            drain_on_reload: true,
+            schema_fingerprint: None,
+            schema_version: None,
+            encoding_pool_size: None,
+            acknowledgement_provenance: false,
+            content_language: None,
+            sealed_batch: None,
+            max_inflight_bytes: None,
+            blob_path_policy: BlobPathPolicy::Keep,
             acknowledgements: Default::default(),
         })
         .unwrap()
@@ -70,22 +247,41 @@ impl GenerateConfig for AzureBlobSinkConfig {
 #[typetag::serde(name = "azure_blob")]
 impl SinkConfig for AzureBlobSinkConfig {
     async fn build(&self, _cx: SinkContext) -> Result<(VectorSink, Healthcheck)> {
-        let client = azure_common::config::build_client(
-            self.connection_string.clone(),
-            self.storage_account.clone(),
-            self.container_name.clone(),
-        )?;
+        let client = azure_common::config::build_client(ClientOptions {
+            token_warm_up: self.token_warm_up()?,
+            endpoint: self.endpoint(&self.write_endpoint, "write_endpoint")?,
+            ..self.client_options()?
+        })
+        .await?;
+        // Reads go to the account's own endpoint unless routed elsewhere, even when writes are.
+        let read_client = match (&self.write_endpoint, &self.read_endpoint) {
+            (None, None) => Arc::clone(&client),
+            _ => {
+                azure_common::config::build_client(ClientOptions {
+                    endpoint: self.endpoint(&self.read_endpoint, "read_endpoint")?,
+                    ..self.client_options()?
+                })
+                .await?
+            }
+        };
 
+        let soft_delete = check_soft_delete(Arc::clone(&client), self.overwrite);
         let healthcheck = azure_common::config::build_healthcheck(
             self.container_name.clone(),
-            Arc::clone(&client),
-        )?;
-        let sink = self.build_processor(client)?;
+            Arc::clone(&read_client),
+        )?
+        .and_then(|()| soft_delete.map(Ok))
+        .boxed();
+        let sink = self.build_processor(client, read_client)?;
         Ok((sink, healthcheck))
     }
 
     fn input(&self) -> Input {
-        Input::new(self.encoding.config().1.input_type() & DataType::Log)
+        match self.encoding.config().1 {
+            // Line protocol is only written for metrics, the other codecs are restricted to logs.
+            SerializerConfig::LineProtocol => Input::metric(),
+            serializer => Input::new(serializer.input_type() & DataType::Log),
+        }
     }
 
     fn sink_type(&self) -> &'static str {
@@ -105,14 +301,169 @@ const DEFAULT_FILENAME_TIME_FORMAT: &str = "%s";
 const DEFAULT_FILENAME_APPEND_UUID: bool = true;
 
 impl AzureBlobSinkConfig {
-    pub fn build_processor(&self, client: Arc<ContainerClient>) -> crate::Result<VectorSink> {
+    pub fn build_processor(
+        &self,
+        client: Arc<ContainerClient>,
+        read_client: Arc<ContainerClient>,
+    ) -> crate::Result<VectorSink> {
         let request_limits = self.request.unwrap_with(&DEFAULT_REQUEST_LIMITS);
-        let service = ServiceBuilder::new()
-            .settings(request_limits, AzureBlobRetryLogic)
-            .service(AzureBlobService::new(client));
+        let janitor = match &self.janitor {
+            Some(janitor) => {
+                // Never let the janitor loose on a prefix the sink writes user data to.
+                if self
+                    .blob_prefix
+                    .as_deref()
+                    .map_or(false, |prefix| prefix.starts_with(RESERVED_PREFIX))
+                {
+                    return Err(format!(
+                        "`blob_prefix` can't start with {:?} when `janitor` is enabled",
+                        RESERVED_PREFIX
+                    )
+                    .into());
+                }
+                Some(janitor.build(Arc::clone(&client), request_limits.clone())?)
+            }
+            None => None,
+        };
+        let batch_summary = match self.batch_summary_interval_secs {
+            Some(0) => return Err("`batch_summary_interval_secs` must be greater than zero".into()),
+            Some(secs) => Some((Arc::new(BatchSummary::default()), Duration::from_secs(secs))),
+            None => None,
+        };
+        let max_batch_age = match self.max_batch_age_before_send_secs {
+            Some(0) => {
+                return Err("`max_batch_age_before_send_secs` must be greater than zero".into())
+            }
+            Some(secs) => Some(chrono::Duration::seconds(secs as i64)),
+            None => None,
+        };
+        let csv = self.csv.as_ref().map(CsvConfig::build).transpose()?;
+        let schema_sidecar = csv.as_ref().and_then(Csv::schema).map(|schema| {
+            Arc::new(SchemaSidecar::new(
+                CSV_SCHEMA_SIDECAR,
+                CSV_CONTENT_TYPE,
+                schema,
+            ))
+        });
+        let container_guard = ContainerGuard::new(self.create_missing_container);
+        let success_markers = self
+            .success_marker
+            .as_ref()
+            .map(|marker| marker.build(Arc::clone(&client)))
+            .transpose()?;
+        let sealed_batches = self
+            .sealed_batch
+            .as_ref()
+            .map(SealedBatchConfig::build)
+            .transpose()?;
+        // Every container is written to through a stack of its own, so that each one retries and
+        // adapts its concurrency independently of the others. Success markers are only written to
+        // the sink's own container.
+        let target_service = |client: Arc<ContainerClient>,
+                              guard: Arc<ContainerGuard>,
+                              markers: Option<Arc<SuccessMarkers>>| {
+            let target_read_client = Arc::clone(read_client.storage_client())
+                .as_container_client(client.container_name());
+            ServiceBuilder::new()
+                .settings(request_limits.clone(), AzureBlobRetryLogic)
+                .service(
+                    AzureBlobService::new(client)
+                        .with_read_client(target_read_client)
+                        .with_checksum_sidecar(self.sidecar_checksum)
+                        .with_verify_write(self.verify_write)
+                        .with_overwrite(self.overwrite)
+                        .with_write_receipts(self.write_receipts)
+                        .with_provenance(self.acknowledgement_provenance)
+                        .with_container_guard(guard)
+                        .with_success_markers(markers)
+                        .with_max_batch_age(max_batch_age)
+                        .with_schema_sidecar(schema_sidecar.clone())
+                        .with_sealed_batches(sealed_batches.clone())
+                        .with_batch_summary(
+                            batch_summary
+                                .as_ref()
+                                .map(|(summary, _)| Arc::clone(summary)),
+                        ),
+                )
+        };
+        let service = match &self.fan_out {
+            Some(fan_out) => {
+                let quorum = fan_out.build(&self.container_name)?;
+                let mut targets = vec![FanOutTarget {
+                    container: self.container_name.clone(),
+                    blob_prefix: String::new(),
+                    service: target_service(
+                        Arc::clone(&client),
+                        Arc::clone(&container_guard),
+                        success_markers.clone(),
+                    ),
+                }];
+                for target in &fan_out.containers {
+                    // Shares the credentials and the connection pool of the sink's container.
+                    let target_client = Arc::clone(client.storage_client())
+                        .as_container_client(target.container_name.as_str());
+                    targets.push(FanOutTarget {
+                        container: target.container_name.clone(),
+                        blob_prefix: target.blob_prefix.clone(),
+                        service: target_service(
+                            target_client,
+                            ContainerGuard::new(self.create_missing_container),
+                            None,
+                        ),
+                    });
+                }
+                FanOutService::new(targets, quorum)
+            }
+            None => FanOutService::single(
+                self.container_name.clone(),
+                target_service(
+                    client,
+                    Arc::clone(&container_guard),
+                    success_markers.clone(),
+                ),
+            ),
+        };
+        let in_flight_limit = self
+            .in_flight_limit
+            .as_ref()
+            .map(InFlightLimitConfig::build)
+            .transpose()?;
+        let slow_start = self
+            .slow_start
+            .as_ref()
+            .map(|slow_start| slow_start.build(request_limits.concurrency))
+            .transpose()?;
+        let service = SlowStartService::new(service, slow_start);
+        let service = InFlightLimitService::new(service, in_flight_limit);
+        let cancellation = (!self.drain_on_reload).then(CancellationToken::new);
+        let service = CancellationService::new(service, cancellation.clone());
+        let service = AcknowledgementService::new(service);
 
         // Configure our partitioning/batching.
-        let batcher_settings = self.batch.into_batcher_settings()?;
+        let mut batch = self.batch;
+        if self.passthrough.is_some() {
+            // Every event is written as a blob of its own.
+            match batch.max_events {
+                Some(max_events) if max_events > 1 => {
+                    return Err(
+                        "`batch.max_events` can't be greater than 1 when `passthrough` is enabled"
+                            .into(),
+                    )
+                }
+                _ => batch.max_events = Some(1),
+            }
+        }
+        let batcher_settings = batch.into_batcher_settings()?;
+        let coalescer = self
+            .coalesce
+            .as_ref()
+            .map(|coalesce| coalesce.build(&batcher_settings))
+            .transpose()?;
+        let load_shedder = self
+            .load_shedding
+            .as_ref()
+            .map(LoadSheddingConfig::build)
+            .transpose()?;
 
         let blob_time_format = self
             .blob_time_format
@@ -125,14 +476,261 @@ impl AzureBlobSinkConfig {
 
         let transformer = self.encoding.transformer();
         let (framer, serializer) = self.encoding.build(SinkType::MessageBased)?;
+        let content_type_sniffing = match &self.content_type_sniffing {
+            // Only the raw message codec writes payloads whose type isn't known up front.
+            Some(_) if !matches!(serializer, Serializer::RawMessage(_)) => {
+                return Err(
+                    "`content_type_sniffing` can only be enabled with the `raw_message` codec"
+                        .into(),
+                )
+            }
+            Some(sniffing) => Some(sniffing.build()?),
+            None => None,
+        };
+        let line_protocol = matches!(serializer, Serializer::LineProtocol(_));
+        let passthrough = match &self.passthrough {
+            Some(passthrough) => {
+                // The payloads are written as is, so nothing may be added to or merged into them.
+                if !matches!(serializer, Serializer::RawMessage(_)) {
+                    return Err(
+                        "`passthrough` can only be enabled with the `raw_message` codec".into(),
+                    );
+                }
+                if self.coalesce.is_some() || self.content_type_sniffing.is_some() {
+                    return Err("`passthrough` can't be enabled along with `coalesce` or \
+                                `content_type_sniffing`"
+                        .into());
+                }
+                if self.encoding_on_error != EncodingErrorPolicy::DropBatch {
+                    return Err(
+                        "`encoding_on_error` must be `drop_batch` when `passthrough` is enabled"
+                            .into(),
+                    );
+                }
+                Some(passthrough.build()?)
+            }
+            None => None,
+        };
+        let avro_ocf = match &self.avro_ocf {
+            Some(avro_ocf) => {
+                // Each blob is a file of its own, which can't be compressed or merged as a whole.
+                if line_protocol || passthrough.is_some() {
+                    return Err(
+                        "`avro_ocf` can't be enabled along with the `line_protocol` \
+                                codec or `passthrough`"
+                            .into(),
+                    );
+                }
+                if self.coalesce.is_some() || self.content_type_sniffing.is_some() {
+                    return Err("`avro_ocf` can't be enabled along with `coalesce` or \
+                                `content_type_sniffing`"
+                        .into());
+                }
+                if self.compression()?.is_compressed() {
+                    return Err(
+                        "`compression` must be `none` when `avro_ocf` is enabled, the \
+                                files compress their data blocks according to `avro_ocf.codec`"
+                            .into(),
+                    );
+                }
+                Some(avro_ocf.build()?)
+            }
+            None => None,
+        };
+        let envelope = match &self.envelope {
+            Some(envelope) => {
+                // Envelopes are JSON objects, written one per line.
+                if !matches!(serializer, Serializer::Json(_)) {
+                    return Err("`envelope` can only be enabled with the `json` codec".into());
+                }
+                if avro_ocf.is_some() {
+                    return Err("`envelope` can't be enabled along with `avro_ocf`".into());
+                }
+                Some(envelope.build()?)
+            }
+            None => None,
+        };
+        let zstd = match &self.zstd {
+            Some(zstd) => {
+                if avro_ocf.is_some() {
+                    return Err("`zstd` can't be enabled along with `avro_ocf`".into());
+                }
+                if self.compression()?.is_compressed() {
+                    return Err("`compression` must be `none` when `zstd` is enabled".into());
+                }
+                Some(zstd.build()?)
+            }
+            None => None,
+        };
+        let blob_type = match &self.blob_type {
+            Some(blob_type) => {
+                let router = blob_type.build()?;
+                if router.appends() {
+                    // Appended payloads are read back as a single blob, so they have to be streams
+                    // that can be concatenated.
+                    if avro_ocf.is_some() {
+                        return Err("`blob_type` can't append to blobs along with `avro_ocf`, \
+                                    the files can't be concatenated"
+                            .into());
+                    }
+                    if matches!(self.compression()?, Compression::Zlib(_)) {
+                        return Err("`blob_type` can't append to blobs compressed with `zlib`, \
+                                    the streams can't be concatenated"
+                            .into());
+                    }
+                    // The checks of these options describe whole blobs rather than the payloads
+                    // appended.
+                    if self.verify_write
+                        || self.sidecar_checksum.is_some()
+                        || self.overwrite != OverwritePolicy::Allow
+                    {
+                        return Err(
+                            "`blob_type` can't append to blobs along with `verify_write`, \
+                             `sidecar_checksum` or `overwrite`"
+                                .into(),
+                        );
+                    }
+                    // An orphaned append blob would take the batches appended before it along.
+                    if self.sealed_batch.is_some() {
+                        return Err(
+                            "`blob_type` can't append to blobs along with `sealed_batch`, \
+                                    the entries of the index point to whole blobs"
+                                .into(),
+                        );
+                    }
+                    if router.ordering() == AppendOrdering::Strict
+                        && self.encoding_pool_size.is_some()
+                    {
+                        return Err("`blob_type.ordering` can't be `strict` along with \
+                             `encoding_pool_size`, which hands the batches over out of order"
+                            .into());
+                    }
+                }
+                Some(router)
+            }
+            None => None,
+        };
+        let ordering_keys = if self.ordering_key {
+            if self.blob_time_format.is_some() {
+                return Err(
+                    "`blob_time_format` can't be set when `ordering_key` is enabled, the key \
+                     takes the place of the time in the names of the blobs"
+                        .into(),
+                );
+            }
+            if blob_type.as_ref().map_or(false, |router| router.appends()) {
+                return Err(
+                    "`ordering_key` can't be enabled along with `blob_type` appending \
+                     to blobs, whose names are shared by their batches"
+                        .into(),
+                );
+            }
+            Some(Arc::new(OrderingKeys::default()))
+        } else {
+            None
+        };
+        let precompressed = match &self.precompressed {
+            Some(precompressed) => {
+                // The payloads already compressed are written as is, so nothing may be added to
+                // them but other payloads, which compressed streams can be concatenated with.
+                if !matches!(serializer, Serializer::RawMessage(_)) {
+                    return Err(
+                        "`precompressed` can only be enabled with the `raw_message` codec".into(),
+                    );
+                }
+                if passthrough.is_none() && !matches!(framer, Framer::Bytes(_)) {
+                    return Err(
+                        "`framing.method` must be `bytes` when `precompressed` is enabled \
+                         without `passthrough`"
+                            .into(),
+                    );
+                }
+                if avro_ocf.is_some() {
+                    return Err("`precompressed` can't be enabled along with `avro_ocf`".into());
+                }
+                Some(precompressed.build()?)
+            }
+            None => None,
+        };
+        if csv.is_some() {
+            // The tables take the place of the payloads of the codec.
+            if passthrough.is_some()
+                || avro_ocf.is_some()
+                || envelope.is_some()
+                || precompressed.is_some()
+                || self.content_type_sniffing.is_some()
+            {
+                return Err(
+                    "`csv` can't be enabled along with `passthrough`, `avro_ocf`, \
+                            `envelope`, `precompressed` or `content_type_sniffing`"
+                        .into(),
+                );
+            }
+            // Every payload appended would repeat the header.
+            if blob_type.as_ref().map_or(false, |router| router.appends())
+                && self.csv.as_ref().map(|csv| csv.header) == Some(CsvHeader::FirstLine)
+            {
+                return Err(
+                    "`csv.header` can't be `first_line` when `blob_type` appends to \
+                            blobs"
+                        .into(),
+                );
+            }
+        }
         let encoder = Encoder::<Framer>::new(framer, serializer);
 
+        let encoding_errors = match (self.encoding_on_error, &avro_ocf) {
+            // Batches failing to encode are dropped by the request builder already.
+            (EncodingErrorPolicy::DropBatch, _) => None,
+            // Events are checked against the schema of the files rather than the codec, and every
+            // event fits a schema inferred from its batch.
+            (policy, Some(avro_ocf)) => avro_ocf
+                .conformance_encoder()
+                .map(|encoder| EncodingErrorHandler::new(policy, (transformer.clone(), encoder))),
+            (policy, None) => Some(EncodingErrorHandler::new(
+                policy,
+                (transformer.clone(), encoder.clone()),
+            )),
+        };
+
+        let schema_fingerprints = self
+            .schema_fingerprint
+            .as_ref()
+            .map(SchemaFingerprintConfig::build);
+        let schema_versions = self
+            .schema_version
+            .as_ref()
+            .map(SchemaVersionConfig::build)
+            .transpose()?;
+        let content_languages = self
+            .content_language
+            .as_ref()
+            .map(ContentLanguageConfig::build)
+            .transpose()?;
         let request_options = AzureBlobRequestOptions {
             container_name: self.container_name.clone(),
             blob_time_format,
             blob_append_uuid,
             encoder: (transformer, encoder),
-            compression: self.compression,
+            compression: self.compression()?,
+            event_time_metadata: self.event_time_metadata,
+            content_type_sniffing,
+            source_type_metadata: self.source_type_metadata,
+            line_protocol,
+            passthrough,
+            avro_ocf,
+            zstd,
+            batch_metadata: ComputedMetadata::new(&self.batch_metadata)?,
+            track_batch_age: max_batch_age.is_some(),
+            envelope,
+            blob_type: blob_type.clone(),
+            ordering_keys,
+            precompressed: precompressed.clone(),
+            batch_acknowledgements: self.batch_acknowledgements,
+            csv,
+            schema_fingerprints: schema_fingerprints.clone(),
+            schema_versions: schema_versions.clone(),
+            content_languages,
         };
 
         let sink = AzureBlobSink::new(
@@ -141,10 +739,180 @@ impl AzureBlobSinkConfig {
             self.key_partitioner()?,
             batcher_settings,
         );
+        let sink = match janitor {
+            Some(janitor) => {
+                // The fan-out targets sharing the sink's container write under their own prefix.
+                let blob_prefixes = std::iter::once(String::new())
+                    .chain(
+                        self.fan_out
+                            .iter()
+                            .flat_map(|fan_out| &fan_out.containers)
+                            .filter(|target| target.container_name == self.container_name)
+                            .map(|target| target.blob_prefix.clone()),
+                    )
+                    .collect();
+                sink.with_janitor(janitor)
+                    .with_reserved_prefix_guard(ReservedPrefixGuard::new(blob_prefixes))
+            }
+            None => sink,
+        };
+        let sink = match batch_summary {
+            Some((summary, period)) => sink.with_batch_summary(summary, period),
+            None => sink,
+        };
+        let sink = match coalescer {
+            Some(coalescer) => sink.with_coalescer(coalescer),
+            None => sink,
+        }
+        .with_unresolvable_partition(self.unresolvable_partition.clone().unwrap_or_default())
+        .with_blob_path_policy(self.blob_path_policy);
+        let sink = match load_shedder {
+            Some(load_shedder) => sink.with_load_shedder(load_shedder),
+            None => sink,
+        };
+        let sink = match encoding_errors {
+            Some(handler) => sink.with_encoding_errors(handler),
+            None => sink,
+        };
+        let sink = match self.concurrency_mode.build() {
+            Some(limiter) => sink.with_partition_limiter(limiter),
+            None => sink,
+        };
+        let sink = match &self.hash_bucket {
+            Some(hash_bucket) => sink.with_hash_bucket(hash_bucket.build()?),
+            None => sink,
+        }
+        .with_container_guard(container_guard);
+        let sink = match success_markers {
+            Some(markers) => sink.with_success_markers(markers),
+            None => sink,
+        };
+        let sink = match self.flush_signal {
+            Some(signal) => sink.with_flush_listener(signal.build()?),
+            None => sink,
+        };
+        let sink = match blob_type {
+            Some(router) if router.ordering() == AppendOrdering::Strict => sink
+                .with_append_sequencer(PartitionLimiter::sequential_appends())
+                .with_blob_type_router(router),
+            Some(router) => sink.with_blob_type_router(router),
+            None => sink,
+        };
+        let sink = match precompressed {
+            Some(precompressed) => sink.with_precompressed(precompressed),
+            None => sink,
+        };
+        let sink = match cancellation {
+            Some(token) => sink.with_cancellation(token),
+            None => sink,
+        };
+        let sink = match schema_fingerprints {
+            Some(fingerprints) => sink.with_schema_fingerprints(fingerprints),
+            None => sink,
+        };
+        let sink = match schema_versions {
+            Some(versions) => sink.with_schema_versions(versions),
+            None => sink,
+        };
+        let sink = match self.encoding_pool_size {
+            Some(size) => sink.with_encoding_pool(size),
+            None => sink,
+        };
+        let sink = match self.max_inflight_bytes {
+            Some(max_bytes) => sink.with_byte_limiter(ByteLimiter::new(max_bytes)),
+            None => sink,
+        };
 
         Ok(VectorSink::from_event_streamsink(sink))
     }
 
+    pub fn imds_endpoint(&self) -> crate::Result<Option<Uri>> {
+        self.imds_endpoint
+            .as_deref()
+            .map(imds::parse_endpoint)
+            .transpose()
+    }
+
+    /// The endpoint the connections are routed to by the given option, if set.
+    pub fn endpoint(
+        &self,
+        endpoint: &Option<String>,
+        option: &str,
+    ) -> crate::Result<Option<Endpoint>> {
+        endpoint
+            .as_deref()
+            .map(|endpoint| Endpoint::parse(endpoint, option))
+            .transpose()
+    }
+
+    /// The token broker of `storage_account`, if configured.
+    pub fn token_broker(&self) -> crate::Result<Option<TokenBroker>> {
+        match &self.token_broker {
+            Some(_) if self.storage_account.is_none() => {
+                Err("`token_broker` can only be configured along with `storage_account`".into())
+            }
+            Some(_) if self.imds_endpoint.is_some() => {
+                Err("`token_broker` and `imds_endpoint` can't be configured together".into())
+            }
+            Some(token_broker) => token_broker.build().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The `User-Agent` sent along with every request to the storage account.
+    pub fn user_agent(&self) -> crate::Result<HeaderValue> {
+        match &self.user_agent {
+            Some(user_agent) => azure_common::config::parse_user_agent(user_agent),
+            None => Ok(azure_common::config::default_user_agent()),
+        }
+    }
+
+    /// The headers sent along with every request to the storage account on top of the ones set
+    /// by the sink.
+    pub fn extra_headers(&self) -> crate::Result<HeaderMap> {
+        azure_common::config::parse_extra_headers(&self.extra_headers)
+    }
+
+    /// The options shared by the clients of the container, whichever endpoint they go through.
+    pub fn client_options(&self) -> crate::Result<ClientOptions> {
+        Ok(ClientOptions {
+            connection_string: self.connection_string.clone(),
+            storage_account: self.storage_account.clone(),
+            imds_endpoint: self.imds_endpoint()?,
+            token_broker: self.token_broker()?,
+            container_name: self.container_name.clone(),
+            force_http1: self.force_http1,
+            user_agent: self.user_agent()?,
+            extra_headers: self.extra_headers()?,
+            ..Default::default()
+        })
+    }
+
+    /// The warm-up of the token of `storage_account`, if enabled.
+    pub fn token_warm_up(&self) -> crate::Result<Option<TokenWarmUp>> {
+        match &self.token_warm_up {
+            Some(_) if self.storage_account.is_none() => {
+                Err("`token_warm_up` can only be enabled along with `storage_account`".into())
+            }
+            Some(token_warm_up) => token_warm_up.build().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Applies `gzip_level`, if set, to the configured compression.
+    pub fn compression(&self) -> crate::Result<Compression> {
+        match (self.compression, self.gzip_level) {
+            (compression, None) => Ok(compression),
+            (Compression::Gzip(_), Some(level)) if level <= 9 => {
+                Ok(Compression::Gzip(CompressionLevel::new(level)))
+            }
+            (Compression::Gzip(_), Some(level)) => {
+                Err(format!("`gzip_level` must be between 0 and 9, got {}", level).into())
+            }
+            (_, Some(_)) => Err("`gzip_level` can only be set when `compression` is `gzip`".into()),
+        }
+    }
+
     pub fn key_partitioner(&self) -> crate::Result<KeyPartitioner> {
         let blob_prefix = self
             .blob_prefix
@@ -152,6 +920,18 @@ impl AzureBlobSinkConfig {
             .cloned()
             .unwrap_or_else(|| DEFAULT_KEY_PREFIX.into())
             .try_into()?;
-        Ok(KeyPartitioner::new(blob_prefix))
+        let fallback = matches!(
+            self.unresolvable_partition,
+            Some(UnresolvablePartition::Fallback { .. })
+        );
+        let time_window = self
+            .time_window
+            .as_ref()
+            .map(TimeWindowConfig::build)
+            .transpose()?;
+        Ok(KeyPartitioner::new(blob_prefix)
+            .with_timezone(self.timezone)
+            .with_drop_on_error(!fallback)
+            .with_time_window(time_window))
     }
 }
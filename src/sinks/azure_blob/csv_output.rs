@@ -0,0 +1,183 @@
+use std::{collections::BTreeSet, io};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    codecs::Transformer,
+    event::{Event, LogEvent, Value},
+};
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv";
+
+/// The name of the sidecar holding the header of the blobs written next to it.
+pub const CSV_SCHEMA_SIDECAR: &str = "_schema.csv";
+
+const fn default_delimiter() -> char {
+    ','
+}
+
+/// What happens to the fields of the events missing from the columns.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvUnlistedFields {
+    /// The fields are left out.
+    #[derivative(Default)]
+    Drop,
+    /// The fields are written as columns of their own after the listed ones, sorted by name.
+    Append,
+}
+
+/// Where the header row naming the columns is written.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvHeader {
+    /// As the first line of each blob.
+    #[derivative(Default)]
+    FirstLine,
+    /// As a `_schema.csv` sidecar next to the blobs.
+    Sidecar,
+    /// Nowhere.
+    None,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CsvConfig {
+    /// The fields written as the columns of each row, in order.
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub unlisted_fields: CsvUnlistedFields,
+    #[serde(default)]
+    pub header: CsvHeader,
+    /// The value written for null and missing fields.
+    #[serde(default)]
+    pub null_placeholder: String,
+    /// The character separating the values of each row.
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+}
+
+impl CsvConfig {
+    pub fn build(&self) -> crate::Result<Csv> {
+        if !self.delimiter.is_ascii() || matches!(self.delimiter, '"' | '\r' | '\n') {
+            return Err(format!(
+                "`csv.delimiter` {:?} must be an ASCII character other than a quote or a line \
+                 break",
+                self.delimiter
+            )
+            .into());
+        }
+        if self.columns.is_empty() && self.unlisted_fields == CsvUnlistedFields::Drop {
+            return Err(
+                "`csv.columns` can't be empty unless `csv.unlisted_fields` is `append`".into(),
+            );
+        }
+        let mut listed = BTreeSet::new();
+        if let Some(column) = self.columns.iter().find(|column| !listed.insert(*column)) {
+            return Err(format!("`csv.columns` lists {:?} more than once", column).into());
+        }
+        // The sidecar describes every blob written next to it, so they all need the same columns.
+        if self.header == CsvHeader::Sidecar && self.unlisted_fields == CsvUnlistedFields::Append {
+            return Err(
+                "`csv.header` can't be `sidecar` when `csv.unlisted_fields` is `append`, the \
+                 columns of the blobs would vary"
+                    .into(),
+            );
+        }
+
+        Ok(Csv {
+            columns: self.columns.clone(),
+            unlisted_fields: self.unlisted_fields,
+            header: self.header,
+            null_placeholder: self.null_placeholder.clone(),
+            delimiter: self.delimiter as u8,
+        })
+    }
+}
+
+/// Writes each batch as a CSV table, with a row for each event and a column for each field.
+#[derive(Clone, Debug)]
+pub struct Csv {
+    columns: Vec<String>,
+    unlisted_fields: CsvUnlistedFields,
+    header: CsvHeader,
+    null_placeholder: String,
+    delimiter: u8,
+}
+
+impl Csv {
+    /// The content of the sidecar holding the header of the blobs, when written as a sidecar.
+    pub fn schema(&self) -> Option<Bytes> {
+        (self.header == CsvHeader::Sidecar).then(|| {
+            let mut writer = self.writer();
+            writer
+                .write_record(&self.columns)
+                .expect("writing to memory can't fail");
+            writer
+                .into_inner()
+                .expect("writing to memory can't fail")
+                .into()
+        })
+    }
+
+    /// Writes the table holding the events.
+    pub fn write(&self, events: Vec<Event>, transformer: &Transformer) -> io::Result<Vec<u8>> {
+        let logs = events
+            .into_iter()
+            .map(|mut event| {
+                transformer.transform(&mut event);
+                match event {
+                    Event::Log(log) => Ok(log),
+                    _ => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Only logs can be written as CSV",
+                    )),
+                }
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut columns = self.columns.clone();
+        if self.unlisted_fields == CsvUnlistedFields::Append {
+            // The unlisted fields of the whole batch, so that every row has the same columns.
+            let unlisted = logs
+                .iter()
+                .filter_map(LogEvent::as_map)
+                .flat_map(|fields| fields.keys())
+                .filter(|field| !self.columns.contains(field))
+                .collect::<BTreeSet<_>>();
+            columns.extend(unlisted.into_iter().cloned());
+        }
+
+        let mut writer = self.writer();
+        if self.header == CsvHeader::FirstLine {
+            writer.write_record(&columns)?;
+        }
+        for log in &logs {
+            writer.write_record(columns.iter().enumerate().map(|(index, column)| {
+                // The listed columns are paths, while the appended ones are the names of fields.
+                let value = if index < self.columns.len() {
+                    log.get(column.as_str())
+                } else {
+                    log.as_map().and_then(|fields| fields.get(column))
+                };
+                match value {
+                    None | Some(Value::Null) => self.null_placeholder.clone(),
+                    Some(value) => value.to_string_lossy(),
+                }
+            }))?;
+        }
+        writer
+            .into_inner()
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error.to_string()))
+    }
+
+    fn writer(&self) -> csv::Writer<Vec<u8>> {
+        csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(Vec::new())
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::log_schema,
+    event::{Event, LogEvent, Value},
+};
+
+fn default_meta_key() -> String {
+    "meta".to_owned()
+}
+
+fn default_event_key() -> String {
+    "event".to_owned()
+}
+
+fn default_fields() -> BTreeMap<String, EnvelopeField> {
+    BTreeMap::from([
+        ("partition".to_owned(), EnvelopeField::Partition),
+        ("received_at".to_owned(), EnvelopeField::ReceivedAt),
+        ("source_type".to_owned(), EnvelopeField::SourceType),
+    ])
+}
+
+/// The metadata written into the envelope of each event.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvelopeField {
+    /// The timestamp of the event, set when it was received.
+    ReceivedAt,
+    /// The type of the source the event was received by.
+    SourceType,
+    /// The partition of the blob the event is written to.
+    Partition,
+    /// The time the blob holding the event was encoded at.
+    EncodedAt,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EnvelopeConfig {
+    /// The key of the metadata in the envelope.
+    #[serde(default = "default_meta_key")]
+    pub meta_key: String,
+    /// The key of the event in the envelope.
+    #[serde(default = "default_event_key")]
+    pub event_key: String,
+    /// The metadata written into each envelope, by name.
+    #[serde(default = "default_fields")]
+    pub fields: BTreeMap<String, EnvelopeField>,
+}
+
+impl EnvelopeConfig {
+    pub fn build(&self) -> crate::Result<Envelope> {
+        if self.meta_key.is_empty() || self.event_key.is_empty() {
+            return Err("`envelope.meta_key` and `envelope.event_key` can't be empty".into());
+        }
+        if self.meta_key == self.event_key {
+            return Err("`envelope.meta_key` and `envelope.event_key` must differ".into());
+        }
+
+        Ok(Envelope {
+            meta_key: self.meta_key.clone(),
+            event_key: self.event_key.clone(),
+            fields: self
+                .fields
+                .iter()
+                .map(|(name, field)| (name.clone(), *field))
+                .collect(),
+        })
+    }
+}
+
+/// Wraps each event into an envelope holding its metadata next to the event, left untouched.
+#[derive(Clone, Debug)]
+pub struct Envelope {
+    meta_key: String,
+    event_key: String,
+    fields: Vec<(String, EnvelopeField)>,
+}
+
+impl Envelope {
+    /// Wraps `event`, written to `partition` in a blob encoded at `encoded_at`.
+    ///
+    /// Metadata the event has no value for, such as the source type of a metric, is left out.
+    pub fn wrap(&self, event: Event, partition: &str, encoded_at: DateTime<Utc>) -> Event {
+        let meta = self
+            .fields
+            .iter()
+            .filter_map(|(name, field)| {
+                let value = match field {
+                    EnvelopeField::ReceivedAt => match &event {
+                        Event::Log(log) => log.get(log_schema().timestamp_key()).cloned(),
+                        Event::Metric(metric) => metric.timestamp().map(Value::from),
+                        Event::Trace(trace) => trace.get(log_schema().timestamp_key()).cloned(),
+                    },
+                    EnvelopeField::SourceType => match &event {
+                        Event::Log(log) => log.get(log_schema().source_type_key()).cloned(),
+                        Event::Metric(_) => None,
+                        Event::Trace(trace) => trace.get(log_schema().source_type_key()).cloned(),
+                    },
+                    EnvelopeField::Partition => Some(Value::from(partition)),
+                    EnvelopeField::EncodedAt => Some(Value::from(encoded_at)),
+                }?;
+                Some((name.clone(), value))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let (value, metadata) = match event {
+            Event::Log(log) => log.into_parts(),
+            Event::Trace(trace) => {
+                let (fields, metadata) = trace.into_parts();
+                (Value::Object(fields), metadata)
+            }
+            Event::Metric(metric) => {
+                let value = serde_json::to_value(&metric)
+                    .map(Value::from)
+                    .unwrap_or(Value::Null);
+                (value, metric.into_parts().2)
+            }
+        };
+        let envelope = BTreeMap::from([
+            (self.meta_key.clone(), Value::Object(meta)),
+            (self.event_key.clone(), value),
+        ]);
+        Event::Log(LogEvent::from_parts(Value::Object(envelope), metadata))
+    }
+}
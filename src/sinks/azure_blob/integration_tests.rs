@@ -1,11 +1,12 @@
 use std::{
     io::{BufRead, BufReader},
     num::NonZeroU32,
+    sync::Arc,
 };
 
 use azure_core::{prelude::Range, HttpError};
 use azure_storage_blobs::prelude::*;
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use codecs::{
     encoding::FramingConfig, JsonSerializerConfig, NewlineDelimitedEncoderConfig,
     TextSerializerConfig,
@@ -17,26 +18,34 @@ use vector_core::ByteSizeOf;
 
 use super::config::AzureBlobSinkConfig;
 use crate::{
+    config::{log_schema, SourceConfig, SourceContext},
     event::{Event, EventArray, LogEvent},
     sinks::{
-        azure_common,
+        azure_common::{
+            self,
+            config::{ChecksumAlgorithm, ClientOptions},
+            janitor::JanitorConfig,
+        },
         util::{Compression, TowerRequestConfig},
         VectorSink,
     },
+    sources::azure_blob::AzureBlobSourceConfig,
     test_util::{
         components::{run_and_assert_sink_compliance, SINK_TAGS},
         random_events_with_stream, random_lines, random_lines_with_stream, random_string,
     },
+    SourceSender,
 };
 
 #[tokio::test]
 async fn azure_blob_healthcheck_passed() {
     let config = AzureBlobSinkConfig::new_emulator().await;
-    let client = azure_common::config::build_client(
-        config.connection_string,
-        None,
-        config.container_name.clone(),
-    )
+    let client = azure_common::config::build_client(ClientOptions {
+        connection_string: config.connection_string,
+        container_name: config.container_name.clone(),
+        ..Default::default()
+    })
+    .await
     .expect("Failed to create client");
 
     let response = azure_common::config::build_healthcheck(config.container_name, client);
@@ -51,11 +60,13 @@ async fn azure_blob_healthcheck_unknown_container() {
         container_name: String::from("other-container-name"),
         ..config
     };
-    let client = azure_common::config::build_client(
-        config.connection_string,
-        config.storage_account,
-        config.container_name.clone(),
-    )
+    let client = azure_common::config::build_client(ClientOptions {
+        connection_string: config.connection_string,
+        storage_account: config.storage_account,
+        container_name: config.container_name.clone(),
+        ..Default::default()
+    })
+    .await
     .expect("Failed to create client");
 
     assert_eq!(
@@ -76,7 +87,7 @@ async fn azure_blob_insert_lines_into_blob() {
         blob_prefix: Some(blob_prefix.clone()),
         ..config
     };
-    let sink = config.to_sink();
+    let sink = config.to_sink().await;
     let (lines, input) = random_lines_with_stream(100, 10, None);
 
     run_and_assert_sink_compliance(sink, input, &SINK_TAGS).await;
@@ -102,7 +113,7 @@ async fn azure_blob_insert_json_into_blob() {
             .into(),
         ..config
     };
-    let sink = config.to_sink();
+    let sink = config.to_sink().await;
     let (events, input) = random_events_with_stream(100, 10, None);
 
     run_and_assert_sink_compliance(sink, input, &SINK_TAGS).await;
@@ -130,7 +141,7 @@ async fn azure_blob_insert_lines_into_blob_gzip() {
         compression: Compression::gzip_default(),
         ..config
     };
-    let sink = config.to_sink();
+    let sink = config.to_sink().await;
     let (lines, events) = random_lines_with_stream(100, 10, None);
 
     run_and_assert_sink_compliance(sink, events, &SINK_TAGS).await;
@@ -163,7 +174,7 @@ async fn azure_blob_insert_json_into_blob_gzip() {
         compression: Compression::gzip_default(),
         ..config
     };
-    let sink = config.to_sink();
+    let sink = config.to_sink().await;
     let (events, input) = random_events_with_stream(100, 10, None);
 
     run_and_assert_sink_compliance(sink, input, &SINK_TAGS).await;
@@ -200,7 +211,7 @@ async fn azure_blob_rotate_files_after_the_buffer_size_is_reached() {
         ..config
     };
 
-    let sink = config.to_sink();
+    let sink = config.to_sink().await;
     run_and_assert_sink_compliance(sink, input, &SINK_TAGS).await;
 
     let blobs = config.list_blobs(blob_prefix.as_str()).await;
@@ -218,20 +229,182 @@ async fn azure_blob_rotate_files_after_the_buffer_size_is_reached() {
     }
 }
 
+#[tokio::test]
+async fn azure_blob_insert_lines_with_checksum_sidecar() {
+    let blob_prefix = format!("lines-sidecar/into/blob/{}", random_string(10));
+    let config = AzureBlobSinkConfig::new_emulator().await;
+    let config = AzureBlobSinkConfig {
+        blob_prefix: Some(blob_prefix.clone()),
+        sidecar_checksum: Some(ChecksumAlgorithm::Sha256),
+        ..config
+    };
+    let sink = config.to_sink().await;
+    let (_lines, input) = random_lines_with_stream(100, 10, None);
+
+    run_and_assert_sink_compliance(sink, input, &SINK_TAGS).await;
+
+    let mut blobs = config.list_blobs(blob_prefix.as_str()).await;
+    blobs.sort();
+    assert_eq!(blobs.len(), 2);
+    assert_eq!(blobs[1], format!("{}.sha256", blobs[0]));
+
+    let data = config.get_blob_bytes(blobs[0].as_str()).await;
+    let sidecar = config.get_blob_bytes(blobs[1].as_str()).await;
+    assert_eq!(
+        String::from_utf8(sidecar).unwrap(),
+        ChecksumAlgorithm::Sha256.hex_digest(&data)
+    );
+}
+
+#[tokio::test]
+async fn azure_blob_janitor_deletes_only_expired_reserved_objects() {
+    let suffix = random_string(10);
+    let reserved = format!("_vector/probe/{}", suffix);
+    let user = format!("janitor/user/{}", suffix);
+    let config = AzureBlobSinkConfig::new_emulator().await;
+    let client = azure_common::config::build_client(ClientOptions {
+        connection_string: config.connection_string.clone(),
+        storage_account: config.storage_account.clone(),
+        container_name: config.container_name.clone(),
+        ..Default::default()
+    })
+    .await
+    .expect("Failed to create client");
+
+    for blob in [&reserved, &user] {
+        client
+            .as_blob_client(blob.as_str())
+            .put_block_blob(Bytes::from_static(b"data"))
+            .execute()
+            .await
+            .expect("Failed to put blob");
+    }
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+
+    let janitor = JanitorConfig {
+        max_age_secs: 1,
+        interval_secs: 60,
+        prefixes: vec!["probe".into()],
+        batch_deletes: false,
+    }
+    .build(
+        client,
+        TowerRequestConfig::default().unwrap_with(&Default::default()),
+    )
+    .expect("Failed to build janitor");
+    janitor.sweep().await;
+
+    assert!(config.list_blobs(reserved.as_str()).await.is_empty());
+    assert_eq!(config.list_blobs(user.as_str()).await, vec![user.clone()]);
+}
+
+#[tokio::test]
+async fn azure_blob_source_reads_back_written_blobs() {
+    let blob_prefix = format!("replay/from/blob/{}/", random_string(10));
+    let config = AzureBlobSinkConfig::new_emulator().await;
+    let config = AzureBlobSinkConfig {
+        blob_prefix: Some(blob_prefix.clone()),
+        compression: Compression::gzip_default(),
+        ..config
+    };
+    let sink = config.to_sink().await;
+    let (lines, input) = random_lines_with_stream(100, 10, None);
+
+    run_and_assert_sink_compliance(sink, input, &SINK_TAGS).await;
+
+    let data_dir = tempfile::tempdir().unwrap();
+    let source_config = AzureBlobSourceConfig {
+        connection_string: config.connection_string.clone(),
+        container_name: config.container_name.clone(),
+        blob_prefix,
+        data_dir: Some(data_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let replay = || async {
+        let (out, events) = SourceSender::new_test();
+        source_config
+            .build(SourceContext::new_test(out, None))
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        events
+            .map(|event| event.as_log()[log_schema().message_key()].to_string_lossy())
+            .collect::<Vec<_>>()
+            .await
+    };
+
+    assert_eq!(replay().await, lines);
+    // The blobs already read aren't read again.
+    assert!(replay().await.is_empty());
+}
+
 impl AzureBlobSinkConfig {
     pub async fn new_emulator() -> AzureBlobSinkConfig {
         let address = std::env::var("AZURE_ADDRESS").unwrap_or_else(|_| "localhost".into());
         let config = AzureBlobSinkConfig {
                 connection_string: Some(format!("UseDevelopmentStorage=true;DefaultEndpointsProtocol=http;AccountName=devstoreaccount1;AccountKey=Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==;BlobEndpoint=http://{}:10000/devstoreaccount1;QueueEndpoint=http://{}:10001/devstoreaccount1;TableEndpoint=http://{}:10002/devstoreaccount1;", address, address, address)),
                 storage_account: None,
+                imds_endpoint: None,
                 container_name: "logs".to_string(),
                 blob_prefix: None,
                 blob_time_format: None,
                 blob_append_uuid: None,
+                timezone: None,
                 encoding: (None::<FramingConfig>, TextSerializerConfig::new()).into(),
                 compression: Compression::None,
+                gzip_level: None,
                 batch: Default::default(),
                 request: TowerRequestConfig::default(),
+                janitor: None,
+                sidecar_checksum: None,
+                batch_summary_interval_secs: None,
+                coalesce: None,
+                unresolvable_partition: None,
+                force_http1: false,
+                event_time_metadata: false,
+                content_type_sniffing: None,
+                load_shedding: None,
+                source_type_metadata: true,
+                verify_write: false,
+                encoding_on_error: Default::default(),
+                overwrite: Default::default(),
+                user_agent: None,
+                extra_headers: Default::default(),
+                concurrency_mode: Default::default(),
+                passthrough: None,
+                time_window: None,
+                token_warm_up: None,
+                avro_ocf: None,
+                hash_bucket: None,
+                token_broker: None,
+                write_receipts: false,
+                create_missing_container: false,
+                fan_out: None,
+                zstd: None,
+                in_flight_limit: None,
+                batch_metadata: Default::default(),
+                flush_signal: None,
+                success_marker: None,
+                write_endpoint: None,
+                read_endpoint: None,
+                max_batch_age_before_send_secs: None,
+                envelope: None,
+                slow_start: None,
+                blob_type: None,
+                ordering_key: false,
+                precompressed: None,
+                batch_acknowledgements: false,
+                csv: None,
+                drain_on_reload: true,
+                schema_fingerprint: None,
+                schema_version: None,
+                encoding_pool_size: None,
+                acknowledgement_provenance: false,
+                content_language: None,
+                sealed_batch: None,
+                max_inflight_bytes: None,
+                blob_path_policy: Default::default(),
                 acknowledgements: Default::default(),
             };
 
@@ -240,23 +413,28 @@ impl AzureBlobSinkConfig {
         config
     }
 
-    pub fn to_sink(&self) -> VectorSink {
-        let client = azure_common::config::build_client(
-            self.connection_string.clone(),
-            self.storage_account.clone(),
-            self.container_name.clone(),
-        )
+    pub async fn to_sink(&self) -> VectorSink {
+        let client = azure_common::config::build_client(ClientOptions {
+            connection_string: self.connection_string.clone(),
+            storage_account: self.storage_account.clone(),
+            container_name: self.container_name.clone(),
+            ..Default::default()
+        })
+        .await
         .expect("Failed to create client");
 
-        self.build_processor(client).expect("Failed to create sink")
+        self.build_processor(Arc::clone(&client), client)
+            .expect("Failed to create sink")
     }
 
     pub async fn list_blobs(&self, prefix: &str) -> Vec<String> {
-        let client = azure_common::config::build_client(
-            self.connection_string.clone(),
-            self.storage_account.clone(),
-            self.container_name.clone(),
-        )
+        let client = azure_common::config::build_client(ClientOptions {
+            connection_string: self.connection_string.clone(),
+            storage_account: self.storage_account.clone(),
+            container_name: self.container_name.clone(),
+            ..Default::default()
+        })
+        .await
         .unwrap();
         let response = client
             .list_blobs()
@@ -279,11 +457,13 @@ impl AzureBlobSinkConfig {
     }
 
     pub async fn get_blob(&self, blob: String) -> (Blob, Vec<String>) {
-        let client = azure_common::config::build_client(
-            self.connection_string.clone(),
-            self.storage_account.clone(),
-            self.container_name.clone(),
-        )
+        let client = azure_common::config::build_client(ClientOptions {
+            connection_string: self.connection_string.clone(),
+            storage_account: self.storage_account.clone(),
+            container_name: self.container_name.clone(),
+            ..Default::default()
+        })
+        .await
         .unwrap();
         let response = client
             .as_blob_client(blob.as_str())
@@ -296,6 +476,26 @@ impl AzureBlobSinkConfig {
         (response.blob, self.get_blob_content(response.data.to_vec()))
     }
 
+    pub async fn get_blob_bytes(&self, blob: &str) -> Vec<u8> {
+        let client = azure_common::config::build_client(ClientOptions {
+            connection_string: self.connection_string.clone(),
+            storage_account: self.storage_account.clone(),
+            container_name: self.container_name.clone(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let response = client
+            .as_blob_client(blob)
+            .get()
+            .range(Range::new(0, 1024 * 1024))
+            .execute()
+            .await
+            .expect("Failed to get blob");
+
+        response.data.to_vec()
+    }
+
     fn get_blob_content(&self, data: Vec<u8>) -> Vec<String> {
         let body = BytesMut::from(data.as_slice()).freeze().reader();
 
@@ -310,11 +510,13 @@ impl AzureBlobSinkConfig {
     }
 
     async fn ensure_container(&self) {
-        let client = azure_common::config::build_client(
-            self.connection_string.clone(),
-            self.storage_account.clone(),
-            self.container_name.clone(),
-        )
+        let client = azure_common::config::build_client(ClientOptions {
+            connection_string: self.connection_string.clone(),
+            storage_account: self.storage_account.clone(),
+            container_name: self.container_name.clone(),
+            ..Default::default()
+        })
+        .await
         .unwrap();
         let request = client.create().public_access(PublicAccess::None).execute();
 
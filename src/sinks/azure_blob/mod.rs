@@ -1,5 +1,13 @@
+mod avro_ocf;
 mod config;
+mod csv_output;
+mod envelope;
+mod ordering;
+mod passthrough;
 mod request_builder;
+mod sniff;
+mod time_window;
+mod zstd_dictionary;
 
 #[cfg(feature = "azure-blob-integration-tests")]
 #[cfg(test)]
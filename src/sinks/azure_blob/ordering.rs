@@ -0,0 +1,39 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use chrono::{DateTime, Utc};
+
+/// The width the sequence of the ordering keys is zero-padded to, which fits any positive `i64`.
+const SEQUENCE_WIDTH: usize = 19;
+
+/// Hands out the ordering keys starting the names of the blobs, which sort in the order they were
+/// handed out.
+///
+/// A key is made of a sequence followed by the time it was handed out at, to the microsecond. The
+/// sequence is the number of microseconds since the Unix epoch, bumped past the previous one
+/// whenever several keys are handed out within the same microsecond. Being derived from the clock,
+/// it keeps going up across restarts, as long as the clock doesn't go back in between.
+#[derive(Debug, Default)]
+pub struct OrderingKeys {
+    last_sequence: AtomicI64,
+}
+
+impl OrderingKeys {
+    /// Returns the key of a blob named at `now`.
+    pub fn next(&self, now: DateTime<Utc>) -> String {
+        let micros = now.timestamp_nanos() / 1_000;
+        let previous = self
+            .last_sequence
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(micros.max(last + 1))
+            })
+            .expect("the update always succeeds");
+        let sequence = micros.max(previous + 1);
+
+        format!(
+            "{:0width$}-{}",
+            sequence,
+            now.format("%Y%m%dT%H%M%S%.6fZ"),
+            width = SEQUENCE_WIDTH
+        )
+    }
+}
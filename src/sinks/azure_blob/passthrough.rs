@@ -0,0 +1,86 @@
+use bytes::Bytes;
+use http::HeaderValue;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::log_schema,
+    event::{Event, Value},
+};
+
+fn default_field() -> String {
+    log_schema().message_key().to_owned()
+}
+
+fn default_content_type() -> String {
+    "application/octet-stream".to_owned()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PassthroughConfig {
+    /// The field of each event holding the bytes written as its blob.
+    #[serde(default = "default_field")]
+    pub field: String,
+    /// The field of each event holding the content type of its blob.
+    pub content_type_field: Option<String>,
+    /// The content type of the blobs whose event has no valid `content_type_field`.
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
+}
+
+impl PassthroughConfig {
+    pub fn build(&self) -> crate::Result<Passthrough> {
+        if let Err(error) = HeaderValue::from_str(&self.content_type) {
+            return Err(format!(
+                "Invalid `passthrough.content_type` {:?}: {}",
+                self.content_type, error
+            )
+            .into());
+        }
+
+        Ok(Passthrough {
+            field: self.field.clone(),
+            content_type_field: self.content_type_field.clone(),
+            content_type: self.content_type.clone(),
+        })
+    }
+}
+
+/// Writes the bytes of a field of each event as is, as a blob of its own.
+#[derive(Clone, Debug)]
+pub struct Passthrough {
+    field: String,
+    content_type_field: Option<String>,
+    content_type: String,
+}
+
+impl Passthrough {
+    /// Returns the payload of the blob written for `event`.
+    pub fn payload(&self, event: &Event) -> Result<Bytes, String> {
+        match event
+            .maybe_as_log()
+            .and_then(|log| log.get(self.field.as_str()))
+        {
+            Some(Value::Bytes(bytes)) => Ok(bytes.clone()),
+            Some(value) => Err(format!(
+                "Field {:?} holds {} instead of bytes",
+                self.field,
+                value.kind_str()
+            )),
+            None => Err(format!("Field {:?} is missing", self.field)),
+        }
+    }
+
+    /// Returns the content type of the blob written for `event`.
+    ///
+    /// Content types that aren't valid header values are ignored, as the blob couldn't be written
+    /// with them.
+    pub fn content_type(&self, event: &Event) -> String {
+        self.content_type_field
+            .as_deref()
+            .and_then(|field| event.maybe_as_log()?.get(field))
+            .map(Value::to_string_lossy)
+            .filter(|content_type| HeaderValue::from_str(content_type).is_ok())
+            .unwrap_or_else(|| self.content_type.clone())
+    }
+}
@@ -1,18 +1,50 @@
+use std::{borrow::Cow, io, io::Write, sync::Arc};
+
 use bytes::Bytes;
 use chrono::Utc;
 use codecs::encoding::Framer;
 use uuid::Uuid;
 use vector_core::ByteSizeOf;
 
+use super::{
+    avro_ocf::{AvroOcf, AVRO_CONTENT_TYPE},
+    csv_output::{Csv, CSV_CONTENT_TYPE},
+    envelope::Envelope,
+    ordering::OrderingKeys,
+    passthrough::Passthrough,
+    sniff::{blob_head, sniff_content_type},
+    zstd_dictionary::{Zstd, ZstdDictionary, ZSTD_CONTENT_TYPE},
+};
 use crate::{
     codecs::{Encoder, Transformer},
     event::{Event, Finalizable},
+    internal_events::azure_blob::AzureBlobEncodingError,
     sinks::{
-        azure_common::config::{AzureBlobMetadata, AzureBlobRequest},
-        util::{request_builder::EncodeResult, Compression, RequestBuilder},
+        azure_common::{
+            blob_type::{BlobType, BlobTypeRouter},
+            computed_metadata::ComputedMetadata,
+            config::{
+                event_time_range, source_types, AzureBlobMetadata, AzureBlobRequest,
+                EncodingErrorPolicy,
+            },
+            content_language::ContentLanguages,
+            fingerprint::SchemaFingerprints,
+            precompressed::{self, Precompressed},
+            schema_version::SchemaVersions,
+        },
+        util::{
+            encoding::Encoder as _, request_builder::EncodeResult, Compression, Compressor,
+            RequestBuilder,
+        },
     },
 };
 
+/// The content type InfluxDB expects line protocol to be written with.
+const LINE_PROTOCOL_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// The content type of the blobs already compressed, unless known otherwise.
+const PRECOMPRESSED_CONTENT_TYPE: &str = "application/octet-stream";
+
 #[derive(Clone)]
 pub struct AzureBlobRequestOptions {
     pub container_name: String,
@@ -20,15 +52,65 @@ pub struct AzureBlobRequestOptions {
     pub blob_append_uuid: bool,
     pub encoder: (Transformer, Encoder<Framer>),
     pub compression: Compression,
+    pub event_time_metadata: bool,
+    /// Number of leading bytes inspected to guess the content type of each blob, if enabled.
+    pub content_type_sniffing: Option<usize>,
+    pub source_type_metadata: bool,
+    /// Whether the blobs hold InfluxDB line protocol, which gets its own extension.
+    pub line_protocol: bool,
+    /// Writes a field of the events as is instead of encoding them, when set.
+    pub passthrough: Option<Passthrough>,
+    /// Writes each batch as an Avro Object Container File instead of encoding its events, when
+    /// set.
+    pub avro_ocf: Option<AvroOcf>,
+    /// Compresses the blobs with zstd, using the dictionary of their partition, when set.
+    pub zstd: Option<Zstd>,
+    /// The metadata computed from each batch as a whole, if any.
+    pub batch_metadata: Option<ComputedMetadata>,
+    /// Whether the timestamp of the oldest event of each batch is recorded, for stale batches to
+    /// be dropped before they are written.
+    pub track_batch_age: bool,
+    /// Wraps each event into an envelope holding its metadata before encoding it, when set.
+    pub envelope: Option<Envelope>,
+    /// Picks the type of blob of each batch from its events, which are all written to blobs of
+    /// the same type. Batches are written to block blobs when unset.
+    pub blob_type: Option<BlobTypeRouter>,
+    /// Starts the names of the blobs with keys sorting in the order they were named, in place of
+    /// the time formatted with `blob_time_format`, when set.
+    pub ordering_keys: Option<Arc<OrderingKeys>>,
+    /// Writes the batches whose events are already compressed without compressing them again,
+    /// when set.
+    pub precompressed: Option<Precompressed>,
+    /// Whether the finalizers of each batch are merged by source batch and resolved by the sink
+    /// once the batch is written, rather than one by one by the driver.
+    pub batch_acknowledgements: bool,
+    /// Writes each batch as a CSV table instead of encoding its events, when set.
+    pub csv: Option<Csv>,
+    /// Records the fingerprint of the schema the events of each batch were grouped by in the
+    /// metadata of its blob, when set.
+    pub schema_fingerprints: Option<SchemaFingerprints>,
+    /// Records the version of the schema of the events of each batch in the metadata of its
+    /// blob, when set.
+    pub schema_versions: Option<SchemaVersions>,
+    /// Sets the `Content-Language` of each blob from its events, when set.
+    pub content_languages: Option<ContentLanguages>,
 }
 
 impl RequestBuilder<(String, Vec<Event>)> for AzureBlobRequestOptions {
-    type Metadata = AzureBlobMetadata;
-    type Events = Vec<Event>;
+    /// The metadata of the batch along with the content type and the content encoding its events
+    /// are already compressed with, if any.
+    type Metadata = (
+        AzureBlobMetadata,
+        Option<Cow<'static, str>>,
+        Option<&'static str>,
+    );
+    /// The events along with their partition, the dictionary it is compressed with and whether
+    /// they are already compressed.
+    type Events = (Option<Arc<ZstdDictionary>>, bool, String, Vec<Event>);
     type Encoder = (Transformer, Encoder<Framer>);
     type Payload = Bytes;
     type Request = AzureBlobRequest;
-    type Error = std::io::Error;
+    type Error = io::Error;
 
     fn compression(&self) -> Compression {
         self.compression
@@ -40,52 +122,254 @@ impl RequestBuilder<(String, Vec<Event>)> for AzureBlobRequestOptions {
 
     fn split_input(&self, input: (String, Vec<Event>)) -> (Self::Metadata, Self::Events) {
         let (partition_key, mut events) = input;
-        let finalizers = events.take_finalizers();
+        let mut finalizers = events.take_finalizers();
+        if self.batch_acknowledgements {
+            finalizers.compact();
+        }
+        // The batches are split by content encoding beforehand, so the first event stands for
+        // the whole batch.
+        let content_encoding = match (&self.precompressed, events.first()) {
+            (Some(precompressed), Some(event)) => precompressed.content_encoding(event),
+            _ => None,
+        };
+        let dictionary = self
+            .zstd
+            .as_ref()
+            .filter(|_| content_encoding.is_none())
+            .and_then(|zstd| zstd.dictionary(&partition_key));
+        let byte_size = events.size_of();
+        let partition = partition_key.clone();
         let metadata = AzureBlobMetadata {
+            partition: partition_key.clone(),
             partition_key,
             count: events.len(),
-            byte_size: events.size_of(),
+            byte_size,
             finalizers,
+            event_time_range: self
+                .event_time_metadata
+                .then(|| event_time_range(&events))
+                .flatten(),
+            source_types: self
+                .source_type_metadata
+                .then(|| source_types(&events))
+                .flatten(),
+            zstd_dictionary_id: dictionary.as_ref().map(|dictionary| dictionary.id()),
+            computed_metadata: self
+                .batch_metadata
+                .as_ref()
+                .map(|batch_metadata| batch_metadata.render(&events, byte_size))
+                .unwrap_or_default(),
+            oldest_event_time: self
+                .track_batch_age
+                .then(|| event_time_range(&events).map(|(oldest, _)| oldest))
+                .flatten(),
+            blob_type: match (&self.blob_type, events.first()) {
+                (Some(router), Some(event)) => router.resolve(event),
+                _ => BlobType::Block,
+            },
+            // The batches are grouped by fingerprint beforehand, so the first event stands for
+            // the whole batch.
+            schema_fingerprint: match (&self.schema_fingerprints, events.first()) {
+                (Some(fingerprints), Some(event)) => Some(fingerprints.lookup(event)),
+                _ => None,
+            },
+            // Likewise for the versions, which are only recorded when the events hold one.
+            schema_version: match (&self.schema_versions, events.first()) {
+                (Some(versions), Some(event)) => versions.version(event),
+                _ => None,
+            },
+            // Numbered once handed on in order, when the appends are sequenced.
+            append_sequence: None,
+            content_language: self
+                .content_languages
+                .as_ref()
+                .and_then(|languages| languages.language(&events)),
+        };
+        let content_type = match &self.passthrough {
+            Some(passthrough) => events
+                .first()
+                .map(|event| passthrough.content_type(event).into()),
+            None => self
+                .content_type_sniffing
+                .and_then(|max_bytes| blob_head(&events, max_bytes))
+                .and_then(|head| sniff_content_type(&head))
+                .map(Cow::Borrowed),
+        };
+
+        let precompressed = content_encoding.is_some();
+        (
+            (metadata, content_type, content_encoding),
+            (dictionary, precompressed, partition, events),
+        )
+    }
+
+    fn encode_events(
+        &self,
+        (dictionary, precompressed, partition, events): Self::Events,
+    ) -> Result<EncodeResult<Self::Payload>, Self::Error> {
+        let count = events.len();
+        let compression = if precompressed {
+            Compression::None
+        } else {
+            self.compression
+        };
+        let mut compressor = Compressor::from(compression);
+        let written = if let Some(passthrough) = &self.passthrough {
+            write_passthrough(passthrough, &events, &mut compressor)
+        } else if let Some(avro_ocf) = &self.avro_ocf {
+            avro_ocf.write(events, &self.encoder.0).and_then(|file| {
+                compressor.write_all(&file)?;
+                Ok(file.len())
+            })
+        } else if let Some(csv) = &self.csv {
+            csv.write(events, &self.encoder.0).and_then(|table| {
+                compressor.write_all(&table)?;
+                Ok(table.len())
+            })
+        } else if let Some(envelope) = &self.envelope {
+            // The events are transformed before being wrapped, so that the fields of the
+            // transformer keep applying to the events rather than to their envelopes.
+            let encoded_at = Utc::now();
+            let events = events
+                .into_iter()
+                .map(|mut event| {
+                    self.encoder.0.transform(&mut event);
+                    envelope.wrap(event, &partition, encoded_at)
+                })
+                .collect::<Vec<_>>();
+            (Transformer::default(), self.encoder.1.clone()).encode_input(events, &mut compressor)
+        } else {
+            self.encoder.encode_input(events, &mut compressor)
         };
+        let uncompressed_byte_size = written.map_err(|error| {
+            emit!(AzureBlobEncodingError {
+                policy: EncodingErrorPolicy::DropBatch.as_str(),
+                count,
+                error: error.to_string(),
+            });
+            error
+        })?;
 
-        (metadata, events)
+        // Finishing the compressor flushes the pending output and writes the trailer, so every
+        // blob is a single complete gzip member that can be concatenated with others as is.
+        let payload = compressor.finish()?.freeze();
+        Ok(match &self.zstd {
+            // The compressor is disabled along with zstd, so the payload is compressed as a whole.
+            Some(zstd) if !precompressed => EncodeResult::compressed(
+                zstd.compress(&payload, dictionary.as_deref())?.into(),
+                uncompressed_byte_size,
+            ),
+            _ if compression.is_compressed() => {
+                EncodeResult::compressed(payload, uncompressed_byte_size)
+            }
+            _ => EncodeResult::uncompressed(payload),
+        })
     }
 
     fn build_request(
         &self,
-        mut metadata: Self::Metadata,
+        (mut metadata, content_type, precompressed_encoding): Self::Metadata,
         payload: EncodeResult<Self::Payload>,
     ) -> Self::Request {
         let blob_name = {
-            let formatted_ts = Utc::now().format(self.blob_time_format.as_str());
+            let now = Utc::now();
+            let formatted_ts = match &self.ordering_keys {
+                Some(ordering_keys) => ordering_keys.next(now),
+                None => now.format(self.blob_time_format.as_str()).to_string(),
+            };
 
-            self.blob_append_uuid
+            // Every batch of the partition is appended to the same blob until the time format
+            // rolls it over.
+            (self.blob_append_uuid && metadata.blob_type == BlobType::Block)
                 .then(|| format!("{}-{}", formatted_ts, Uuid::new_v4().hyphenated()))
-                .unwrap_or_else(|| formatted_ts.to_string())
+                .unwrap_or(formatted_ts)
         };
 
-        let extension = self.compression.extension();
+        let extension = if let Some(encoding) = precompressed_encoding {
+            precompressed::extension(encoding)
+        } else if self.zstd.is_some() {
+            if self.line_protocol {
+                "lp.zst"
+            } else if self.csv.is_some() {
+                "csv.zst"
+            } else {
+                "zst"
+            }
+        } else if self.line_protocol {
+            self.compression.line_protocol_extension()
+        } else if self.csv.is_some() {
+            self.compression.csv_extension()
+        } else if self.avro_ocf.is_some() {
+            // The data blocks of the files are compressed, the files themselves aren't.
+            "avro"
+        } else {
+            self.compression.extension()
+        };
         metadata.partition_key = format!("{}{}.{}", metadata.partition_key, blob_name, extension);
 
         let payload = payload.into_payload();
+        let batched_finalizers = if self.batch_acknowledgements {
+            std::mem::take(&mut metadata.finalizers)
+        } else {
+            Default::default()
+        };
 
         debug!(
             message = "Sending events.",
             bytes = ?payload.len(),
             events_len = ?metadata.count,
             blob = ?metadata.partition_key,
+            blob_type = metadata.blob_type.as_str(),
             container = ?self.container_name,
         );
 
         AzureBlobRequest {
             blob_data: payload,
-            content_encoding: self.compression.content_encoding(),
-            content_type: self.compression.content_type(),
+            content_encoding: match (precompressed_encoding, &self.zstd) {
+                (Some(encoding), _) => Some(encoding),
+                (None, Some(_)) => Some("zstd"),
+                (None, None) => self.compression.content_encoding(),
+            },
+            content_type: content_type.unwrap_or_else(|| {
+                // The content type describes the payloads once decoded, which is unknown.
+                if precompressed_encoding.is_some() {
+                    return PRECOMPRESSED_CONTENT_TYPE.into();
+                }
+                match self.compression {
+                    Compression::None if self.zstd.is_some() => ZSTD_CONTENT_TYPE,
+                    Compression::None if self.line_protocol => LINE_PROTOCOL_CONTENT_TYPE,
+                    Compression::None if self.avro_ocf.is_some() => AVRO_CONTENT_TYPE,
+                    Compression::None if self.csv.is_some() => CSV_CONTENT_TYPE,
+                    compression => compression.content_type(),
+                }
+                .into()
+            }),
             metadata,
+            attempts: Default::default(),
+            partition_permit: Default::default(),
+            batched_finalizers,
+            finalizers_taken: false,
         }
     }
 }
 
+/// Writes the payloads of the events one after the other, without any framing.
+fn write_passthrough(
+    passthrough: &Passthrough,
+    events: &[Event],
+    writer: &mut dyn Write,
+) -> io::Result<usize> {
+    let mut written = 0;
+    for event in events {
+        let payload = passthrough
+            .payload(event)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        writer.write_all(&payload)?;
+        written += payload.len();
+    }
+    Ok(written)
+}
+
 impl Compression {
     pub const fn content_type(self) -> &'static str {
         match self {
@@ -94,4 +378,20 @@ impl Compression {
             Self::Zlib(_) => "application/zlib",
         }
     }
+
+    pub const fn line_protocol_extension(self) -> &'static str {
+        match self {
+            Self::None => "lp",
+            Self::Gzip(_) => "lp.gz",
+            Self::Zlib(_) => "lp.zz",
+        }
+    }
+
+    pub const fn csv_extension(self) -> &'static str {
+        match self {
+            Self::None => "csv",
+            Self::Gzip(_) => "csv.gz",
+            Self::Zlib(_) => "csv.zz",
+        }
+    }
 }
@@ -0,0 +1,49 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::log_schema, event::Event};
+
+const fn default_max_bytes() -> usize {
+    512
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ContentTypeSniffingConfig {
+    /// How many bytes at the start of each blob are inspected.
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: usize,
+}
+
+impl ContentTypeSniffingConfig {
+    pub fn build(&self) -> crate::Result<usize> {
+        if self.max_bytes == 0 {
+            return Err("`content_type_sniffing.max_bytes` must be greater than zero".into());
+        }
+        Ok(self.max_bytes)
+    }
+}
+
+/// Returns the first `max_bytes` bytes of the blob the raw message codec writes for `events`.
+///
+/// The raw message codec writes the messages as is, so the blob starts with the message of the
+/// first event.
+pub fn blob_head(events: &[Event], max_bytes: usize) -> Option<Bytes> {
+    let message = events
+        .first()?
+        .maybe_as_log()?
+        .get(log_schema().message_key())?
+        .coerce_to_bytes();
+    Some(message.slice(..message.len().min(max_bytes)))
+}
+
+/// Guesses the MIME type of a payload from its leading bytes.
+pub fn sniff_content_type(head: &[u8]) -> Option<&'static str> {
+    if let Some(kind) = infer::get(head) {
+        return Some(kind.mime_type());
+    }
+    match head.iter().find(|byte| !byte.is_ascii_whitespace()) {
+        Some(b'{' | b'[') => Some("application/json"),
+        _ => None,
+    }
+}
@@ -1,34 +1,205 @@
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    io::{Read, Write},
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration as StdDuration, Instant},
+};
+
 use bytes::Bytes;
-use chrono::Utc;
+use chrono::{Duration, TimeZone as _, Utc};
 use codecs::{
     encoding::{Framer, FramingConfig},
-    NewlineDelimitedEncoder, TextSerializer, TextSerializerConfig,
+    BytesEncoder, GelfSerializerConfig, JsonSerializer, JsonSerializerConfig,
+    LineProtocolSerializer, LineProtocolSerializerConfig, NewlineDelimitedEncoder,
+    RawMessageSerializer, RawMessageSerializerConfig, TextSerializer, TextSerializerConfig,
+};
+use flate2::read::{GzDecoder, MultiGzDecoder};
+use futures::{stream, StreamExt};
+use http::HeaderMap;
+use hyper::{server::conn::Http, service::service_fn, Body, Response};
+use openssl::ssl::{select_next_proto, AlpnError, Ssl, SslAcceptor, SslFiletype, SslMethod};
+use tokio::net::TcpListener;
+use tokio_openssl::SslStream;
+use tokio_util::sync::CancellationToken;
+use tower::{Service, ServiceBuilder, ServiceExt};
+use vector_common::{btreemap, TimeZone};
+use vector_core::{
+    partition::Partitioner,
+    sink::StreamSink,
+    stream::{BatcherSettings, DriverResponse},
+    ByteSizeOf,
+};
+use wiremock::{
+    matchers::{header, method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
 };
-use vector_core::partition::Partitioner;
 
+use super::avro_ocf::{AvroOcfCodec, AvroOcfConfig};
 use super::config::AzureBlobSinkConfig;
+use super::csv_output::{CsvConfig, CsvHeader, CsvUnlistedFields, CSV_CONTENT_TYPE};
+use super::envelope::{EnvelopeConfig, EnvelopeField};
+use super::ordering::OrderingKeys;
+use super::passthrough::PassthroughConfig;
 use super::request_builder::AzureBlobRequestOptions;
+use super::sniff::ContentTypeSniffingConfig;
+use super::time_window::TimeWindowConfig;
+use super::zstd_dictionary::{ZstdConfig, ZstdDictionaryConfig};
 use crate::codecs::EncodingConfigWithFraming;
-use crate::event::{Event, LogEvent};
-use crate::sinks::util::{request_builder::RequestBuilder, Compression};
-use crate::{codecs::Encoder, sinks::util::request_builder::EncodeResult};
+use crate::config::{log_schema, DataType, SinkConfig};
+use crate::event::{
+    BatchNotifier, BatchStatus, Event, EventStatus, Finalizable, LogEvent, Metric, MetricKind,
+    MetricValue, Value,
+};
+use crate::sinks::{
+    azure_common::{
+        acknowledgements::AcknowledgementService,
+        blob_path::BlobPathPolicy,
+        blob_type::{
+            AppendOrdering, BlobType, BlobTypeConfig, MAX_APPEND_BLOCK_BYTES, MAX_PUT_BLOB_BYTES,
+        },
+        broker::TokenBrokerConfig,
+        bucket::{BucketedPartitioner, HashBucketConfig},
+        cancellation::CancellationService,
+        computed_metadata::{count_bucket, ComputedMetadata},
+        config::{
+            build_client, build_credential, build_healthcheck, default_user_agent,
+            http_client_builder, parse_user_agent, AzureBlobMetadata, AzureBlobRequest,
+            AzureBlobRetryLogic, EncodingErrorPolicy, HealthcheckError, OverwritePolicy,
+            UnresolvablePartition, MAX_SOURCE_TYPES_LEN,
+        },
+        container::{ContainerGuard, MAX_CONTAINER_NOT_FOUND},
+        content_language::ContentLanguageConfig,
+        encoding::EncodingErrorHandler,
+        fingerprint::{fingerprint, SchemaFingerprintConfig},
+        flush::FlushTrigger,
+        janitor::ReservedPrefixGuard,
+        precompressed::PrecompressedConfig,
+        receipt::{BlobProvenance, WriteReceipt},
+        schema::SchemaSidecar,
+        schema_version::SchemaVersionConfig,
+        service::AzureBlobService,
+        sink::AzureBlobSink,
+        test_util::{
+            counter_value, mock_append_response, mock_blob_exists_response, mock_client,
+            mock_connection_string, mock_container_properties_response, mock_not_found_response,
+            mock_properties_response, mock_put_response, mock_request, received_puts, MOCK_ETAG,
+        },
+    },
+    util::{request_builder::RequestBuilder, Compression, ServiceBuilderExt, TowerRequestConfig},
+};
+use crate::template::Template;
+use crate::tls::{TEST_PEM_CRT_PATH, TEST_PEM_KEY_PATH};
+use crate::{
+    codecs::{Encoder, Transformer},
+    sinks::util::request_builder::EncodeResult,
+};
 
 fn default_config(encoding: EncodingConfigWithFraming) -> AzureBlobSinkConfig {
     AzureBlobSinkConfig {
         connection_string: Default::default(),
         storage_account: Default::default(),
+        imds_endpoint: None,
         container_name: Default::default(),
         blob_prefix: Default::default(),
         blob_time_format: Default::default(),
         blob_append_uuid: Default::default(),
+        timezone: None,
         encoding,
         compression: Compression::gzip_default(),
+        gzip_level: None,
         batch: Default::default(),
         request: Default::default(),
+        janitor: None,
+        sidecar_checksum: None,
+        batch_summary_interval_secs: None,
+        coalesce: None,
+        unresolvable_partition: None,
+        force_http1: false,
+        event_time_metadata: false,
+        content_type_sniffing: None,
+        load_shedding: None,
+        source_type_metadata: true,
+        verify_write: false,
+        encoding_on_error: Default::default(),
+        overwrite: Default::default(),
+        user_agent: None,
+        extra_headers: BTreeMap::new(),
+        concurrency_mode: Default::default(),
+        passthrough: None,
+        time_window: None,
+        token_warm_up: None,
+        avro_ocf: None,
+        hash_bucket: None,
+        token_broker: None,
+        write_receipts: false,
+        create_missing_container: false,
+        fan_out: None,
+        zstd: None,
+        in_flight_limit: None,
+        batch_metadata: Default::default(),
+        flush_signal: None,
+        success_marker: None,
+        write_endpoint: None,
+        read_endpoint: None,
+        max_batch_age_before_send_secs: None,
+        envelope: None,
+        slow_start: None,
+        blob_type: None,
+        ordering_key: false,
+        precompressed: None,
+        batch_acknowledgements: false,
+        csv: None,
+        drain_on_reload: true,
+        schema_fingerprint: None,
+        schema_version: None,
+        encoding_pool_size: None,
+        acknowledgement_provenance: false,
+        content_language: None,
+        sealed_batch: None,
+        max_inflight_bytes: None,
+        blob_path_policy: Default::default(),
         acknowledgements: Default::default(),
     }
 }
 
+/// Request options writing the events as lines of text to the `logs` container, as they are.
+fn request_options() -> AzureBlobRequestOptions {
+    AzureBlobRequestOptions {
+        container_name: "logs".into(),
+        blob_time_format: "".into(),
+        blob_append_uuid: false,
+        encoder: (
+            Default::default(),
+            Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::new().into(),
+                TextSerializer::new().into(),
+            ),
+        ),
+        compression: Compression::None,
+        event_time_metadata: false,
+        content_type_sniffing: None,
+        source_type_metadata: false,
+        line_protocol: false,
+        passthrough: None,
+        avro_ocf: None,
+        zstd: None,
+        batch_metadata: None,
+        track_batch_age: false,
+        envelope: None,
+        blob_type: None,
+        ordering_keys: None,
+        precompressed: None,
+        batch_acknowledgements: false,
+        csv: None,
+        schema_fingerprints: None,
+        schema_versions: None,
+        content_languages: None,
+    }
+}
+
 #[test]
 fn generate_config() {
     crate::test_util::test_generate_config::<AzureBlobSinkConfig>();
@@ -57,14 +228,8 @@ fn azure_blob_build_request_without_compression() {
         container_name,
         blob_time_format,
         blob_append_uuid,
-        encoder: (
-            Default::default(),
-            Encoder::<Framer>::new(
-                NewlineDelimitedEncoder::new().into(),
-                TextSerializer::new().into(),
-            ),
-        ),
         compression,
+        ..request_options()
     };
 
     let (metadata, _events) = request_options.split_input((key, vec![log]));
@@ -98,14 +263,8 @@ fn azure_blob_build_request_with_compression() {
         container_name,
         blob_time_format,
         blob_append_uuid,
-        encoder: (
-            Default::default(),
-            Encoder::<Framer>::new(
-                NewlineDelimitedEncoder::new().into(),
-                TextSerializer::new().into(),
-            ),
-        ),
         compression,
+        ..request_options()
     };
 
     let (metadata, _events) = request_options.split_input((key, vec![log]));
@@ -139,14 +298,8 @@ fn azure_blob_build_request_with_time_format() {
         container_name,
         blob_time_format,
         blob_append_uuid,
-        encoder: (
-            Default::default(),
-            Encoder::<Framer>::new(
-                NewlineDelimitedEncoder::new().into(),
-                TextSerializer::new().into(),
-            ),
-        ),
         compression,
+        ..request_options()
     };
 
     let (metadata, _events) = request_options.split_input((key, vec![log]));
@@ -183,20 +336,3172 @@ fn azure_blob_build_request_with_uuid() {
         container_name,
         blob_time_format,
         blob_append_uuid,
+        compression,
+        ..request_options()
+    };
+
+    let (metadata, _events) = request_options.split_input((key, vec![log]));
+    let request = request_options.build_request(metadata, EncodeResult::uncompressed(Bytes::new()));
+
+    assert_ne!(request.metadata.partition_key, "blob.log".to_string());
+    assert_eq!(request.content_encoding, None);
+    assert_eq!(request.content_type, "text/plain");
+}
+
+#[test]
+fn azure_blob_key_partitioner_renders_prefix_in_timezone() {
+    let mut log = LogEvent::from("test message");
+    log.insert(
+        log_schema().timestamp_key(),
+        Utc.ymd(2022, 3, 27).and_hms(0, 30, 0),
+    );
+    let log = Event::Log(log);
+    let partition = |timezone: Option<&str>| {
+        AzureBlobSinkConfig {
+            blob_prefix: Some("date=%F/hour=%H/".into()),
+            timezone: timezone.map(|name| TimeZone::parse(name).unwrap()),
+            ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+        }
+        .key_partitioner()
+        .unwrap()
+        .partition(&log)
+        .expect("key wasn't provided")
+    };
+
+    assert_eq!(partition(None), "date=2022-03-27/hour=00/");
+    assert_eq!(partition(Some("Etc/GMT+5")), "date=2022-03-26/hour=19/");
+    // Europe/Paris switches from CET to CEST at 01:00 UTC on that day.
+    assert_eq!(partition(Some("Europe/Paris")), "date=2022-03-27/hour=01/");
+}
+
+#[test]
+fn azure_blob_time_window_files_skewed_events_apart() {
+    let now = Utc.ymd(2022, 9, 1).and_hms(12, 0, 0);
+    let time_window = TimeWindowConfig {
+        max_future_skew_secs: Some(300),
+        max_past_age_secs: Some(86400),
+        future_prefix: "_future/".into(),
+        late_prefix: "_late/".into(),
+    };
+    let partitioner = AzureBlobSinkConfig {
+        blob_prefix: Some("date=%F/hour=%H/".into()),
+        time_window: Some(time_window.clone()),
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    }
+    .key_partitioner()
+    .unwrap();
+    let partition = |timestamp: chrono::DateTime<Utc>| {
+        let mut log = LogEvent::from("test message");
+        log.insert(log_schema().timestamp_key(), timestamp);
+        partitioner
+            .partition_at(&Event::Log(log), now)
+            .expect("key wasn't provided")
+    };
+
+    // Events within the window keep their own partition.
+    assert_eq!(
+        partition(now - Duration::hours(3)),
+        "date=2022-09-01/hour=09/"
+    );
+    assert_eq!(
+        partition(now + Duration::minutes(4)),
+        "date=2022-09-01/hour=12/"
+    );
+    // A device whose clock runs a few hours ahead is filed at the edge of the window.
+    assert_eq!(
+        partition(now + Duration::hours(13)),
+        "_future/date=2022-09-01/hour=12/"
+    );
+    // A backfill from last year doesn't create a partition of its own back then.
+    assert_eq!(
+        partition(Utc.ymd(2021, 6, 15).and_hms(8, 0, 0)),
+        "_late/date=2022-08-31/hour=12/"
+    );
+
+    let unbounded = TimeWindowConfig {
+        max_future_skew_secs: None,
+        max_past_age_secs: None,
+        ..time_window.clone()
+    };
+    assert!(unbounded.build().is_err());
+    let templated = TimeWindowConfig {
+        late_prefix: "_late/%F/".into(),
+        ..time_window
+    };
+    assert!(templated.build().is_err());
+}
+
+#[test]
+fn azure_blob_hash_bucket_caps_partition_cardinality() {
+    let hash_bucket = HashBucketConfig {
+        field: "user_id".into(),
+        buckets: 16.try_into().unwrap(),
+    }
+    .build()
+    .unwrap();
+    let partitioner = BucketedPartitioner::new(
+        AzureBlobSinkConfig {
+            blob_prefix: Some("logs".into()),
+            ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+        }
+        .key_partitioner()
+        .unwrap(),
+        Some(hash_bucket.clone()),
+    );
+    let event = |user_id: Option<&str>| {
+        let mut log = LogEvent::from("test message");
+        if let Some(user_id) = user_id {
+            log.insert("user_id", user_id);
+        }
+        Event::Log(log)
+    };
+
+    // The same key always lands in the same bucket.
+    let key = partitioner.partition(&event(Some("user-42"))).unwrap();
+    assert!(key.starts_with("logs/bucket="), "{}", key);
+    assert_eq!(partitioner.partition(&event(Some("user-42"))).unwrap(), key);
+    assert_eq!(
+        partitioner.partition(&event(None)).unwrap(),
+        "logs/bucket=none/"
+    );
+
+    // Distinct keys are spread evenly over the buckets.
+    let mut counts = [0; 16];
+    for user in 0..16_000 {
+        let bucket = hash_bucket
+            .bucket(&event(Some(&format!("user-{}", user))))
+            .unwrap();
+        counts[bucket as usize] += 1;
+    }
+    for count in counts {
+        assert!((750..1250).contains(&count), "uneven buckets {:?}", counts);
+    }
+
+    // Metrics are bucketed by their tags.
+    let metric = Event::Metric(
+        Metric::new(
+            "requests",
+            MetricKind::Incremental,
+            MetricValue::Counter { value: 1.0 },
+        )
+        .with_tags(Some(btreemap! { "user_id" => "user-42" })),
+    );
+    assert_eq!(
+        hash_bucket.bucket(&metric),
+        hash_bucket.bucket(&event(Some("user-42")))
+    );
+}
+
+#[tokio::test]
+async fn azure_blob_token_broker_endpoint_provides_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/token"))
+        .and(query_param("resource", "https://storage.azure.com/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "token": "broker-token",
+            "expires_on": "2100-01-01T00:00:00Z",
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let broker = TokenBrokerConfig::Http {
+        endpoint: format!("{}/token", server.uri()),
+        timeout_secs: 5,
+    }
+    .build()
+    .unwrap();
+
+    let token = build_credential(None, Some(broker))
+        .get_token("https://storage.azure.com/")
+        .await
+        .unwrap();
+    assert_eq!(token.token.secret(), "broker-token");
+    assert_eq!(token.expires_on, Utc.ymd(2100, 1, 1).and_hms(0, 0, 0));
+
+    assert!(TokenBrokerConfig::Http {
+        endpoint: "/token".into(),
+        timeout_secs: 5,
+    }
+    .build()
+    .is_err());
+    assert!(AzureBlobSinkConfig {
+        token_broker: Some(TokenBrokerConfig::Http {
+            endpoint: format!("{}/token", server.uri()),
+            timeout_secs: 5,
+        }),
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    }
+    .token_broker()
+    .is_err());
+}
+
+#[test]
+fn azure_blob_gzip_blobs_are_concatenable_members() {
+    let sink_config = AzureBlobSinkConfig {
+        gzip_level: Some(9),
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+    let request_options = AzureBlobRequestOptions {
+        compression: sink_config.compression().unwrap(),
+        ..request_options()
+    };
+    let encode = |messages: &[&str]| {
+        let events = messages
+            .iter()
+            .map(|message| Event::Log(LogEvent::from(*message)))
+            .collect();
+        request_options
+            .encode_events((None, false, "blob/".into(), events))
+            .unwrap()
+            .into_payload()
+    };
+
+    let first = encode(&["one", "two"]);
+    let second = encode(&["three"]);
+
+    // Each blob is exactly one gzip member, with nothing after its trailer.
+    let mut decoder = GzDecoder::new(first.as_ref());
+    let mut content = String::new();
+    decoder.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "one\ntwo\n");
+    assert!(decoder.into_inner().is_empty());
+
+    let concatenated = [first.as_ref(), second.as_ref()].concat();
+    let mut content = String::new();
+    MultiGzDecoder::new(concatenated.as_slice())
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "one\ntwo\nthree\n");
+}
+
+#[test]
+fn azure_blob_gzip_level_requires_gzip() {
+    let config = |compression, gzip_level| AzureBlobSinkConfig {
+        compression,
+        gzip_level,
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+
+    assert_eq!(
+        config(Compression::gzip_default(), Some(1))
+            .compression()
+            .unwrap()
+            .level()
+            .level(),
+        1
+    );
+    assert!(config(Compression::gzip_default(), Some(10))
+        .compression()
+        .is_err());
+    assert!(config(Compression::None, Some(1)).compression().is_err());
+}
+
+#[tokio::test]
+async fn azure_blob_response_reports_attempts_and_elapsed() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let client = mock_client(&server).await;
+    let request_settings = TowerRequestConfig::default()
+        .retry_initial_backoff_secs(1)
+        .unwrap_with(&TowerRequestConfig::default());
+    let mut service = ServiceBuilder::new()
+        .settings(request_settings, AzureBlobRetryLogic)
+        .service(AzureBlobService::new(client));
+
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(AzureBlobRequest {
+            blob_data: Bytes::from("test message\n"),
+            content_encoding: None,
+            content_type: "text/plain".into(),
+            metadata: AzureBlobMetadata {
+                partition_key: "blob.log".into(),
+                partition: "".into(),
+                count: 1,
+                byte_size: 13,
+                finalizers: Default::default(),
+                event_time_range: None,
+                source_types: None,
+                zstd_dictionary_id: None,
+                computed_metadata: Vec::new(),
+                oldest_event_time: None,
+                blob_type: Default::default(),
+                schema_fingerprint: None,
+                schema_version: None,
+                append_sequence: None,
+                content_language: None,
+            },
+            attempts: Default::default(),
+            partition_permit: Default::default(),
+            batched_finalizers: Default::default(),
+            finalizers_taken: false,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(response.attempts, 2);
+    assert!(response.elapsed > StdDuration::ZERO);
+}
+
+#[tokio::test]
+async fn azure_blob_unresolvable_partition_modes() {
+    let resolve = |mode: UnresolvablePartition| {
+        let (batch, receiver) = BatchNotifier::new_with_receiver();
+        let log = Event::Log(LogEvent::from("test message").with_batch_notifier(&batch));
+        drop(batch);
+
+        let sink_config = AzureBlobSinkConfig {
+            blob_prefix: Some("tenant={{ tenant }}/".into()),
+            unresolvable_partition: Some(mode.clone()),
+            ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+        };
+        let key = sink_config.key_partitioner().unwrap().partition(&log);
+        assert_eq!(key, None);
+
+        let resolved = mode
+            .resolve(vec![log])
+            .map(|(partition, events)| (partition, events.len()));
+        (resolved, receiver)
+    };
+
+    let (resolved, receiver) = resolve(UnresolvablePartition::Drop);
+    assert_eq!(resolved, None);
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+
+    let (resolved, receiver) = resolve(UnresolvablePartition::Reject);
+    assert_eq!(resolved, None);
+    assert_eq!(receiver.await, BatchStatus::Rejected);
+
+    let (resolved, receiver) = resolve(UnresolvablePartition::Fallback {
+        partition: "tenant=unknown/".into(),
+    });
+    assert_eq!(resolved, Some(("tenant=unknown/".to_string(), 1)));
+    // The events are still on their way to be written.
+    drop(receiver);
+}
+
+/// Serves a single connection over TLS, offering both HTTP/2 and HTTP/1.1 through ALPN, and
+/// returns the protocol the client built with `force_http1` negotiated.
+async fn negotiated_http_version(force_http1: bool) -> http::Version {
+    let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate_chain_file(TEST_PEM_CRT_PATH)
+        .unwrap();
+    acceptor
+        .set_private_key_file(TEST_PEM_KEY_PATH, SslFiletype::PEM)
+        .unwrap();
+    acceptor.set_alpn_select_callback(|_, client| {
+        select_next_proto(b"\x02h2\x08http/1.1", client).ok_or(AlpnError::NOACK)
+    });
+    let acceptor = acceptor.build();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = SslStream::new(Ssl::new(acceptor.context()).unwrap(), stream).unwrap();
+        Pin::new(&mut stream).accept().await.unwrap();
+        let http2 = stream.ssl().selected_alpn_protocol() == Some(b"h2".as_ref());
+        Http::new()
+            .http2_only(http2)
+            .serve_connection(
+                stream,
+                service_fn(|_| async { Ok::<_, Infallible>(Response::new(Body::empty())) }),
+            )
+            .await
+            .unwrap();
+    });
+
+    http_client_builder(force_http1, default_user_agent(), HeaderMap::new())
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+        .get(format!("https://localhost:{}/", port))
+        .send()
+        .await
+        .unwrap()
+        .version()
+}
+
+#[tokio::test]
+async fn azure_blob_negotiates_http2_unless_forced_to_http1() {
+    assert_eq!(negotiated_http_version(false).await, http::Version::HTTP_2);
+    assert_eq!(negotiated_http_version(true).await, http::Version::HTTP_11);
+}
+
+#[tokio::test]
+async fn azure_blob_sends_configured_user_agent() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(header("user-agent", "my-pipeline/1.2 (team-a)"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let config = AzureBlobSinkConfig {
+        connection_string: Some(mock_connection_string(&server)),
+        container_name: "logs".into(),
+        user_agent: Some("my-pipeline/1.2 (team-a)".into()),
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+    let client = build_client(config.client_options().unwrap())
+        .await
+        .unwrap();
+
+    // The mock only accepts writes carrying the configured user agent.
+    AzureBlobService::new(client)
+        .call(mock_request())
+        .await
+        .unwrap();
+
+    let default = default_user_agent();
+    assert!(default.to_str().unwrap().starts_with("Vector/"));
+    assert!(parse_user_agent("").is_err());
+    assert!(parse_user_agent("vector\r\nX-Injected: 1").is_err());
+}
+
+#[tokio::test]
+async fn azure_blob_sends_extra_headers() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(header("x-cost-center", "cc-1234"))
+        .and(header("x-team", "observability"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let config = AzureBlobSinkConfig {
+        connection_string: Some(mock_connection_string(&server)),
+        container_name: "logs".into(),
+        extra_headers: btreemap! {
+            "X-Cost-Center" => "cc-1234",
+            "x-team" => "observability",
+        },
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+    let client = build_client(config.client_options().unwrap())
+        .await
+        .unwrap();
+
+    // The mock only accepts writes carrying the extra headers.
+    AzureBlobService::new(client)
+        .call(mock_request())
+        .await
+        .unwrap();
+}
+
+#[test]
+fn azure_blob_records_event_time_range() {
+    let event = |timestamp: Option<chrono::DateTime<Utc>>| {
+        let mut log = LogEvent::from("test message");
+        match timestamp {
+            Some(timestamp) => log.insert(log_schema().timestamp_key(), timestamp),
+            None => log.remove(log_schema().timestamp_key()),
+        };
+        Event::Log(log)
+    };
+    let oldest = Utc.ymd(2022, 9, 1).and_hms_milli(10, 0, 0, 250);
+    let newest = Utc.ymd(2022, 9, 1).and_hms(11, 30, 0);
+    let request_options = AzureBlobRequestOptions {
+        event_time_metadata: true,
+        ..request_options()
+    };
+
+    let ((metadata, _, _), _events) = request_options.split_input((
+        "blob".into(),
+        vec![event(Some(newest)), event(None), event(Some(oldest))],
+    ));
+    assert_eq!(metadata.event_time_range, Some((oldest, newest)));
+
+    // Events without a timestamp don't prevent the batch from being written.
+    let ((metadata, _, _), _events) =
+        request_options.split_input(("blob".into(), vec![event(None)]));
+    assert_eq!(metadata.event_time_range, None);
+}
+
+#[test]
+fn azure_blob_sniffs_raw_content_type() {
+    let request_options = AzureBlobRequestOptions {
         encoder: (
             Default::default(),
             Encoder::<Framer>::new(
                 NewlineDelimitedEncoder::new().into(),
-                TextSerializer::new().into(),
+                RawMessageSerializer::new().into(),
             ),
         ),
-        compression,
+        content_type_sniffing: Some(16),
+        ..request_options()
+    };
+    let content_type = |message: &'static [u8]| {
+        let log = Event::Log(LogEvent::from(Bytes::from_static(message)));
+        let (metadata, _events) = request_options.split_input(("blob".into(), vec![log]));
+        request_options
+            .build_request(metadata, EncodeResult::uncompressed(Bytes::new()))
+            .content_type
     };
 
-    let (metadata, _events) = request_options.split_input((key, vec![log]));
-    let request = request_options.build_request(metadata, EncodeResult::uncompressed(Bytes::new()));
+    assert_eq!(content_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+    assert_eq!(content_type(b"\x1f\x8b\x08\0\0\0\0\0"), "application/gzip");
+    assert_eq!(content_type(b"  {\"message\": 1}"), "application/json");
+    // Payloads that can't be identified keep the default content type.
+    assert_eq!(content_type(b"plain text"), "text/plain");
 
-    assert_ne!(request.metadata.partition_key, "blob.log".to_string());
+    assert!(ContentTypeSniffingConfig { max_bytes: 0 }.build().is_err());
+}
+
+#[test]
+fn azure_blob_writes_line_protocol() {
+    let sink_config =
+        default_config((None::<FramingConfig>, LineProtocolSerializerConfig::new()).into());
+    assert_eq!(sink_config.input().data_type(), DataType::Metric);
+
+    let request_options = AzureBlobRequestOptions {
+        container_name: String::from("metrics"),
+        encoder: (
+            Default::default(),
+            Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::new().into(),
+                LineProtocolSerializer::new().into(),
+            ),
+        ),
+        line_protocol: true,
+        ..request_options()
+    };
+    let timestamp = Utc.ymd(2022, 9, 1).and_hms(10, 0, 0);
+    let events = vec![
+        Event::Metric(
+            Metric::new(
+                "requests",
+                MetricKind::Incremental,
+                MetricValue::Counter { value: 2.0 },
+            )
+            .with_namespace(Some("app"))
+            .with_timestamp(Some(timestamp)),
+        ),
+        Event::Metric(
+            Metric::new(
+                "memory used",
+                MetricKind::Absolute,
+                MetricValue::Gauge { value: 0.75 },
+            )
+            .with_timestamp(Some(timestamp)),
+        ),
+        Event::Metric(
+            Metric::new(
+                "requests",
+                MetricKind::Incremental,
+                MetricValue::Counter { value: 1.0 },
+            )
+            .with_tags(Some(btreemap! {
+                "status" => "200",
+                "path" => "/a=b,c",
+            })),
+        ),
+    ];
+
+    let (metadata, events) = request_options.split_input(("blob".into(), events));
+    let payload = request_options.encode_events(events).unwrap();
+    let request = request_options.build_request(metadata, payload);
+
+    assert_eq!(request.metadata.partition_key, "blob.lp");
+    assert_eq!(request.content_type, "text/plain; charset=utf-8");
+    assert_eq!(
+        String::from_utf8(request.blob_data.to_vec()).unwrap(),
+        "app.requests,metric_type=counter value=2 1662026400000000000\n\
+         memory\\ used,metric_type=gauge value=0.75 1662026400000000000\n\
+         requests,metric_type=counter,path=/a\\=b\\,c,status=200 value=1"
+    );
+
+    // Logs can't be written as line protocol.
+    let log = Event::Log(LogEvent::from("message"));
+    let (_, events) = request_options.split_input(("blob".into(), vec![log]));
+    assert!(request_options.encode_events(events).is_err());
+}
+
+#[test]
+fn azure_blob_passthrough_round_trips_binary_payload() {
+    let passthrough = PassthroughConfig {
+        field: String::from("payload"),
+        content_type_field: Some(String::from("mime")),
+        content_type: String::from("application/octet-stream"),
+    };
+    let request_options = AzureBlobRequestOptions {
+        container_name: String::from("files"),
+        encoder: (
+            Default::default(),
+            Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::new().into(),
+                RawMessageSerializer::new().into(),
+            ),
+        ),
+        passthrough: Some(passthrough.build().unwrap()),
+        ..request_options()
+    };
+    let write = |payload: &'static [u8], mime: Option<&'static str>| {
+        let mut log = LogEvent::default();
+        log.insert("payload", Bytes::from_static(payload));
+        if let Some(mime) = mime {
+            log.insert("mime", mime);
+        }
+        let (metadata, events) = request_options.split_input(("blob".into(), vec![log.into()]));
+        let payload = request_options.encode_events(events)?;
+        Ok::<_, std::io::Error>(request_options.build_request(metadata, payload))
+    };
+
+    // The payload is written byte for byte, without any framing.
+    let payload = b"\x89PNG\r\n\x1a\n\0\xff\n";
+    let request = write(payload, Some("image/png")).unwrap();
+    assert_eq!(request.blob_data, Bytes::from_static(payload));
+    assert_eq!(request.content_type, "image/png");
+
+    // Events without a content type of their own fall back to the configured one.
+    let request = write(b"\0\x01", None).unwrap();
+    assert_eq!(request.content_type, "application/octet-stream");
+    let request = write(b"\0\x01", Some("bad\ntype")).unwrap();
+    assert_eq!(request.content_type, "application/octet-stream");
+
+    // Events without the payload field can't be written.
+    let mut log = LogEvent::from("message");
+    log.insert("mime", "text/plain");
+    let (_, events) = request_options.split_input(("blob".into(), vec![log.into()]));
+    assert!(request_options.encode_events(events).is_err());
+
+    let invalid = PassthroughConfig {
+        content_type: String::from("bad\ntype"),
+        ..passthrough
+    };
+    assert!(invalid.build().is_err());
+}
+
+#[test]
+fn azure_blob_compresses_with_partition_zstd_dictionary() {
+    let samples = (0..2000)
+        .map(|i| {
+            format!(
+                r#"{{"host":"web-{}","path":"/api/v1/users/{}","status":200,"message":"GET served in {}ms"}}"#,
+                i % 7,
+                i,
+                i % 97
+            )
+        })
+        .collect::<Vec<_>>();
+    let dictionary = zstd::dict::from_samples(&samples, 4096).unwrap();
+    let dictionary_id = zstd::zstd_safe::get_dict_id_from_dict(&dictionary);
+    let path = crate::test_util::temp_file();
+    std::fs::write(&path, &dictionary).unwrap();
+
+    let zstd = ZstdConfig {
+        level: 3,
+        dictionaries: vec![ZstdDictionaryConfig {
+            partitions: "logs/*/app=nginx/".into(),
+            path: Some(path),
+            train_from: None,
+            max_size: 4096,
+        }],
+    };
+    let request_options = AzureBlobRequestOptions {
+        zstd: Some(zstd.build().unwrap()),
+        ..request_options()
+    };
+    let request = |partition: &str| {
+        let events = samples[..10]
+            .iter()
+            .map(|sample| Event::Log(LogEvent::from(sample.as_str())))
+            .collect();
+        let (metadata, events) = request_options.split_input((partition.into(), events));
+        let payload = request_options.encode_events(events).unwrap();
+        request_options.build_request(metadata, payload)
+    };
+    let expected = format!("{}\n", samples[..10].join("\n"));
+
+    // The blob decompresses with the dictionary named by its metadata.
+    let request = request("logs/2022-09-01/app=nginx/");
+    assert_eq!(request.metadata.zstd_dictionary_id, Some(dictionary_id));
+    assert_eq!(
+        request.metadata.partition_key,
+        "logs/2022-09-01/app=nginx/.zst"
+    );
+    assert_eq!(request.content_encoding, Some("zstd"));
+    assert_eq!(request.content_type, "application/zstd");
+    let decompressed = zstd::bulk::Decompressor::with_dictionary(&dictionary)
+        .unwrap()
+        .decompress(&request.blob_data, expected.len())
+        .unwrap();
+    assert_eq!(String::from_utf8(decompressed).unwrap(), expected);
+    assert!(zstd::stream::decode_all(request.blob_data.as_ref()).is_err());
+
+    // Partitions without a dictionary fall back to plain zstd.
+    let request = request("logs/2022-09-01/app=redis/");
+    assert_eq!(request.metadata.zstd_dictionary_id, None);
+    let decompressed = zstd::stream::decode_all(request.blob_data.as_ref()).unwrap();
+    assert_eq!(String::from_utf8(decompressed).unwrap(), expected);
+}
+
+#[test]
+fn azure_blob_computes_metadata_from_batch_statistics() {
+    let templates: BTreeMap<String, String> = btreemap! {
+        "count_bucket" => "{{ batch.count_bucket }}",
+        "totals" => "{{ batch.count }} events, {{ batch.byte_size }} bytes",
+        "time_range" => "{{ batch.time_min }}/{{ batch.time_max }}",
+        "level" => "{{ batch.dominant.level }}",
+        "host" => "host-{{ host }}",
+        "missing" => "{{ missing }}",
+    };
+    let request_options = AzureBlobRequestOptions {
+        batch_metadata: ComputedMetadata::new(&templates).unwrap(),
+        ..request_options()
+    };
+
+    let start = Utc.ymd(2022, 9, 1).and_hms(12, 0, 0);
+    let events = (0..1500)
+        .map(|i| {
+            let mut log = LogEvent::from("message");
+            log.insert(log_schema().timestamp_key(), start + Duration::seconds(i));
+            log.insert("level", if i % 3 == 0 { "error" } else { "info" });
+            log.insert("host", "web-1");
+            Event::Log(log)
+        })
+        .collect::<Vec<_>>();
+    let byte_size = events.size_of();
+    let ((metadata, _, _), _) = request_options.split_input(("blob/".into(), events));
+
+    assert_eq!(
+        metadata.computed_metadata,
+        vec![
+            ("count_bucket".to_owned(), "1k-10k".to_owned()),
+            ("host".to_owned(), "host-web-1".to_owned()),
+            ("level".to_owned(), "info".to_owned()),
+            (
+                "time_range".to_owned(),
+                "2022-09-01T12:00:00.000Z/2022-09-01T12:24:59.000Z".to_owned()
+            ),
+            (
+                "totals".to_owned(),
+                format!("1500 events, {} bytes", byte_size)
+            ),
+        ]
+    );
+
+    assert_eq!(count_bucket(1), "1-10");
+    assert_eq!(count_bucket(999), "100-1k");
+    assert_eq!(count_bucket(250_000), "100k-1M");
+}
+
+fn avro_ocf_request_options(avro_ocf: AvroOcfConfig) -> AzureBlobRequestOptions {
+    AzureBlobRequestOptions {
+        avro_ocf: Some(avro_ocf.build().unwrap()),
+        ..request_options()
+    }
+}
+
+/// Reads the records of an Object Container File back with a standard Avro reader.
+fn read_avro_ocf(file: &[u8]) -> Vec<Vec<(String, avro_rs::types::Value)>> {
+    avro_rs::Reader::new(file)
+        .expect("blob isn't an Avro Object Container File")
+        .map(|record| match record.unwrap() {
+            avro_rs::types::Value::Record(fields) => fields,
+            value => panic!("unexpected value {:?}", value),
+        })
+        .collect()
+}
+
+#[test]
+fn azure_blob_writes_avro_ocf_with_configured_schema() {
+    use avro_rs::types::Value as AvroValue;
+
+    let schema = r#"{
+        "type": "record",
+        "name": "Log",
+        "fields": [
+            {"name": "message", "type": "string"},
+            {"name": "status", "type": ["null", "long"], "default": null}
+        ]
+    }"#;
+    let request_options = avro_ocf_request_options(AvroOcfConfig {
+        schema: Some(schema.into()),
+        record_name: "Event".into(),
+        codec: AvroOcfCodec::Deflate,
+    });
+    let events = (0..3)
+        .map(|status| {
+            let mut log = LogEvent::default();
+            log.insert("message", format!("request {}", status));
+            log.insert("status", status);
+            Event::Log(log)
+        })
+        .collect();
+
+    let (metadata, events) = request_options.split_input(("blob/".into(), events));
+    let payload = request_options.encode_events(events).unwrap();
+    let request = request_options.build_request(metadata, payload);
+
+    assert_eq!(request.metadata.partition_key, "blob/.avro");
+    assert_eq!(request.content_type, "application/avro");
     assert_eq!(request.content_encoding, None);
-    assert_eq!(request.content_type, "text/plain");
+    let records = read_avro_ocf(&request.blob_data);
+    assert_eq!(records.len(), 3);
+    assert_eq!(
+        records[2],
+        vec![
+            ("message".into(), AvroValue::String("request 2".into())),
+            (
+                "status".into(),
+                AvroValue::Union(Box::new(AvroValue::Long(2)))
+            ),
+        ]
+    );
+
+    // Events that don't conform to the schema fail the batch under the `drop_batch` policy.
+    let mut log = LogEvent::default();
+    log.insert("status", 1);
+    let (_, events) = request_options.split_input(("blob/".into(), vec![log.clone().into()]));
+    assert!(request_options.encode_events(events).is_err());
+
+    // Under the other policies they're taken out of the batch against the schema of the files.
+    let handler = EncodingErrorHandler::new(
+        EncodingErrorPolicy::DropEvent,
+        (
+            Default::default(),
+            request_options
+                .avro_ocf
+                .as_ref()
+                .unwrap()
+                .conformance_encoder()
+                .unwrap(),
+        ),
+    );
+    let kept = handler
+        .handle(vec![log.into(), LogEvent::from("valid").into()])
+        .unwrap();
+    assert_eq!(kept.len(), 1);
+}
+
+#[test]
+fn azure_blob_writes_avro_ocf_with_inferred_schema() {
+    use avro_rs::types::Value as AvroValue;
+
+    let request_options = avro_ocf_request_options(AvroOcfConfig {
+        schema: None,
+        record_name: "Event".into(),
+        codec: AvroOcfCodec::Snappy,
+    });
+    let mut first = LogEvent::default();
+    first.insert("message", "first");
+    first.insert("@count", 1);
+    first.insert("tags", vec!["a", "b"]);
+    let mut second = LogEvent::default();
+    second.insert("message", "second");
+    second.insert("@count", 2.5);
+    let timestamp = Utc.ymd(2022, 9, 1).and_hms(10, 0, 0);
+    second.insert("when", timestamp);
+
+    let (_, events) =
+        request_options.split_input(("blob/".into(), vec![first.into(), second.into()]));
+    let file = request_options
+        .encode_events(events)
+        .unwrap()
+        .into_payload();
+
+    let schema = avro_rs::Reader::new(&file[..])
+        .unwrap()
+        .writer_schema()
+        .clone();
+    assert!(matches!(schema, avro_rs::Schema::Record { ref name, .. } if name.name == "Event"));
+    let records = read_avro_ocf(&file);
+    assert_eq!(records.len(), 2);
+    let union = |value| AvroValue::Union(Box::new(value));
+    assert_eq!(
+        records[0],
+        vec![
+            // Field names are turned into valid Avro names, and mixed numbers into doubles.
+            ("_count".into(), union(AvroValue::Double(1.0))),
+            ("message".into(), union(AvroValue::String("first".into()))),
+            // Nested values are written as JSON.
+            (
+                "tags".into(),
+                union(AvroValue::String(r#"["a","b"]"#.into()))
+            ),
+            ("when".into(), union(AvroValue::Null)),
+        ]
+    );
+    assert_eq!(
+        records[1][3],
+        (
+            "when".into(),
+            union(AvroValue::TimestampMicros(
+                timestamp.timestamp() * 1_000_000
+            ))
+        )
+    );
+
+    assert!(AvroOcfConfig {
+        schema: None,
+        record_name: "not a name".into(),
+        codec: AvroOcfCodec::None,
+    }
+    .build()
+    .is_err());
+}
+
+#[test]
+fn azure_blob_records_source_types() {
+    let event = |source_type: Option<&str>| {
+        let mut log = LogEvent::from("test message");
+        if let Some(source_type) = source_type {
+            log.insert(log_schema().source_type_key(), source_type.to_string());
+        }
+        Event::Log(log)
+    };
+    let request_options = AzureBlobRequestOptions {
+        source_type_metadata: true,
+        ..request_options()
+    };
+    let source_types = |events: Vec<Event>| {
+        let ((metadata, _, _), _events) = request_options.split_input(("blob".into(), events));
+        metadata.source_types
+    };
+
+    assert_eq!(
+        source_types(vec![
+            event(Some("stdin")),
+            event(None),
+            event(Some("file")),
+            event(Some("stdin")),
+        ]),
+        Some("file,stdin".to_string())
+    );
+    assert_eq!(source_types(vec![event(None)]), None);
+
+    // Batches mixing more sources than fit in the metadata aren't stamped.
+    let many = (0..MAX_SOURCE_TYPES_LEN)
+        .map(|i| event(Some(&format!("source_{}", i))))
+        .collect();
+    assert_eq!(source_types(many), None);
+}
+
+#[tokio::test]
+async fn azure_blob_verify_write_retries_mismatched_blob() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .respond_with(mock_properties_response("5"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .respond_with(mock_properties_response("13"))
+        .mount(&server)
+        .await;
+
+    let request_settings = TowerRequestConfig::default()
+        .retry_initial_backoff_secs(1)
+        .unwrap_with(&TowerRequestConfig::default());
+    let mut service = ServiceBuilder::new()
+        .settings(request_settings, AzureBlobRetryLogic)
+        .service(AzureBlobService::new(mock_client(&server).await).with_verify_write(true));
+
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(mock_request())
+        .await
+        .unwrap();
+
+    // The blob read back with the wrong size made the request be written again.
+    assert_eq!(response.attempts, 2);
+    assert_eq!(response.verify_error, None);
+    assert_eq!(received_puts(&server).await, 2);
+}
+
+#[tokio::test]
+async fn azure_blob_rewrites_blob_with_mismatched_checksum() {
+    let server = MockServer::start().await;
+    // The MD5 of an empty payload rather than of the one sent.
+    Mock::given(method("PUT"))
+        .and(path("/devstoreaccount1/logs/blob/test.log"))
+        .respond_with(mock_put_response().insert_header("content-md5", "1B2M2Y8AsgTpgAmY7PhCfg=="))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/devstoreaccount1/logs/blob/test.log"))
+        .and(header("if-match", MOCK_ETAG))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let request_settings = TowerRequestConfig::default()
+        .retry_initial_backoff_secs(1)
+        .unwrap_with(&TowerRequestConfig::default());
+    let mut service = ServiceBuilder::new()
+        .settings(request_settings, AzureBlobRetryLogic)
+        .service(
+            AzureBlobService::new(mock_client(&server).await)
+                .with_checksum_sidecar(Some(ChecksumAlgorithm::Md5)),
+        );
+
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(mock_request())
+        .await
+        .unwrap();
+
+    // The mismatched blob was deleted and written again, and only then described by a sidecar.
+    assert_eq!(response.attempts, 2);
+    assert_eq!(response.verify_error, None);
+    assert!(vector_common::event_test_util::contains_name(
+        "AzureBlobChecksumMismatch"
+    ));
+    let paths = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|request| format!("{} {}", request.method, request.url.path()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "PUT /devstoreaccount1/logs/blob/test.log",
+            "DELETE /devstoreaccount1/logs/blob/test.log",
+            "PUT /devstoreaccount1/logs/blob/test.log",
+            "PUT /devstoreaccount1/logs/blob/test.log.md5",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn azure_blob_write_receipts_describe_written_blobs() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let client = mock_client(&server).await;
+    let oldest = Utc.ymd(2022, 9, 1).and_hms_milli(10, 0, 0, 0);
+    let newest = Utc.ymd(2022, 9, 1).and_hms_milli(10, 5, 0, 250);
+
+    let mut request = mock_request();
+    request.metadata.count = 2;
+    request.metadata.event_time_range = Some((oldest, newest));
+    let response = AzureBlobService::new(Arc::clone(&client))
+        .with_write_receipts(true)
+        .call(request)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.receipt,
+        Some(WriteReceipt {
+            container: "logs".into(),
+            blob: "blob/test.log".into(),
+            etag: MOCK_ETAG.into(),
+            content_md5: ChecksumAlgorithm::Md5.hex_digest(b"test message\n"),
+            count: 2,
+            byte_size: 13,
+            event_time_range: Some((oldest, newest)),
+        })
+    );
+    assert!(vector_common::event_test_util::contains_name(
+        "AzureBlobWriteReceipt"
+    ));
+
+    let response = AzureBlobService::new(client)
+        .call(mock_request())
+        .await
+        .unwrap();
+    assert_eq!(response.receipt, None);
+}
+
+#[tokio::test]
+async fn azure_blob_acknowledgement_provenance_names_each_blob() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let client = mock_client(&server).await;
+    let mut service = AcknowledgementService::new(
+        AzureBlobService::new(Arc::clone(&client)).with_provenance(true),
+    );
+
+    for blob in ["blob/first.log", "blob/second.log"] {
+        let mut request = mock_request();
+        request.metadata.partition_key = blob.into();
+        let before = Utc::now();
+        let response = service.call(request).await.unwrap();
+        let provenance = response.provenance.unwrap();
+        assert!(provenance.written_at >= before && provenance.written_at <= Utc::now());
+        assert_eq!(
+            provenance,
+            BlobProvenance {
+                container: "logs".into(),
+                blob: blob.into(),
+                etag: MOCK_ETAG.into(),
+                written_at: provenance.written_at,
+            }
+        );
+    }
+    assert!(vector_common::event_test_util::contains_name(
+        "AzureBlobAcknowledgementProvenance"
+    ));
+
+    let response = AcknowledgementService::new(AzureBlobService::new(client))
+        .call(mock_request())
+        .await
+        .unwrap();
+    assert_eq!(response.provenance, None);
+}
+
+#[tokio::test]
+async fn azure_blob_payload_too_large_is_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(413))
+        .mount(&server)
+        .await;
+
+    let request_settings = TowerRequestConfig::default()
+        .retry_initial_backoff_secs(1)
+        .unwrap_with(&TowerRequestConfig::default());
+    let mut service = ServiceBuilder::new()
+        .settings(request_settings, AzureBlobRetryLogic)
+        .service(AzureBlobService::new(mock_client(&server).await));
+
+    let error = service
+        .ready()
+        .await
+        .unwrap()
+        .call(mock_request())
+        .await
+        .unwrap_err();
+
+    assert!(
+        error.to_string().contains("of 13 bytes is larger than"),
+        "{}",
+        error
+    );
+    assert!(error.to_string().contains("batch.max_bytes"), "{}", error);
+    assert_eq!(received_puts(&server).await, 1);
+}
+
+#[tokio::test]
+async fn azure_blob_oversized_batches_are_refused_before_sending() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let mut service = AzureBlobService::new(mock_client(&server).await);
+    let sized_request = |blob_type: BlobType, byte_size: usize| {
+        let mut request = mock_request();
+        request.blob_data = Bytes::from(vec![b'x'; byte_size]);
+        request.metadata.blob_type = blob_type;
+        request
+    };
+    let largest_sent = || async {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|request| request.body.len())
+            .max()
+    };
+
+    assert_eq!(BlobType::Block.max_write_bytes(), 256 * 1024 * 1024);
+    assert_eq!(BlobType::Append.max_write_bytes(), 4 * 1024 * 1024);
+
+    // A block just over the limit is refused without being sent.
+    let error = service
+        .call(sized_request(BlobType::Append, MAX_APPEND_BLOCK_BYTES + 1))
+        .await
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("of 4194305 bytes is larger than the 4194304 bytes"),
+        "{}",
+        error
+    );
+    assert!(error.to_string().contains("batch.max_bytes"), "{}", error);
+    assert_eq!(largest_sent().await, None);
+
+    // A block of exactly the limit is sent.
+    let _ = service
+        .call(sized_request(BlobType::Append, MAX_APPEND_BLOCK_BYTES))
+        .await;
+    assert_eq!(largest_sent().await, Some(MAX_APPEND_BLOCK_BYTES));
+
+    // Block blobs are held to the limit of a single put.
+    let error = service
+        .call(sized_request(BlobType::Block, MAX_PUT_BLOB_BYTES + 1))
+        .await
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("of 268435457 bytes is larger than the 268435456 bytes"),
+        "{}",
+        error
+    );
+    assert_eq!(largest_sent().await, Some(MAX_APPEND_BLOCK_BYTES));
+    service
+        .call(sized_request(BlobType::Block, MAX_APPEND_BLOCK_BYTES + 1))
+        .await
+        .unwrap();
+    assert_eq!(largest_sent().await, Some(MAX_APPEND_BLOCK_BYTES + 1));
+}
+
+#[tokio::test]
+async fn azure_blob_recreates_deleted_container() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/devstoreaccount1/logs"))
+        .and(query_param("restype", "container"))
+        .respond_with(
+            ResponseTemplate::new(201)
+                .insert_header("etag", MOCK_ETAG)
+                .insert_header("last-modified", "Thu, 01 Sep 2022 00:00:00 GMT")
+                .insert_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+                .insert_header("date", "Thu, 01 Sep 2022 00:00:00 GMT"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    // The container is deleted after the sink started, so the first upload finds it gone.
+    Mock::given(method("PUT"))
+        .and(path("/devstoreaccount1/logs/blob/test.log"))
+        .respond_with(mock_not_found_response("ContainerNotFound"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let guard = ContainerGuard::new(true);
+    let mut service =
+        AzureBlobService::new(mock_client(&server).await).with_container_guard(Arc::clone(&guard));
+    service.call(mock_request()).await.unwrap();
+    service.call(mock_request()).await.unwrap();
+
+    // Both uploads went through, the first one once the container was recreated.
+    assert_eq!(received_puts(&server).await, 4);
+    assert!(!guard.is_lost());
+}
+
+#[tokio::test]
+async fn azure_blob_fails_fast_on_deleted_container() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/devstoreaccount1/logs/missing.log"))
+        .respond_with(mock_not_found_response("BlobNotFound"))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_not_found_response("ContainerNotFound"))
+        .mount(&server)
+        .await;
+
+    let guard = ContainerGuard::new(false);
+    let mut service =
+        AzureBlobService::new(mock_client(&server).await).with_container_guard(Arc::clone(&guard));
+
+    // A missing blob doesn't mean the container is gone.
+    let mut request = mock_request();
+    request.metadata.partition_key = "missing.log".into();
+    for _ in 0..MAX_CONTAINER_NOT_FOUND {
+        service.call(request.clone()).await.unwrap_err();
+    }
+    assert!(!guard.is_lost());
+
+    for attempt in 1..=MAX_CONTAINER_NOT_FOUND {
+        assert!(!guard.is_lost(), "lost after {} attempts", attempt);
+        let error = service.call(mock_request()).await.unwrap_err();
+        assert!(
+            error.to_string().contains("doesn't exist anymore"),
+            "{}",
+            error
+        );
+    }
+    tokio::time::timeout(StdDuration::from_secs(1), guard.lost())
+        .await
+        .expect("container isn't lost");
+
+    // Once lost, uploads fail without being sent.
+    let puts = received_puts(&server).await;
+    service.call(mock_request()).await.unwrap_err();
+    assert_eq!(received_puts(&server).await, puts);
+}
+
+#[tokio::test]
+async fn azure_blob_refuses_overwriting_existing_blob() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/devstoreaccount1/logs/existing.log"))
+        .and(header("if-none-match", "*"))
+        .respond_with(mock_blob_exists_response())
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(header("if-none-match", "*"))
+        .respond_with(mock_put_response())
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut service =
+        AzureBlobService::new(mock_client(&server).await).with_overwrite(OverwritePolicy::Refuse);
+
+    // The storage account refuses the write itself, so nothing is checked beforehand.
+    let mut request = mock_request();
+    request.metadata.partition_key = "existing.log".into();
+    let error = service.call(request).await.unwrap_err();
+    assert!(error.to_string().contains("already exists"), "{}", error);
+
+    service.call(mock_request()).await.unwrap();
+    let methods = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|request| request.method.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(methods, vec!["PUT", "PUT"]);
+}
+
+#[tokio::test]
+async fn azure_blob_retries_find_blobs_written_by_earlier_attempts() {
+    // The first attempt is written but answered with an error, so the retry finds its blob.
+    for (content_length, written) in [("13", true), ("5", false)] {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(header("if-none-match", "*"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(header("if-none-match", "*"))
+            .respond_with(mock_blob_exists_response())
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .respond_with(mock_properties_response(content_length))
+            .mount(&server)
+            .await;
+
+        let request_settings = TowerRequestConfig::default()
+            .retry_initial_backoff_secs(1)
+            .unwrap_with(&TowerRequestConfig::default());
+        let mut service = ServiceBuilder::new()
+            .settings(request_settings, AzureBlobRetryLogic)
+            .service(
+                AzureBlobService::new(mock_client(&server).await)
+                    .with_overwrite(OverwritePolicy::Refuse),
+            );
+        let result = service.ready().await.unwrap().call(mock_request()).await;
+
+        if written {
+            let response = result.unwrap();
+            assert_eq!(response.attempts, 2);
+            assert_eq!(response.event_status(), EventStatus::Delivered);
+            assert_eq!(response.inner.unwrap().etag(), MOCK_ETAG);
+        } else {
+            // Another blob exists under the name, so overwriting it is still refused.
+            let error = result.unwrap_err();
+            assert!(error.to_string().contains("already exists"), "{}", error);
+        }
+        assert_eq!(received_puts(&server).await, 2);
+    }
+}
+
+#[tokio::test]
+async fn azure_blob_verify_write_deletes_blobs_not_overwritten() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(header("if-none-match", "*"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .respond_with(mock_properties_response("5"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .respond_with(mock_properties_response("13"))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(header("if-match", MOCK_ETAG))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let request_settings = TowerRequestConfig::default()
+        .retry_initial_backoff_secs(1)
+        .unwrap_with(&TowerRequestConfig::default());
+    let mut service = ServiceBuilder::new()
+        .settings(request_settings, AzureBlobRetryLogic)
+        .service(
+            AzureBlobService::new(mock_client(&server).await)
+                .with_verify_write(true)
+                .with_overwrite(OverwritePolicy::Skip),
+        );
+
+    // The blob that failed its verification is deleted, so that the retry can write it again.
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(mock_request())
+        .await
+        .unwrap();
+    assert_eq!(response.attempts, 2);
+    assert_eq!(response.event_status(), EventStatus::Delivered);
+    assert_eq!(received_puts(&server).await, 2);
+}
+
+#[tokio::test]
+async fn azure_blob_counts_name_collisions() {
+    crate::test_util::components::init_test();
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(header("if-none-match", "*"))
+        .respond_with(mock_blob_exists_response())
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let client = mock_client(&server).await;
+    let conflicts = || {
+        counter_value(
+            "component_errors_total",
+            &[("error_code", "http_response_409")],
+        )
+    };
+    let errors = conflicts();
+    let send = |overwrite: OverwritePolicy| {
+        let (batch, receiver) = BatchNotifier::new_with_receiver();
+        let mut request = mock_request();
+        request.metadata.finalizers = LogEvent::from("test message")
+            .with_batch_notifier(&batch)
+            .take_finalizers();
+        let finalizers = request.metadata.finalizers.clone();
+        let result = AzureBlobService::new(Arc::clone(&client))
+            .with_overwrite(overwrite)
+            .call(request);
+        async move {
+            match result.await {
+                Ok(response) => finalizers.update_status(response.event_status()),
+                Err(_) => finalizers.update_status(EventStatus::Rejected),
+            }
+            drop(finalizers);
+            drop(batch);
+            receiver.await
+        }
+    };
+
+    // Skipped blobs are left untouched, and their events are acknowledged.
+    let skipped = counter_value(
+        "azure_blob_name_collisions_total",
+        &[("policy", OverwritePolicy::Skip.as_str())],
+    );
+    assert_eq!(send(OverwritePolicy::Skip).await, BatchStatus::Delivered);
+    assert!(
+        counter_value(
+            "azure_blob_name_collisions_total",
+            &[("policy", OverwritePolicy::Skip.as_str())]
+        ) >= skipped + 1.0
+    );
+    assert_eq!(received_puts(&server).await, 1);
+
+    // Refused blobs fail the request, so their events are rejected.
+    let refused = counter_value(
+        "azure_blob_name_collisions_total",
+        &[("policy", OverwritePolicy::Refuse.as_str())],
+    );
+    assert_eq!(send(OverwritePolicy::Refuse).await, BatchStatus::Rejected);
+    assert!(
+        counter_value(
+            "azure_blob_name_collisions_total",
+            &[("policy", OverwritePolicy::Refuse.as_str())]
+        ) >= refused + 1.0
+    );
+    assert_eq!(received_puts(&server).await, 2);
+    // The conflicts are collisions rather than errors of the storage account.
+    assert_eq!(conflicts(), errors);
+
+    // Overwrites aren't checked for, and so never counted, when they are allowed.
+    assert_eq!(send(OverwritePolicy::Allow).await, BatchStatus::Delivered);
+    assert_eq!(received_puts(&server).await, 3);
+}
+
+#[tokio::test]
+async fn azure_blob_permanently_deletes_prior_blob_before_overwriting() {
+    crate::test_util::components::init_test();
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/devstoreaccount1/logs/blob/test.log"))
+        .and(header("x-ms-delete-snapshots", "include"))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/devstoreaccount1/logs/blob/test.log"))
+        .and(query_param("deletetype", "permanent"))
+        .respond_with(
+            ResponseTemplate::new(202).insert_header("x-ms-delete-type-permanent", "true"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let deleted = counter_value(
+        "azure_blob_name_collisions_total",
+        &[("policy", OverwritePolicy::DeletePermanently.as_str())],
+    );
+    let response = AzureBlobService::new(mock_client(&server).await)
+        .with_overwrite(OverwritePolicy::DeletePermanently)
+        .call(mock_request())
+        .await
+        .unwrap();
+    assert_eq!(response.event_status(), EventStatus::Delivered);
+
+    // The blob is only written once its previous content is gone for good.
+    let requests = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|request| request.method.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(requests, vec!["DELETE", "DELETE", "PUT"]);
+    assert!(
+        counter_value(
+            "azure_blob_name_collisions_total",
+            &[("policy", OverwritePolicy::DeletePermanently.as_str())]
+        ) >= deleted + 1.0
+    );
+}
+
+#[tokio::test]
+async fn azure_blob_encoding_error_policies() {
+    let encoder = || {
+        (
+            Transformer::default(),
+            Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::new().into(),
+                GelfSerializerConfig::new().build().into(),
+            ),
+        )
+    };
+    // GELF requires a `host`, so only the second event can't be encoded.
+    let batch = || {
+        let (batch, receiver) = BatchNotifier::new_with_receiver();
+        let event = |message: &str, host: Option<&str>| {
+            let mut log = LogEvent::from(message);
+            if let Some(host) = host {
+                log.insert("host", host);
+            }
+            Event::Log(log)
+        };
+        let events = vec![
+            event("first", Some("example.com")),
+            Event::Log(event("second", None).into_log().with_batch_notifier(&batch)),
+            event("third", Some("example.com")),
+        ];
+        (events, receiver)
+    };
+    let messages = |events: &[Event]| {
+        events
+            .iter()
+            .map(|event| event.as_log()[log_schema().message_key()].to_string_lossy())
+            .collect::<Vec<_>>()
+    };
+
+    let handler = EncodingErrorHandler::new(EncodingErrorPolicy::DropEvent, encoder());
+    let (events, receiver) = batch();
+    let events = handler.handle(events).unwrap();
+    assert_eq!(messages(&events), vec!["first", "third"]);
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+
+    let handler = EncodingErrorHandler::new(EncodingErrorPolicy::Reject, encoder());
+    let (events, receiver) = batch();
+    let events = handler.handle(events).unwrap();
+    assert_eq!(messages(&events), vec!["first", "third"]);
+    assert_eq!(receiver.await, BatchStatus::Rejected);
+
+    // Nothing is left to write once every event of the batch is taken out.
+    let (mut events, _receiver) = batch();
+    assert!(handler.handle(events.drain(1..2).collect()).is_none());
+
+    // Batches are dropped as a whole when encoding any of their events fails otherwise.
+    let request_options = AzureBlobRequestOptions {
+        encoder: encoder(),
+        ..request_options()
+    };
+    let (events, receiver) = batch();
+    let ((metadata, _, _), events) = request_options.split_input(("blob".into(), events));
+    assert!(request_options.encode_events(events).is_err());
+    drop(metadata);
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+}
+
+#[tokio::test]
+async fn azure_blob_response_reports_partition_as_output() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let response = AzureBlobService::new(mock_client(&server).await)
+        .call(mock_request())
+        .await
+        .unwrap();
+
+    assert_eq!(response.partition, "blob/");
+    let events_sent = response.events_sent();
+    assert_eq!(events_sent.output, Some("blob/"));
+    assert_eq!(events_sent.count, 1);
+    assert_eq!(events_sent.byte_size, 13);
+}
+
+#[tokio::test]
+async fn azure_blob_flushes_on_demand_and_keeps_running() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let client = mock_client(&server).await;
+
+    let sink_config = AzureBlobSinkConfig {
+        blob_prefix: Some("blob/".into()),
+        container_name: "logs".into(),
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+    let request_options = AzureBlobRequestOptions {
+        blob_time_format: "%s".into(),
+        blob_append_uuid: true,
+        ..request_options()
+    };
+    // Nothing is flushed by the batch settings while the test runs.
+    let settings = BatcherSettings::new(
+        StdDuration::from_secs(300),
+        NonZeroUsize::new(10_000_000).unwrap(),
+        NonZeroUsize::new(1000).unwrap(),
+    );
+    let trigger = FlushTrigger::new();
+    let sink = AzureBlobSink::new(
+        AzureBlobService::new(client),
+        request_options,
+        sink_config.key_partitioner().unwrap(),
+        settings,
+    )
+    .with_flush_trigger(Arc::clone(&trigger));
+
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    let sink = tokio::spawn(async move { Box::new(sink).run(rx.boxed()).await });
+
+    for round in 1..=2 {
+        let (batch, receiver) = BatchNotifier::new_with_receiver();
+        for _ in 0..3 {
+            let log = LogEvent::from("test message").with_batch_notifier(&batch);
+            tx.unbounded_send(Event::Log(log)).unwrap();
+        }
+        drop(batch);
+        // Lets the sink take in the events before flushing it.
+        tokio::time::sleep(StdDuration::from_millis(100)).await;
+
+        trigger.flush().await;
+        assert_eq!(received_puts(&server).await, round);
+        assert_eq!(receiver.await, BatchStatus::Delivered);
+    }
+    assert!(!sink.is_finished());
+
+    drop(tx);
+    assert_eq!(sink.await.unwrap(), Ok(()));
+    // Flushing a stopped sink has nothing left to wait for.
+    trigger.flush().await;
+}
+
+#[tokio::test]
+async fn azure_blob_rejects_events_rendered_under_reserved_prefix() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let sink_config = AzureBlobSinkConfig {
+        blob_prefix: Some("{{ dir }}/".into()),
+        container_name: "logs".into(),
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+    let sink = AzureBlobSink::new(
+        AzureBlobService::new(mock_client(&server).await),
+        request_options(),
+        sink_config.key_partitioner().unwrap(),
+        BatcherSettings::new(
+            StdDuration::from_secs(1),
+            NonZeroUsize::new(10_000_000).unwrap(),
+            NonZeroUsize::new(1000).unwrap(),
+        ),
+    )
+    .with_reserved_prefix_guard(ReservedPrefixGuard::new(vec![String::new()]));
+
+    let mut receivers = Vec::new();
+    let events = ["app", "_vector"]
+        .into_iter()
+        .map(|dir| {
+            let (batch, receiver) = BatchNotifier::new_with_receiver();
+            receivers.push(receiver);
+            let mut log = LogEvent::from("test message").with_batch_notifier(&batch);
+            log.insert("dir", dir);
+            Event::Log(log)
+        })
+        .collect::<Vec<_>>();
+    Box::new(sink)
+        .run(stream::iter(events).boxed())
+        .await
+        .unwrap();
+
+    let mut receivers = receivers.into_iter();
+    assert_eq!(receivers.next().unwrap().await, BatchStatus::Delivered);
+    assert_eq!(receivers.next().unwrap().await, BatchStatus::Rejected);
+    assert_eq!(received_puts(&server).await, 1);
+}
+
+#[tokio::test]
+async fn azure_blob_drops_stale_batches_before_sending() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let mut service = AzureBlobService::new(mock_client(&server).await)
+        .with_max_batch_age(Some(Duration::seconds(60)));
+    let aged_request = |age: Duration| {
+        let mut request = mock_request();
+        request.metadata.oldest_event_time = Some(Utc::now() - age);
+        request
+    };
+
+    // A batch held back past the limit, such as during an outage, is dropped unwritten.
+    let response = service
+        .call(aged_request(Duration::seconds(120)))
+        .await
+        .unwrap();
+    assert_eq!(response.event_status(), EventStatus::Dropped);
+    assert_eq!(received_puts(&server).await, 0);
+
+    let response = service
+        .call(aged_request(Duration::seconds(10)))
+        .await
+        .unwrap();
+    assert_eq!(response.event_status(), EventStatus::Delivered);
+    assert_eq!(received_puts(&server).await, 1);
+
+    // Batches without timestamps have no age to expire by.
+    let response = service.call(mock_request()).await.unwrap();
+    assert_eq!(response.event_status(), EventStatus::Delivered);
+    assert_eq!(received_puts(&server).await, 2);
+
+    let mut config = default_config((None::<FramingConfig>, TextSerializerConfig::new()).into());
+    config.max_batch_age_before_send_secs = Some(0);
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_err());
+}
+
+#[tokio::test]
+async fn azure_blob_wraps_events_into_envelopes() {
+    let envelope_config = EnvelopeConfig {
+        meta_key: "meta".into(),
+        event_key: "event".into(),
+        fields: BTreeMap::from([
+            ("partition".to_owned(), EnvelopeField::Partition),
+            ("received_at".to_owned(), EnvelopeField::ReceivedAt),
+            ("source_type".to_owned(), EnvelopeField::SourceType),
+        ]),
+    };
+    let request_options = AzureBlobRequestOptions {
+        blob_time_format: String::from("%s"),
+        encoder: (
+            Default::default(),
+            Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::new().into(),
+                JsonSerializer::new().into(),
+            ),
+        ),
+        envelope: Some(envelope_config.build().unwrap()),
+        ..request_options()
+    };
+    let received_at = Utc.ymd(2022, 9, 1).and_hms(12, 0, 0);
+    let events = ["first", "second"]
+        .into_iter()
+        .map(|message| {
+            let mut log = LogEvent::from(message);
+            log.insert(log_schema().timestamp_key(), received_at);
+            log.insert(log_schema().source_type_key(), "http");
+            log.insert("nested.level", "info");
+            Event::Log(log)
+        })
+        .collect::<Vec<_>>();
+    let originals = events
+        .iter()
+        .map(|event| serde_json::to_value(event.as_log()).unwrap())
+        .collect::<Vec<_>>();
+
+    let (_, events) = request_options.split_input(("blob/2022-09-01/".into(), events));
+    let payload = request_options
+        .encode_events(events)
+        .unwrap()
+        .into_payload();
+    let lines = String::from_utf8(payload.to_vec()).unwrap();
+    let lines = lines.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    for (line, original) in lines.into_iter().zip(originals) {
+        let envelope: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(
+            envelope,
+            serde_json::json!({
+                "meta": {
+                    "partition": "blob/2022-09-01/",
+                    "received_at": "2022-09-01T12:00:00Z",
+                    "source_type": "http",
+                },
+                "event": original,
+            })
+        );
+    }
+
+    // Envelopes are JSON objects, and so need the `json` codec.
+    let mut config = default_config((None::<FramingConfig>, TextSerializerConfig::new()).into());
+    config.envelope = Some(envelope_config.clone());
+    let server = MockServer::start().await;
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_err());
+    let conflicting = EnvelopeConfig {
+        event_key: "meta".into(),
+        ..envelope_config
+    };
+    assert!(conflicting.build().is_err());
+}
+
+#[tokio::test]
+async fn azure_blob_reports_sent_body_bytes() {
+    crate::test_util::components::init_test();
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let mut service = AzureBlobService::new(mock_client(&server).await);
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all("test message\n".repeat(100).as_bytes())
+        .unwrap();
+    let payload = Bytes::from(encoder.finish().unwrap());
+    let request = || {
+        let mut request = mock_request();
+        request.blob_data = payload.clone();
+        request.content_encoding = Some("gzip");
+        request.metadata.byte_size = 1300;
+        request
+    };
+
+    let sent = counter_value("azure_blob_request_body_bytes_total", &[]);
+    let refused = service.ready().await.unwrap().call(request()).await;
+    assert!(refused.is_err());
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(request())
+        .await
+        .unwrap();
+
+    let bodies = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.method == wiremock::http::Method::Put)
+        .map(|request| request.body.len())
+        .collect::<Vec<_>>();
+    assert_eq!(bodies, vec![payload.len(), payload.len()]);
+    assert_eq!(response.byte_size, payload.len());
+    assert_eq!(response.events_byte_size, 1300);
+    // Other tests upload concurrently, so the counter may have gone up further.
+    assert!(
+        counter_value("azure_blob_request_body_bytes_total", &[]) - sent
+            >= (2 * payload.len()) as f64
+    );
+}
+
+#[cfg(feature = "sources-azure_blob")]
+#[tokio::test]
+async fn azure_blob_round_trips_events_through_source() {
+    use codecs::decoding::{DeserializerConfig, FramingConfig as DecodingFramingConfig};
+    use vector_core::config::LogNamespace;
+
+    use crate::{
+        codecs::DecodingConfig,
+        sources::azure_blob::{decode_blob, Compression as SourceCompression},
+    };
+
+    let request_options = AzureBlobRequestOptions {
+        blob_time_format: String::from("%s"),
+        blob_append_uuid: true,
+        encoder: (
+            Default::default(),
+            Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::new().into(),
+                JsonSerializer::new().into(),
+            ),
+        ),
+        compression: Compression::gzip_default(),
+        ..request_options()
+    };
+    let events = (0..10)
+        .map(|index| {
+            let mut log = LogEvent::from(format!("message {}", index));
+            log.insert(
+                log_schema().timestamp_key(),
+                Utc.ymd(2022, 9, 1).and_hms(12, 0, index),
+            );
+            log.insert("nested.index", index);
+            Event::Log(log)
+        })
+        .collect::<Vec<_>>();
+    let originals = events
+        .iter()
+        .map(|event| serde_json::to_value(event.as_log()).unwrap())
+        .collect::<Vec<_>>();
+
+    let (metadata, events) = request_options.split_input(("blob/".into(), events));
+    let payload = request_options.encode_events(events).unwrap();
+    let request = request_options.build_request(metadata, payload);
+
+    let decoder = DecodingConfig::new(
+        DecodingFramingConfig::NewlineDelimited {
+            newline_delimited: Default::default(),
+        },
+        DeserializerConfig::Json,
+        LogNamespace::Legacy,
+    )
+    .build();
+    // The blob is read back as listed, with its `Content-Encoding` and its name.
+    let read = decode_blob(
+        decoder.clone(),
+        SourceCompression::Auto,
+        request.content_encoding,
+        &request.metadata.partition_key,
+        request.blob_data.clone(),
+    )
+    .await
+    .unwrap();
+    let read = read
+        .iter()
+        .map(|event| serde_json::to_value(event.as_log()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(read, originals);
+
+    // Blobs served without their `Content-Encoding` are recognized by their extension.
+    let read = decode_blob(
+        decoder,
+        SourceCompression::Auto,
+        None,
+        &request.metadata.partition_key,
+        request.blob_data,
+    )
+    .await
+    .unwrap();
+    assert_eq!(read.len(), 10);
+}
+
+#[tokio::test]
+async fn azure_blob_routes_events_to_block_and_append_blobs() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(query_param("comp", "appendblock"))
+        .respond_with(mock_not_found_response("BlobNotFound"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(query_param("comp", "appendblock"))
+        .respond_with(mock_append_response())
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .respond_with(mock_not_found_response("BlobNotFound"))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let client = mock_client(&server).await;
+
+    let blob_type_config = BlobTypeConfig {
+        field: "kind".into(),
+        values: btreemap! { "stream" => BlobType::Append },
+        default: BlobType::Block,
+        ordering: AppendOrdering::BestEffort,
+    };
+    let router = blob_type_config.build().unwrap();
+    let sink_config = AzureBlobSinkConfig {
+        blob_prefix: Some("blob/".into()),
+        container_name: "logs".into(),
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+    let request_options = AzureBlobRequestOptions {
+        blob_time_format: "%Y".into(),
+        blob_append_uuid: true,
+        blob_type: Some(router.clone()),
+        ..request_options()
+    };
+    let settings = BatcherSettings::new(
+        StdDuration::from_secs(300),
+        NonZeroUsize::new(10_000_000).unwrap(),
+        NonZeroUsize::new(1000).unwrap(),
+    );
+    let sink = AzureBlobSink::new(
+        AzureBlobService::new(client),
+        request_options,
+        sink_config.key_partitioner().unwrap(),
+        settings,
+    )
+    .with_blob_type_router(router);
+
+    let (batch, receiver) = BatchNotifier::new_with_receiver();
+    let events = [
+        ("snapshot 1", Some("snapshot")),
+        ("stream 1", Some("stream")),
+        ("unknown 1", None),
+        ("stream 2", Some("stream")),
+    ]
+    .into_iter()
+    .map(|(message, kind)| {
+        let mut log = LogEvent::from(message).with_batch_notifier(&batch);
+        if let Some(kind) = kind {
+            log.insert("kind", kind);
+        }
+        Event::Log(log)
+    })
+    .collect::<Vec<_>>();
+    drop(batch);
+    Box::new(sink)
+        .run(stream::iter(events).boxed())
+        .await
+        .unwrap();
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+
+    let puts = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.method == wiremock::http::Method::Put)
+        .collect::<Vec<_>>();
+    let blob_type = |request: &wiremock::Request| {
+        request
+            .headers
+            .iter()
+            .find(|(name, _)| name.as_str() == "x-ms-blob-type")
+            .map(|(_, values)| values.last().as_str().to_owned())
+    };
+    let appending = |request: &wiremock::Request| {
+        request
+            .url
+            .query_pairs()
+            .any(|(name, value)| name == "comp" && value == "appendblock")
+    };
+
+    // The events without the field or with another value are written as a block blob of their
+    // own, named as usual.
+    let blocks = puts
+        .iter()
+        .filter(|request| blob_type(request).as_deref() == Some("BlockBlob"))
+        .collect::<Vec<_>>();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(
+        String::from_utf8_lossy(&blocks[0].body),
+        "snapshot 1\nunknown 1\n"
+    );
+
+    // The append blob is created once the first append finds it missing, and the events are then
+    // appended to it, in order, under a name without UUID shared by the batches that follow.
+    let year = Utc::now().format("%Y").to_string();
+    let append_path = format!("/devstoreaccount1/logs/blob/{}.log", year);
+    let appends = puts
+        .iter()
+        .filter(|request| request.url.path() == append_path)
+        .collect::<Vec<_>>();
+    assert_eq!(appends.len(), 3);
+    assert!(appending(appends[0]));
+    assert_eq!(blob_type(appends[1]).as_deref(), Some("AppendBlob"));
+    assert!(appending(appends[2]));
+    assert_eq!(
+        String::from_utf8_lossy(&appends[2].body),
+        "stream 1\nstream 2\n"
+    );
+    assert_ne!(blocks[0].url.path(), append_path);
+
+    // Appended payloads have to be concatenable and can't be checked as a whole blob.
+    let mut config = default_config((None::<FramingConfig>, TextSerializerConfig::new()).into());
+    config.blob_type = Some(blob_type_config.clone());
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_ok());
+    config.verify_write = true;
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_err());
+    config.verify_write = false;
+    config.compression = Compression::zlib_default();
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_err());
+    config.compression = Compression::None;
+    // Strictly ordered appends can't be handed over out of order by the encoding pool.
+    config.encoding_pool_size = NonZeroUsize::new(2);
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_ok());
+    config.blob_type = Some(BlobTypeConfig {
+        ordering: AppendOrdering::Strict,
+        ..blob_type_config.clone()
+    });
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_err());
+    config.encoding_pool_size = None;
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_ok());
+    config.compression = Compression::zlib_default();
+    // Only blobs written as a whole don't restrict the other options.
+    config.blob_type = Some(BlobTypeConfig {
+        values: btreemap! { "stream" => BlobType::Block },
+        ..blob_type_config
+    });
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_ok());
+}
+
+#[tokio::test]
+async fn azure_blob_healthcheck_retries_throttled_checks() {
+    let server = MockServer::start().await;
+    Mock::given(query_param("restype", "container"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(query_param("restype", "container"))
+        .respond_with(mock_container_properties_response())
+        .mount(&server)
+        .await;
+
+    build_healthcheck("logs".into(), mock_client(&server).await)
+        .unwrap()
+        .await
+        .unwrap();
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+
+    // The check fails once the storage account kept throttling it through all the attempts.
+    let server = MockServer::start().await;
+    Mock::given(query_param("restype", "container"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .mount(&server)
+        .await;
+    let error = build_healthcheck("logs".into(), mock_client(&server).await)
+        .unwrap()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            error.downcast_ref::<HealthcheckError>(),
+            Some(HealthcheckError::Throttled { attempts: 4 })
+        ),
+        "{}",
+        error
+    );
+    assert_eq!(server.received_requests().await.unwrap().len(), 4);
+
+    // Other failures aren't retried.
+    let server = MockServer::start().await;
+    Mock::given(query_param("restype", "container"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&server)
+        .await;
+    let error = build_healthcheck("logs".into(), mock_client(&server).await)
+        .unwrap()
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<HealthcheckError>(),
+        Some(HealthcheckError::InvalidCredentials)
+    ));
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn azure_blob_ordering_keys_sort_in_write_order() {
+    let ordering_keys = Arc::new(OrderingKeys::default());
+    let request_options = AzureBlobRequestOptions {
+        blob_time_format: "%s".into(),
+        blob_append_uuid: true,
+        ordering_keys: Some(Arc::clone(&ordering_keys)),
+        ..request_options()
+    };
+    let blob_name = |partition: &str| {
+        let (metadata, events) = request_options.split_input((
+            partition.into(),
+            vec![Event::Log(LogEvent::from("message"))],
+        ));
+        let payload = request_options.encode_events(events).unwrap();
+        request_options
+            .build_request(metadata, payload)
+            .metadata
+            .partition_key
+    };
+
+    // A burst of writes, far more than one per microsecond, across two partitions.
+    let names = (0..1000)
+        .map(|i| {
+            let partition = if i % 2 == 0 { "a/" } else { "b/" };
+            let name = blob_name(partition);
+            name.strip_prefix(partition).unwrap().to_owned()
+        })
+        .collect::<Vec<_>>();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(sorted, names);
+    sorted.dedup();
+    assert_eq!(sorted.len(), names.len());
+
+    let (key, _) = names[0].split_at(19 + 1 + 23);
+    let (sequence, timestamp) = key.split_once('-').unwrap();
+    assert!(sequence.chars().all(|c| c.is_ascii_digit()));
+    assert!(chrono::NaiveDateTime::parse_from_str(timestamp, "%Y%m%dT%H%M%S%.6fZ").is_ok());
+
+    // The sequence is derived from the clock, so the keys keep sorting after a restart.
+    tokio::time::sleep(StdDuration::from_millis(10)).await;
+    let restarted = OrderingKeys::default().next(Utc::now());
+    assert!(restarted > *names.last().unwrap());
+
+    // The key takes the place of the time format in the names.
+    let mut config = default_config((None::<FramingConfig>, TextSerializerConfig::new()).into());
+    config.ordering_key = true;
+    config.blob_time_format = Some("%s".into());
+    let server = MockServer::start().await;
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_err());
+    config.blob_time_format = None;
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_ok());
+}
+
+#[tokio::test]
+async fn azure_blob_writes_precompressed_payloads_as_is() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let client = mock_client(&server).await;
+
+    let precompressed_config = PrecompressedConfig {
+        field: "message".into(),
+        detect_magic_bytes: true,
+        marker_field: Some("encoding".into()),
+    };
+    let precompressed = precompressed_config.build().unwrap();
+    let encoding: EncodingConfigWithFraming = (
+        Some(FramingConfig::Bytes),
+        RawMessageSerializerConfig::new(),
+    )
+        .into();
+    let sink_config = AzureBlobSinkConfig {
+        blob_prefix: Some("blob/".into()),
+        container_name: "logs".into(),
+        ..default_config(encoding.clone())
+    };
+    let request_options = AzureBlobRequestOptions {
+        blob_time_format: "%s".into(),
+        blob_append_uuid: true,
+        encoder: (
+            Default::default(),
+            Encoder::<Framer>::new(
+                BytesEncoder::new().into(),
+                RawMessageSerializer::new().into(),
+            ),
+        ),
+        compression: Compression::gzip_default(),
+        precompressed: Some(precompressed.clone()),
+        ..request_options()
+    };
+
+    let gzipped = {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(b"already compressed").unwrap();
+        encoder.finish().unwrap()
+    };
+
+    // Events marked as compressed are written as is with the encoding of their marker, whatever
+    // their payload.
+    let mut marked = LogEvent::from("not really zstd");
+    marked.insert("encoding", "zstd");
+    let (metadata, events) = request_options.split_input(("blob/".into(), vec![marked.into()]));
+    let payload = request_options.encode_events(events).unwrap();
+    let request = request_options.build_request(metadata, payload);
+    assert_eq!(request.blob_data, Bytes::from("not really zstd"));
+    assert_eq!(request.content_encoding, Some("zstd"));
+    assert_eq!(request.content_type, "application/octet-stream");
+    assert!(request.metadata.partition_key.ends_with(".zst"));
+
+    let settings = BatcherSettings::new(
+        StdDuration::from_secs(300),
+        NonZeroUsize::new(10_000_000).unwrap(),
+        NonZeroUsize::new(1000).unwrap(),
+    );
+    let sink = AzureBlobSink::new(
+        AzureBlobService::new(client),
+        request_options,
+        sink_config.key_partitioner().unwrap(),
+        settings,
+    )
+    .with_precompressed(precompressed);
+
+    let (batch, receiver) = BatchNotifier::new_with_receiver();
+    let events = vec![
+        Bytes::from("plain 1"),
+        Bytes::from(gzipped.clone()),
+        Bytes::from("plain 2"),
+    ]
+    .into_iter()
+    .map(|payload| Event::Log(LogEvent::from(payload).with_batch_notifier(&batch)))
+    .collect::<Vec<_>>();
+    drop(batch);
+    Box::new(sink)
+        .run(stream::iter(events).boxed())
+        .await
+        .unwrap();
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+
+    // The mixed batch is split into a blob compressed by the sink and a blob of the payload
+    // already compressed, which isn't compressed a second time.
+    let puts = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.method == wiremock::http::Method::Put)
+        .collect::<Vec<_>>();
+    assert_eq!(puts.len(), 2);
+    let (compressed, precompressed): (Vec<_>, Vec<_>) = puts
+        .iter()
+        .partition(|request| request.url.path().ends_with(".log.gz"));
+    assert_eq!(compressed.len(), 1);
+    let mut plain = String::new();
+    GzDecoder::new(&compressed[0].body[..])
+        .read_to_string(&mut plain)
+        .unwrap();
+    assert_eq!(plain, "plain 1plain 2");
+    assert_eq!(precompressed.len(), 1);
+    assert!(precompressed[0].url.path().ends_with(".gz"));
+    assert_eq!(precompressed[0].body, gzipped);
+
+    // The payloads already compressed have to be written as is.
+    let mut config = default_config(encoding);
+    config.precompressed = Some(precompressed_config);
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_ok());
+    config.encoding = (None::<FramingConfig>, RawMessageSerializerConfig::new()).into();
+    assert!(config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .is_err());
+}
+
+#[tokio::test]
+async fn azure_blob_batched_acknowledgements_resolve_once_written() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/devstoreaccount1/logs/blob/rejected.log"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response().set_delay(StdDuration::from_millis(200)))
+        .mount(&server)
+        .await;
+    let mut service =
+        AcknowledgementService::new(AzureBlobService::new(mock_client(&server).await));
+
+    let request_options = AzureBlobRequestOptions {
+        blob_time_format: "%s".into(),
+        blob_append_uuid: true,
+        batch_acknowledgements: true,
+        ..request_options()
+    };
+    let batch = |count: usize| {
+        let (notifiers, receivers): (Vec<_>, Vec<_>) =
+            (0..3).map(|_| BatchNotifier::new_with_receiver()).unzip();
+        let events = (0..count)
+            .map(|i| {
+                Event::Log(
+                    LogEvent::from(format!("event {}", i)).with_batch_notifier(&notifiers[i % 3]),
+                )
+            })
+            .collect::<Vec<_>>();
+        (events, receivers)
+    };
+    let build_request = |partition: &str, events: Vec<Event>| {
+        let (metadata, events) = request_options.split_input((partition.into(), events));
+        let payload = request_options.encode_events(events).unwrap();
+        request_options.build_request(metadata, payload)
+    };
+
+    // The finalizers of the large batch are merged by source batch and held by the request
+    // rather than left to the driver.
+    let (events, mut receivers) = batch(10_000);
+    let mut request = build_request("blob/", events);
+    assert_eq!(request.batched_finalizers.len(), 3);
+    assert!(request.take_finalizers().is_empty());
+
+    // No source batch is acknowledged before the blob is written, and each one is acknowledged
+    // exactly once, as delivered, afterwards.
+    let response = tokio::spawn(service.ready().await.unwrap().call(request));
+    tokio::time::sleep(StdDuration::from_millis(50)).await;
+    for receiver in &mut receivers {
+        assert!(receiver.try_recv().is_err());
+    }
+    let response = response.await.unwrap().unwrap();
+    assert_eq!(response.event_status(), EventStatus::Delivered);
+    for receiver in receivers {
+        assert_eq!(receiver.await, BatchStatus::Delivered);
+    }
+
+    // Failed writes reject their events.
+    let (events, receivers) = batch(1_000);
+    let mut request = build_request("blob/", events);
+    request.metadata.partition_key = "blob/rejected.log".into();
+    let _ = service.ready().await.unwrap().call(request).await;
+    for receiver in receivers {
+        assert_eq!(receiver.await, BatchStatus::Rejected);
+    }
+}
+
+fn csv_config(columns: &[&str]) -> CsvConfig {
+    CsvConfig {
+        columns: columns.iter().map(|column| column.to_string()).collect(),
+        unlisted_fields: CsvUnlistedFields::Drop,
+        header: CsvHeader::FirstLine,
+        null_placeholder: "NULL".into(),
+        delimiter: ',',
+    }
+}
+
+fn csv_log(fields: BTreeMap<String, Value>) -> Event {
+    Event::Log(LogEvent::from(fields))
+}
+
+#[test]
+fn azure_blob_csv_quotes_values() {
+    let csv = csv_config(&["id", "message"]).build().unwrap();
+    let events = vec![
+        csv_log(btreemap! {
+            "id" => 1,
+            "message" => "one, two",
+        }),
+        csv_log(btreemap! {
+            "id" => 2,
+            "message" => "first line\nsecond line",
+        }),
+        csv_log(btreemap! {
+            "id" => 3,
+            "message" => "say \"hi\"",
+        }),
+    ];
+
+    let table = csv.write(events, &Transformer::default()).unwrap();
+    assert_eq!(
+        String::from_utf8(table).unwrap(),
+        "id,message\n\
+         1,\"one, two\"\n\
+         2,\"first line\nsecond line\"\n\
+         3,\"say \"\"hi\"\"\"\n"
+    );
+
+    // Values holding the delimiter are quoted, whichever it is.
+    let csv = CsvConfig {
+        delimiter: ';',
+        ..csv_config(&["id", "message"])
+    }
+    .build()
+    .unwrap();
+    let events = vec![csv_log(btreemap! {
+        "id" => 1,
+        "message" => "one; two, three",
+    })];
+    let table = csv.write(events, &Transformer::default()).unwrap();
+    assert_eq!(
+        String::from_utf8(table).unwrap(),
+        "id;message\n1;\"one; two, three\"\n"
+    );
+}
+
+#[test]
+fn azure_blob_csv_keeps_column_order() {
+    let events = || {
+        vec![
+            csv_log(btreemap! {
+                "message" => "first",
+                "host" => "a",
+                "zone" => "east",
+            }),
+            csv_log(btreemap! {
+                "status" => Value::Null,
+                "message" => "second",
+                "code" => 500,
+            }),
+            csv_log(btreemap! {
+                "host" => "c",
+                "status" => "ok",
+            }),
+        ]
+    };
+
+    // Every row has the listed columns, in order, whichever fields the events have.
+    let csv = csv_config(&["host", "message", "status"]).build().unwrap();
+    let table = csv.write(events(), &Transformer::default()).unwrap();
+    assert_eq!(
+        String::from_utf8(table).unwrap(),
+        "host,message,status\n\
+         a,first,NULL\n\
+         NULL,second,NULL\n\
+         c,NULL,ok\n"
+    );
+
+    // The unlisted fields of the batch follow, sorted by name.
+    let csv = CsvConfig {
+        unlisted_fields: CsvUnlistedFields::Append,
+        ..csv_config(&["message"])
+    }
+    .build()
+    .unwrap();
+    let table = csv.write(events(), &Transformer::default()).unwrap();
+    assert_eq!(
+        String::from_utf8(table).unwrap(),
+        "message,code,host,status,zone\n\
+         first,NULL,a,NULL,east\n\
+         second,500,NULL,NULL,NULL\n\
+         NULL,NULL,c,ok,NULL\n"
+    );
+
+    // Nested fields are listed by path.
+    let csv = CsvConfig {
+        header: CsvHeader::None,
+        ..csv_config(&["request.method", "request.path"])
+    }
+    .build()
+    .unwrap();
+    let mut log = LogEvent::default();
+    log.insert("request.method", "GET");
+    log.insert("request.path", "/");
+    let table = csv
+        .write(vec![log.into()], &Transformer::default())
+        .unwrap();
+    assert_eq!(String::from_utf8(table).unwrap(), "GET,/\n");
+}
+
+#[tokio::test]
+async fn azure_blob_csv_header_sidecar() {
+    let schema_path = "/devstoreaccount1/logs/blob/_schema.csv";
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let csv = CsvConfig {
+        header: CsvHeader::Sidecar,
+        ..csv_config(&["host", "message"])
+    }
+    .build()
+    .unwrap();
+    let event = || {
+        csv_log(btreemap! {
+            "host" => "a",
+            "message" => "first",
+        })
+    };
+    // The blobs hold the rows only.
+    let table = csv.write(vec![event()], &Transformer::default()).unwrap();
+    assert_eq!(String::from_utf8(table).unwrap(), "a,first\n");
+    assert!(csv_config(&["host"]).build().unwrap().schema().is_none());
+
+    let schema = Arc::new(SchemaSidecar::new(
+        "_schema.csv",
+        CSV_CONTENT_TYPE,
+        csv.schema().unwrap(),
+    ));
+    assert_eq!(schema.sidecar("blob/test.log"), "blob/_schema.csv");
+    assert_eq!(schema.sidecar("test.log"), "_schema.csv");
+    let mut service =
+        AzureBlobService::new(mock_client(&server).await).with_schema_sidecar(Some(schema));
+    service.call(mock_request()).await.unwrap();
+    service.call(mock_request()).await.unwrap();
+
+    // The sidecar is written once, along with the first blob of its directory.
+    let sidecars = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.url.path() == schema_path)
+        .collect::<Vec<_>>();
+    assert_eq!(sidecars.len(), 1);
+    assert_eq!(sidecars[0].body, b"host,message\n");
+    assert_eq!(received_puts(&server).await, 3);
+}
+
+#[tokio::test]
+async fn azure_blob_csv_config_errors() {
+    let server = MockServer::start().await;
+    let client = mock_client(&server).await;
+    let build = |config: AzureBlobSinkConfig| {
+        config
+            .build_processor(Arc::clone(&client), Arc::clone(&client))
+            .map(|_| ())
+    };
+    let config = |csv: CsvConfig| AzureBlobSinkConfig {
+        csv: Some(csv),
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+
+    assert!(build(config(csv_config(&["message"]))).is_ok());
+    assert!(build(config(csv_config(&[]))).is_err());
+    assert!(build(config(csv_config(&["message", "message"]))).is_err());
+    assert!(build(config(CsvConfig {
+        delimiter: '"',
+        ..csv_config(&["message"])
+    }))
+    .is_err());
+    // The columns of the blobs vary with the fields appended, so no sidecar can describe them.
+    assert!(build(config(CsvConfig {
+        unlisted_fields: CsvUnlistedFields::Append,
+        header: CsvHeader::Sidecar,
+        ..csv_config(&[])
+    }))
+    .is_err());
+    assert!(build(AzureBlobSinkConfig {
+        avro_ocf: Some(AvroOcfConfig {
+            schema: None,
+            record_name: "Event".into(),
+            codec: AvroOcfCodec::Deflate,
+        }),
+        ..config(csv_config(&["message"]))
+    })
+    .is_err());
+}
+
+#[tokio::test]
+async fn azure_blob_reload_cancels_queued_requests_without_draining() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response().set_delay(StdDuration::from_secs(30)))
+        .mount(&server)
+        .await;
+    let client = mock_client(&server).await;
+
+    let sink_config = AzureBlobSinkConfig {
+        blob_prefix: Some("blob/".into()),
+        container_name: "logs".into(),
+        drain_on_reload: false,
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+    let request_options = AzureBlobRequestOptions {
+        blob_time_format: "%s".into(),
+        blob_append_uuid: true,
+        ..request_options()
+    };
+    // Every event is a request of its own, and a single request is in flight at a time.
+    let settings = BatcherSettings::new(
+        StdDuration::from_secs(300),
+        NonZeroUsize::new(10_000_000).unwrap(),
+        NonZeroUsize::new(1).unwrap(),
+    );
+    let token = CancellationToken::new();
+    let service = CancellationService::new(
+        ServiceBuilder::new()
+            .concurrency_limit(1)
+            .service(AzureBlobService::new(client)),
+        Some(token.clone()),
+    );
+    let sink = AzureBlobSink::new(
+        service,
+        request_options,
+        sink_config.key_partitioner().unwrap(),
+        settings,
+    )
+    .with_cancellation(token.clone());
+
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    let sink = tokio::spawn(async move { Box::new(sink).run(rx.boxed()).await });
+
+    let receivers = (0..3)
+        .map(|_| {
+            let (batch, receiver) = BatchNotifier::new_with_receiver();
+            let log = LogEvent::from("test message").with_batch_notifier(&batch);
+            tx.unbounded_send(Event::Log(log)).unwrap();
+            receiver
+        })
+        .collect::<Vec<_>>();
+    // Lets the first request go in flight while the others queue up behind it.
+    tokio::time::sleep(StdDuration::from_millis(200)).await;
+    assert!(!token.is_cancelled());
+
+    // Reloading ends the input of the sink, which stops without waiting for the write in flight.
+    let started = Instant::now();
+    drop(tx);
+    assert_eq!(sink.await.unwrap(), Ok(()));
+    assert!(started.elapsed() < StdDuration::from_secs(5));
+    assert!(token.is_cancelled());
+
+    // Neither the queued requests nor the one in flight were written, and none of their events
+    // were dropped silently.
+    assert!(received_puts(&server).await <= 1);
+    for receiver in receivers {
+        assert_eq!(receiver.await, BatchStatus::Rejected);
+    }
+}
+
+#[tokio::test]
+async fn azure_blob_groups_events_by_schema_fingerprint() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let config = AzureBlobSinkConfig {
+        blob_prefix: Some("blob/".into()),
+        container_name: "logs".into(),
+        schema_fingerprint: Some(SchemaFingerprintConfig {
+            max_fingerprints: 10.try_into().unwrap(),
+        }),
+        ..default_config((None::<FramingConfig>, JsonSerializerConfig::new()).into())
+    };
+    let sink = config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .unwrap();
+
+    let (batch, receiver) = BatchNotifier::new_with_receiver();
+    let request = |path: &str, status: i64| -> BTreeMap<String, Value> {
+        btreemap! { "path" => path, "status" => status }
+    };
+    let login = |user: &str| -> BTreeMap<String, Value> {
+        btreemap! { "user" => user, "success" => true }
+    };
+    let events = vec![
+        request("/", 200),
+        login("alice"),
+        request("/health", 503),
+        login("bob"),
+    ]
+    .into_iter()
+    .map(|fields| Event::Log(LogEvent::from(fields).with_batch_notifier(&batch)))
+    .collect::<Vec<_>>();
+    drop(batch);
+    let requests = fingerprint(&events[0]);
+    let logins = fingerprint(&events[1]);
+    assert_ne!(requests, logins);
+    sink.run_events(events).await.unwrap();
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+
+    let puts = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.method == wiremock::http::Method::Put)
+        .collect::<Vec<_>>();
+    assert_eq!(puts.len(), 2);
+
+    // Each blob holds the events of a single schema, named and tagged after its fingerprint.
+    let mut written = Vec::new();
+    for put in &puts {
+        let fingerprint = put
+            .url
+            .path()
+            .split("/schema=")
+            .nth(1)
+            .and_then(|rest| rest.split('/').next())
+            .unwrap()
+            .to_owned();
+        let metadata = put
+            .headers
+            .iter()
+            .find(|(name, _)| name.as_str() == "x-ms-meta-schema_fingerprint")
+            .map(|(_, values)| values.last().as_str().to_owned());
+        assert_eq!(metadata.as_ref(), Some(&fingerprint));
+
+        let body = String::from_utf8(put.body.clone()).unwrap();
+        let expected = if fingerprint == requests {
+            [r#""path":"/""#, r#""path":"/health""#]
+        } else {
+            assert_eq!(fingerprint, logins);
+            [r#""user":"alice""#, r#""user":"bob""#]
+        };
+        for line in expected {
+            assert!(body.contains(line), "{}", body);
+        }
+        written.push(fingerprint);
+    }
+    written.sort();
+    let mut expected = vec![requests, logins];
+    expected.sort();
+    assert_eq!(written, expected);
+}
+
+#[tokio::test]
+async fn azure_blob_splits_batches_by_schema_version() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let config = AzureBlobSinkConfig {
+        blob_prefix: Some("blob/".into()),
+        blob_append_uuid: Some(false),
+        container_name: "logs".into(),
+        schema_version: Some(SchemaVersionConfig {
+            field: "schema_version".into(),
+        }),
+        ..default_config((None::<FramingConfig>, JsonSerializerConfig::new()).into())
+    };
+    let sink = config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .unwrap();
+
+    // The events share a partition and a batch, but not the version of their schema.
+    let (batch, receiver) = BatchNotifier::new_with_receiver();
+    let event = |message: &str, version: &str| -> BTreeMap<String, Value> {
+        btreemap! { "message" => message, "schema_version" => version }
+    };
+    let events = vec![
+        event("first v1", "1"),
+        event("first v2", "2"),
+        event("second v1", "1"),
+        event("second v2", "2"),
+    ]
+    .into_iter()
+    .map(|fields| Event::Log(LogEvent::from(fields).with_batch_notifier(&batch)))
+    .collect::<Vec<_>>();
+    drop(batch);
+    sink.run_events(events).await.unwrap();
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+
+    let mut puts = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.method == wiremock::http::Method::Put)
+        .collect::<Vec<_>>();
+    puts.sort_by(|a, b| a.url.path().cmp(b.url.path()));
+    assert_eq!(puts.len(), 2);
+
+    for (put, version) in puts.iter().zip(["1", "2"]) {
+        assert!(
+            put.url
+                .path()
+                .starts_with(&format!("/devstoreaccount1/logs/blob/version={}/", version)),
+            "{}",
+            put.url.path()
+        );
+        let metadata = put
+            .headers
+            .iter()
+            .find(|(name, _)| name.as_str() == "x-ms-meta-schema_version")
+            .map(|(_, values)| values.last().as_str().to_owned());
+        assert_eq!(metadata.as_deref(), Some(version));
+
+        let body = String::from_utf8(put.body.clone()).unwrap();
+        for message in ["first", "second"] {
+            assert!(
+                body.contains(&format!(r#""message":"{} v{}""#, message, version)),
+                "{}",
+                body
+            );
+        }
+        assert_eq!(body.matches(r#""message":"#).count(), 2, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn azure_blob_sets_content_language_from_events() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let config = AzureBlobSinkConfig {
+        blob_prefix: Some("{{ dir }}/".into()),
+        blob_append_uuid: Some(false),
+        container_name: "logs".into(),
+        content_language: Some(ContentLanguageConfig {
+            template: Template::try_from("{{ language }}").unwrap(),
+            default: None,
+        }),
+        ..default_config((None::<FramingConfig>, JsonSerializerConfig::new()).into())
+    };
+    let sink = config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .unwrap();
+
+    let (batch, receiver) = BatchNotifier::new_with_receiver();
+    let events: Vec<BTreeMap<String, Value>> = vec![
+        btreemap! { "dir" => "french", "language" => "fr-CA" },
+        btreemap! { "dir" => "invalid", "language" => "not a language" },
+        btreemap! { "dir" => "mixed", "language" => "de" },
+        btreemap! { "dir" => "mixed", "language" => "es" },
+        btreemap! { "dir" => "unset" },
+    ];
+    let events = events
+        .into_iter()
+        .map(|fields| Event::Log(LogEvent::from(fields).with_batch_notifier(&batch)))
+        .collect::<Vec<_>>();
+    drop(batch);
+    sink.run_events(events).await.unwrap();
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+
+    let languages = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.method == wiremock::http::Method::Put)
+        .map(|put| {
+            let language = put
+                .headers
+                .iter()
+                .find(|(name, _)| name.as_str() == "x-ms-blob-content-language")
+                .map(|(_, values)| values.last().as_str().to_owned());
+            // The directory the blob was written to, after the account and the container.
+            let dir = put.url.path().split('/').nth(3).unwrap().to_owned();
+            (dir, language)
+        })
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(
+        languages,
+        btreemap! {
+            "french" => Some("fr-CA".to_owned()),
+            "invalid" => None,
+            "mixed" => None,
+            "unset" => None,
+        }
+    );
+}
+
+#[tokio::test]
+async fn azure_blob_normalizes_rendered_blob_paths() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+
+    let config = AzureBlobSinkConfig {
+        blob_prefix: Some("/{{ dir }}/".into()),
+        container_name: "logs".into(),
+        blob_path_policy: BlobPathPolicy::Normalize,
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+    let sink = config
+        .build_processor(mock_client(&server).await, mock_client(&server).await)
+        .unwrap();
+
+    let (batch, receiver) = BatchNotifier::new_with_receiver();
+    let mut event = LogEvent::from("message").with_batch_notifier(&batch);
+    // A path from a Windows host.
+    event.insert("dir", "app\\2022\\");
+    drop(batch);
+    sink.run_events(vec![Event::Log(event)]).await.unwrap();
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+
+    let paths = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|request| request.url.path().to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(paths.len(), 1);
+    assert!(
+        paths[0].starts_with("/devstoreaccount1/logs/app/2022/"),
+        "{}",
+        paths[0]
+    );
+    assert!(!paths[0]["/devstoreaccount1/logs/app/2022/".len()..].contains('/'));
+}
+
+/// Encodes the batches of the partitions starting with `slow` as if compressing them took a
+/// while, blocking the thread meanwhile.
+struct SlowEncoding {
+    inner: AzureBlobRequestOptions,
+    delay: StdDuration,
+}
+
+impl RequestBuilder<(String, Vec<Event>)> for SlowEncoding {
+    type Metadata = <AzureBlobRequestOptions as RequestBuilder<(String, Vec<Event>)>>::Metadata;
+    type Events = <AzureBlobRequestOptions as RequestBuilder<(String, Vec<Event>)>>::Events;
+    type Encoder = <AzureBlobRequestOptions as RequestBuilder<(String, Vec<Event>)>>::Encoder;
+    type Payload = Bytes;
+    type Request = AzureBlobRequest;
+    type Error = std::io::Error;
+
+    fn compression(&self) -> Compression {
+        self.inner.compression()
+    }
+
+    fn encoder(&self) -> &Self::Encoder {
+        self.inner.encoder()
+    }
+
+    fn split_input(&self, input: (String, Vec<Event>)) -> (Self::Metadata, Self::Events) {
+        self.inner.split_input(input)
+    }
+
+    fn encode_events(
+        &self,
+        events: Self::Events,
+    ) -> Result<EncodeResult<Self::Payload>, Self::Error> {
+        if events.2.starts_with("slow") {
+            std::thread::sleep(self.delay);
+        }
+        self.inner.encode_events(events)
+    }
+
+    fn build_request(
+        &self,
+        metadata: Self::Metadata,
+        payload: EncodeResult<Self::Payload>,
+    ) -> Self::Request {
+        self.inner.build_request(metadata, payload)
+    }
+}
+
+#[tokio::test]
+async fn azure_blob_encoding_pool_keeps_heavy_batches_off_the_upload_path() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(mock_put_response())
+        .mount(&server)
+        .await;
+    let client = mock_client(&server).await;
+
+    let sink_config = AzureBlobSinkConfig {
+        blob_prefix: Some("{{ speed }}/".into()),
+        container_name: "logs".into(),
+        ..default_config((None::<FramingConfig>, TextSerializerConfig::new()).into())
+    };
+    let request_options = AzureBlobRequestOptions {
+        blob_time_format: "%s".into(),
+        blob_append_uuid: true,
+        ..request_options()
+    };
+    // Every event is flushed as a batch of its own as soon as it's taken in.
+    let settings = BatcherSettings::new(
+        StdDuration::from_secs(300),
+        NonZeroUsize::new(10_000_000).unwrap(),
+        NonZeroUsize::new(1).unwrap(),
+    );
+    let sink = AzureBlobSink::new(
+        AzureBlobService::new(client),
+        SlowEncoding {
+            inner: request_options,
+            delay: StdDuration::from_secs(2),
+        },
+        sink_config.key_partitioner().unwrap(),
+        settings,
+    )
+    .with_encoding_pool(NonZeroUsize::new(2).unwrap());
+
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    let sink = tokio::spawn(async move { Box::new(sink).run(rx.boxed()).await });
+    let (batch, receiver) = BatchNotifier::new_with_receiver();
+    for speed in ["slow", "fast"] {
+        let mut log = LogEvent::from("test message").with_batch_notifier(&batch);
+        log.insert("speed", speed);
+        tx.unbounded_send(Event::Log(log)).unwrap();
+    }
+    drop(batch);
+
+    // The batch flushed second is uploaded while the first one is still being encoded.
+    tokio::time::sleep(StdDuration::from_millis(500)).await;
+    let puts = server.received_requests().await.unwrap();
+    assert_eq!(puts.len(), 1);
+    assert!(puts[0]
+        .url
+        .path()
+        .starts_with("/devstoreaccount1/logs/fast/"));
+
+    drop(tx);
+    assert_eq!(sink.await.unwrap(), Ok(()));
+    assert_eq!(receiver.await, BatchStatus::Delivered);
+    assert_eq!(received_puts(&server).await, 2);
 }
@@ -0,0 +1,57 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::sinks::util::partitioner::TimeWindow;
+
+fn default_future_prefix() -> String {
+    "_future/".to_owned()
+}
+
+fn default_late_prefix() -> String {
+    "_late/".to_owned()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TimeWindowConfig {
+    /// How far ahead of the current time events can be timestamped before being filed as future.
+    pub max_future_skew_secs: Option<u64>,
+    /// How far behind the current time events can be timestamped before being filed as late.
+    pub max_past_age_secs: Option<u64>,
+    /// Prepended to the blob name of the events timestamped too far ahead.
+    #[serde(default = "default_future_prefix")]
+    pub future_prefix: String,
+    /// Prepended to the blob name of the events timestamped too far behind.
+    #[serde(default = "default_late_prefix")]
+    pub late_prefix: String,
+}
+
+impl TimeWindowConfig {
+    pub fn build(&self) -> crate::Result<TimeWindow> {
+        if self.max_future_skew_secs.is_none() && self.max_past_age_secs.is_none() {
+            return Err("`time_window` needs `max_future_skew_secs` or `max_past_age_secs`".into());
+        }
+        for (option, prefix) in [
+            ("future_prefix", &self.future_prefix),
+            ("late_prefix", &self.late_prefix),
+        ] {
+            if prefix.contains('%') || prefix.contains("{{") {
+                return Err(format!("`time_window.{}` can't be a template", option).into());
+            }
+        }
+
+        let duration = |option: &str, secs: Option<u64>| {
+            secs.map(|secs| {
+                Duration::from_std(std::time::Duration::from_secs(secs))
+                    .map_err(|_| format!("`time_window.{}` is too large", option))
+            })
+            .transpose()
+        };
+        Ok(TimeWindow {
+            max_future_skew: duration("max_future_skew_secs", self.max_future_skew_secs)?,
+            max_past_age: duration("max_past_age_secs", self.max_past_age_secs)?,
+            future_prefix: self.future_prefix.clone(),
+            late_prefix: self.late_prefix.clone(),
+        })
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use zstd::{bulk::Compressor, dict::EncoderDictionary};
+
+pub const ZSTD_CONTENT_TYPE: &str = "application/zstd";
+
+const fn default_level() -> i32 {
+    3
+}
+
+/// The size the zstd command line trains its dictionaries to by default.
+const fn default_max_size() -> usize {
+    112_640
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ZstdConfig {
+    #[serde(default = "default_level")]
+    pub level: i32,
+    /// The dictionaries to compress with, the first one whose pattern matches the partition of
+    /// a batch being used for it.
+    #[serde(default)]
+    pub dictionaries: Vec<ZstdDictionaryConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ZstdDictionaryConfig {
+    /// Glob pattern the partitions compressed with the dictionary match.
+    pub partitions: String,
+    /// A dictionary file, as trained by `zstd --train`.
+    pub path: Option<PathBuf>,
+    /// A sample of the data of the partitions, one sample per line, to train the dictionary from
+    /// when the sink starts.
+    pub train_from: Option<PathBuf>,
+    /// The largest dictionary trained from `train_from`.
+    #[serde(default = "default_max_size")]
+    pub max_size: usize,
+}
+
+impl ZstdConfig {
+    pub fn build(&self) -> crate::Result<Zstd> {
+        let max_level = *zstd::compression_level_range().end();
+        if !(1..=max_level).contains(&self.level) {
+            return Err(format!("`zstd.level` must be between 1 and {}", max_level).into());
+        }
+        let dictionaries = self
+            .dictionaries
+            .iter()
+            .map(|dictionary| dictionary.build(self.level).map(Arc::new))
+            .collect::<crate::Result<_>>()?;
+        Ok(Zstd {
+            level: self.level,
+            dictionaries,
+        })
+    }
+}
+
+impl ZstdDictionaryConfig {
+    fn build(&self, level: i32) -> crate::Result<ZstdDictionary> {
+        let pattern = Pattern::new(&self.partitions).map_err(|error| {
+            format!(
+                "Invalid `zstd.dictionaries.partitions` {:?}: {}",
+                self.partitions, error
+            )
+        })?;
+        let dictionary = match (&self.path, &self.train_from) {
+            (Some(path), None) => fs::read(path)
+                .map_err(|error| format!("Failed to read zstd dictionary {:?}: {}", path, error))?,
+            (None, Some(train_from)) => train(train_from, self.max_size)?,
+            _ => {
+                return Err("Exactly one of `zstd.dictionaries.path` and \
+                            `zstd.dictionaries.train_from` must be set"
+                    .into())
+            }
+        };
+
+        // Raw content dictionaries have no ID, so the blobs compressed with them couldn't tell
+        // which dictionary they need.
+        let id = zstd::zstd_safe::get_dict_id_from_dict(&dictionary);
+        if id == 0 {
+            return Err(format!(
+                "zstd dictionary for {:?} has no ID; train it with `zstd --train`",
+                self.partitions
+            )
+            .into());
+        }
+        Ok(ZstdDictionary {
+            pattern,
+            id,
+            dictionary: EncoderDictionary::copy(&dictionary, level),
+        })
+    }
+}
+
+/// Trains a dictionary from the lines of the sample file.
+fn train(train_from: &Path, max_size: usize) -> crate::Result<Vec<u8>> {
+    let sample = fs::read(train_from)
+        .map_err(|error| format!("Failed to read zstd sample {:?}: {}", train_from, error))?;
+    let samples = sample
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    zstd::dict::from_samples(&samples, max_size).map_err(|error| {
+        format!(
+            "Failed to train a zstd dictionary from {:?}: {}",
+            train_from, error
+        )
+        .into()
+    })
+}
+
+/// A dictionary compressing the batches of the partitions matching its pattern.
+pub struct ZstdDictionary {
+    pattern: Pattern,
+    id: u32,
+    dictionary: EncoderDictionary<'static>,
+}
+
+impl ZstdDictionary {
+    pub const fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+/// Compresses each blob as a single zstd frame, with the dictionary of its partition if there is
+/// one.
+#[derive(Clone)]
+pub struct Zstd {
+    level: i32,
+    dictionaries: Vec<Arc<ZstdDictionary>>,
+}
+
+impl Zstd {
+    /// The dictionary the batches of the partition are compressed with.
+    pub fn dictionary(&self, partition: &str) -> Option<Arc<ZstdDictionary>> {
+        self.dictionaries
+            .iter()
+            .find(|dictionary| dictionary.pattern.matches(partition))
+            .map(Arc::clone)
+    }
+
+    pub fn compress(
+        &self,
+        data: &[u8],
+        dictionary: Option<&ZstdDictionary>,
+    ) -> io::Result<Vec<u8>> {
+        match dictionary {
+            Some(dictionary) => {
+                Compressor::with_prepared_dictionary(&dictionary.dictionary)?.compress(data)
+            }
+            None => zstd::bulk::compress(data, self.level),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trains_dictionary_from_sample() {
+        let sample = crate::test_util::temp_file();
+        let lines = (0..2000)
+            .map(|i| format!("level=info host=web-{} msg=\"request {} served\"", i % 7, i))
+            .collect::<Vec<_>>();
+        fs::write(&sample, lines.join("\n")).unwrap();
+
+        let dictionary = |path: Option<PathBuf>, train_from| ZstdConfig {
+            level: 3,
+            dictionaries: vec![ZstdDictionaryConfig {
+                partitions: "*".into(),
+                path,
+                train_from,
+                max_size: 4096,
+            }],
+        };
+        let zstd = dictionary(None, Some(sample.clone())).build().unwrap();
+        assert!(zstd.dictionary("any/").is_some());
+
+        // Raw content isn't a dictionary the blobs could refer to.
+        assert!(dictionary(Some(sample.clone()), None).build().is_err());
+        assert!(dictionary(Some(sample.clone()), Some(sample))
+            .build()
+            .is_err());
+        assert!(dictionary(None, None).build().is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use futures::future::BoxFuture;
+use tower::Service;
+use vector_core::{event::EventStatus, stream::DriverResponse};
+
+use super::config::{AzureBlobRequest, AzureBlobResponse};
+use crate::internal_events::azure_blob::{
+    AzureBlobAcknowledgementProvenance, AzureBlobFinalizersResolved,
+};
+
+/// Resolves the batched finalizers of the requests once they are done, retries included, in a
+/// single pass rather than leaving them to the driver.
+///
+/// It wraps the whole stack of the sink, so that the finalizers are only resolved once the
+/// outcome of their request is final: no event is acknowledged before its blob is written.
+///
+/// The provenance of the written blobs, when described by their responses, is announced before
+/// their events are acknowledged, whether by this service or by the driver.
+pub struct AcknowledgementService<S> {
+    inner: S,
+}
+
+impl<S> AcknowledgementService<S> {
+    pub const fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S> Service<AzureBlobRequest> for AcknowledgementService<S>
+where
+    S: Service<AzureBlobRequest, Response = AzureBlobResponse>,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: AzureBlobRequest) -> Self::Future {
+        let finalizers = std::mem::take(&mut request.batched_finalizers);
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let result = response.await;
+            if let Ok(AzureBlobResponse {
+                provenance: Some(provenance),
+                count,
+                ..
+            }) = &result
+            {
+                emit!(AzureBlobAcknowledgementProvenance {
+                    provenance,
+                    count: *count,
+                });
+            }
+            if !finalizers.is_empty() {
+                let status = match &result {
+                    Ok(response) => response.event_status(),
+                    Err(_) => EventStatus::Rejected,
+                };
+                let count = finalizers.len();
+                let started = Instant::now();
+                finalizers.resolve(status);
+                emit!(AzureBlobFinalizersResolved {
+                    count,
+                    elapsed: started.elapsed(),
+                });
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::{ServiceBuilder, ServiceExt};
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::{
+        event::{BatchNotifier, BatchStatus, EventFinalizer, EventFinalizers, Finalizable},
+        sinks::{
+            azure_common::{
+                config::AzureBlobRetryLogic,
+                service::AzureBlobService,
+                test_util::{mock_client, mock_put_response, mock_request, received_puts},
+            },
+            util::{ServiceBuilderExt, TowerRequestConfig},
+        },
+    };
+
+    #[tokio::test]
+    async fn acknowledges_retried_requests_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(mock_put_response())
+            .mount(&server)
+            .await;
+
+        let request_settings = TowerRequestConfig::default()
+            .retry_initial_backoff_secs(1)
+            .unwrap_with(&TowerRequestConfig::default());
+        let mut service = AcknowledgementService::new(
+            ServiceBuilder::new()
+                .settings(request_settings, AzureBlobRetryLogic)
+                .service(AzureBlobService::new(mock_client(&server).await)),
+        );
+
+        let (batch, receiver) = BatchNotifier::new_with_receiver();
+        let (batched, batched_receiver) = BatchNotifier::new_with_receiver();
+        let mut request = mock_request();
+        request.metadata.finalizers = EventFinalizers::new(EventFinalizer::new(batch));
+        request.batched_finalizers = EventFinalizers::new(EventFinalizer::new(batched));
+        // The driver takes the finalizers before sending the request, whose retries go through
+        // clones of it.
+        let finalizers = request.take_finalizers();
+
+        let response = service.ready().await.unwrap().call(request).await.unwrap();
+        assert_eq!(response.attempts, 2);
+        assert_eq!(received_puts(&server).await, 2);
+        finalizers.resolve(response.event_status());
+
+        assert_eq!(receiver.await, BatchStatus::Delivered);
+        assert_eq!(batched_receiver.await, BatchStatus::Delivered);
+        assert!(!vector_common::event_test_util::contains_name(
+            "AzureBlobFinalizerDoubleTake"
+        ));
+    }
+}
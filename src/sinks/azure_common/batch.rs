@@ -0,0 +1,365 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use azure_core::HttpClient;
+use azure_storage::prelude::*;
+use azure_storage_blobs::prelude::*;
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::future::BoxFuture;
+use http::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    Method, Request, StatusCode,
+};
+use snafu::Snafu;
+use tower::Service;
+use uuid::Uuid;
+
+/// The most sub-requests the Blob Batch API accepts in a single batch.
+pub const MAX_BATCH_SIZE: usize = 256;
+
+#[derive(Debug, Snafu)]
+pub enum BatchError {
+    #[snafu(display("Batch response has no multipart boundary"))]
+    MissingBoundary,
+    #[snafu(display("Malformed batch response: {}", reason))]
+    MalformedResponse { reason: String },
+}
+
+/// The response to one of the sub-requests of a batch.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubResponse {
+    /// The index of the sub-request within the batch, which is missing when the batch as a whole
+    /// was refused.
+    pub content_id: Option<usize>,
+    pub status: StatusCode,
+    /// The `x-ms-error-code` of the sub-response, if it failed.
+    pub error_code: Option<String>,
+}
+
+/// What became of a blob deleted as part of a batch.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeleteOutcome {
+    Deleted,
+    /// The blob was already gone, which is what deleting it was meant to achieve anyway.
+    NotFound,
+    Failed(String),
+}
+
+/// Writes the sub-requests as the `multipart/mixed` body of a batch delimited by `boundary`.
+///
+/// Each sub-request is laid out as an HTTP/1.1 request of its own, with its index in the batch as
+/// its `Content-ID` so that its response can be matched back to it.
+pub fn encode_batch(boundary: &str, sub_requests: &[Request<Bytes>]) -> Bytes {
+    let mut body = BytesMut::new();
+    for (index, request) in sub_requests.iter().enumerate() {
+        let target = request
+            .uri()
+            .path_and_query()
+            .map_or("/", |path_and_query| path_and_query.as_str());
+        body.put_slice(
+            format!(
+                "--{}\r\nContent-Type: application/http\r\nContent-Transfer-Encoding: binary\r\n\
+                 Content-ID: {}\r\n\r\n{} {} HTTP/1.1\r\n",
+                boundary,
+                index,
+                request.method(),
+                target
+            )
+            .as_bytes(),
+        );
+        for (name, value) in request.headers() {
+            body.put_slice(name.as_str().as_bytes());
+            body.put_slice(b": ");
+            body.put_slice(value.as_bytes());
+            body.put_slice(b"\r\n");
+        }
+        if !request.headers().contains_key(CONTENT_LENGTH) {
+            body.put_slice(format!("Content-Length: {}\r\n", request.body().len()).as_bytes());
+        }
+        body.put_slice(b"\r\n");
+        body.put_slice(request.body());
+        body.put_slice(b"\r\n");
+    }
+    body.put_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body.freeze()
+}
+
+/// Parses the `multipart/mixed` response to a batch into the responses to its sub-requests.
+pub fn parse_batch_response(
+    content_type: &str,
+    body: &[u8],
+) -> Result<Vec<SubResponse>, BatchError> {
+    let boundary = content_type
+        .split(';')
+        .filter_map(|parameter| parameter.trim().strip_prefix("boundary="))
+        .map(|boundary| boundary.trim_matches('"'))
+        .find(|boundary| !boundary.is_empty())
+        .ok_or(BatchError::MissingBoundary)?;
+    let body = String::from_utf8_lossy(body).replace("\r\n", "\n");
+    let delimiter = format!("--{}", boundary);
+
+    let mut parts = body.split(delimiter.as_str());
+    // Anything before the first delimiter is a preamble to be ignored.
+    parts.next();
+    let mut responses = Vec::new();
+    for part in parts {
+        if part.starts_with("--") {
+            return Ok(responses);
+        }
+        responses.push(parse_part(part.trim_start_matches('\n'))?);
+    }
+    Err(BatchError::MalformedResponse {
+        reason: "missing closing delimiter".into(),
+    })
+}
+
+fn parse_part(part: &str) -> Result<SubResponse, BatchError> {
+    let malformed = |reason: &str| BatchError::MalformedResponse {
+        reason: reason.into(),
+    };
+    let (part_headers, response) = part
+        .split_once("\n\n")
+        .ok_or_else(|| malformed("part has no response"))?;
+    let content_id = match header(part_headers.lines(), "content-id") {
+        Some(content_id) => Some(
+            content_id
+                .parse()
+                .map_err(|_| malformed("invalid Content-ID"))?,
+        ),
+        None => None,
+    };
+
+    let mut lines = response.lines();
+    let status = lines
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .ok_or_else(|| malformed("invalid status line"))?;
+    let error_code = header(lines.take_while(|line| !line.is_empty()), "x-ms-error-code");
+
+    Ok(SubResponse {
+        content_id,
+        status,
+        error_code: error_code.map(str::to_owned),
+    })
+}
+
+/// Finds the value of the header `name` among the header lines.
+fn header<'a>(mut lines: impl Iterator<Item = &'a str>, name: &str) -> Option<&'a str> {
+    lines.find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+/// Matches the responses of a batch of deletes back to the blobs they were sent for.
+///
+/// A response without a `Content-ID` means the batch was refused as a whole, so it is the outcome
+/// of every blob that has no response of its own.
+pub fn delete_outcomes(
+    blobs: &[String],
+    responses: &[SubResponse],
+) -> Vec<(String, DeleteOutcome)> {
+    let batch_failure = responses
+        .iter()
+        .find(|response| response.content_id.is_none());
+    blobs
+        .iter()
+        .enumerate()
+        .map(|(index, blob)| {
+            let response = responses
+                .iter()
+                .find(|response| response.content_id == Some(index))
+                .or(batch_failure);
+            let outcome = match response {
+                Some(response) if response.status.is_success() => DeleteOutcome::Deleted,
+                Some(response) if response.status == StatusCode::NOT_FOUND => {
+                    DeleteOutcome::NotFound
+                }
+                Some(response) => DeleteOutcome::Failed(match &response.error_code {
+                    Some(error_code) => format!("{} ({})", response.status, error_code),
+                    None => response.status.to_string(),
+                }),
+                None => DeleteOutcome::Failed("no response to the delete in the batch".into()),
+            };
+            (blob.clone(), outcome)
+        })
+        .collect()
+}
+
+/// Deletes up to [`MAX_BATCH_SIZE`] blobs of the container through the Blob Batch API.
+///
+/// Errors only cover the batch as a whole, the failures of single deletes are reported through
+/// their outcome.
+pub struct BatchDeleteService {
+    pub client: Arc<ContainerClient>,
+}
+
+impl Service<Vec<String>> for BatchDeleteService {
+    type Response = Vec<(String, DeleteOutcome)>;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, blobs: Vec<String>) -> Self::Future {
+        let client = Arc::clone(&self.client);
+        Box::pin(async move {
+            let account = client.storage_client().storage_account_client();
+            let sub_requests = blobs
+                .iter()
+                .map(|blob| {
+                    let url = Arc::clone(&client)
+                        .as_blob_client(blob.as_str())
+                        .url_with_segments(None)?;
+                    let (request, _) = account.prepare_request(
+                        url.as_str(),
+                        &Method::DELETE,
+                        ServiceType::Blob,
+                        &|builder| builder,
+                        None,
+                    )?;
+                    Ok(request)
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+
+            let boundary = format!("batch_{}", Uuid::new_v4());
+            let body = encode_batch(&boundary, &sub_requests);
+            let mut url = client.url_with_segments(None)?;
+            url.query_pairs_mut()
+                .append_pair("restype", "container")
+                .append_pair("comp", "batch");
+            let content_type = format!("multipart/mixed; boundary={}", boundary);
+            let content_length = body.len();
+            let (request, _) = account.prepare_request(
+                url.as_str(),
+                &Method::POST,
+                ServiceType::Blob,
+                &|builder| {
+                    builder
+                        .header(CONTENT_TYPE, content_type.as_str())
+                        .header(CONTENT_LENGTH, content_length)
+                },
+                Some(body),
+            )?;
+
+            let response = account
+                .http_client()
+                .execute_request_check_status(request, StatusCode::ACCEPTED)
+                .await?;
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .unwrap_or_default();
+            let responses = parse_batch_response(content_type, response.body())?;
+            Ok(delete_outcomes(&blobs, &responses))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_partially_successful_batch() {
+        let sub_requests = ["_vector/probe/a", "_vector/probe/b"]
+            .iter()
+            .map(|blob| {
+                http::Request::delete(format!("http://127.0.0.1/devstoreaccount1/logs/{}", blob))
+                    .header("x-ms-date", "Thu, 01 Sep 2022 00:00:00 GMT")
+                    .body(Bytes::new())
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let body = String::from_utf8(encode_batch("batch_0", &sub_requests).to_vec()).unwrap();
+        assert!(body.starts_with(
+            "--batch_0\r\nContent-Type: application/http\r\nContent-Transfer-Encoding: binary\r\n\
+             Content-ID: 0\r\n\r\nDELETE /devstoreaccount1/logs/_vector/probe/a HTTP/1.1\r\n\
+             x-ms-date: Thu, 01 Sep 2022 00:00:00 GMT\r\nContent-Length: 0\r\n\r\n"
+        ));
+        assert!(body.contains("Content-ID: 1\r\n"));
+        assert!(body.ends_with("--batch_0--\r\n"));
+
+        let response = "--batchresponse_1\r\n\
+            Content-Type: application/http\r\n\
+            Content-ID: 0\r\n\
+            \r\n\
+            HTTP/1.1 202 Accepted\r\n\
+            x-ms-delete-type-permanent: true\r\n\
+            x-ms-request-id: 00000000-0000-0000-0000-000000000000\r\n\
+            \r\n\
+            --batchresponse_1\r\n\
+            Content-Type: application/http\r\n\
+            Content-ID: 1\r\n\
+            \r\n\
+            HTTP/1.1 404 The specified blob does not exist.\r\n\
+            x-ms-error-code: BlobNotFound\r\n\
+            Content-Type: application/xml\r\n\
+            \r\n\
+            <?xml version=\"1.0\" encoding=\"utf-8\"?><Error><Code>BlobNotFound</Code></Error>\r\n\
+            --batchresponse_1\r\n\
+            Content-Type: application/http\r\n\
+            Content-ID: 2\r\n\
+            \r\n\
+            HTTP/1.1 403 Server failed to authenticate the request.\r\n\
+            x-ms-error-code: AuthenticationFailed\r\n\
+            \r\n\
+            --batchresponse_1--\r\n";
+        let responses = parse_batch_response(
+            "multipart/mixed; boundary=batchresponse_1",
+            response.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            responses[1],
+            SubResponse {
+                content_id: Some(1),
+                status: http::StatusCode::NOT_FOUND,
+                error_code: Some("BlobNotFound".into()),
+            }
+        );
+
+        let blobs = ["a", "b", "c", "d"].map(String::from);
+        assert_eq!(
+            delete_outcomes(&blobs, &responses),
+            vec![
+                ("a".into(), DeleteOutcome::Deleted),
+                ("b".into(), DeleteOutcome::NotFound),
+                (
+                    "c".into(),
+                    DeleteOutcome::Failed("403 Forbidden (AuthenticationFailed)".into())
+                ),
+                (
+                    "d".into(),
+                    DeleteOutcome::Failed("no response to the delete in the batch".into())
+                ),
+            ]
+        );
+
+        // A batch refused as a whole answers with a single part without a `Content-ID`.
+        let refused = "--batchresponse_2\nContent-Type: application/http\n\n\
+            HTTP/1.1 400 One of the request inputs is not valid.\nx-ms-error-code: InvalidInput\n\n\
+            --batchresponse_2--\n";
+        let responses = parse_batch_response(
+            "multipart/mixed; boundary=batchresponse_2",
+            refused.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            delete_outcomes(&blobs[..1], &responses),
+            vec![(
+                "a".into(),
+                DeleteOutcome::Failed("400 Bad Request (InvalidInput)".into())
+            )]
+        );
+
+        assert!(parse_batch_response("multipart/mixed", response.as_bytes()).is_err());
+    }
+}
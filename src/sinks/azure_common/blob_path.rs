@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    event::{Event, EventStatus, Finalizable},
+    internal_events::azure_blob::{AzureBlobPathNormalized, AzureBlobPathRejected},
+};
+
+/// The longest name of a blob, in characters.
+pub const MAX_BLOB_NAME_LEN: usize = 1024;
+/// The most segments the name of a blob can be made of.
+pub const MAX_BLOB_NAME_SEGMENTS: usize = 254;
+
+/// What happens to the partitions rendered into malformed paths, such as ones holding double
+/// slashes, leading slashes, `.` segments or backslashes.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobPathPolicy {
+    /// The partitions are written as rendered.
+    #[derivative(Default)]
+    Keep,
+    /// The partitions are rewritten into the path they stand for.
+    Normalize,
+    /// The events of the partitions are rejected.
+    Reject,
+}
+
+impl BlobPathPolicy {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Normalize => "normalize",
+            Self::Reject => "reject",
+        }
+    }
+
+    /// Checks the partition of a batch of events, returning the partition to write it under if
+    /// it is to be written at all.
+    ///
+    /// The paths no normalization can make valid, such as the ones holding `..` segments, are
+    /// rejected under either policy.
+    pub fn apply(self, partition: String, mut events: Vec<Event>) -> Option<(String, Vec<Event>)> {
+        if self == Self::Keep {
+            return Some((partition, events));
+        }
+        let normalized = normalize(&partition);
+        let error = match (self, invalid(&normalized)) {
+            (_, Some(error)) => Some(error),
+            (Self::Reject, None) if normalized != partition => Some("malformed path"),
+            _ => None,
+        };
+        if let Some(error) = error {
+            emit!(AzureBlobPathRejected {
+                partition: partition.as_str(),
+                error,
+                count: events.len(),
+            });
+            events
+                .take_finalizers()
+                .update_status(EventStatus::Rejected);
+            return None;
+        }
+        if normalized != partition {
+            emit!(AzureBlobPathNormalized {
+                partition: partition.as_str(),
+                normalized: normalized.as_str(),
+            });
+        }
+        Some((normalized, events))
+    }
+}
+
+/// Rewrites the path into the one it stands for: backslashes are turned into slashes, while the
+/// leading slashes, the redundant ones and the `.` segments are removed. A trailing slash is kept,
+/// as the name of the blob is appended to the partition.
+pub fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut normalized = path
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect::<Vec<_>>()
+        .join("/");
+    if path.ends_with('/') && !normalized.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+/// Returns why the path can't be the name of a blob, according to the naming rules of Azure,
+/// unless it can.
+///
+/// The name of the blob is appended to the path, so the path has to leave room for one more
+/// segment.
+pub fn invalid(path: &str) -> Option<&'static str> {
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .count();
+    if path.chars().count() > MAX_BLOB_NAME_LEN {
+        Some("longer than 1024 characters")
+    } else if segments >= MAX_BLOB_NAME_SEGMENTS {
+        Some("more than 253 segments")
+    } else if path.split('/').any(|segment| segment == "..") {
+        Some("`..` segment")
+    } else if path.chars().any(char::is_control) {
+        Some("control character")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{BatchNotifier, BatchStatus, LogEvent};
+    use crate::sinks::azure_common::test_util::counter_value;
+
+    #[tokio::test]
+    async fn policies_handle_malformed_paths() {
+        crate::test_util::components::init_test();
+        let apply = |policy: BlobPathPolicy, partition: &str| {
+            let (batch, receiver) = BatchNotifier::new_with_receiver();
+            let events = vec![Event::Log(
+                LogEvent::from("message").with_batch_notifier(&batch),
+            )];
+            drop(batch);
+            let partition = policy
+                .apply(partition.to_owned(), events)
+                .map(|(partition, _)| partition);
+            (partition, receiver)
+        };
+
+        let malformed = [
+            ("logs//app/", "logs/app/"),
+            ("./logs/app/", "logs/app/"),
+            ("logs/./app/", "logs/app/"),
+            ("/logs/app/", "logs/app/"),
+            ("logs\\app\\", "logs/app/"),
+            ("\\\\server\\share/app", "server/share/app"),
+        ];
+        for (partition, normalized) in malformed {
+            // Kept as rendered.
+            let (kept, receiver) = apply(BlobPathPolicy::Keep, partition);
+            assert_eq!(kept.as_deref(), Some(partition));
+            assert_eq!(receiver.await, BatchStatus::Delivered);
+
+            // Rewritten into the path they stand for.
+            let before = counter_value(
+                "azure_blob_malformed_paths_total",
+                &[("outcome", "normalized")],
+            );
+            let (rewritten, receiver) = apply(BlobPathPolicy::Normalize, partition);
+            assert_eq!(rewritten.as_deref(), Some(normalized), "{}", partition);
+            assert_eq!(receiver.await, BatchStatus::Delivered);
+            assert!(
+                counter_value(
+                    "azure_blob_malformed_paths_total",
+                    &[("outcome", "normalized")]
+                ) >= before + 1.0
+            );
+
+            // Or rejected along with their events.
+            let before = counter_value(
+                "azure_blob_malformed_paths_total",
+                &[("outcome", "rejected")],
+            );
+            let (rejected, receiver) = apply(BlobPathPolicy::Reject, partition);
+            assert_eq!(rejected, None, "{}", partition);
+            assert_eq!(receiver.await, BatchStatus::Rejected);
+            assert!(
+                counter_value(
+                    "azure_blob_malformed_paths_total",
+                    &[("outcome", "rejected")]
+                ) >= before + 1.0
+            );
+        }
+
+        // No normalization makes these valid, so they are rejected under either policy.
+        let long = format!("{}/", "a".repeat(MAX_BLOB_NAME_LEN));
+        let deep = "a/".repeat(MAX_BLOB_NAME_SEGMENTS);
+        let invalid = [
+            "logs/../app/",
+            "logs\\..\\app/",
+            "logs/\u{7}/",
+            long.as_str(),
+            deep.as_str(),
+        ];
+        for partition in invalid {
+            for policy in [BlobPathPolicy::Normalize, BlobPathPolicy::Reject] {
+                let (rejected, receiver) = apply(policy, partition);
+                assert_eq!(rejected, None, "{:?} {}", policy, partition);
+                assert_eq!(receiver.await, BatchStatus::Rejected);
+            }
+        }
+
+        // Well-formed paths are left alone by both policies.
+        for policy in [BlobPathPolicy::Normalize, BlobPathPolicy::Reject] {
+            for partition in ["", "logs", "logs/app/", "date=2022-09-01/app.v1/"] {
+                let (kept, receiver) = apply(policy, partition);
+                assert_eq!(kept.as_deref(), Some(partition));
+                assert_eq!(receiver.await, BatchStatus::Delivered);
+            }
+        }
+    }
+}
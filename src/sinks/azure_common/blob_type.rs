@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::event::Event;
+
+/// The largest payload Azure accepts for a block blob written in a single request.
+pub const MAX_PUT_BLOB_BYTES: usize = 256 * 1024 * 1024;
+/// The largest block Azure accepts to be appended to an append blob.
+pub const MAX_APPEND_BLOCK_BYTES: usize = 4 * 1024 * 1024;
+
+/// The type of blob a batch is written to.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobType {
+    /// Every batch is written as a blob of its own.
+    #[derivative(Default)]
+    Block,
+    /// The batches are appended to a blob shared by every batch of the partition.
+    Append,
+}
+
+impl BlobType {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::Append => "append",
+        }
+    }
+
+    /// The largest payload Azure accepts for a single write to a blob of the type, above which
+    /// the write is refused.
+    pub const fn max_write_bytes(self) -> usize {
+        match self {
+            Self::Block => MAX_PUT_BLOB_BYTES,
+            Self::Append => MAX_APPEND_BLOCK_BYTES,
+        }
+    }
+}
+
+/// The order the batches appended to a blob are written in.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AppendOrdering {
+    /// The appends to a blob are sent concurrently, so they may be written out of order.
+    #[derivative(Default)]
+    BestEffort,
+    /// The appends to a blob are sent one at a time, in the order their batches were flushed.
+    Strict,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BlobTypeConfig {
+    /// The field whose value picks the type of blob each event is written to.
+    pub field: String,
+    /// The type of blob of the events holding each value of the field.
+    pub values: BTreeMap<String, BlobType>,
+    /// The type of blob of the events lacking the field or holding any other value.
+    #[serde(default)]
+    pub default: BlobType,
+    #[serde(default)]
+    pub ordering: AppendOrdering,
+}
+
+impl BlobTypeConfig {
+    pub fn build(&self) -> crate::Result<BlobTypeRouter> {
+        if self.field.is_empty() {
+            return Err("`blob_type.field` must not be empty".into());
+        }
+        if self.values.is_empty() {
+            return Err("`blob_type.values` must not be empty".into());
+        }
+        Ok(BlobTypeRouter {
+            field: self.field.clone(),
+            values: self.values.clone(),
+            default: self.default,
+            ordering: self.ordering,
+        })
+    }
+}
+
+/// Picks the type of blob of every event from one of its fields.
+#[derive(Clone, Debug)]
+pub struct BlobTypeRouter {
+    field: String,
+    values: BTreeMap<String, BlobType>,
+    default: BlobType,
+    ordering: AppendOrdering,
+}
+
+impl BlobTypeRouter {
+    /// Whether any event can be written to an append blob.
+    pub fn appends(&self) -> bool {
+        self.default == BlobType::Append
+            || self
+                .values
+                .values()
+                .any(|blob_type| *blob_type == BlobType::Append)
+    }
+
+    /// The order the batches appended to a blob are written in.
+    pub const fn ordering(&self) -> AppendOrdering {
+        self.ordering
+    }
+
+    /// Returns the type of blob the event is written to.
+    ///
+    /// The field is looked up among the tags of metrics.
+    pub fn resolve(&self, event: &Event) -> BlobType {
+        let value = match event {
+            Event::Log(log) => log
+                .get(self.field.as_str())
+                .map(|value| value.to_string_lossy()),
+            Event::Metric(metric) => metric.tag_value(&self.field),
+            Event::Trace(trace) => trace
+                .get(self.field.as_str())
+                .map(|value| value.to_string_lossy()),
+        };
+        value
+            .and_then(|value| self.values.get(&value).copied())
+            .unwrap_or(self.default)
+    }
+
+    /// Splits the batch of a partition into a batch for each type of blob, so that every batch
+    /// is written to a single type of blob. The events keep their order within each batch.
+    pub fn split(&self, key: String, events: Vec<Event>) -> Vec<(String, Vec<Event>)> {
+        let (append, block): (Vec<_>, Vec<_>) = events
+            .into_iter()
+            .partition(|event| self.resolve(event) == BlobType::Append);
+        [block, append]
+            .into_iter()
+            .filter(|events| !events.is_empty())
+            .map(|events| (key.clone(), events))
+            .collect()
+    }
+}
@@ -0,0 +1,324 @@
+use std::{process::Stdio, time::Duration};
+
+use azure_core::{
+    auth::{AccessToken, TokenCredential, TokenResponse},
+    Error as AzureError,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use http::{uri::Scheme, Uri};
+use serde::{Deserialize, Serialize};
+use tokio::{process::Command, time::timeout};
+
+use crate::{config::ProxyConfig, http::HttpClient};
+
+/// The environment variable holding the resource a token is requested for, set for the command.
+const RESOURCE_ENV_VAR: &str = "AZURE_TOKEN_RESOURCE";
+
+/// The largest output of the broker read as a token.
+const MAX_OUTPUT_LEN: usize = 64 * 1024;
+
+/// How many characters of the error output of a failed command are kept in the error.
+const MAX_ERROR_LEN: usize = 512;
+
+const fn default_timeout_secs() -> u64 {
+    10
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TokenBrokerConfig {
+    /// Runs a command printing the token to its standard output.
+    Command {
+        /// The program to run followed by its arguments, which aren't interpreted by a shell.
+        command: Vec<String>,
+        #[serde(default = "default_timeout_secs")]
+        timeout_secs: u64,
+    },
+    /// Gets the token from an HTTP endpoint.
+    Http {
+        endpoint: String,
+        #[serde(default = "default_timeout_secs")]
+        timeout_secs: u64,
+    },
+}
+
+impl TokenBrokerConfig {
+    pub fn build(&self) -> crate::Result<TokenBroker> {
+        let timeout_secs = match self {
+            Self::Command { timeout_secs, .. } | Self::Http { timeout_secs, .. } => *timeout_secs,
+        };
+        if timeout_secs == 0 {
+            return Err("`token_broker.timeout_secs` must be greater than zero".into());
+        }
+        let timeout = Duration::from_secs(timeout_secs);
+
+        match self {
+            Self::Command { command, .. } => match command.split_first() {
+                Some((program, args)) if !program.is_empty() => Ok(TokenBroker::Command {
+                    program: program.clone(),
+                    args: args.to_vec(),
+                    timeout,
+                }),
+                _ => Err("`token_broker.command` must name the program to run".into()),
+            },
+            Self::Http { endpoint, .. } => {
+                let invalid = |reason: &str| {
+                    format!("Invalid `token_broker.endpoint` {:?}: {}", endpoint, reason)
+                };
+                let uri = endpoint
+                    .parse::<Uri>()
+                    .map_err(|error| invalid(&error.to_string()))?;
+                match (uri.scheme(), uri.authority()) {
+                    (Some(scheme), Some(_))
+                        if *scheme == Scheme::HTTP || *scheme == Scheme::HTTPS =>
+                    {
+                        Ok(TokenBroker::Http {
+                            endpoint: uri,
+                            timeout,
+                        })
+                    }
+                    _ => Err(invalid("expected an absolute http(s) URL").into()),
+                }
+            }
+        }
+    }
+}
+
+/// A token broker fronting Azure Active Directory.
+///
+/// The broker answers with a JSON object holding the token as `access_token` (or `token`) and its
+/// expiry, either as `expires_on`, a Unix timestamp or an RFC 3339 date, or as `expires_in`, a
+/// number of seconds.
+#[derive(Clone, Debug)]
+pub enum TokenBroker {
+    Command {
+        program: String,
+        args: Vec<String>,
+        timeout: Duration,
+    },
+    Http {
+        endpoint: Uri,
+        timeout: Duration,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Expiry {
+    Timestamp(i64),
+    Text(String),
+}
+
+#[derive(Deserialize)]
+struct BrokerToken {
+    #[serde(alias = "token")]
+    access_token: String,
+    expires_on: Option<Expiry>,
+    expires_in: Option<i64>,
+}
+
+impl BrokerToken {
+    fn expires_on(&self) -> crate::Result<DateTime<Utc>> {
+        match (&self.expires_on, self.expires_in) {
+            (Some(Expiry::Timestamp(timestamp)), _) => Ok(Utc.timestamp(*timestamp, 0)),
+            (Some(Expiry::Text(text)), _) => match text.parse() {
+                Ok(timestamp) => Ok(Utc.timestamp(timestamp, 0)),
+                Err(_) => Ok(DateTime::parse_from_rfc3339(text)
+                    .map_err(|error| format!("Invalid token expiry {:?}: {}", text, error))?
+                    .with_timezone(&Utc)),
+            },
+            (None, Some(expires_in)) => Ok(Utc::now() + chrono::Duration::seconds(expires_in)),
+            (None, None) => Err("Token broker response has no `expires_on` or `expires_in`".into()),
+        }
+    }
+}
+
+/// Credential getting its tokens from a token broker.
+///
+/// Every call asks the broker for a new token, so it is meant to be wrapped in an
+/// `AutoRefreshingTokenCredential` caching the token until it gets close to its expiry.
+pub struct BrokerCredential {
+    broker: TokenBroker,
+}
+
+impl BrokerCredential {
+    pub const fn new(broker: TokenBroker) -> Self {
+        Self { broker }
+    }
+
+    async fn fetch_token(&self, resource: &str) -> crate::Result<TokenResponse> {
+        let output = match &self.broker {
+            TokenBroker::Command {
+                program,
+                args,
+                timeout: limit,
+            } => timeout(*limit, run_command(program, args, resource))
+                .await
+                .map_err(|_| format!("Token broker command timed out after {:?}", limit))??,
+            TokenBroker::Http {
+                endpoint,
+                timeout: limit,
+            } => timeout(*limit, request_endpoint(endpoint, resource))
+                .await
+                .map_err(|_| format!("Token broker endpoint timed out after {:?}", limit))??,
+        };
+        if output.len() > MAX_OUTPUT_LEN {
+            return Err(format!(
+                "Token broker response is larger than {} bytes",
+                MAX_OUTPUT_LEN
+            )
+            .into());
+        }
+
+        // The response isn't part of the errors, as it may well hold the token.
+        let token = serde_json::from_slice::<BrokerToken>(&output)
+            .map_err(|error| format!("Invalid token broker response: {}", error))?;
+        let expires_on = token.expires_on()?;
+        Ok(TokenResponse::new(
+            AccessToken::new(token.access_token),
+            expires_on,
+        ))
+    }
+}
+
+/// Runs the command of the broker with a cleared environment and no standard input, killing it
+/// if it is still running once its output is no longer awaited.
+async fn run_command(program: &str, args: &[String], resource: &str) -> crate::Result<Vec<u8>> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .env_clear()
+        .env(RESOURCE_ENV_VAR, resource)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(path) = std::env::var_os("PATH") {
+        command.env("PATH", path);
+    }
+
+    let output = command
+        .spawn()
+        .map_err(|error| {
+            format!(
+                "Failed to run token broker command {:?}: {}",
+                program, error
+            )
+        })?
+        .wait_with_output()
+        .await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .chars()
+            .take(MAX_ERROR_LEN)
+            .collect::<String>();
+        return Err(format!(
+            "Token broker command {:?} failed with {}: {}",
+            program, output.status, stderr
+        )
+        .into());
+    }
+    Ok(output.stdout)
+}
+
+async fn request_endpoint(endpoint: &Uri, resource: &str) -> crate::Result<Vec<u8>> {
+    let separator = if endpoint.query().is_some() { '&' } else { '?' };
+    let uri = format!(
+        "{}{}resource={}",
+        endpoint,
+        separator,
+        percent_encoding::utf8_percent_encode(resource, percent_encoding::NON_ALPHANUMERIC)
+    );
+    let request = http::Request::get(uri).body(hyper::Body::empty())?;
+
+    let response = HttpClient::new(None, &ProxyConfig::from_env())?
+        .send(request)
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Token broker endpoint responded with {}", status).into());
+    }
+    Ok(hyper::body::to_bytes(response.into_body()).await?.to_vec())
+}
+
+#[async_trait::async_trait]
+impl TokenCredential for BrokerCredential {
+    async fn get_token(&self, resource: &str) -> Result<TokenResponse, AzureError> {
+        self.fetch_token(resource)
+            .await
+            .map_err(AzureError::GetTokenError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use azure_identity::AutoRefreshingTokenCredential;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn command_provides_cached_token() {
+        let command = |script: String, timeout_secs| {
+            BrokerCredential::new(
+                TokenBrokerConfig::Command {
+                    command: vec!["sh".into(), "-c".into(), script],
+                    timeout_secs,
+                }
+                .build()
+                .unwrap(),
+            )
+        };
+
+        // The broker runs with a cleared environment, and is told the resource of the token.
+        let calls = crate::test_util::temp_file();
+        let broker = command(
+            format!(
+                r#"test -z "$HOME" || exit 3; echo call >> {:?}; printf '{{"access_token": "token-for-%s", "expires_in": 3600}}' "$AZURE_TOKEN_RESOURCE""#,
+                calls
+            ),
+            5,
+        );
+        let credential = AutoRefreshingTokenCredential::new(Arc::new(broker));
+        for _ in 0..2 {
+            let token = credential
+                .get_token("https://storage.azure.com/")
+                .await
+                .unwrap();
+            assert_eq!(token.token.secret(), "token-for-https://storage.azure.com/");
+            assert!(token.expires_on > Utc::now() + chrono::Duration::minutes(59));
+        }
+        // The token is cached until it gets close to its expiry.
+        assert_eq!(std::fs::read_to_string(&calls).unwrap().lines().count(), 1);
+        std::fs::remove_file(calls).unwrap();
+
+        let error = command("sleep 30".into(), 1)
+            .get_token("https://storage.azure.com/")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("timed out"), "{}", error);
+
+        // Failures report the error output of the broker, never its output which may hold a token.
+        let error = command(
+            "echo secret-token; echo access denied >&2; exit 1".into(),
+            5,
+        )
+        .get_token("https://storage.azure.com/")
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("access denied"), "{}", error);
+        assert!(!error.contains("secret-token"), "{}", error);
+
+        assert!(TokenBrokerConfig::Command {
+            command: Vec::new(),
+            timeout_secs: 5,
+        }
+        .build()
+        .is_err());
+    }
+}
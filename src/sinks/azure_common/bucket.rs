@@ -0,0 +1,132 @@
+use std::num::NonZeroU32;
+
+use serde::{Deserialize, Serialize};
+use vector_core::partition::Partitioner;
+
+use super::{
+    fingerprint::SchemaFingerprints,
+    schema_version::{SchemaVersions, MISSING_SCHEMA_VERSION},
+};
+use crate::{event::Event, sinks::util::partitioner::KeyPartitioner};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HashBucketConfig {
+    /// The field whose value picks the bucket of each event.
+    pub field: String,
+    /// The number of buckets the events are spread over.
+    pub buckets: NonZeroU32,
+}
+
+impl HashBucketConfig {
+    pub fn build(&self) -> crate::Result<HashBucket> {
+        if self.field.is_empty() {
+            return Err("`hash_bucket.field` must not be empty".into());
+        }
+        Ok(HashBucket {
+            field: self.field.clone(),
+            buckets: self.buckets,
+        })
+    }
+}
+
+/// Spreads events over a fixed number of buckets by the hash of one of their fields.
+///
+/// The hash doesn't depend on the process, so the events holding the same value are always put in
+/// the same bucket, even across restarts.
+#[derive(Clone, Debug)]
+pub struct HashBucket {
+    field: String,
+    buckets: NonZeroU32,
+}
+
+impl HashBucket {
+    /// Returns the bucket of the event, unless it lacks the field.
+    ///
+    /// The field is looked up among the tags of metrics.
+    pub fn bucket(&self, event: &Event) -> Option<u32> {
+        let value = match event {
+            Event::Log(log) => log.get(self.field.as_str())?.to_string_lossy(),
+            Event::Metric(metric) => metric.tag_value(&self.field)?,
+            Event::Trace(trace) => trace.get(self.field.as_str())?.to_string_lossy(),
+        };
+        let bucket = seahash::hash(value.as_bytes()) % u64::from(self.buckets.get());
+        Some(bucket as u32)
+    }
+}
+
+/// Partitions events by their key, each key being further split into the schema versions, the
+/// hash buckets and the schema fingerprints.
+#[derive(Clone)]
+pub struct BucketedPartitioner {
+    key: KeyPartitioner,
+    hash_bucket: Option<HashBucket>,
+    schema_fingerprints: Option<SchemaFingerprints>,
+    schema_versions: Option<SchemaVersions>,
+}
+
+impl BucketedPartitioner {
+    pub const fn new(key: KeyPartitioner, hash_bucket: Option<HashBucket>) -> Self {
+        Self {
+            key,
+            hash_bucket,
+            schema_fingerprints: None,
+            schema_versions: None,
+        }
+    }
+
+    /// Splits every key further by the schema fingerprint of the events.
+    pub fn with_schema_fingerprints(mut self, fingerprints: Option<SchemaFingerprints>) -> Self {
+        self.schema_fingerprints = fingerprints;
+        self
+    }
+
+    /// Splits every key further by the schema version of the events.
+    pub fn with_schema_versions(mut self, versions: Option<SchemaVersions>) -> Self {
+        self.schema_versions = versions;
+        self
+    }
+}
+
+impl Partitioner for BucketedPartitioner {
+    type Item = Event;
+    type Key = Option<String>;
+
+    fn partition(&self, item: &Self::Item) -> Self::Key {
+        let mut key = self.key.partition(item)?;
+        if let Some(versions) = &self.schema_versions {
+            let version = versions.version(item);
+            key = format!(
+                "{}{}version={}/",
+                key,
+                separator(&key),
+                version.as_deref().unwrap_or(MISSING_SCHEMA_VERSION)
+            );
+        }
+        if let Some(hash_bucket) = &self.hash_bucket {
+            let separator = separator(&key);
+            key = match hash_bucket.bucket(item) {
+                Some(bucket) => format!("{}{}bucket={}/", key, separator, bucket),
+                None => format!("{}{}bucket=none/", key, separator),
+            };
+        }
+        if let Some(fingerprints) = &self.schema_fingerprints {
+            key = format!(
+                "{}{}schema={}/",
+                key,
+                separator(&key),
+                fingerprints.resolve(item)
+            );
+        }
+        Some(key)
+    }
+}
+
+/// The separator needed between the key and the next directory of the partition.
+fn separator(key: &str) -> &'static str {
+    if key.is_empty() || key.ends_with('/') {
+        ""
+    } else {
+        "/"
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    future::Future,
+    task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use snafu::Snafu;
+use tokio_util::sync::CancellationToken;
+use tower::Service;
+
+use super::config::AzureBlobRequest;
+use crate::internal_events::azure_blob::AzureBlobRequestCancelled;
+
+#[derive(Debug, Snafu)]
+pub enum CancellationError {
+    #[snafu(display("Cancelled a queued batch of {} events as the sink is stopping", count))]
+    Queued { count: usize },
+    #[snafu(display(
+        "Cancelled a batch of {} events in flight as the sink is stopping",
+        count
+    ))]
+    InFlight { count: usize },
+}
+
+/// Cancels the requests of the sink once its token is cancelled, which the sink does when its
+/// input ends on reload or shutdown, rather than draining them.
+///
+/// Once cancelled, the service is ready regardless of the service it wraps, and fails the
+/// requests it is called with without starting them, while the requests in flight are dropped,
+/// retries included. Their events are rejected either way. Without a token, every request is
+/// drained.
+pub struct CancellationService<S> {
+    inner: S,
+    token: Option<CancellationToken>,
+    cancelled: Option<BoxFuture<'static, ()>>,
+}
+
+impl<S> CancellationService<S> {
+    pub fn new(inner: S, token: Option<CancellationToken>) -> Self {
+        let cancelled = token
+            .clone()
+            .map(|token| async move { token.cancelled().await }.boxed());
+        Self {
+            inner,
+            token,
+            cancelled,
+        }
+    }
+}
+
+impl<S> Service<AzureBlobRequest> for CancellationService<S>
+where
+    S: Service<AzureBlobRequest>,
+    S::Error: Into<crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let (Some(token), Some(cancelled)) = (&self.token, &mut self.cancelled) {
+            // The future isn't polled again once it completed, as the token is cancelled then.
+            if token.is_cancelled() || cancelled.as_mut().poll(cx).is_ready() {
+                // The queued requests are failed without waiting for the inner service.
+                return Poll::Ready(Ok(()));
+            }
+        }
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: AzureBlobRequest) -> Self::Future {
+        let count = request.metadata.count;
+        let token = match &self.token {
+            Some(token) if token.is_cancelled() => {
+                emit!(AzureBlobRequestCancelled {
+                    count,
+                    state: "queued",
+                });
+                return Box::pin(async move { Err(CancellationError::Queued { count }.into()) });
+            }
+            Some(token) => token.clone(),
+            None => {
+                return Box::pin(
+                    self.inner
+                        .call(request)
+                        .map(|result| result.map_err(Into::into)),
+                )
+            }
+        };
+
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            tokio::select! {
+                biased;
+
+                result = response => result.map_err(Into::into),
+                _ = token.cancelled() => {
+                    emit!(AzureBlobRequestCancelled {
+                        count,
+                        state: "in_flight",
+                    });
+                    Err(CancellationError::InFlight { count }.into())
+                }
+            }
+        })
+    }
+}
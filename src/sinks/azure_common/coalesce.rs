@@ -0,0 +1,206 @@
+use std::{mem, time::Duration};
+
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use vector_core::{stream::BatcherSettings, ByteSizeOf};
+
+use crate::event::Event;
+
+fn default_blob_prefix() -> String {
+    "coalesced/".to_string()
+}
+
+fn default_partition_field() -> String {
+    "partition".to_string()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CoalesceConfig {
+    /// Batches of partitions with fewer events than this are written to a shared blob.
+    pub min_events: usize,
+    /// The prefix of the shared blobs.
+    #[serde(default = "default_blob_prefix")]
+    pub blob_prefix: String,
+    /// The field of each coalesced event that is set to the partition it came from.
+    #[serde(default = "default_partition_field")]
+    pub partition_field: String,
+}
+
+impl CoalesceConfig {
+    pub fn build(&self, settings: &BatcherSettings) -> crate::Result<Coalescer> {
+        if self.min_events == 0 {
+            return Err("`coalesce.min_events` must be greater than zero".into());
+        }
+        if self.partition_field.is_empty() {
+            return Err("`coalesce.partition_field` can't be empty".into());
+        }
+
+        Ok(Coalescer {
+            min_events: self.min_events,
+            blob_prefix: self.blob_prefix.clone(),
+            partition_field: self.partition_field.clone(),
+            timeout: settings.timeout,
+            size_limit: settings.size_limit,
+            item_limit: settings.item_limit,
+            events: Vec::new(),
+            byte_size: 0,
+        })
+    }
+}
+
+/// Merges the batches of low-volume partitions into shared batches.
+///
+/// Batches with at least `min_events` events keep their partition. The events of smaller batches
+/// are tagged with their partition and buffered under `blob_prefix` until the buffer fills up or
+/// the batch timeout expires.
+#[derive(Clone)]
+pub struct Coalescer {
+    min_events: usize,
+    blob_prefix: String,
+    partition_field: String,
+    timeout: Duration,
+    size_limit: usize,
+    item_limit: usize,
+    events: Vec<Event>,
+    byte_size: usize,
+}
+
+impl Coalescer {
+    /// Returns the batches ready to be written after adding `events` of partition `key`.
+    pub(crate) fn push(&mut self, key: String, events: Vec<Event>) -> Vec<(String, Vec<Event>)> {
+        if events.len() >= self.min_events {
+            return vec![(key, events)];
+        }
+
+        let mut ready = Vec::new();
+        for mut event in events {
+            if let Event::Log(log) = &mut event {
+                log.insert(self.partition_field.as_str(), key.clone());
+            }
+            let byte_size = event.size_of();
+            if !self.events.is_empty()
+                && (self.events.len() >= self.item_limit
+                    || self.byte_size + byte_size > self.size_limit)
+            {
+                ready.extend(self.flush());
+            }
+            self.byte_size += byte_size;
+            self.events.push(event);
+        }
+        ready
+    }
+
+    /// Returns the shared batch, if any events were buffered.
+    pub(crate) fn flush(&mut self) -> Option<(String, Vec<Event>)> {
+        if self.events.is_empty() {
+            return None;
+        }
+        self.byte_size = 0;
+        Some((self.blob_prefix.clone(), mem::take(&mut self.events)))
+    }
+
+    pub fn coalesce<'a, S>(mut self, mut input: S) -> impl Stream<Item = (String, Vec<Event>)> + 'a
+    where
+        S: Stream<Item = (String, Vec<Event>)> + Send + Unpin + 'a,
+    {
+        let mut flush_interval = tokio::time::interval(self.timeout);
+
+        stream! {
+            let mut output = Vec::new();
+            let mut done = false;
+            while !done {
+                tokio::select! {
+                    _ = flush_interval.tick() => output.extend(self.flush()),
+                    batch = input.next() => match batch {
+                        Some((key, events)) => output.extend(self.push(key, events)),
+                        None => {
+                            output.extend(self.flush());
+                            done = true;
+                        }
+                    },
+                };
+                for batch in output.drain(..) {
+                    yield batch;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use futures::stream;
+
+    use super::*;
+    use crate::event::LogEvent;
+
+    #[tokio::test]
+    async fn coalesces_low_volume_partitions() {
+        let settings = BatcherSettings::new(
+            Duration::from_secs(300),
+            NonZeroUsize::new(10_000_000).unwrap(),
+            NonZeroUsize::new(1000).unwrap(),
+        );
+        let coalescer = CoalesceConfig {
+            min_events: 3,
+            blob_prefix: "coalesced/".into(),
+            partition_field: "partition".into(),
+        }
+        .build(&settings)
+        .unwrap();
+        let batch = |key: &str, messages: &[&str]| {
+            (
+                key.to_string(),
+                messages
+                    .iter()
+                    .map(|message| Event::Log(LogEvent::from(*message)))
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let batches = coalescer
+            .coalesce(stream::iter(vec![
+                batch("quiet-a/", &["a1"]),
+                batch("busy/", &["b1", "b2", "b3"]),
+                batch("quiet-b/", &["c1", "c2"]),
+            ]))
+            .collect::<Vec<_>>()
+            .await;
+
+        let summarize = |events: &[Event]| {
+            events
+                .iter()
+                .map(|event| {
+                    let log = event.as_log();
+                    (
+                        log.get("message").unwrap().to_string_lossy(),
+                        log.get("partition").map(|value| value.to_string_lossy()),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].0, "busy/");
+        assert_eq!(
+            summarize(&batches[0].1),
+            vec![
+                ("b1".to_string(), None),
+                ("b2".to_string(), None),
+                ("b3".to_string(), None),
+            ]
+        );
+        assert_eq!(batches[1].0, "coalesced/");
+        assert_eq!(
+            summarize(&batches[1].1),
+            vec![
+                ("a1".to_string(), Some("quiet-a/".to_string())),
+                ("c1".to_string(), Some("quiet-b/".to_string())),
+                ("c2".to_string(), Some("quiet-b/".to_string())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{
+    config::event_time_range,
+    service::{
+        EVENT_TIME_MAX_METADATA, EVENT_TIME_MIN_METADATA, SOURCE_TYPE_METADATA,
+        ZSTD_DICTIONARY_METADATA,
+    },
+};
+use crate::event::Event;
+
+/// The most metadata computed for each blob, as for blob index tags.
+pub const MAX_COMPUTED_METADATA: usize = 10;
+/// The longest name of computed metadata.
+pub const MAX_NAME_LEN: usize = 128;
+/// The longest value of computed metadata, longer values being left out.
+pub const MAX_VALUE_LEN: usize = 256;
+
+/// A statistic of the batch a blob is written for.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum BatchStatistic {
+    Count,
+    /// The power of ten range the count falls in, such as `1k-10k`.
+    CountBucket,
+    ByteSize,
+    TimeMin,
+    TimeMax,
+    /// The most frequent value of the field among the events of the batch.
+    Dominant(String),
+}
+
+impl BatchStatistic {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "count" => Some(Self::Count),
+            "count_bucket" => Some(Self::CountBucket),
+            "byte_size" => Some(Self::ByteSize),
+            "time_min" => Some(Self::TimeMin),
+            "time_max" => Some(Self::TimeMax),
+            _ => name
+                .strip_prefix("dominant.")
+                .filter(|field| !field.is_empty())
+                .map(|field| Self::Dominant(field.to_owned())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Part {
+    Literal(String),
+    /// A `{{ batch.<statistic> }}` placeholder.
+    Batch(BatchStatistic),
+    /// A `{{ <field> }}` placeholder, rendered from the first event of the batch.
+    Field(String),
+}
+
+/// Parses the template of a value into its literal parts and placeholders.
+fn parse_template(template: &str) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            parts.push(Part::Literal(rest[..start].to_owned()));
+        }
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| format!("unclosed placeholder in {:?}", template))?;
+        let placeholder = rest[start + 2..start + end].trim();
+        parts.push(match placeholder.strip_prefix("batch.") {
+            Some(statistic) => Part::Batch(
+                BatchStatistic::parse(statistic)
+                    .ok_or_else(|| format!("unknown batch statistic {:?}", statistic))?,
+            ),
+            None if placeholder.is_empty() => {
+                return Err(format!("empty placeholder in {:?}", template))
+            }
+            None => Part::Field(placeholder.to_owned()),
+        });
+        rest = &rest[start + end + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest.to_owned()));
+    }
+    Ok(parts)
+}
+
+/// Whether the name is a valid C# identifier, as the names of the metadata of blobs have to be.
+fn is_metadata_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+/// Blob metadata computed from each batch as a whole, once it is flushed.
+#[derive(Clone, Debug)]
+pub struct ComputedMetadata {
+    entries: Vec<(String, Vec<Part>)>,
+}
+
+impl ComputedMetadata {
+    /// Parses the templates of the metadata, keyed by the name of the metadata.
+    pub fn new(templates: &BTreeMap<String, String>) -> crate::Result<Option<Self>> {
+        if templates.is_empty() {
+            return Ok(None);
+        }
+        if templates.len() > MAX_COMPUTED_METADATA {
+            return Err(format!(
+                "`batch_metadata` can't hold more than {} entries",
+                MAX_COMPUTED_METADATA
+            )
+            .into());
+        }
+        let reserved = [
+            EVENT_TIME_MIN_METADATA,
+            EVENT_TIME_MAX_METADATA,
+            SOURCE_TYPE_METADATA,
+            ZSTD_DICTIONARY_METADATA,
+        ];
+
+        let entries = templates
+            .iter()
+            .map(|(name, template)| {
+                if name.len() > MAX_NAME_LEN || !is_metadata_name(name) {
+                    return Err(format!(
+                        "`batch_metadata` name {:?} isn't an identifier of at most {} characters",
+                        name, MAX_NAME_LEN
+                    ));
+                }
+                // Names of metadata are case insensitive.
+                if reserved
+                    .iter()
+                    .any(|reserved| reserved.eq_ignore_ascii_case(name))
+                {
+                    return Err(format!(
+                        "`batch_metadata` name {:?} is reserved by the sink",
+                        name
+                    ));
+                }
+                let parts = parse_template(template).map_err(|error| {
+                    format!("Invalid `batch_metadata.{}` template: {}", name, error)
+                })?;
+                Ok((name.clone(), parts))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Some(Self { entries }))
+    }
+
+    /// Renders the metadata of a batch.
+    ///
+    /// Values referring to a field the first event lacks, or longer than [`MAX_VALUE_LEN`], are
+    /// left out.
+    pub fn render(&self, events: &[Event], byte_size: usize) -> Vec<(String, String)> {
+        // Only computed if a value refers to it.
+        let mut cached = None;
+        let mut time_range = || *cached.get_or_insert_with(|| event_time_range(events));
+
+        self.entries
+            .iter()
+            .filter_map(|(name, parts)| {
+                let mut value = String::new();
+                for part in parts {
+                    match part {
+                        Part::Literal(literal) => value.push_str(literal),
+                        Part::Batch(BatchStatistic::Count) => {
+                            value.push_str(&events.len().to_string())
+                        }
+                        Part::Batch(BatchStatistic::CountBucket) => {
+                            value.push_str(&count_bucket(events.len()))
+                        }
+                        Part::Batch(BatchStatistic::ByteSize) => {
+                            value.push_str(&byte_size.to_string())
+                        }
+                        Part::Batch(BatchStatistic::TimeMin) => {
+                            value.push_str(&format_time(time_range()?.0))
+                        }
+                        Part::Batch(BatchStatistic::TimeMax) => {
+                            value.push_str(&format_time(time_range()?.1))
+                        }
+                        Part::Batch(BatchStatistic::Dominant(field)) => {
+                            value.push_str(&dominant_value(events, field)?)
+                        }
+                        Part::Field(field) => value.push_str(&field_value(events.first()?, field)?),
+                    }
+                }
+                (value.len() <= MAX_VALUE_LEN).then(|| (name.clone(), value))
+            })
+            .collect()
+    }
+}
+
+fn format_time(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn field_value(event: &Event, field: &str) -> Option<String> {
+    match event {
+        Event::Log(log) => Some(log.get(field)?.to_string_lossy()),
+        Event::Metric(metric) => metric.tag_value(field),
+        Event::Trace(trace) => Some(trace.get(field)?.to_string_lossy()),
+    }
+}
+
+/// The most frequent value of the field among the events, the smallest one winning ties.
+fn dominant_value(events: &[Event], field: &str) -> Option<String> {
+    let mut counts = HashMap::<String, usize>::new();
+    for value in events.iter().filter_map(|event| field_value(event, field)) {
+        *counts.entry(value).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then_with(|| b.cmp(a)))
+        .map(|(value, _)| value)
+}
+
+/// The power of ten range the count falls in, such as `1k-10k` for 1000 to 9999.
+pub fn count_bucket(count: usize) -> String {
+    if count == 0 {
+        return "0".to_owned();
+    }
+    let mut lower = 1;
+    while count / lower >= 10 {
+        lower *= 10;
+    }
+    format!("{}-{}", short_count(lower), short_count(lower * 10))
+}
+
+fn short_count(count: usize) -> String {
+    match count {
+        count if count >= 1_000_000_000 => format!("{}G", count / 1_000_000_000),
+        count if count >= 1_000_000 => format!("{}M", count / 1_000_000),
+        count if count >= 1_000 => format!("{}k", count / 1_000),
+        count => count.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::LogEvent;
+
+    #[test]
+    fn respects_limits() {
+        let build = |templates: &[(&str, &str)]| {
+            let templates = templates
+                .iter()
+                .map(|(name, template)| (name.to_string(), template.to_string()))
+                .collect();
+            ComputedMetadata::new(&templates)
+        };
+
+        assert!(build(&[]).unwrap().is_none());
+        let too_many = (0..=MAX_COMPUTED_METADATA)
+            .map(|i| (format!("tag_{}", i), "{{ batch.count }}".to_owned()))
+            .collect();
+        assert!(ComputedMetadata::new(&too_many).is_err());
+        assert!(build(&[("event_time_min", "{{ batch.time_min }}")]).is_err());
+        assert!(build(&[("not-an-identifier", "value")]).is_err());
+        assert!(build(&[("stat", "{{ batch.median }}")]).is_err());
+        assert!(build(&[("stat", "{{ batch.count")]).is_err());
+
+        // Values longer than the limit are left out.
+        let long = build(&[("long", "{{ message }}")]).unwrap().unwrap();
+        let event = Event::Log(LogEvent::from("x".repeat(MAX_VALUE_LEN + 1)));
+        assert!(long.render(&[event], 0).is_empty());
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    num::{NonZeroU32, NonZeroUsize},
+    sync::Arc,
+};
+
+use async_stream::stream;
+use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::{blob_type::BlobType, config::AzureBlobRequest};
+
+/// Number of requests held back while their partition is busy, above which no more requests are
+/// built until some of them are sent.
+const MAX_HELD_REQUESTS: usize = 64;
+
+/// Number of partitions tracked before the idle ones are forgotten.
+const MAX_TRACKED_PARTITIONS: usize = 1024;
+
+/// How the requests in flight are limited.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ConcurrencyMode {
+    /// Requests of every partition share the `request.concurrency` limit.
+    #[derivative(Default)]
+    Global,
+    /// At most `limit` requests of each partition are in flight at once, on top of the
+    /// `request.concurrency` limit.
+    PerPartition { limit: NonZeroUsize },
+}
+
+impl ConcurrencyMode {
+    pub const fn build(self) -> Option<PartitionLimiter> {
+        match self {
+            Self::Global => None,
+            Self::PerPartition { limit } => Some(PartitionLimiter {
+                limit,
+                scope: LimitScope::Partition,
+            }),
+        }
+    }
+}
+
+/// Holds the permits of a request to the concurrency of its partition and of its append blob,
+/// and to the bytes in flight.
+///
+/// Retried requests are clones of the original one, so the permits are only released once every
+/// attempt of the request is done with.
+#[derive(Clone, Debug, Default)]
+pub struct PartitionPermit(Vec<Arc<OwnedSemaphorePermit>>);
+
+impl PartitionPermit {
+    fn add(&mut self, permit: OwnedSemaphorePermit) {
+        self.0.push(Arc::new(permit));
+    }
+}
+
+/// The requests a limiter holds back, and what they are grouped by.
+#[derive(Clone, Copy, Debug)]
+enum LimitScope {
+    /// Every request, grouped by partition.
+    Partition,
+    /// The requests appending to blobs, grouped by blob and numbered in the order they are
+    /// handed on.
+    AppendBlob,
+}
+
+impl LimitScope {
+    fn key(self, request: &AzureBlobRequest) -> Option<String> {
+        match self {
+            Self::Partition => Some(request.metadata.partition.clone()),
+            Self::AppendBlob => (request.metadata.blob_type == BlobType::Append)
+                .then(|| request.metadata.partition_key.clone()),
+        }
+    }
+}
+
+/// Holds requests back until fewer than `limit` requests of their partition, or of their append
+/// blob, are in flight.
+///
+/// Requests are handed on in the order they were built within each partition, while the requests
+/// of other partitions go ahead of the ones held back, so a busy partition doesn't take up the
+/// concurrency of the others.
+#[derive(Clone, Debug)]
+pub struct PartitionLimiter {
+    limit: NonZeroUsize,
+    scope: LimitScope,
+}
+
+impl PartitionLimiter {
+    /// Hands the appends to each blob on one at a time, in the order they were built, each one
+    /// once the previous one is done, retries included. The other requests aren't held back.
+    pub fn sequential_appends() -> Self {
+        Self {
+            limit: NonZeroUsize::new(1).expect("one is not zero"),
+            scope: LimitScope::AppendBlob,
+        }
+    }
+
+    pub fn limit<'a, S>(self, mut requests: S) -> impl Stream<Item = AzureBlobRequest> + 'a
+    where
+        S: Stream<Item = AzureBlobRequest> + Send + Unpin + 'a,
+    {
+        let limit = self.limit.get();
+        let scope = self.scope;
+        let mut sequence = 0;
+        let mut partitions = HashMap::<String, Arc<Semaphore>>::new();
+        let mut held = HashMap::<String, VecDeque<AzureBlobRequest>>::new();
+        let mut held_count = 0;
+        let mut acquiring = FuturesUnordered::<Acquire>::new();
+        let mut done = false;
+
+        stream! {
+            loop {
+                let request = tokio::select! {
+                    Some((partition, permit)) = acquiring.next(), if !acquiring.is_empty() => {
+                        let queue = held.get_mut(&partition).expect("partition is held");
+                        let mut request = queue.pop_front().expect("partition is held");
+                        if queue.is_empty() {
+                            held.remove(&partition);
+                        } else {
+                            acquiring.push(acquire(&partitions[&partition], partition));
+                        }
+                        held_count -= 1;
+                        request.partition_permit.add(permit);
+                        Some(request)
+                    }
+                    request = requests.next(), if !done && held_count < MAX_HELD_REQUESTS => {
+                        match request {
+                            Some(mut request) => match scope.key(&request) {
+                                // The requests out of scope are handed on right away.
+                                None => Some(request),
+                                Some(partition) => {
+                                    if let Some(queue) = held.get_mut(&partition) {
+                                        queue.push_back(request);
+                                        held_count += 1;
+                                        None
+                                    } else {
+                                        if partitions.len() >= MAX_TRACKED_PARTITIONS {
+                                            partitions.retain(|partition, semaphore| {
+                                                held.contains_key(partition)
+                                                    || semaphore.available_permits() < limit
+                                            });
+                                        }
+                                        let semaphore = partitions
+                                            .entry(partition.clone())
+                                            .or_insert_with(|| Arc::new(Semaphore::new(limit)));
+                                        match Arc::clone(semaphore).try_acquire_owned() {
+                                            Ok(permit) => {
+                                                request.partition_permit.add(permit);
+                                                Some(request)
+                                            }
+                                            Err(_) => {
+                                                acquiring
+                                                    .push(acquire(semaphore, partition.clone()));
+                                                held.insert(partition, VecDeque::from([request]));
+                                                held_count += 1;
+                                                None
+                                            }
+                                        }
+                                    }
+                                }
+                            },
+                            None => {
+                                done = true;
+                                None
+                            }
+                        }
+                    }
+                    else => break,
+                };
+
+                if let Some(mut request) = request {
+                    if let (LimitScope::AppendBlob, BlobType::Append) =
+                        (scope, request.metadata.blob_type)
+                    {
+                        request.metadata.append_sequence = Some(sequence);
+                        sequence += 1;
+                    }
+                    yield request;
+                }
+            }
+        }
+    }
+}
+
+/// Holds requests back, in the order they were built, until the payloads of the requests in
+/// flight leave room for theirs, so that bursts of large batches don't take up more memory than
+/// `max_bytes`, on top of the `request.concurrency` limit.
+///
+/// The payloads larger than the limit take it up whole, so they are sent alone.
+#[derive(Clone, Debug)]
+pub struct ByteLimiter {
+    max_bytes: NonZeroU32,
+}
+
+impl ByteLimiter {
+    pub const fn new(max_bytes: NonZeroU32) -> Self {
+        Self { max_bytes }
+    }
+
+    pub fn limit<'a, S>(self, requests: S) -> impl Stream<Item = AzureBlobRequest> + 'a
+    where
+        S: Stream<Item = AzureBlobRequest> + Send + Unpin + 'a,
+    {
+        let max_bytes = self.max_bytes.get();
+        let semaphore = Arc::new(Semaphore::new(max_bytes as usize));
+        requests.then(move |mut request| {
+            let semaphore = Arc::clone(&semaphore);
+            async move {
+                let bytes = u32::try_from(request.blob_data.len())
+                    .unwrap_or(u32::MAX)
+                    .min(max_bytes);
+                let permit = semaphore
+                    .acquire_many_owned(bytes)
+                    .await
+                    .expect("byte semaphore is never closed");
+                request.partition_permit.add(permit);
+                request
+            }
+        })
+    }
+}
+
+/// Waits for a permit of the partition whose oldest held request is to be sent next.
+type Acquire = BoxFuture<'static, (String, OwnedSemaphorePermit)>;
+
+fn acquire(semaphore: &Arc<Semaphore>, partition: String) -> Acquire {
+    Arc::clone(semaphore)
+        .acquire_owned()
+        .map(|permit| {
+            (
+                partition,
+                permit.expect("partition semaphore is never closed"),
+            )
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use futures::stream::{self, BoxStream};
+
+    use super::*;
+    use crate::sinks::azure_common::test_util::mock_request;
+
+    #[tokio::test(start_paused = true)]
+    async fn limits_concurrency_per_partition() {
+        let request = |partition: &str| {
+            let mut request = mock_request();
+            request.metadata.partition = partition.into();
+            request
+        };
+        let limiter = ConcurrencyMode::PerPartition {
+            limit: NonZeroUsize::new(1).unwrap(),
+        }
+        .build()
+        .unwrap();
+        let requests = limiter.limit(stream::iter(vec![
+            request("a/"),
+            request("a/"),
+            request("b/"),
+        ]));
+
+        // Each request is in flight for a second once handed on, as if it was being written.
+        let started = tokio::time::Instant::now();
+        let mut spans = requests
+            .map(|request| async move {
+                let start = started.elapsed();
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                (request.metadata.partition.clone(), start, started.elapsed())
+            })
+            .buffer_unordered(10)
+            .collect::<Vec<_>>()
+            .await;
+        spans.sort_by_key(|(partition, start, _)| (partition.clone(), *start));
+
+        let (first_a, second_a, b) = (&spans[0], &spans[1], &spans[2]);
+        assert_eq!(
+            (first_a.0.as_str(), second_a.0.as_str(), b.0.as_str()),
+            ("a/", "a/", "b/")
+        );
+        // The second request of `a/` waited for the first one to be done with.
+        assert!(second_a.1 >= first_a.2);
+        // The request of `b/` went ahead of it, alongside the first request of `a/`.
+        assert!(b.1 < first_a.2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn strict_append_ordering_writes_appends_in_order() {
+        let requests = || {
+            [
+                ("first", BlobType::Append),
+                ("second", BlobType::Append),
+                ("snapshot", BlobType::Block),
+                ("third", BlobType::Append),
+            ]
+            .into_iter()
+            .map(|(message, blob_type)| {
+                let mut request = mock_request();
+                request.blob_data = Bytes::from(message);
+                request.metadata.partition_key = match blob_type {
+                    BlobType::Append => "blob/stream.log".into(),
+                    BlobType::Block => "blob/snapshot.log".into(),
+                };
+                request.metadata.blob_type = blob_type;
+                request
+            })
+            .collect::<Vec<_>>()
+        };
+        // The requests take longer to write the earlier they were built, as if the first ones were
+        // retried, and are written in the order they are done.
+        let write = |requests: BoxStream<'static, AzureBlobRequest>| async move {
+            let started = tokio::time::Instant::now();
+            requests
+                .map(|request| async move {
+                    let message = String::from_utf8(request.blob_data.to_vec()).unwrap();
+                    let duration = match message.as_str() {
+                        "first" => 3,
+                        "second" => 2,
+                        _ => 1,
+                    };
+                    tokio::time::sleep(Duration::from_secs(duration)).await;
+                    (message, request.metadata.append_sequence, started.elapsed())
+                })
+                .buffer_unordered(10)
+                .collect::<Vec<_>>()
+                .await
+        };
+        let appends = |written: &[(String, Option<u64>, Duration)]| {
+            written
+                .iter()
+                .filter(|(message, _, _)| message != "snapshot")
+                .map(|(message, sequence, _)| (message.clone(), *sequence))
+                .collect::<Vec<_>>()
+        };
+
+        // Sent concurrently, the appends are written out of order.
+        let written = write(stream::iter(requests()).boxed()).await;
+        assert_eq!(
+            appends(&written),
+            [
+                ("third".to_owned(), None),
+                ("second".to_owned(), None),
+                ("first".to_owned(), None),
+            ]
+        );
+
+        // Sequenced, each append waits for the previous one, while the block blob goes ahead.
+        let sequenced = PartitionLimiter::sequential_appends()
+            .limit(stream::iter(requests()))
+            .boxed();
+        let written = write(sequenced).await;
+        assert_eq!(
+            appends(&written),
+            [
+                ("first".to_owned(), Some(0)),
+                ("second".to_owned(), Some(1)),
+                ("third".to_owned(), Some(2)),
+            ]
+        );
+        assert_eq!(written[0].0, "snapshot");
+        assert_eq!(written[0].2, Duration::from_secs(1));
+        assert_eq!(written[3].2, Duration::from_secs(6));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn byte_limiter_serializes_large_batches() {
+        let requests = || {
+            [
+                ("small", 10),
+                ("small", 10),
+                ("small", 10),
+                ("large", 100),
+                ("large", 150),
+            ]
+            .into_iter()
+            .map(|(name, byte_size)| {
+                let mut request = mock_request();
+                request.blob_data = Bytes::from(vec![b'x'; byte_size]);
+                request.metadata.partition_key = format!("blob/{}.log", name);
+                request
+            })
+            .collect::<Vec<_>>()
+        };
+        // Every request takes a second to write, and holds its permits until then.
+        let write = |requests: BoxStream<'static, AzureBlobRequest>| async move {
+            let started = tokio::time::Instant::now();
+            requests
+                .map(|request| async move {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    (
+                        request.metadata.partition_key.clone(),
+                        started.elapsed().as_secs(),
+                    )
+                })
+                .buffer_unordered(10)
+                .collect::<Vec<_>>()
+                .await
+        };
+
+        // Without a limit, every request is written at once.
+        let written = write(stream::iter(requests()).boxed()).await;
+        assert!(written.iter().all(|(_, elapsed)| *elapsed == 1));
+
+        // The small requests fit together, while each large one waits for the room of the whole
+        // limit, the one over it included.
+        let limited = ByteLimiter::new(NonZeroU32::new(100).unwrap())
+            .limit(stream::iter(requests()))
+            .boxed();
+        let written = write(limited).await;
+        assert_eq!(
+            written,
+            [
+                ("blob/small.log".to_owned(), 1),
+                ("blob/small.log".to_owned(), 1),
+                ("blob/small.log".to_owned(), 1),
+                ("blob/large.log".to_owned(), 2),
+                ("blob/large.log".to_owned(), 3),
+            ]
+        );
+    }
+}
@@ -1,40 +1,335 @@
-use std::sync::Arc;
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::{Duration, Instant},
+};
 
-use azure_core::{new_http_client, HttpError};
+use azure_core::{
+    auth::{TokenCredential, TokenResponse},
+    HttpClient, HttpError,
+};
 use azure_identity::{AutoRefreshingTokenCredential, DefaultAzureCredential};
 use azure_storage::prelude::*;
-use azure_storage_blobs::{blob::responses::PutBlockBlobResponse, prelude::*};
+use azure_storage_blobs::{
+    blob::responses::{AppendBlockResponse, PutBlockBlobResponse},
+    prelude::*,
+};
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use futures::FutureExt;
-use http::StatusCode;
+use http::{header::HeaderName, HeaderMap, HeaderValue, StatusCode, Uri};
+use openssl::hash::{hash, MessageDigest};
+use serde::{Deserialize, Serialize};
 use snafu::Snafu;
+use tokio::time::{interval, timeout};
 use vector_core::{internal_event::EventsSent, stream::DriverResponse};
 
 use crate::{
-    event::{EventFinalizers, EventStatus, Finalizable},
-    sinks::{util::retries::RetryLogic, Healthcheck},
+    config::log_schema,
+    event::{Event, EventFinalizers, EventStatus, Finalizable, Value},
+    internal_events::azure_blob::{
+        AzureBlobCredentialFallback, AzureBlobFinalizerDoubleTake, AzureBlobHealthcheckThrottled,
+        AzureBlobRetryBackoff, AzureBlobTokenRefreshError, AzureBlobUnresolvablePartition,
+    },
+    sinks::{
+        azure_common::{
+            blob_type::BlobType,
+            broker::{BrokerCredential, TokenBroker},
+            concurrency::PartitionPermit,
+            endpoint::{account_host, Endpoint},
+            fan_out::ContainerOutcome,
+            imds::ImdsCredential,
+            receipt::{BlobProvenance, WriteReceipt},
+            retry_after::{capture_retry_after, RetryAfterClient},
+        },
+        util::retries::{BackoffSource, RetryAction, RetryLogic},
+        Healthcheck,
+    },
 };
 
 #[derive(Debug, Clone)]
 pub struct AzureBlobRequest {
     pub blob_data: Bytes,
     pub content_encoding: Option<&'static str>,
-    pub content_type: &'static str,
+    pub content_type: Cow<'static, str>,
     pub metadata: AzureBlobMetadata,
+    pub attempts: DeliveryAttempts,
+    pub partition_permit: PartitionPermit,
+    /// The finalizers resolved by the sink itself once the request is done, in place of the
+    /// finalizers of the metadata, when acknowledgements are batched.
+    pub batched_finalizers: EventFinalizers,
+    /// Whether the finalizers of the metadata were taken already, carried over to the clones of
+    /// the request made for its retries.
+    pub finalizers_taken: bool,
 }
 
 impl Finalizable for AzureBlobRequest {
+    /// Takes the finalizers of the metadata, which can only be done once.
+    ///
+    /// Taking them again would silently return none, leaving their events to whoever took them
+    /// first, so it is reported as an error, and panics in debug builds.
     fn take_finalizers(&mut self) -> EventFinalizers {
+        if self.finalizers_taken {
+            emit!(AzureBlobFinalizerDoubleTake {
+                blob: self.metadata.partition_key.as_str(),
+                count: self.metadata.count,
+            });
+        }
+        debug_assert!(
+            !self.finalizers_taken,
+            "Finalizers of blob {:?} taken twice",
+            self.metadata.partition_key
+        );
+        self.finalizers_taken = true;
         std::mem::take(&mut self.metadata.finalizers)
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct AzureBlobMetadata {
+    /// The name of the blob, which starts out as the partition of the batch.
     pub partition_key: String,
+    /// The partition of the batch the blob is written for.
+    pub partition: String,
     pub count: usize,
     pub byte_size: usize,
     pub finalizers: EventFinalizers,
+    /// The oldest and newest event timestamps of the batch, when they are written to the blob
+    /// metadata.
+    pub event_time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// The source types of the events of the batch, when they are written to the blob metadata.
+    pub source_types: Option<String>,
+    /// The ID of the zstd dictionary the blob is compressed with, if any.
+    pub zstd_dictionary_id: Option<u32>,
+    /// The metadata computed from the batch as a whole, when `batch_metadata` is set.
+    pub computed_metadata: Vec<(String, String)>,
+    /// The timestamp of the oldest event of the batch, when `max_batch_age_before_send_secs` is
+    /// set.
+    pub oldest_event_time: Option<DateTime<Utc>>,
+    /// The type of blob the batch is written to.
+    pub blob_type: BlobType,
+    /// The fingerprint of the schema of the events of the batch, when they are grouped by it.
+    pub schema_fingerprint: Option<String>,
+    /// The version of the schema of the events of the batch, when they are grouped by it.
+    pub schema_version: Option<String>,
+    /// The position of the append among the appends handed on by the sink, when the appends to
+    /// each blob are sequenced.
+    pub append_sequence: Option<u64>,
+    /// The `Content-Language` of the blob, when rendered from its events.
+    pub content_language: Option<String>,
+}
+
+/// Returns the oldest and newest timestamps of the events, ignoring the events without one.
+pub fn event_time_range(events: &[Event]) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let timestamp_key = log_schema().timestamp_key();
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Log(log) => log
+                .get(timestamp_key)
+                .and_then(Value::as_timestamp)
+                .copied(),
+            _ => None,
+        })
+        .fold(None, |range, timestamp| match range {
+            None => Some((timestamp, timestamp)),
+            Some((min, max)) => Some((min.min(timestamp), max.max(timestamp))),
+        })
+}
+
+/// Longest list of source types written to the blob metadata.
+///
+/// All the metadata of a blob has to fit in 8KiB, so batches mixing more sources than fit in this
+/// aren't stamped rather than risking the write being refused.
+pub const MAX_SOURCE_TYPES_LEN: usize = 256;
+
+/// Returns the sorted, comma separated source types of the events, ignoring the events without one.
+pub fn source_types(events: &[Event]) -> Option<String> {
+    let source_type_key = log_schema().source_type_key();
+    let source_types = events
+        .iter()
+        .filter_map(|event| event.maybe_as_log()?.get(source_type_key))
+        .map(Value::to_string_lossy)
+        .collect::<BTreeSet<_>>();
+    let source_types = source_types.into_iter().collect::<Vec<_>>().join(",");
+    (!source_types.is_empty() && source_types.len() <= MAX_SOURCE_TYPES_LEN).then(|| source_types)
+}
+
+/// Counts the attempts made to deliver a request.
+///
+/// Retried requests are clones of the original one, so every attempt of the same request shares
+/// the same counter.
+#[derive(Clone, Debug, Default)]
+pub struct DeliveryAttempts(Arc<DeliveryAttemptsInner>);
+
+#[derive(Debug, Default)]
+struct DeliveryAttemptsInner {
+    count: AtomicUsize,
+    first_attempt: Mutex<Option<Instant>>,
+    /// The delay the server asked for before the next attempt, if the last one was throttled.
+    retry_after: Mutex<Option<Duration>>,
+}
+
+impl DeliveryAttempts {
+    /// Records the start of an attempt, returning its number starting from one.
+    pub fn start(&self) -> usize {
+        self.0
+            .first_attempt
+            .lock()
+            .expect("attempts lock poisoned")
+            .get_or_insert_with(Instant::now);
+        self.set_retry_after(None);
+        self.0.count.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Records the delay the server asked for before the next attempt.
+    pub fn set_retry_after(&self, retry_after: Option<Duration>) {
+        *self.0.retry_after.lock().expect("attempts lock poisoned") = retry_after;
+    }
+
+    /// The delay the server asked for before the next attempt, if any.
+    pub fn retry_after(&self) -> Option<Duration> {
+        *self.0.retry_after.lock().expect("attempts lock poisoned")
+    }
+
+    /// The time elapsed since the first attempt started.
+    pub fn elapsed(&self) -> Duration {
+        self.0
+            .first_attempt
+            .lock()
+            .expect("attempts lock poisoned")
+            .map(|started| started.elapsed())
+            .unwrap_or_default()
+    }
+}
+
+/// Digest algorithms available for checksum sidecar blobs.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    /// The suffix appended to the data blob name to form the sidecar blob name.
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    pub fn hex_digest(self, data: &[u8]) -> String {
+        let digest = match self {
+            Self::Md5 => MessageDigest::md5(),
+            Self::Sha256 => MessageDigest::sha256(),
+        };
+        hex::encode(&*hash(digest, data).expect("computing a digest can't fail"))
+    }
+}
+
+/// What happens when a blob is written under the name of an existing blob.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    /// The existing blob is replaced.
+    #[derivative(Default)]
+    Allow,
+    /// The blob isn't written, failing the request and rejecting its events.
+    Refuse,
+    /// The blob isn't written, and its events are dropped.
+    Skip,
+    /// The existing blob is permanently deleted before being replaced, so that soft delete
+    /// doesn't retain its previous content. Append blobs are appended to rather than replaced.
+    DeletePermanently,
+}
+
+impl OverwritePolicy {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Refuse => "refuse",
+            Self::Skip => "skip",
+            Self::DeletePermanently => "delete_permanently",
+        }
+    }
+}
+
+/// What happens to events that can't be encoded.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EncodingErrorPolicy {
+    /// Only the events that can't be encoded are discarded.
+    DropEvent,
+    /// The whole batch is discarded.
+    #[derivative(Default)]
+    DropBatch,
+    /// The events that can't be encoded are rejected, failing their acknowledgement.
+    Reject,
+}
+
+impl EncodingErrorPolicy {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::DropEvent => "drop_event",
+            Self::DropBatch => "drop_batch",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+/// What happens to events whose partition can't be rendered from the `blob_prefix` template.
+#[derive(Clone, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub enum UnresolvablePartition {
+    /// The events are discarded.
+    #[derivative(Default)]
+    Drop,
+    /// The events are written under the given partition instead.
+    Fallback { partition: String },
+    /// The events are rejected, failing their acknowledgement.
+    Reject,
+}
+
+impl UnresolvablePartition {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Drop => "drop",
+            Self::Fallback { .. } => "fallback",
+            Self::Reject => "reject",
+        }
+    }
+
+    /// Handles a batch of events whose partition couldn't be rendered, returning the partition
+    /// to write it under if it is to be written at all.
+    pub fn resolve(&self, mut events: Vec<Event>) -> Option<(String, Vec<Event>)> {
+        emit!(AzureBlobUnresolvablePartition {
+            mode: self.as_str(),
+            count: events.len(),
+        });
+        match self {
+            Self::Drop => {
+                events.take_finalizers().update_status(EventStatus::Dropped);
+                None
+            }
+            Self::Fallback { partition } => Some((partition.clone(), events)),
+            Self::Reject => {
+                events
+                    .take_finalizers()
+                    .update_status(EventStatus::Rejected);
+                None
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -46,31 +341,96 @@ impl RetryLogic for AzureBlobRetryLogic {
 
     fn is_retriable_error(&self, error: &Self::Error) -> bool {
         match error {
+            // The blob would be refused again, however many times it is retried.
+            HttpError::StatusCode { status, .. } if status == &StatusCode::PAYLOAD_TOO_LARGE => {
+                false
+            }
             HttpError::StatusCode { status, .. } => {
                 status.is_server_error() || status == &StatusCode::TOO_MANY_REQUESTS
             }
             _ => false,
         }
     }
+
+    fn should_retry_response(&self, response: &Self::Response) -> RetryAction {
+        match &response.verify_error {
+            Some(error) => RetryAction::Retry(error.clone().into()),
+            None => RetryAction::Successful,
+        }
+    }
+
+    fn retry_after(&self, request: &dyn Any) -> Option<Duration> {
+        request
+            .downcast_ref::<AzureBlobRequest>()
+            .and_then(|request| request.attempts.retry_after())
+    }
+
+    fn on_backoff(&self, delay: Duration, source: BackoffSource) {
+        emit!(AzureBlobRetryBackoff { delay, source });
+    }
 }
 
 #[derive(Debug)]
 pub struct AzureBlobResponse {
-    pub inner: PutBlockBlobResponse,
+    /// The response to the write, unless the blob was skipped.
+    pub inner: Option<BlobWrite>,
     pub count: usize,
+    /// The size of the events in memory before they were encoded, in bytes.
     pub events_byte_size: usize,
+    /// The length of the body of the upload as sent, once encoded and compressed, in bytes.
+    pub byte_size: usize,
+    pub attempts: usize,
+    pub elapsed: Duration,
+    /// Why the written blob couldn't be verified, when `verify_write` is enabled or its stored
+    /// Content-MD5 doesn't match the payload. The request is retried.
+    pub verify_error: Option<String>,
+    pub partition: String,
+    /// The receipt of the written blob, when `write_receipts` is enabled.
+    pub receipt: Option<WriteReceipt>,
+    /// Where the blob was written, when `acknowledgement_provenance` is enabled.
+    pub provenance: Option<BlobProvenance>,
+    /// The position of the append among the appends handed on by the sink, when
+    /// `blob_type.ordering` is `strict`. The appends to each blob are written in this order.
+    pub append_sequence: Option<u64>,
+    /// What became of the batch in each of its containers, when `fan_out` is enabled.
+    pub outcomes: Vec<ContainerOutcome>,
+}
+
+/// The response of the storage account to the write of a blob.
+#[derive(Debug)]
+pub enum BlobWrite {
+    /// The blob was written as a whole.
+    Put(PutBlockBlobResponse),
+    /// The payload was appended to the blob.
+    Append(AppendBlockResponse),
+    /// The blob was found already written with the payload, by an earlier attempt at the request.
+    Existing { etag: String },
+}
+
+impl BlobWrite {
+    pub fn etag(&self) -> String {
+        match self {
+            Self::Put(response) => response.etag.to_string(),
+            Self::Append(response) => response.etag.to_string(),
+            Self::Existing { etag } => etag.clone(),
+        }
+    }
 }
 
 impl DriverResponse for AzureBlobResponse {
     fn event_status(&self) -> EventStatus {
-        EventStatus::Delivered
+        match (&self.inner, &self.verify_error) {
+            (None, _) => EventStatus::Dropped,
+            (_, Some(_)) => EventStatus::Errored,
+            (_, None) => EventStatus::Delivered,
+        }
     }
 
     fn events_sent(&self) -> EventsSent {
         EventsSent {
             count: self.count,
             byte_size: self.events_byte_size,
-            output: None,
+            output: Some(self.partition.as_str()),
         }
     }
 }
@@ -83,70 +443,598 @@ pub enum HealthcheckError {
     UnknownContainer { container: String },
     #[snafu(display("Unknown status code: {}", status))]
     Unknown { status: StatusCode },
+    #[snafu(display("Storage account still throttling after {} attempts", attempts))]
+    Throttled { attempts: usize },
 }
 
+/// The attempts made at the healthcheck while the storage account throttles it.
+const HEALTHCHECK_ATTEMPTS: usize = 4;
+/// How long the first retry of a throttled healthcheck waits when not told how long to wait by
+/// the storage account, doubling on every retry.
+const HEALTHCHECK_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// The longest a throttled healthcheck waits before being retried, whatever the storage account
+/// asks for, so that startup isn't held up for long.
+const HEALTHCHECK_MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Checks that the container can be read.
+///
+/// A storage account being throttled is perfectly usable, as the sink retries the throttled
+/// uploads, so the throttled checks are retried a few times after the delay the storage account
+/// asks for before failing.
 pub fn build_healthcheck(
     container_name: String,
     client: Arc<ContainerClient>,
 ) -> crate::Result<Healthcheck> {
     let healthcheck = async move {
-        let request = client.get_properties().execute().await;
-
-        match request {
-            Ok(_) => Ok(()),
-            Err(reason) => Err(match reason.downcast_ref::<HttpError>() {
-                Some(HttpError::StatusCode { status, .. }) => match *status {
-                    StatusCode::FORBIDDEN => HealthcheckError::InvalidCredentials.into(),
-                    StatusCode::NOT_FOUND => HealthcheckError::UnknownContainer {
-                        container: container_name,
-                    }
-                    .into(),
-                    status => HealthcheckError::Unknown { status }.into(),
+        let mut backoff = HEALTHCHECK_INITIAL_BACKOFF;
+        for attempt in 1..=HEALTHCHECK_ATTEMPTS {
+            let (request, retry_after) =
+                capture_retry_after(client.get_properties().execute()).await;
+
+            let error = match request {
+                Ok(_) => return Ok(()),
+                Err(reason) => match reason.downcast_ref::<HttpError>() {
+                    Some(HttpError::StatusCode { status, .. }) => match *status {
+                        StatusCode::FORBIDDEN => HealthcheckError::InvalidCredentials,
+                        StatusCode::NOT_FOUND => HealthcheckError::UnknownContainer {
+                            container: container_name.clone(),
+                        },
+                        StatusCode::TOO_MANY_REQUESTS => {
+                            HealthcheckError::Throttled { attempts: attempt }
+                        }
+                        status => HealthcheckError::Unknown { status },
+                    },
+                    _ => return Err(reason),
                 },
-                _ => reason,
-            }),
+            };
+            if !matches!(error, HealthcheckError::Throttled { .. })
+                || attempt == HEALTHCHECK_ATTEMPTS
+            {
+                return Err(error.into());
+            }
+
+            let delay = retry_after.unwrap_or(backoff).min(HEALTHCHECK_MAX_BACKOFF);
+            emit!(AzureBlobHealthcheckThrottled { attempt, delay });
+            tokio::time::sleep(delay).await;
+            backoff *= 2;
         }
+        unreachable!("the last attempt returns")
     };
 
     Ok(healthcheck.boxed())
 }
 
-pub fn build_client(
+/// The resource a token is requested for when checking that a managed identity is available.
+const STORAGE_TOKEN_RESOURCE: &str = "https://storage.azure.com/";
+
+/// How long to wait for a token when checking that a managed identity is available, so an
+/// unreachable metadata endpoint doesn't hold up startup.
+const TOKEN_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Builds the credential used with `storage_account`.
+///
+/// Tokens are fetched from `token_broker` or `imds_endpoint`, in that order, when they are set,
+/// instead of going through the chain of the default credential.
+pub(crate) fn build_credential(
+    imds_endpoint: Option<Uri>,
+    token_broker: Option<TokenBroker>,
+) -> Arc<dyn TokenCredential> {
+    match (token_broker, imds_endpoint) {
+        (Some(broker), _) => Arc::new(BrokerCredential::new(broker)),
+        (None, Some(endpoint)) => Arc::new(ImdsCredential::new(endpoint)),
+        (None, None) => Arc::new(DefaultAzureCredential::default()),
+    }
+}
+
+const fn default_warm_up_timeout_secs() -> u64 {
+    30
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TokenWarmUpConfig {
+    /// How long to wait for the token at startup.
+    #[serde(default = "default_warm_up_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Whether the token is renewed in the background before it expires.
+    #[serde(default)]
+    pub keep_fresh: bool,
+}
+
+impl TokenWarmUpConfig {
+    pub fn build(&self) -> crate::Result<TokenWarmUp> {
+        if self.timeout_secs == 0 {
+            return Err("`token_warm_up.timeout_secs` must be greater than zero".into());
+        }
+        Ok(TokenWarmUp {
+            timeout: Duration::from_secs(self.timeout_secs),
+            keep_fresh: self.keep_fresh,
+        })
+    }
+}
+
+/// How often a kept fresh token is checked, which renews it once it gets close to its expiry.
+const TOKEN_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Acquires the token of the storage account before the client is handed out, so the first
+/// upload doesn't wait for it.
+#[derive(Clone, Copy, Debug)]
+pub struct TokenWarmUp {
+    timeout: Duration,
+    keep_fresh: bool,
+}
+
+impl TokenWarmUp {
+    async fn run(
+        self,
+        credential: &Arc<AutoRefreshingTokenCredential>,
+        storage_account: &str,
+    ) -> crate::Result<()> {
+        let error = match timeout(self.timeout, credential.get_token(STORAGE_TOKEN_RESOURCE)).await
+        {
+            Ok(Ok(_)) => None,
+            Ok(Err(error)) => Some(error.to_string()),
+            Err(_) => Some(format!("no token acquired within {:?}", self.timeout)),
+        };
+        if let Some(error) = error {
+            return Err(format!(
+                "Failed to acquire a token for storage account {:?}: {}",
+                storage_account, error
+            )
+            .into());
+        }
+
+        if self.keep_fresh {
+            tokio::spawn(keep_fresh(Arc::downgrade(credential)));
+        }
+        Ok(())
+    }
+}
+
+/// Renews the token ahead of its expiry until the client using the credential is dropped.
+async fn keep_fresh(credential: Weak<AutoRefreshingTokenCredential>) {
+    let mut ticks = interval(TOKEN_REFRESH_INTERVAL);
+    // The first tick completes right away, and the token was just acquired.
+    ticks.tick().await;
+    loop {
+        ticks.tick().await;
+        let credential = match credential.upgrade() {
+            Some(credential) => credential,
+            None => break,
+        };
+        if let Err(error) = credential.get_token(STORAGE_TOKEN_RESOURCE).await {
+            emit!(AzureBlobTokenRefreshError {
+                error: error.to_string(),
+            });
+        }
+    }
+}
+
+/// Lets the token cache of a credential be shared between the storage client and the warm-up.
+struct SharedCredential(Arc<AutoRefreshingTokenCredential>);
+
+#[async_trait::async_trait]
+impl TokenCredential for SharedCredential {
+    async fn get_token(&self, resource: &str) -> Result<TokenResponse, azure_core::Error> {
+        self.0.get_token(resource).await
+    }
+}
+
+/// The way the sink authenticates against the storage account.
+pub(crate) enum AzureBlobAuth {
+    ConnectionString(String),
+    TokenCredential {
+        storage_account: String,
+        credential: Arc<dyn TokenCredential>,
+    },
+}
+
+/// Picks the authentication method from the configured credentials.
+///
+/// When both `connection_string` and `storage_account` are provided, the managed identity of
+/// `storage_account` takes precedence and the connection string is only used if no token can be
+/// acquired at startup.
+pub(crate) async fn resolve_auth(
     connection_string: Option<String>,
     storage_account: Option<String>,
-    container_name: String,
-) -> crate::Result<Arc<ContainerClient>> {
-    let client;
+    credential: Arc<dyn TokenCredential>,
+    probe_timeout: Duration,
+) -> crate::Result<AzureBlobAuth> {
     match (connection_string, storage_account) {
-        (Some(connection_string_p), None) => {
-            client = StorageAccountClient::new_connection_string(
-                new_http_client(),
-                &connection_string_p,
-            )?
-            .as_storage_client()
-            .as_container_client(container_name);
-        }
-        (None, Some(storage_account_p)) => {
-            let creds = std::sync::Arc::new(DefaultAzureCredential::default());
-            let auto_creds = Box::new(AutoRefreshingTokenCredential::new(creds));
-
-            client = StorageAccountClient::new_token_credential(
-                new_http_client(),
-                storage_account_p,
-                auto_creds,
-            )
-            .as_storage_client()
-            .as_container_client(container_name);
+        (Some(connection_string), None) => Ok(AzureBlobAuth::ConnectionString(connection_string)),
+        (None, Some(storage_account)) => Ok(AzureBlobAuth::TokenCredential {
+            storage_account,
+            credential,
+        }),
+        (Some(connection_string), Some(storage_account)) => {
+            let error =
+                match timeout(probe_timeout, credential.get_token(STORAGE_TOKEN_RESOURCE)).await {
+                    Ok(Ok(_)) => {
+                        return Ok(AzureBlobAuth::TokenCredential {
+                            storage_account,
+                            credential,
+                        })
+                    }
+                    Ok(Err(error)) => error.to_string(),
+                    Err(_) => format!("no token acquired within {:?}", probe_timeout),
+                };
+            emit!(AzureBlobCredentialFallback { error });
+            Ok(AzureBlobAuth::ConnectionString(connection_string))
         }
         (None, None) => {
-            return Err("Either `connection_string` or `storage_account` has to be provided".into())
+            Err("Either `connection_string` or `storage_account` has to be provided".into())
         }
-        (Some(_), Some(_)) => {
+    }
+}
+
+/// The `User-Agent` sent to the storage account unless `user_agent` is configured.
+pub fn default_user_agent() -> HeaderValue {
+    HeaderValue::from_str(&format!("Vector/{}", crate::get_version()))
+        .expect("Invalid header value for version!")
+}
+
+/// Parses and validates the `user_agent` option.
+pub fn parse_user_agent(user_agent: &str) -> crate::Result<HeaderValue> {
+    if user_agent.trim().is_empty() {
+        return Err("`user_agent` must not be empty".into());
+    }
+    HeaderValue::from_str(user_agent)
+        .map_err(|error| format!("Invalid `user_agent` {:?}: {}", user_agent, error).into())
+}
+
+/// The headers `extra_headers` can't set.
+///
+/// Besides the credentials and the headers owned by the client, the headers covered by the Shared
+/// Key signature are reserved: they're added once the request is signed, so the storage account
+/// would reject it.
+const RESERVED_HEADERS: &[&str] = &[
+    "authorization",
+    "content-encoding",
+    "content-language",
+    "content-length",
+    "content-md5",
+    "content-type",
+    "date",
+    "host",
+    "if-match",
+    "if-modified-since",
+    "if-none-match",
+    "if-unmodified-since",
+    "range",
+    "user-agent",
+];
+
+/// Parses and validates the `extra_headers` option.
+///
+/// `x-ms-date` and the other `x-ms-` headers are rejected along with `RESERVED_HEADERS`.
+pub fn parse_extra_headers(extra_headers: &BTreeMap<String, String>) -> crate::Result<HeaderMap> {
+    let mut headers = HeaderMap::with_capacity(extra_headers.len());
+    for (name, value) in extra_headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|error| {
+            format!(
+                "Invalid header name {:?} in `extra_headers`: {}",
+                name, error
+            )
+        })?;
+        if header_name.as_str().starts_with("x-ms-")
+            || RESERVED_HEADERS.contains(&header_name.as_str())
+        {
             return Err(
-                "`connection_string` and `storage_account` can't be provided at the same time"
-                    .into(),
+                format!("The {:?} header can't be set through `extra_headers`", name).into(),
+            );
+        }
+        let header_value = HeaderValue::from_str(value).map_err(|error| {
+            format!(
+                "Invalid value of the {:?} header in `extra_headers`: {}",
+                name, error
+            )
+        })?;
+        headers.insert(header_name, header_value);
+    }
+    Ok(headers)
+}
+
+/// Starts the builder of the HTTP client used to talk to the storage account.
+///
+/// HTTP/2 is negotiated through ALPN when the endpoint supports it, unless `force_http1` is set.
+/// Every request is sent with `user_agent` and `extra_headers`.
+pub(crate) fn http_client_builder(
+    force_http1: bool,
+    user_agent: HeaderValue,
+    extra_headers: HeaderMap,
+) -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder()
+        .user_agent(user_agent)
+        .default_headers(extra_headers);
+    if force_http1 {
+        builder.http1_only()
+    } else {
+        builder
+    }
+}
+
+/// How the client of a container authenticates to the storage account and sends its requests.
+pub struct ClientOptions {
+    pub connection_string: Option<String>,
+    pub storage_account: Option<String>,
+    pub imds_endpoint: Option<Uri>,
+    pub token_broker: Option<TokenBroker>,
+    pub container_name: String,
+    pub force_http1: bool,
+    pub user_agent: HeaderValue,
+    pub extra_headers: HeaderMap,
+    pub token_warm_up: Option<TokenWarmUp>,
+    /// Where the requests are routed to instead of the account's own endpoint.
+    pub endpoint: Option<Endpoint>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connection_string: None,
+            storage_account: None,
+            imds_endpoint: None,
+            token_broker: None,
+            container_name: String::new(),
+            force_http1: false,
+            user_agent: default_user_agent(),
+            extra_headers: HeaderMap::new(),
+            token_warm_up: None,
+            endpoint: None,
+        }
+    }
+}
+
+pub async fn build_client(options: ClientOptions) -> crate::Result<Arc<ContainerClient>> {
+    let credential = build_credential(options.imds_endpoint, options.token_broker);
+    let auth = resolve_auth(
+        options.connection_string,
+        options.storage_account,
+        credential,
+        TOKEN_PROBE_TIMEOUT,
+    )
+    .await?;
+    let builder = http_client_builder(
+        options.force_http1,
+        options.user_agent,
+        options.extra_headers,
+    );
+    let builder = match options.endpoint {
+        Some(endpoint) => endpoint.route(builder, &account_host(&auth)?).await?,
+        None => builder,
+    };
+    let http_client: Arc<dyn HttpClient> = Arc::new(RetryAfterClient::new(builder.build()?));
+    container_client(
+        http_client,
+        auth,
+        options.container_name,
+        options.token_warm_up,
+    )
+    .await
+}
+
+/// Builds the client of the container with the authentication method picked.
+///
+/// The token is only warmed up when authenticating with a token credential.
+pub(crate) async fn container_client(
+    http_client: Arc<dyn HttpClient>,
+    auth: AzureBlobAuth,
+    container_name: String,
+    token_warm_up: Option<TokenWarmUp>,
+) -> crate::Result<Arc<ContainerClient>> {
+    let client = match auth {
+        AzureBlobAuth::ConnectionString(connection_string) => {
+            StorageAccountClient::new_connection_string(http_client, &connection_string)?
+        }
+        AzureBlobAuth::TokenCredential {
+            storage_account,
+            credential,
+        } => {
+            let credential = Arc::new(AutoRefreshingTokenCredential::new(credential));
+            if let Some(token_warm_up) = token_warm_up {
+                token_warm_up.run(&credential, &storage_account).await?;
+            }
+            StorageAccountClient::new_token_credential(
+                http_client,
+                storage_account,
+                Box::new(SharedCredential(credential)),
             )
         }
+    };
+    Ok(client
+        .as_storage_client()
+        .as_container_client(container_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use azure_core::Error as AzureError;
+    use vector_common::btreemap;
+
+    use super::*;
+    use crate::{
+        event::{BatchNotifier, BatchStatus, EventFinalizer},
+        sinks::azure_common::test_util::mock_request,
+    };
+
+    struct UnavailableCredential;
+
+    #[async_trait::async_trait]
+    impl TokenCredential for UnavailableCredential {
+        async fn get_token(&self, _resource: &str) -> Result<TokenResponse, AzureError> {
+            Err(AzureError::GetTokenError("no managed identity".into()))
+        }
+    }
+
+    /// Counts the tokens handed out, which expire an hour after being issued.
+    #[derive(Default)]
+    struct CountingCredential(AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl TokenCredential for CountingCredential {
+        async fn get_token(&self, _resource: &str) -> Result<TokenResponse, AzureError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(TokenResponse::new(
+                azure_core::auth::AccessToken::new("token".to_owned()),
+                Utc::now() + chrono::Duration::hours(1),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn token_warm_up_fetches_token_before_first_put() {
+        let warm_up = TokenWarmUpConfig {
+            timeout_secs: 1,
+            keep_fresh: false,
+        }
+        .build()
+        .unwrap();
+        let client = |credential: Arc<dyn TokenCredential>, warm_up| {
+            container_client(
+                Arc::new(reqwest::Client::new()),
+                AzureBlobAuth::TokenCredential {
+                    storage_account: "mylogstorage".into(),
+                    credential,
+                },
+                "logs".into(),
+                warm_up,
+            )
+        };
+
+        // The client is only handed out, and so can only put blobs, once the token is acquired.
+        let credential = Arc::new(CountingCredential::default());
+        client(Arc::clone(&credential) as _, Some(warm_up))
+            .await
+            .unwrap();
+        assert_eq!(credential.0.load(Ordering::SeqCst), 1);
+
+        // Without the warm-up the token waits for the first request.
+        let credential = Arc::new(CountingCredential::default());
+        client(Arc::clone(&credential) as _, None).await.unwrap();
+        assert_eq!(credential.0.load(Ordering::SeqCst), 0);
+
+        let error = client(Arc::new(UnavailableCredential), Some(warm_up))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Failed to acquire a token"), "{}", error);
+        assert!(error.contains("mylogstorage"), "{}", error);
+
+        assert!(TokenWarmUpConfig {
+            timeout_secs: 0,
+            keep_fresh: false,
+        }
+        .build()
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn auth_falls_back_to_connection_string() {
+        let connection_string = "UseDevelopmentStorage=true".to_string();
+        let auth = resolve_auth(
+            Some(connection_string.clone()),
+            Some("mylogstorage".into()),
+            Arc::new(UnavailableCredential),
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+        assert!(
+            matches!(auth, AzureBlobAuth::ConnectionString(value) if value == connection_string)
+        );
+
+        // A lone storage account is used as is, without checking for a token up front.
+        let auth = resolve_auth(
+            None,
+            Some("mylogstorage".into()),
+            Arc::new(UnavailableCredential),
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            auth,
+            AzureBlobAuth::TokenCredential { storage_account, .. } if storage_account == "mylogstorage"
+        ));
+
+        assert!(resolve_auth(
+            None,
+            None,
+            Arc::new(UnavailableCredential),
+            Duration::from_secs(1)
+        )
+        .await
+        .is_err());
+    }
+
+    #[test]
+    fn checksum_sidecar_digests() {
+        assert_eq!(ChecksumAlgorithm::Md5.extension(), "md5");
+        assert_eq!(
+            ChecksumAlgorithm::Md5.hex_digest(b"abc"),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(ChecksumAlgorithm::Sha256.extension(), "sha256");
+        assert_eq!(
+            ChecksumAlgorithm::Sha256.hex_digest(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn rejects_reserved_extra_headers() {
+        let parse = |name: &str, value: &str| parse_extra_headers(&btreemap! { name => value });
+
+        assert!(parse("x-cost-center", "cc-1234").is_ok());
+        for reserved in [
+            "Authorization",
+            "x-ms-date",
+            "X-MS-Version",
+            "x-ms-meta-team",
+            "Content-Length",
+            "Host",
+            "User-Agent",
+        ] {
+            assert!(
+                parse(reserved, "value").is_err(),
+                "{} was accepted",
+                reserved
+            );
+        }
+        assert!(parse("x cost center", "cc-1234").is_err());
+        assert!(parse("x-cost-center", "cc-1234\r\nX-Injected: 1").is_err());
+    }
+
+    #[test]
+    fn finalizers_taken_twice_are_reported() {
+        let (batch, receiver) = BatchNotifier::new_with_receiver();
+        let mut request = mock_request();
+        request.metadata.finalizers = EventFinalizers::new(EventFinalizer::new(batch));
+        // Retries go through clones of the request whose finalizers were taken.
+        let finalizers = request.take_finalizers();
+        let mut retried = request.clone();
+        assert!(!vector_common::event_test_util::contains_name(
+            "AzureBlobFinalizerDoubleTake"
+        ));
+
+        let taken_again =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| retried.take_finalizers()));
+        assert!(vector_common::event_test_util::contains_name(
+            "AzureBlobFinalizerDoubleTake"
+        ));
+        match taken_again {
+            Err(_) => assert!(cfg!(debug_assertions)),
+            Ok(finalizers) => {
+                assert!(!cfg!(debug_assertions));
+                assert!(finalizers.is_empty());
+            }
+        }
+
+        // The finalizers taken first still resolve the events.
+        finalizers.resolve(EventStatus::Delivered);
+        assert_eq!(
+            futures::executor::block_on(receiver),
+            BatchStatus::Delivered
+        );
     }
-    Ok(client)
 }
@@ -0,0 +1,126 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+use azure_core::HttpError;
+use azure_storage_blobs::prelude::*;
+use http::StatusCode;
+use snafu::Snafu;
+use tokio::sync::{Mutex, Notify};
+
+use crate::internal_events::azure_blob::{AzureBlobContainerLost, AzureBlobContainerRecreated};
+
+/// Number of uploads in a row finding the container gone after which it is considered lost.
+pub const MAX_CONTAINER_NOT_FOUND: usize = 3;
+
+#[derive(Debug, Snafu)]
+pub enum ContainerError {
+    #[snafu(display(
+        "Container {:?} doesn't exist anymore; set `create_missing_container` to recreate it",
+        container
+    ))]
+    ContainerNotFound { container: String },
+    #[snafu(display("Failed to recreate container {:?}: {}", container, error))]
+    RecreateFailed {
+        container: String,
+        error: crate::Error,
+    },
+}
+
+/// Whether a request failed because the container it was sent to doesn't exist.
+///
+/// A missing blob answers with the same status, so the two are told apart by the error code of
+/// the response.
+pub fn is_container_not_found(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        error.downcast_ref::<HttpError>(),
+        Some(HttpError::StatusCode { status, body })
+            if *status == StatusCode::NOT_FOUND && body.contains("<Code>ContainerNotFound</Code>")
+    )
+}
+
+/// Watches over the container the sink writes to, which can be deleted while the sink is running.
+///
+/// Depending on `create_missing_container`, the container is either recreated as soon as an upload
+/// finds it gone, or considered lost once [`MAX_CONTAINER_NOT_FOUND`] uploads in a row did. Lost
+/// containers stop the sink rather than failing every upload that follows.
+pub struct ContainerGuard {
+    create_missing: bool,
+    not_found: AtomicUsize,
+    lost: AtomicBool,
+    lost_notify: Notify,
+    /// Serializes the recreations of the container, so concurrent uploads only create it once.
+    creating: Mutex<()>,
+}
+
+impl ContainerGuard {
+    pub fn new(create_missing: bool) -> Arc<Self> {
+        Arc::new(Self {
+            create_missing,
+            not_found: AtomicUsize::new(0),
+            lost: AtomicBool::new(false),
+            lost_notify: Notify::new(),
+            creating: Mutex::new(()),
+        })
+    }
+
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Acquire)
+    }
+
+    /// Completes once the container is lost.
+    pub async fn lost(&self) {
+        loop {
+            // Registered before checking, so the container can't be lost in between unnoticed.
+            let notified = self.lost_notify.notified();
+            if self.is_lost() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Records an upload that found the container in place.
+    pub fn found(&self) {
+        self.not_found.store(0, Ordering::Release);
+    }
+
+    /// Handles an upload that found the container gone.
+    ///
+    /// Returns once the container is recreated, so the upload can be tried again, or fails when
+    /// it isn't to be recreated.
+    pub async fn not_found(&self, client: &ContainerClient) -> crate::Result<()> {
+        let container = client.container_name().to_owned();
+        if !self.create_missing {
+            let not_found = self.not_found.fetch_add(1, Ordering::AcqRel) + 1;
+            if not_found >= MAX_CONTAINER_NOT_FOUND && !self.lost.swap(true, Ordering::AcqRel) {
+                emit!(AzureBlobContainerLost {
+                    container: container.as_str(),
+                });
+                self.lost_notify.notify_waiters();
+            }
+            return Err(ContainerError::ContainerNotFound { container }.into());
+        }
+
+        let _creating = self.creating.lock().await;
+        match client.create().execute().await {
+            Ok(_) => {
+                emit!(AzureBlobContainerRecreated {
+                    container: container.as_str(),
+                });
+                Ok(())
+            }
+            // Another upload recreated it in the meantime.
+            Err(error) if is_conflict(error.as_ref()) => Ok(()),
+            Err(error) => Err(ContainerError::RecreateFailed { container, error }.into()),
+        }
+    }
+}
+
+fn is_conflict(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        error.downcast_ref::<HttpError>(),
+        Some(HttpError::StatusCode { status, .. }) if *status == StatusCode::CONFLICT
+    )
+}
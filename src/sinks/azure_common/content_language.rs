@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{event::Event, template::Template};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ContentLanguageConfig {
+    /// The template rendering the language of each event, such as `{{ detected_language }}`.
+    pub template: Template,
+    /// The language of the blobs whose events don't render a single valid language tag, which
+    /// otherwise have none.
+    pub default: Option<String>,
+}
+
+impl ContentLanguageConfig {
+    pub fn build(&self) -> crate::Result<ContentLanguages> {
+        if let Some(default) = &self.default {
+            if !is_language_tag(default) {
+                return Err(format!(
+                    "`content_language.default` {:?} isn't a well-formed BCP 47 language tag",
+                    default
+                )
+                .into());
+            }
+        }
+        Ok(ContentLanguages {
+            template: self.template.clone(),
+            default: self.default.clone(),
+        })
+    }
+}
+
+/// Renders the `Content-Language` of each blob from its events.
+#[derive(Clone, Debug)]
+pub struct ContentLanguages {
+    template: Template,
+    default: Option<String>,
+}
+
+impl ContentLanguages {
+    /// Returns the language of the blob holding the events, which is the one they all render,
+    /// or the default when they don't render the same valid language tag.
+    ///
+    /// The batches aren't split by language, so the template should be part of `blob_prefix`
+    /// for the events of every batch to share theirs.
+    pub fn language(&self, events: &[Event]) -> Option<String> {
+        let mut languages = events.iter().map(|event| {
+            self.template
+                .render_string(event)
+                .ok()
+                .filter(|language| is_language_tag(language))
+        });
+        let first = languages.next().flatten();
+        let unanimous = languages.all(|language| language == first);
+        match first {
+            Some(first) if unanimous => Some(first),
+            _ => self.default.clone(),
+        }
+    }
+}
+
+/// Whether the tag is a well-formed BCP 47 language tag, such as `en`, `pt-BR` or
+/// `zh-Hant-TW`, as defined by RFC 5646.
+///
+/// Only the syntax of the tag is checked, not whether its subtags are registered. The irregular
+/// grandfathered tags, such as `i-klingon`, aren't supported.
+pub fn is_language_tag(tag: &str) -> bool {
+    let subtags = tag.split('-').collect::<Vec<_>>();
+    if subtags.iter().any(|subtag| {
+        subtag.is_empty() || subtag.len() > 8 || !subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+    }) {
+        return false;
+    }
+    if is_private_use(&subtags) {
+        return true;
+    }
+
+    let (language, mut rest) = match subtags.split_first() {
+        Some(split) => split,
+        None => return false,
+    };
+    // Four letters are reserved for future use.
+    if !is_alpha(language) || !matches!(language.len(), 2 | 3 | 5..=8) {
+        return false;
+    }
+    if language.len() <= 3 {
+        // Up to three extended language subtags.
+        for _ in 0..3 {
+            match rest.split_first() {
+                Some((extlang, tail)) if extlang.len() == 3 && is_alpha(extlang) => rest = tail,
+                _ => break,
+            }
+        }
+    }
+    // The script.
+    if let Some((script, tail)) = rest.split_first() {
+        if script.len() == 4 && is_alpha(script) {
+            rest = tail;
+        }
+    }
+    // The region.
+    if let Some((region, tail)) = rest.split_first() {
+        if (region.len() == 2 && is_alpha(region))
+            || (region.len() == 3 && region.bytes().all(|b| b.is_ascii_digit()))
+        {
+            rest = tail;
+        }
+    }
+    // The variants.
+    while let Some((variant, tail)) = rest.split_first() {
+        if variant.len() >= 5 || (variant.len() == 4 && variant.as_bytes()[0].is_ascii_digit()) {
+            rest = tail;
+        } else {
+            break;
+        }
+    }
+    // The extensions, each made of a singleton other than `x` followed by subtags of at least two
+    // characters.
+    while let Some((singleton, tail)) = rest.split_first() {
+        if singleton.len() != 1 || singleton.eq_ignore_ascii_case("x") {
+            break;
+        }
+        let extension = tail.iter().take_while(|subtag| subtag.len() >= 2).count();
+        if extension == 0 {
+            return false;
+        }
+        rest = &tail[extension..];
+    }
+    rest.is_empty() || is_private_use(rest)
+}
+
+/// Whether the subtags are an `x` singleton followed by at least one subtag.
+fn is_private_use(subtags: &[&str]) -> bool {
+    match subtags.split_first() {
+        Some((x, tail)) => x.eq_ignore_ascii_case("x") && !tail.is_empty(),
+        None => false,
+    }
+}
+
+fn is_alpha(subtag: &str) -> bool {
+    subtag.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::LogEvent;
+
+    #[test]
+    fn falls_back_to_default() {
+        let event = |language: Option<&str>| {
+            let mut log = LogEvent::from("message");
+            if let Some(language) = language {
+                log.insert("language", language);
+            }
+            Event::Log(log)
+        };
+        let languages = ContentLanguageConfig {
+            template: Template::try_from("{{ language }}").unwrap(),
+            default: Some("en".into()),
+        }
+        .build()
+        .unwrap();
+        assert_eq!(
+            languages.language(&[event(Some("zh-Hant-TW")), event(Some("zh-Hant-TW"))]),
+            Some("zh-Hant-TW".to_owned())
+        );
+        assert_eq!(languages.language(&[event(None)]), Some("en".to_owned()));
+        assert_eq!(
+            languages.language(&[event(Some("en_US"))]),
+            Some("en".to_owned())
+        );
+        assert_eq!(
+            languages.language(&[event(Some("de")), event(Some("es"))]),
+            Some("en".to_owned())
+        );
+
+        assert!(ContentLanguageConfig {
+            template: Template::try_from("{{ language }}").unwrap(),
+            default: Some("english please".into()),
+        }
+        .build()
+        .is_err());
+
+        for tag in [
+            "en",
+            "pt-BR",
+            "zh-Hant-TW",
+            "es-419",
+            "sl-rozaj-biske",
+            "de-CH-1901",
+            "zh-yue-HK",
+            "en-US-u-ca-gregory",
+            "en-x-private",
+            "x-whatever",
+        ] {
+            assert!(is_language_tag(tag), "{}", tag);
+        }
+        for tag in [
+            "",
+            "e",
+            "engl",
+            "en-",
+            "en--US",
+            "en_US",
+            "en-u",
+            "en-u-x-a",
+            "x",
+            "abcdefghi",
+            "en-Ü",
+        ] {
+            assert!(!is_language_tag(tag), "{}", tag);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use bytes::BytesMut;
+use codecs::encoding::Framer;
+
+use super::config::EncodingErrorPolicy;
+use crate::{
+    codecs::{Encoder, Transformer},
+    event::{Event, EventStatus, Finalizable},
+    internal_events::azure_blob::AzureBlobEncodingError,
+};
+
+/// Takes the events that can't be encoded out of the batches, according to the policy.
+///
+/// Each event is encoded on its own to find out whether it can be, so this is only used for the
+/// policies acting on single events. Batches are dropped as a whole when their blob fails to
+/// encode otherwise.
+#[derive(Clone)]
+pub struct EncodingErrorHandler {
+    policy: EncodingErrorPolicy,
+    encoder: (Transformer, Encoder<Framer>),
+}
+
+impl EncodingErrorHandler {
+    pub const fn new(policy: EncodingErrorPolicy, encoder: (Transformer, Encoder<Framer>)) -> Self {
+        Self { policy, encoder }
+    }
+
+    /// Returns the events of the batch that can be encoded, if there are any left.
+    pub fn handle(&self, events: Vec<Event>) -> Option<Vec<Event>> {
+        let mut encoder = self.encoder.1.clone();
+        let mut buffer = BytesMut::new();
+        let mut failures = 0;
+        let mut first_error = None;
+
+        let events = events
+            .into_iter()
+            .filter_map(|mut event| {
+                let mut probe = event.clone();
+                self.encoder.0.transform(&mut probe);
+                buffer.clear();
+                let error = match encoder.serialize(probe, &mut buffer) {
+                    Ok(()) => return Some(event),
+                    Err(error) => error,
+                };
+
+                failures += 1;
+                first_error.get_or_insert_with(|| error.to_string());
+                let status = match self.policy {
+                    EncodingErrorPolicy::Reject => EventStatus::Rejected,
+                    _ => EventStatus::Dropped,
+                };
+                event.take_finalizers().update_status(status);
+                None
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(error) = first_error {
+            emit!(AzureBlobEncodingError {
+                policy: self.policy.as_str(),
+                count: failures,
+                error,
+            });
+        }
+        (!events.is_empty()).then(|| events)
+    }
+}
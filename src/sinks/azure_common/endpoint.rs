@@ -0,0 +1,189 @@
+use http::{uri::Scheme, Uri};
+use tokio::net::lookup_host;
+
+use super::config::AzureBlobAuth;
+
+/// The suffix of the endpoints of storage accounts in the public Azure cloud.
+const DEFAULT_ENDPOINT_SUFFIX: &str = "core.windows.net";
+
+/// An endpoint the connections to the storage account are routed to, such as a private link.
+///
+/// Only the address the connections are made to changes: requests keep the host of the storage
+/// account in their `Host` header and TLS server name, as private endpoints expect, and are sent to
+/// the port of the storage account.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Endpoint {
+    host: String,
+}
+
+impl Endpoint {
+    /// Parses and validates the endpoint of `option`, either a host name or an http(s) URL
+    /// without a port or path.
+    pub fn parse(endpoint: &str, option: &str) -> crate::Result<Self> {
+        let invalid = |reason: &str| -> crate::Error {
+            format!("Invalid `{}` {:?}: {}", option, endpoint, reason).into()
+        };
+        let uri = endpoint
+            .parse::<Uri>()
+            .map_err(|error| invalid(&error.to_string()))?;
+        if let Some(scheme) = uri.scheme() {
+            if *scheme != Scheme::HTTP && *scheme != Scheme::HTTPS {
+                return Err(invalid("expected an http(s) URL"));
+            }
+        }
+        if uri.port().is_some() {
+            return Err(invalid("the port of the storage account is used"));
+        }
+        if !matches!(uri.path(), "" | "/") || uri.query().is_some() {
+            return Err(invalid("expected no path"));
+        }
+        match uri.host() {
+            Some(host) if !host.is_empty() => Ok(Self {
+                host: host.to_string(),
+            }),
+            _ => Err(invalid("expected a host")),
+        }
+    }
+
+    /// Routes the connections of the client to `account_host` to this endpoint.
+    pub(crate) async fn route(
+        &self,
+        builder: reqwest::ClientBuilder,
+        account_host: &str,
+    ) -> crate::Result<reqwest::ClientBuilder> {
+        // The port is ignored by the client, which connects to the port of the request's URL.
+        let address = lookup_host((self.host.as_str(), 443))
+            .await
+            .map_err(|error| format!("Failed to resolve endpoint {:?}: {}", self.host, error))?
+            .next()
+            .ok_or_else(|| format!("Endpoint {:?} resolved to no address", self.host))?;
+        Ok(builder.resolve(account_host, address))
+    }
+}
+
+/// The host of the blob endpoint of the storage account authenticated against.
+pub(crate) fn account_host(auth: &AzureBlobAuth) -> crate::Result<String> {
+    let connection_string = match auth {
+        AzureBlobAuth::TokenCredential {
+            storage_account, ..
+        } => {
+            return Ok(format!(
+                "{}.blob.{}",
+                storage_account, DEFAULT_ENDPOINT_SUFFIX
+            ))
+        }
+        AzureBlobAuth::ConnectionString(connection_string) => connection_string,
+    };
+
+    let setting = |key: &str| {
+        connection_string
+            .split(';')
+            .filter_map(|setting| setting.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.trim())
+    };
+    if let Some(blob_endpoint) = setting("BlobEndpoint") {
+        return blob_endpoint
+            .parse::<Uri>()
+            .ok()
+            .and_then(|uri| uri.host().map(str::to_string))
+            .ok_or_else(|| format!("Invalid `BlobEndpoint` {:?}", blob_endpoint).into());
+    }
+    if setting("UseDevelopmentStorage").map_or(false, |value| value.eq_ignore_ascii_case("true")) {
+        return Ok("127.0.0.1".to_string());
+    }
+    match setting("AccountName") {
+        Some(account) => Ok(format!(
+            "{}.blob.{}",
+            account,
+            setting("EndpointSuffix").unwrap_or(DEFAULT_ENDPOINT_SUFFIX)
+        )),
+        None => Err("The connection string has neither `BlobEndpoint` nor `AccountName`".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::Service;
+    use wiremock::{
+        matchers::{header, method},
+        Mock, MockServer,
+    };
+
+    use super::*;
+    use crate::sinks::azure_common::{
+        config::{build_client, ClientOptions},
+        service::AzureBlobService,
+        test_util::{mock_put_response, mock_request, received_puts},
+    };
+
+    #[tokio::test]
+    async fn writes_go_to_write_endpoint() {
+        let server = MockServer::start().await;
+        // The account's host doesn't resolve, so only the connections routed to the write endpoint
+        // reach the storage account, still addressed to the account's host.
+        let account_host = format!("account.blob.invalid:{}", server.address().port());
+        Mock::given(method("PUT"))
+            .and(header("host", account_host.as_str()))
+            .respond_with(mock_put_response())
+            .mount(&server)
+            .await;
+        let connection_string = format!(
+            "DefaultEndpointsProtocol=http;AccountName=devstoreaccount1;AccountKey=Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==;BlobEndpoint=http://{}/devstoreaccount1;",
+            account_host
+        );
+        let build = |endpoint: Option<&str>| {
+            build_client(ClientOptions {
+                connection_string: Some(connection_string.clone()),
+                container_name: "logs".into(),
+                endpoint: endpoint
+                    .map(|endpoint| Endpoint::parse(endpoint, "write_endpoint").unwrap()),
+                ..Default::default()
+            })
+        };
+
+        let client = build(Some("http://127.0.0.1")).await.unwrap();
+        AzureBlobService::new(client)
+            .call(mock_request())
+            .await
+            .unwrap();
+        assert_eq!(received_puts(&server).await, 1);
+
+        let client = build(None).await.unwrap();
+        assert!(AzureBlobService::new(client)
+            .call(mock_request())
+            .await
+            .is_err());
+        assert_eq!(received_puts(&server).await, 1);
+    }
+
+    #[test]
+    fn validation() {
+        assert!(Endpoint::parse(
+            "mylogstorage.privatelink.blob.core.windows.net",
+            "write_endpoint"
+        )
+        .is_ok());
+        assert!(Endpoint::parse("https://cdn.example.com/", "read_endpoint").is_ok());
+        assert!(Endpoint::parse("https://cdn.example.com:8443", "read_endpoint").is_err());
+        assert!(Endpoint::parse("https://cdn.example.com/logs", "read_endpoint").is_err());
+        assert!(Endpoint::parse("ftp://cdn.example.com", "read_endpoint").is_err());
+
+        let connection_string = |value: &str| AzureBlobAuth::ConnectionString(value.into());
+        assert_eq!(
+            account_host(&connection_string(
+                "DefaultEndpointsProtocol=https;AccountName=mylogstorage;AccountKey=a2V5;EndpointSuffix=core.chinacloudapi.cn"
+            ))
+            .unwrap(),
+            "mylogstorage.blob.core.chinacloudapi.cn"
+        );
+        assert_eq!(
+            account_host(&connection_string(
+                "AccountName=mylogstorage;AccountKey=a2V5;BlobEndpoint=https://logs.example.com/"
+            ))
+            .unwrap(),
+            "logs.example.com"
+        );
+        assert!(account_host(&connection_string("AccountKey=a2V5")).is_err());
+    }
+}
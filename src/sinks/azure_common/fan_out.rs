@@ -0,0 +1,380 @@
+use std::{
+    collections::HashSet,
+    num::NonZeroUsize,
+    task::{Context, Poll},
+};
+
+use futures::{
+    future::{join_all, BoxFuture},
+    TryFutureExt,
+};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use tower::Service;
+use vector_core::{event::EventStatus, stream::DriverResponse};
+
+use super::config::{AzureBlobRequest, AzureBlobResponse, DeliveryAttempts};
+use crate::internal_events::azure_blob::AzureBlobFanOutTargetError;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FanOutContainerConfig {
+    pub container_name: String,
+    /// Prepended to the name of every blob written to the container.
+    #[serde(default)]
+    pub blob_prefix: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FanOutConfig {
+    /// The containers every batch is written to on top of `container_name`.
+    pub containers: Vec<FanOutContainerConfig>,
+    /// The number of containers a batch has to be written to for its events to be acknowledged,
+    /// which defaults to all of them.
+    pub quorum: Option<NonZeroUsize>,
+}
+
+impl FanOutConfig {
+    /// Checks the targets against the container of the sink, returning the quorum.
+    pub fn build(&self, container_name: &str) -> crate::Result<NonZeroUsize> {
+        if self.containers.is_empty() {
+            return Err("`fan_out.containers` must not be empty".into());
+        }
+        let mut targets = HashSet::new();
+        targets.insert((container_name, ""));
+        for target in &self.containers {
+            if !targets.insert((target.container_name.as_str(), target.blob_prefix.as_str())) {
+                return Err(format!(
+                    "`fan_out.containers` writes twice to container {:?} with prefix {:?}",
+                    target.container_name, target.blob_prefix
+                )
+                .into());
+            }
+        }
+
+        let total = NonZeroUsize::new(targets.len()).expect("targets include the sink container");
+        match self.quorum {
+            Some(quorum) if quorum > total => Err(format!(
+                "`fan_out.quorum` can't be greater than the {} containers written to",
+                total
+            )
+            .into()),
+            Some(quorum) => Ok(quorum),
+            None => Ok(total),
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum FanOutError {
+    #[snafu(display(
+        "Batch was written to {} of the {} required containers: {}",
+        written,
+        quorum,
+        errors
+    ))]
+    QuorumNotMet {
+        written: usize,
+        quorum: usize,
+        errors: String,
+    },
+}
+
+/// What became of a batch in one of the containers it was written to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContainerOutcome {
+    pub container: String,
+    pub blob: String,
+    /// Why the batch couldn't be written to the container.
+    pub error: Option<String>,
+}
+
+/// One of the containers a batch is written to, through a service of its own.
+pub struct FanOutTarget<S> {
+    pub container: String,
+    pub blob_prefix: String,
+    pub service: S,
+}
+
+/// Writes every batch to each of its targets, acknowledging its events once `quorum` of them
+/// succeeded.
+///
+/// Each target retries on its own, so the targets a batch was written to aren't written again
+/// when another one fails.
+pub struct FanOutService<S> {
+    targets: Vec<FanOutTarget<S>>,
+    quorum: usize,
+}
+
+impl<S> FanOutService<S> {
+    /// Writes every batch to a single target, as is.
+    pub fn single(container: String, service: S) -> Self {
+        Self {
+            targets: vec![FanOutTarget {
+                container,
+                blob_prefix: String::new(),
+                service,
+            }],
+            quorum: 1,
+        }
+    }
+
+    pub fn new(targets: Vec<FanOutTarget<S>>, quorum: NonZeroUsize) -> Self {
+        Self {
+            targets,
+            quorum: quorum.get(),
+        }
+    }
+}
+
+impl<S> Service<AzureBlobRequest> for FanOutService<S>
+where
+    S: Service<AzureBlobRequest, Response = AzureBlobResponse>,
+    S::Error: Into<crate::Error> + Send,
+    S::Future: Send + 'static,
+{
+    type Response = AzureBlobResponse;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        for target in &mut self.targets {
+            match target.service.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error.into())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: AzureBlobRequest) -> Self::Future {
+        if let [target] = self.targets.as_mut_slice() {
+            if target.blob_prefix.is_empty() {
+                return Box::pin(target.service.call(request).map_err(Into::into));
+            }
+        }
+
+        let calls = self
+            .targets
+            .iter_mut()
+            .map(|target| {
+                let mut request = request.clone();
+                request.attempts = DeliveryAttempts::default();
+                request.metadata.partition_key =
+                    format!("{}{}", target.blob_prefix, request.metadata.partition_key);
+                let blob = request.metadata.partition_key.clone();
+                let future = target.service.call(request);
+                let container = target.container.clone();
+                async move { (container, blob, future.await.map_err(Into::into)) }
+            })
+            .collect::<Vec<_>>();
+        let quorum = self.quorum;
+
+        Box::pin(async move {
+            let mut written = None;
+            let mut outcomes = Vec::new();
+            for (container, blob, result) in join_all(calls).await {
+                let error = match result {
+                    Ok(response) if response.event_status() == EventStatus::Errored => {
+                        Some(response.verify_error.clone().unwrap_or_default())
+                    }
+                    Ok(response) => {
+                        written.get_or_insert(response);
+                        None
+                    }
+                    Err(error) => Some(error.to_string()),
+                };
+                if let Some(error) = &error {
+                    emit!(AzureBlobFanOutTargetError {
+                        container: container.as_str(),
+                        error: error.as_str(),
+                    });
+                }
+                outcomes.push(ContainerOutcome {
+                    container,
+                    blob,
+                    error,
+                });
+            }
+
+            let written_count = outcomes
+                .iter()
+                .filter(|outcome| outcome.error.is_none())
+                .count();
+            match written {
+                Some(mut response) if written_count >= quorum => {
+                    response.outcomes = outcomes;
+                    Ok(response)
+                }
+                _ => Err(FanOutError::QuorumNotMet {
+                    written: written_count,
+                    quorum,
+                    errors: outcomes
+                        .iter()
+                        .filter_map(|outcome| {
+                            let error = outcome.error.as_ref()?;
+                            Some(format!("{:?}: {}", outcome.container, error))
+                        })
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                }
+                .into()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::sinks::azure_common::{
+        service::AzureBlobService,
+        test_util::{mock_client, mock_put_response, mock_request},
+    };
+
+    /// Writes every batch to the "logs" and "redacted" containers of the mock storage account.
+    async fn fan_out_service(
+        server: &MockServer,
+        quorum: usize,
+    ) -> FanOutService<AzureBlobService> {
+        let client = mock_client(server).await;
+        let redacted = Arc::clone(client.storage_client()).as_container_client("redacted");
+        FanOutService::new(
+            vec![
+                FanOutTarget {
+                    container: "logs".into(),
+                    blob_prefix: String::new(),
+                    service: AzureBlobService::new(client),
+                },
+                FanOutTarget {
+                    container: "redacted".into(),
+                    blob_prefix: "copy/".into(),
+                    service: AzureBlobService::new(redacted),
+                },
+            ],
+            NonZeroUsize::new(quorum).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn writes_every_container() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/devstoreaccount1/logs/blob/test.log"))
+            .respond_with(mock_put_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/devstoreaccount1/redacted/copy/blob/test.log"))
+            .respond_with(mock_put_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = fan_out_service(&server, 2)
+            .await
+            .call(mock_request())
+            .await
+            .unwrap();
+        assert_eq!(response.event_status(), EventStatus::Delivered);
+        assert_eq!(
+            response.outcomes,
+            vec![
+                ContainerOutcome {
+                    container: "logs".into(),
+                    blob: "blob/test.log".into(),
+                    error: None,
+                },
+                ContainerOutcome {
+                    container: "redacted".into(),
+                    blob: "copy/blob/test.log".into(),
+                    error: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn acknowledges_per_quorum() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/devstoreaccount1/redacted/copy/blob/test.log"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(mock_put_response())
+            .mount(&server)
+            .await;
+
+        // Requiring every container rejects the events of the batch.
+        let error = fan_out_service(&server, 2)
+            .await
+            .call(mock_request())
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("written to 1 of the 2 required"),
+            "{}",
+            error
+        );
+        assert!(vector_common::event_test_util::contains_name(
+            "AzureBlobFanOutTargetError"
+        ));
+
+        // A single container is enough for the events to be acknowledged, the failure only being
+        // reported in the outcomes.
+        let response = fan_out_service(&server, 1)
+            .await
+            .call(mock_request())
+            .await
+            .unwrap();
+        assert_eq!(response.event_status(), EventStatus::Delivered);
+        assert_eq!(response.outcomes.len(), 2);
+        assert_eq!(response.outcomes[0].error, None);
+        assert_eq!(response.outcomes[1].container, "redacted");
+        assert!(response.outcomes[1].error.is_some());
+    }
+
+    #[test]
+    fn config_validation() {
+        let fan_out = |containers: &[(&str, &str)], quorum: Option<usize>| FanOutConfig {
+            containers: containers
+                .iter()
+                .map(|(container_name, blob_prefix)| FanOutContainerConfig {
+                    container_name: container_name.to_string(),
+                    blob_prefix: blob_prefix.to_string(),
+                })
+                .collect(),
+            quorum: quorum.map(|quorum| NonZeroUsize::new(quorum).unwrap()),
+        };
+
+        assert_eq!(
+            fan_out(&[("redacted", "")], None)
+                .build("logs")
+                .unwrap()
+                .get(),
+            2
+        );
+        assert_eq!(
+            fan_out(&[("logs", "copy/")], Some(1))
+                .build("logs")
+                .unwrap()
+                .get(),
+            1
+        );
+        assert!(fan_out(&[], None).build("logs").is_err());
+        assert!(fan_out(&[("logs", "")], None).build("logs").is_err());
+        assert!(fan_out(&[("redacted", "")], Some(3)).build("logs").is_err());
+    }
+}
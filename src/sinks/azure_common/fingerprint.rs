@@ -0,0 +1,187 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Write as _,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::event::{util::log::all_fields, Event, Value};
+
+/// The fingerprint standing for the events of every schema past `max_fingerprints`.
+pub const MIXED_FINGERPRINT: &str = "mixed";
+
+fn default_max_fingerprints() -> NonZeroUsize {
+    NonZeroUsize::new(100).unwrap()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SchemaFingerprintConfig {
+    /// The number of distinct schemas the events are grouped by, past which the events of any
+    /// other schema are grouped together as `mixed`.
+    #[serde(default = "default_max_fingerprints")]
+    pub max_fingerprints: NonZeroUsize,
+}
+
+impl SchemaFingerprintConfig {
+    pub fn build(&self) -> SchemaFingerprints {
+        SchemaFingerprints {
+            max_fingerprints: self.max_fingerprints.get(),
+            seen: Arc::default(),
+        }
+    }
+}
+
+/// Groups events by the fingerprint of their schema, so that every blob holds events of a single
+/// shape.
+///
+/// The fingerprint is a hash of the paths and types of the fields of the event, so it doesn't
+/// depend on the values of the fields, the order they were inserted in or the process. The
+/// elements of arrays share the path of their array, so arrays of any length have the same
+/// schema.
+#[derive(Clone, Debug)]
+pub struct SchemaFingerprints {
+    max_fingerprints: usize,
+    /// The fingerprints grouped by so far, which are kept for as long as the sink runs.
+    seen: Arc<Mutex<HashSet<String>>>,
+}
+
+impl SchemaFingerprints {
+    /// Returns the fingerprint the event is grouped by, registering it unless there are already
+    /// `max_fingerprints` of them, in which case the event is grouped as `mixed`.
+    pub fn resolve(&self, event: &Event) -> String {
+        let fingerprint = fingerprint(event);
+        let mut seen = self.seen.lock().expect("fingerprints lock poisoned");
+        if seen.contains(&fingerprint) || seen.len() < self.max_fingerprints {
+            seen.insert(fingerprint.clone());
+            fingerprint
+        } else {
+            MIXED_FINGERPRINT.to_owned()
+        }
+    }
+
+    /// Returns the fingerprint the event was grouped by, without registering it.
+    pub fn lookup(&self, event: &Event) -> String {
+        let fingerprint = fingerprint(event);
+        if self
+            .seen
+            .lock()
+            .expect("fingerprints lock poisoned")
+            .contains(&fingerprint)
+        {
+            fingerprint
+        } else {
+            MIXED_FINGERPRINT.to_owned()
+        }
+    }
+}
+
+/// Returns the fingerprint of the schema of the event, as 16 hexadecimal digits.
+///
+/// The schema of metrics is made of the type of their value and the names of their tags.
+pub fn fingerprint(event: &Event) -> String {
+    let schema = match event {
+        Event::Log(log) => match log.as_map() {
+            Some(fields) => field_schema(fields),
+            None => BTreeSet::new(),
+        },
+        Event::Trace(trace) => field_schema(trace.as_map()),
+        Event::Metric(metric) => std::iter::once(format!("metric:{}", metric.value().as_name()))
+            .chain(
+                metric
+                    .tags()
+                    .into_iter()
+                    .flat_map(|tags| tags.keys())
+                    .map(|tag| format!("tag.{}", tag)),
+            )
+            .collect(),
+    };
+
+    let mut lines = String::new();
+    for line in schema {
+        writeln!(lines, "{}", line).expect("writing to a string can't fail");
+    }
+    format!("{:016x}", seahash::hash(lines.as_bytes()))
+}
+
+/// The paths of the fields along with their types, sorted by path.
+fn field_schema(fields: &BTreeMap<String, Value>) -> BTreeSet<String> {
+    all_fields(fields)
+        .map(|(path, value)| format!("{}:{}", strip_indices(&path), value.kind_str()))
+        .collect()
+}
+
+/// Replaces the indices of the path with empty brackets, such as `a[0].b` with `a[].b`.
+fn strip_indices(path: &str) -> String {
+    let mut stripped = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => {
+                in_index = true;
+                stripped.push(c);
+            }
+            ']' => {
+                in_index = false;
+                stripped.push(c);
+            }
+            _ if in_index => {}
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use vector_common::btreemap;
+
+    use super::*;
+    use crate::event::LogEvent;
+
+    #[test]
+    fn fingerprint_is_stable() {
+        let log = |fields: BTreeMap<String, Value>| Event::Log(LogEvent::from(fields));
+
+        // The values of the fields and the order they were inserted in make no difference.
+        let first = log(btreemap! {
+            "message" => "first",
+            "status" => 200,
+            "tags" => vec!["a", "b"],
+        });
+        let second = log(btreemap! {
+            "tags" => vec!["c"],
+            "status" => 404,
+            "message" => "second",
+        });
+        assert_eq!(fingerprint(&first), fingerprint(&second));
+        assert_eq!(fingerprint(&first).len(), 16);
+
+        // Their names and types do.
+        let renamed = log(btreemap! {
+            "message" => "first",
+            "code" => 200,
+            "tags" => vec!["a"],
+        });
+        let retyped = log(btreemap! {
+            "message" => "first",
+            "status" => "200",
+            "tags" => vec!["a"],
+        });
+        assert_ne!(fingerprint(&first), fingerprint(&renamed));
+        assert_ne!(fingerprint(&first), fingerprint(&retyped));
+
+        // Past the limit, the events of any other schema are grouped together.
+        let fingerprints = SchemaFingerprintConfig {
+            max_fingerprints: 1.try_into().unwrap(),
+        }
+        .build();
+        assert_eq!(fingerprints.resolve(&first), fingerprint(&first));
+        assert_eq!(fingerprints.resolve(&second), fingerprint(&first));
+        assert_eq!(fingerprints.resolve(&renamed), MIXED_FINGERPRINT);
+        assert_eq!(fingerprints.lookup(&retyped), MIXED_FINGERPRINT);
+        assert_eq!(fingerprints.lookup(&first), fingerprint(&first));
+    }
+}
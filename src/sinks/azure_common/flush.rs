@@ -0,0 +1,148 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{watch, Notify};
+
+use crate::internal_events::azure_blob::AzureBlobFlushTriggered;
+
+/// The signals that can trigger a flush of the sink.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlushSignal {
+    Sigusr1,
+    Sigusr2,
+}
+
+impl FlushSignal {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Sigusr1 => "SIGUSR1",
+            Self::Sigusr2 => "SIGUSR2",
+        }
+    }
+
+    /// Starts listening for the signal, so that it no longer stops the process.
+    #[cfg(unix)]
+    pub fn build(self) -> crate::Result<FlushListener> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let kind = match self {
+            Self::Sigusr1 => SignalKind::user_defined1(),
+            Self::Sigusr2 => SignalKind::user_defined2(),
+        };
+        let signals = signal(kind)
+            .map_err(|error| format!("Failed to listen for {}: {}", self.as_str(), error))?;
+        Ok(FlushListener {
+            signal: self,
+            signals,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn build(self) -> crate::Result<FlushListener> {
+        Err("`flush_signal` is only supported on Unix".into())
+    }
+}
+
+/// Triggers a flush of the sink each time the signal is received.
+pub struct FlushListener {
+    signal: FlushSignal,
+    #[cfg(unix)]
+    signals: tokio::signal::unix::Signal,
+}
+
+impl FlushListener {
+    /// Flushes the sink through `trigger` on every signal until `shutdown` fires.
+    #[cfg(unix)]
+    pub async fn run(mut self, trigger: Arc<FlushTrigger>, mut shutdown: watch::Receiver<()>) {
+        loop {
+            tokio::select! {
+                received = self.signals.recv() => match received {
+                    Some(()) => {
+                        emit!(AzureBlobFlushTriggered {
+                            signal: self.signal.as_str(),
+                        });
+                        trigger.flush().await;
+                    }
+                    None => break,
+                },
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub async fn run(self, _trigger: Arc<FlushTrigger>, _shutdown: watch::Receiver<()>) {
+        let _ = self.signal;
+    }
+}
+
+/// Flushes the pending batches of the sink on demand, without stopping it.
+///
+/// Every flush is numbered. The sink stops taking in events once a flush is requested, drains all
+/// of its partitions and waits for the uploads in flight the same way it does when shutting down,
+/// then marks the flushes requested so far as complete and starts taking in events again.
+#[derive(Debug, Default)]
+pub struct FlushTrigger {
+    requested: AtomicU64,
+    requested_notify: Notify,
+    /// The last flush the sink started on.
+    flushing: AtomicU64,
+    completed: AtomicU64,
+    completed_notify: Notify,
+}
+
+impl FlushTrigger {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Flushes the sink, completing once the events it took in before were written.
+    ///
+    /// Completes right away once the sink has stopped.
+    pub async fn flush(&self) {
+        let flush = self.requested.fetch_add(1, Ordering::AcqRel) + 1;
+        self.requested_notify.notify_waiters();
+        loop {
+            // Registered before checking, so the flush can't complete in between unnoticed.
+            let notified = self.completed_notify.notified();
+            if self.completed.load(Ordering::Acquire) >= flush {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// The last flush the sink started on, any flush after it is still to be started.
+    pub fn flushing(&self) -> u64 {
+        self.flushing.load(Ordering::Acquire)
+    }
+
+    /// Completes once a flush after `seen` is requested, which the sink then starts on.
+    pub async fn requested(&self, seen: u64) {
+        loop {
+            let notified = self.requested_notify.notified();
+            let requested = self.requested.load(Ordering::Acquire);
+            if requested > seen {
+                self.flushing.fetch_max(requested, Ordering::AcqRel);
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Marks the flushes the sink started on as complete.
+    pub fn complete(&self) {
+        self.completed.fetch_max(self.flushing(), Ordering::AcqRel);
+        self.completed_notify.notify_waiters();
+    }
+
+    /// Marks every flush as complete once the sink has stopped, as there is nothing left to flush.
+    pub fn close(&self) {
+        self.completed.store(u64::MAX, Ordering::Release);
+        self.completed_notify.notify_waiters();
+    }
+}
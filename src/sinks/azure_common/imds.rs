@@ -0,0 +1,165 @@
+use azure_core::{
+    auth::{AccessToken, TokenCredential, TokenResponse},
+    Error as AzureError,
+};
+use chrono::{TimeZone, Utc};
+use http::{uri::Scheme, Uri};
+use serde::Deserialize;
+
+use crate::{config::ProxyConfig, http::HttpClient};
+
+/// The API version of the instance metadata service token endpoint.
+const IMDS_API_VERSION: &str = "2018-02-01";
+
+/// Parses and validates the `imds_endpoint` option.
+pub fn parse_endpoint(endpoint: &str) -> crate::Result<Uri> {
+    let uri = endpoint
+        .parse::<Uri>()
+        .map_err(|error| format!("Invalid `imds_endpoint` {:?}: {}", endpoint, error))?;
+    match (uri.scheme(), uri.authority()) {
+        (Some(scheme), Some(_)) if *scheme == Scheme::HTTP || *scheme == Scheme::HTTPS => Ok(uri),
+        _ => Err(format!(
+            "Invalid `imds_endpoint` {:?}: expected an absolute http(s) URL",
+            endpoint
+        )
+        .into()),
+    }
+}
+
+#[derive(Deserialize)]
+struct ImdsToken {
+    access_token: String,
+    expires_on: String,
+}
+
+/// Managed identity credential fetching its tokens from an instance metadata service at a
+/// non-standard endpoint.
+pub struct ImdsCredential {
+    endpoint: Uri,
+}
+
+impl ImdsCredential {
+    pub const fn new(endpoint: Uri) -> Self {
+        Self { endpoint }
+    }
+
+    async fn fetch_token(&self, resource: &str) -> crate::Result<TokenResponse> {
+        let uri = format!(
+            "{}?api-version={}&resource={}",
+            self.endpoint,
+            IMDS_API_VERSION,
+            percent_encoding::utf8_percent_encode(resource, percent_encoding::NON_ALPHANUMERIC)
+        );
+        let request = http::Request::get(uri)
+            .header("Metadata", "true")
+            .body(hyper::Body::empty())?;
+
+        let response = HttpClient::new(None, &ProxyConfig::from_env())?
+            .send(request)
+            .await?;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        if !status.is_success() {
+            return Err(format!(
+                "Instance metadata service responded with {}: {}",
+                status,
+                String::from_utf8_lossy(&body)
+            )
+            .into());
+        }
+
+        let token = serde_json::from_slice::<ImdsToken>(&body)?;
+        let expires_on = Utc.timestamp(token.expires_on.parse()?, 0);
+        Ok(TokenResponse::new(
+            AccessToken::new(token.access_token),
+            expires_on,
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenCredential for ImdsCredential {
+    async fn get_token(&self, resource: &str) -> Result<TokenResponse, AzureError> {
+        self.fetch_token(resource)
+            .await
+            .map_err(AzureError::GetTokenError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::sinks::azure_common::config::{build_credential, resolve_auth, AzureBlobAuth};
+
+    #[test]
+    fn endpoint_is_validated() {
+        assert!(parse_endpoint("http://192.0.2.1/metadata/identity/oauth2/token").is_ok());
+        assert!(parse_endpoint("/metadata/identity/oauth2/token").is_err());
+        assert!(parse_endpoint("ftp://192.0.2.1/token").is_err());
+        assert!(parse_endpoint("not a url").is_err());
+    }
+
+    #[tokio::test]
+    async fn endpoint_override_is_used() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/metadata/identity/oauth2/token"))
+            .and(header("Metadata", "true"))
+            .and(query_param("resource", "https://storage.azure.com/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "token",
+                "expires_on": "4102444800",
+                "resource": "https://storage.azure.com/",
+                "token_type": "Bearer",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let endpoint =
+            parse_endpoint(&format!("{}/metadata/identity/oauth2/token", server.uri())).unwrap();
+
+        let auth = resolve_auth(
+            Some("UseDevelopmentStorage=true".into()),
+            Some("mylogstorage".into()),
+            build_credential(Some(endpoint), None),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(auth, AzureBlobAuth::TokenCredential { .. }));
+    }
+
+    #[tokio::test]
+    async fn unresponsive_endpoint_does_not_hang() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(60)))
+            .mount(&server)
+            .await;
+        let endpoint =
+            parse_endpoint(&format!("{}/metadata/identity/oauth2/token", server.uri())).unwrap();
+
+        let auth = tokio::time::timeout(
+            Duration::from_secs(10),
+            resolve_auth(
+                Some("UseDevelopmentStorage=true".into()),
+                Some("mylogstorage".into()),
+                build_credential(Some(endpoint), None),
+                Duration::from_millis(200),
+            ),
+        )
+        .await
+        .expect("startup hung on the metadata endpoint")
+        .unwrap();
+
+        assert!(matches!(auth, AzureBlobAuth::ConnectionString(_)));
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use tower::Service;
+
+use super::config::AzureBlobRequest;
+use crate::internal_events::azure_blob::AzureBlobInFlightShed;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct InFlightLimitConfig {
+    /// The payload bytes of the requests in flight, retries included, above which batches are
+    /// shed.
+    pub max_bytes: Option<usize>,
+    /// The events of the requests in flight, retries included, above which batches are shed.
+    pub max_events: Option<usize>,
+}
+
+impl InFlightLimitConfig {
+    pub fn build(&self) -> crate::Result<InFlightLimit> {
+        match (self.max_bytes, self.max_events) {
+            (None, None) => {
+                Err("`in_flight_limit` needs `max_bytes` or `max_events` to be set".into())
+            }
+            (Some(0), _) | (_, Some(0)) => {
+                Err("`in_flight_limit` limits must be greater than zero".into())
+            }
+            (max_bytes, max_events) => Ok(InFlightLimit {
+                max_bytes: max_bytes.unwrap_or(usize::MAX),
+                max_events: max_events.unwrap_or(usize::MAX),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum InFlightLimitError {
+    #[snafu(display(
+        "Shed a batch of {} events as the requests in flight are over `in_flight_limit`",
+        count
+    ))]
+    Shed { count: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct InFlightLimit {
+    max_bytes: usize,
+    max_events: usize,
+}
+
+#[derive(Debug, Default)]
+struct InFlight {
+    bytes: AtomicUsize,
+    events: AtomicUsize,
+}
+
+/// Releases the bytes and events of a request once it is no longer in flight.
+struct InFlightGuard {
+    in_flight: Arc<InFlight>,
+    bytes: usize,
+    events: usize,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.bytes.fetch_sub(self.bytes, Ordering::AcqRel);
+        self.in_flight
+            .events
+            .fetch_sub(self.events, Ordering::AcqRel);
+    }
+}
+
+/// Caps the requests held in flight, including the ones waiting to be retried, so that an
+/// outage of the storage account doesn't back up the pipeline in front of the sink.
+///
+/// While the requests in flight are over the limit, the service is ready regardless of the
+/// service it wraps, and sheds the batches it is called with: their events are rejected rather
+/// than queued until the storage account is back.
+pub struct InFlightLimitService<S> {
+    inner: S,
+    limit: Option<InFlightLimit>,
+    in_flight: Arc<InFlight>,
+    /// Whether the inner service was polled ready for the next call.
+    ready: bool,
+}
+
+impl<S> InFlightLimitService<S> {
+    /// Wraps the service, which is left unlimited without a limit.
+    pub fn new(inner: S, limit: Option<InFlightLimit>) -> Self {
+        Self {
+            inner,
+            limit,
+            in_flight: Arc::default(),
+            ready: false,
+        }
+    }
+
+    fn is_over_limit(&self) -> bool {
+        self.limit.map_or(false, |limit| {
+            self.in_flight.bytes.load(Ordering::Acquire) >= limit.max_bytes
+                || self.in_flight.events.load(Ordering::Acquire) >= limit.max_events
+        })
+    }
+}
+
+impl<S> Service<AzureBlobRequest> for InFlightLimitService<S>
+where
+    S: Service<AzureBlobRequest>,
+    S::Error: Into<crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.ready {
+            return Poll::Ready(Ok(()));
+        }
+        if self.is_over_limit() {
+            // The next call is shed without going through the inner service.
+            return Poll::Ready(Ok(()));
+        }
+        match self.inner.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                self.ready = true;
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(error)) => Poll::Ready(Err(error.into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, request: AzureBlobRequest) -> Self::Future {
+        if !std::mem::replace(&mut self.ready, false) {
+            let count = request.metadata.count;
+            emit!(AzureBlobInFlightShed {
+                count,
+                byte_size: request.blob_data.len(),
+            });
+            return Box::pin(async move { Err(InFlightLimitError::Shed { count }.into()) });
+        }
+
+        let guard = InFlightGuard {
+            in_flight: Arc::clone(&self.in_flight),
+            bytes: request.blob_data.len(),
+            events: request.metadata.count,
+        };
+        self.in_flight
+            .bytes
+            .fetch_add(guard.bytes, Ordering::AcqRel);
+        self.in_flight
+            .events
+            .fetch_add(guard.events, Ordering::AcqRel);
+
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let result = future.await.map_err(Into::into);
+            drop(guard);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::sinks::azure_common::{
+        config::AzureBlobResponse,
+        test_util::{counter_value, mock_request},
+    };
+
+    #[tokio::test]
+    async fn sheds_batches_over_limit() {
+        crate::test_util::components::init_test();
+        let limit = InFlightLimitConfig {
+            max_bytes: Some(20),
+            max_events: None,
+        };
+        // The storage account is down, so the requests never complete.
+        let outage = tower::service_fn(|_: AzureBlobRequest| {
+            futures::future::pending::<crate::Result<AzureBlobResponse>>()
+        });
+        let mut service = InFlightLimitService::new(outage, Some(limit.build().unwrap()));
+        let shed_before = counter_value("azure_blob_in_flight_shed_events_total", &[]);
+
+        // Two requests of 13 bytes get in flight before the limit is reached.
+        let first = service.ready().await.unwrap().call(mock_request());
+        let second = service.ready().await.unwrap().call(mock_request());
+        for _ in 0..3 {
+            let error = service
+                .ready()
+                .await
+                .unwrap()
+                .call(mock_request())
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("in_flight_limit"), "{}", error);
+        }
+        assert_eq!(
+            counter_value("azure_blob_in_flight_shed_events_total", &[]) - shed_before,
+            3.0
+        );
+
+        // Requests are let through again once those in flight are done with.
+        drop((first, second));
+        let mut third = service.ready().await.unwrap().call(mock_request());
+        assert!(futures::poll!(&mut third).is_pending());
+        assert_eq!(
+            counter_value("azure_blob_in_flight_shed_events_total", &[]) - shed_before,
+            3.0
+        );
+
+        assert!(InFlightLimitConfig {
+            max_bytes: None,
+            max_events: None,
+        }
+        .build()
+        .is_err());
+    }
+}
@@ -0,0 +1,367 @@
+use std::sync::Arc;
+
+use azure_core::prelude::Metadata;
+use azure_storage_blobs::prelude::*;
+use bytes::Bytes;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::service::{blob_exists, is_blob_not_found};
+use crate::internal_events::azure_blob::AzureBlobOrphanedBlob;
+
+/// Name of the blob metadata flagging a data blob missing from its index.
+pub const ORPHANED_METADATA: &str = "orphaned";
+
+fn default_index_name() -> String {
+    "_index.jsonl".to_string()
+}
+
+/// What happens to a data blob once its index entry fails to be written.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanHandling {
+    /// The blob is deleted.
+    #[derivative(Default)]
+    Delete,
+    /// The blob is kept, with its `orphaned` metadata set to `true`.
+    Mark,
+}
+
+impl OrphanHandling {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Delete => "delete",
+            Self::Mark => "mark",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SealedBatchConfig {
+    /// The name of the index blob written into the prefix of each partition.
+    #[serde(default = "default_index_name")]
+    pub index_name: String,
+    #[serde(default)]
+    pub orphans: OrphanHandling,
+}
+
+impl SealedBatchConfig {
+    pub fn build(&self) -> crate::Result<Arc<SealedBatches>> {
+        if self.index_name.is_empty() || self.index_name.contains('/') {
+            return Err("`sealed_batch.index_name` must be a non-empty name without `/`".into());
+        }
+
+        Ok(Arc::new(SealedBatches {
+            index_name: self.index_name.clone(),
+            orphans: self.orphans,
+            creating: Mutex::new(()),
+        }))
+    }
+}
+
+/// Appends an entry pointing to every data blob written to the index blob of its partition, so
+/// that the readers going through the index never see a data blob before it is complete.
+///
+/// The partitions hold the time window of `blob_prefix`, so each window gets an index of its
+/// own. The events of a batch are only acknowledged once both its data blob and its index entry
+/// are written; when the entry can't be, the data blob is deleted or marked as orphaned.
+pub struct SealedBatches {
+    index_name: String,
+    orphans: OrphanHandling,
+    /// Serializes the creations of index blobs, so concurrent entries only create them once.
+    creating: Mutex<()>,
+}
+
+impl SealedBatches {
+    /// The name of the index blob of the partition.
+    pub fn index(&self, partition: &str) -> String {
+        let separator = if partition.is_empty() || partition.ends_with('/') {
+            ""
+        } else {
+            "/"
+        };
+        format!("{}{}{}", partition, separator, self.index_name)
+    }
+
+    /// Appends the entry of the data blob to the index of its partition.
+    pub async fn seal(
+        &self,
+        client: Arc<ContainerClient>,
+        partition: &str,
+        blob: &str,
+        etag: &str,
+        count: usize,
+    ) -> crate::Result<()> {
+        let mut entry = serde_json::to_vec(&serde_json::json!({
+            "blob": blob,
+            "etag": etag,
+            "count": count,
+            "sealed_at": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        }))?;
+        entry.push(b'\n');
+        let entry = Bytes::from(entry);
+
+        let client = client.as_blob_client(self.index(partition));
+        match client.append_block(entry.clone()).execute().await {
+            Err(reason) if is_blob_not_found(reason.as_ref()) => {
+                {
+                    let _creating = self.creating.lock().await;
+                    // Another entry may have created the index while this one was waiting.
+                    if !blob_exists(&client).await? {
+                        client
+                            .put_append_blob()
+                            .content_type("application/x-ndjson")
+                            .execute()
+                            .await?;
+                    }
+                }
+                client.append_block(entry).execute().await?;
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// Deletes or marks the data blob whose index entry failed to be written.
+    ///
+    /// The blob is marked along with the metadata it was written with, which setting metadata
+    /// replaces.
+    pub async fn orphan(
+        &self,
+        client: &BlobClient,
+        blob: &str,
+        metadata: Option<Metadata>,
+        error: String,
+    ) {
+        let result = match self.orphans {
+            OrphanHandling::Delete => client.delete().execute().await.map(|_| ()),
+            OrphanHandling::Mark => {
+                let mut metadata = metadata.unwrap_or_else(Metadata::new);
+                metadata.insert(ORPHANED_METADATA, "true".to_owned());
+                client
+                    .set_metadata()
+                    .metadata(&metadata)
+                    .execute()
+                    .await
+                    .map(|_| ())
+            }
+        };
+        emit!(AzureBlobOrphanedBlob {
+            blob,
+            handling: self.orphans.as_str(),
+            error,
+            handling_error: result.err().map(|error| error.to_string()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::Service;
+    use vector_core::stream::DriverResponse;
+    use wiremock::{
+        matchers::{method, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::event::{BatchNotifier, BatchStatus, EventStatus, Finalizable, LogEvent};
+    use crate::sinks::azure_common::{
+        service::AzureBlobService,
+        test_util::{
+            counter_value, mock_append_response, mock_client, mock_not_found_response,
+            mock_put_response, mock_request, MOCK_ETAG,
+        },
+    };
+
+    #[tokio::test]
+    async fn failed_index_write_orphans_sealed_blob() {
+        crate::test_util::components::init_test();
+        let send = |orphans: OrphanHandling| async move {
+            let server = MockServer::start().await;
+            // The index can't be appended to, while the data blob is written.
+            Mock::given(method("PUT"))
+                .and(query_param("comp", "appendblock"))
+                .respond_with(ResponseTemplate::new(403))
+                .mount(&server)
+                .await;
+            Mock::given(method("PUT"))
+                .and(query_param("comp", "metadata"))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .insert_header("etag", MOCK_ETAG)
+                        .insert_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+                        .insert_header("date", "Thu, 01 Sep 2022 00:00:00 GMT"),
+                )
+                .mount(&server)
+                .await;
+            Mock::given(method("PUT"))
+                .respond_with(mock_put_response())
+                .mount(&server)
+                .await;
+            Mock::given(method("DELETE"))
+                .respond_with(
+                    ResponseTemplate::new(202)
+                        .insert_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+                        .insert_header("date", "Thu, 01 Sep 2022 00:00:00 GMT")
+                        .insert_header("x-ms-delete-type-permanent", "true"),
+                )
+                .mount(&server)
+                .await;
+            let sealed_batches = SealedBatchConfig {
+                index_name: "_index.jsonl".into(),
+                orphans,
+            }
+            .build()
+            .unwrap();
+
+            let (batch, receiver) = BatchNotifier::new_with_receiver();
+            let mut request = mock_request();
+            request.metadata.finalizers = LogEvent::from("test message")
+                .with_batch_notifier(&batch)
+                .take_finalizers();
+            let finalizers = request.metadata.finalizers.clone();
+            let result = AzureBlobService::new(mock_client(&server).await)
+                .with_sealed_batches(Some(sealed_batches))
+                .call(request)
+                .await;
+            let error = result.as_ref().unwrap_err().to_string();
+            assert!(error.contains("blob/_index.jsonl"), "{}", error);
+            match result {
+                Ok(response) => finalizers.update_status(response.event_status()),
+                Err(_) => finalizers.update_status(EventStatus::Rejected),
+            }
+            drop(finalizers);
+            drop(batch);
+            assert_eq!(receiver.await, BatchStatus::Rejected);
+
+            server
+                .received_requests()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|request| {
+                    let comp = request
+                        .url
+                        .query_pairs()
+                        .find(|(name, _)| name == "comp")
+                        .map(|(_, value)| value.into_owned());
+                    let orphaned = request
+                        .headers
+                        .iter()
+                        .find(|(name, _)| name.as_str() == "x-ms-meta-orphaned")
+                        .map(|(_, values)| values.last().as_str().to_owned());
+                    (
+                        request.method.to_string(),
+                        request.url.path().to_owned(),
+                        comp,
+                        orphaned,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let orphaned = |handling: &str| {
+            counter_value("azure_blob_orphaned_blobs_total", &[("handling", handling)])
+        };
+        let request = |method: &str, path: &str, comp: Option<&str>, orphaned: Option<&str>| {
+            (
+                method.to_owned(),
+                format!("/devstoreaccount1/logs/{}", path),
+                comp.map(str::to_owned),
+                orphaned.map(str::to_owned),
+            )
+        };
+
+        // The data blob is deleted once its entry fails to be appended to the index.
+        let deleted = orphaned("delete");
+        assert_eq!(
+            send(OrphanHandling::Delete).await,
+            vec![
+                request("PUT", "blob/test.log", None, None),
+                request("PUT", "blob/_index.jsonl", Some("appendblock"), None),
+                request("DELETE", "blob/test.log", None, None),
+            ]
+        );
+        assert!(orphaned("delete") >= deleted + 1.0);
+
+        // Or kept and marked as orphaned.
+        let marked = orphaned("mark");
+        assert_eq!(
+            send(OrphanHandling::Mark).await,
+            vec![
+                request("PUT", "blob/test.log", None, None),
+                request("PUT", "blob/_index.jsonl", Some("appendblock"), None),
+                request("PUT", "blob/test.log", Some("metadata"), Some("true")),
+            ]
+        );
+        assert!(orphaned("mark") >= marked + 1.0);
+    }
+
+    #[tokio::test]
+    async fn appends_index_entry() {
+        let server = MockServer::start().await;
+        // The index is created by the first entry appended to it.
+        Mock::given(method("PUT"))
+            .and(query_param("comp", "appendblock"))
+            .respond_with(mock_not_found_response("BlobNotFound"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(query_param("comp", "appendblock"))
+            .respond_with(mock_append_response())
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .respond_with(mock_not_found_response("BlobNotFound"))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(mock_put_response())
+            .mount(&server)
+            .await;
+        let sealed_batches = SealedBatchConfig {
+            index_name: "_index.jsonl".into(),
+            orphans: OrphanHandling::Delete,
+        }
+        .build()
+        .unwrap();
+
+        let response = AzureBlobService::new(mock_client(&server).await)
+            .with_sealed_batches(Some(sealed_batches))
+            .call(mock_request())
+            .await
+            .unwrap();
+        assert_eq!(response.event_status(), EventStatus::Delivered);
+
+        let entries = server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|request| {
+                request.url.path() == "/devstoreaccount1/logs/blob/_index.jsonl"
+                    && request
+                        .url
+                        .query_pairs()
+                        .any(|(name, value)| name == "comp" && value == "appendblock")
+            })
+            .map(|request| serde_json::from_slice::<serde_json::Value>(&request.body).unwrap())
+            .collect::<Vec<_>>();
+        // Refused once as the index didn't exist yet, then appended once created.
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1]["blob"], "blob/test.log");
+        assert_eq!(entries[1]["etag"], MOCK_ETAG);
+        assert_eq!(entries[1]["count"], 1);
+
+        assert!(SealedBatchConfig {
+            index_name: "windows/_index.jsonl".into(),
+            orphans: OrphanHandling::Mark,
+        }
+        .build()
+        .is_err());
+    }
+}
@@ -0,0 +1,480 @@
+use std::{
+    num::NonZeroU32,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use azure_core::HttpError;
+use azure_storage_blobs::prelude::*;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::{select, sync::watch, time::interval};
+use tower::{Service, ServiceBuilder, ServiceExt};
+
+use crate::{
+    event::{EventStatus, Finalizable},
+    internal_events::azure_blob::{
+        AzureBlobJanitorError, AzureBlobJanitorSweep, AzureBlobReservedBlobRejected,
+    },
+    sinks::{
+        azure_common::{
+            batch::{BatchDeleteService, DeleteOutcome, MAX_BATCH_SIZE},
+            config::{AzureBlobRequest, AzureBlobRetryLogic},
+        },
+        util::{retries::RetryLogic, ServiceBuilderExt, TowerRequestSettings},
+    },
+};
+
+/// The prefix under which Vector keeps the objects it creates for its own bookkeeping.
+///
+/// The janitor refuses to touch anything outside of this prefix, so user data written by the sink
+/// can never be deleted by it.
+pub const RESERVED_PREFIX: &str = "_vector/";
+
+const LIST_PAGE_SIZE: u32 = 1000;
+
+/// The shortest `max_age_secs` accepted, so that the objects still being written or read back
+/// aren't pruned from under the sink.
+pub const MIN_MAX_AGE_SECS: u64 = 60;
+
+const fn default_interval_secs() -> u64 {
+    3600
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct JanitorConfig {
+    /// Objects older than this are deleted.
+    pub max_age_secs: u64,
+    /// How often the janitor lists the reserved prefixes.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Sub-prefixes of `_vector/` to prune. All of `_vector/` is pruned when empty.
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    /// Deletes the expired objects through the Blob Batch API, up to 256 of them per request.
+    #[serde(default)]
+    pub batch_deletes: bool,
+}
+
+impl JanitorConfig {
+    pub fn build(
+        &self,
+        client: Arc<ContainerClient>,
+        request: TowerRequestSettings,
+    ) -> crate::Result<Janitor> {
+        if self.interval_secs == 0 {
+            return Err("`janitor.interval_secs` must be greater than zero".into());
+        }
+        if self.max_age_secs < MIN_MAX_AGE_SECS {
+            return Err(format!(
+                "`janitor.max_age_secs` must be at least {}",
+                MIN_MAX_AGE_SECS
+            )
+            .into());
+        }
+        let max_age = i64::try_from(self.max_age_secs)
+            .ok()
+            .filter(|secs| *secs <= chrono::Duration::max_value().num_seconds())
+            .map(chrono::Duration::seconds)
+            .ok_or_else(|| {
+                format!(
+                    "`janitor.max_age_secs` can't be more than {}",
+                    chrono::Duration::max_value().num_seconds()
+                )
+            })?;
+
+        Ok(Janitor {
+            client,
+            prefixes: reserved_prefixes(&self.prefixes)?,
+            max_age,
+            interval: Duration::from_secs(self.interval_secs),
+            batch_deletes: self.batch_deletes,
+            request,
+        })
+    }
+}
+
+/// Resolves the configured sub-prefixes into full prefixes rooted at [`RESERVED_PREFIX`].
+pub(crate) fn reserved_prefixes(prefixes: &[String]) -> crate::Result<Vec<String>> {
+    if prefixes.is_empty() {
+        return Ok(vec![RESERVED_PREFIX.to_string()]);
+    }
+
+    prefixes
+        .iter()
+        .map(|prefix| {
+            let trimmed = prefix.trim_matches('/');
+            if trimmed.is_empty() || trimmed.split('/').any(|segment| segment == "..") {
+                return Err(format!("Invalid janitor prefix {:?}", prefix).into());
+            }
+            Ok(format!("{}{}/", RESERVED_PREFIX, trimmed))
+        })
+        .collect()
+}
+
+/// Selects the blobs that are both under one of the reserved `prefixes` and older than `max_age`.
+pub(crate) fn expired_blobs(
+    blobs: impl IntoIterator<Item = (String, DateTime<Utc>)>,
+    prefixes: &[String],
+    now: DateTime<Utc>,
+    max_age: chrono::Duration,
+) -> Vec<String> {
+    blobs
+        .into_iter()
+        .filter(|(name, last_modified)| {
+            name.starts_with(RESERVED_PREFIX)
+                && prefixes.iter().any(|prefix| name.starts_with(prefix))
+                && now.signed_duration_since(*last_modified) > max_age
+        })
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Rejects the events of the requests whose blob would land under [`RESERVED_PREFIX`] once its
+/// name is final, whatever it was rendered from, so that the janitor never prunes user data.
+#[derive(Clone, Debug)]
+pub struct ReservedPrefixGuard {
+    /// The prefixes the blobs are written under in the container of the janitor, prepended to
+    /// their names by the fan-out.
+    blob_prefixes: Vec<String>,
+}
+
+impl ReservedPrefixGuard {
+    pub fn new(blob_prefixes: Vec<String>) -> Self {
+        Self { blob_prefixes }
+    }
+
+    /// Hands the request back unless one of the blobs it writes is reserved, in which case its
+    /// events are rejected.
+    pub fn check(&self, mut request: AzureBlobRequest) -> Option<AzureBlobRequest> {
+        let reserved = self
+            .blob_prefixes
+            .iter()
+            .map(|prefix| format!("{}{}", prefix, request.metadata.partition_key))
+            .find(|blob| blob.starts_with(RESERVED_PREFIX));
+        match reserved {
+            None => Some(request),
+            Some(blob) => {
+                emit!(AzureBlobReservedBlobRejected {
+                    blob: blob.as_str(),
+                    count: request.metadata.count,
+                });
+                request
+                    .take_finalizers()
+                    .update_status(EventStatus::Rejected);
+                std::mem::take(&mut request.batched_finalizers)
+                    .update_status(EventStatus::Rejected);
+                None
+            }
+        }
+    }
+}
+
+/// Periodically deletes old objects that Vector created under [`RESERVED_PREFIX`].
+pub struct Janitor {
+    client: Arc<ContainerClient>,
+    prefixes: Vec<String>,
+    max_age: chrono::Duration,
+    interval: Duration,
+    batch_deletes: bool,
+    request: TowerRequestSettings,
+}
+
+impl Janitor {
+    pub async fn run(self, mut shutdown: watch::Receiver<()>) {
+        let mut sweep_interval = interval(self.interval);
+        loop {
+            select! {
+                _ = sweep_interval.tick() => self.sweep().await,
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+
+    pub(crate) async fn sweep(&self) {
+        for prefix in &self.prefixes {
+            let blobs = match self.list_blobs(prefix).await {
+                Ok(blobs) => blobs,
+                Err(error) => {
+                    emit!(AzureBlobJanitorError {
+                        path: prefix.clone(),
+                        error: error.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let expired = expired_blobs(blobs, &self.prefixes, Utc::now(), self.max_age);
+            let deleted = if self.batch_deletes {
+                self.delete_batched(expired).await
+            } else {
+                self.delete_one_by_one(expired).await
+            };
+
+            emit!(AzureBlobJanitorSweep {
+                prefix: prefix.as_str(),
+                deleted,
+            });
+        }
+    }
+
+    async fn delete_one_by_one(&self, blobs: Vec<String>) -> usize {
+        let mut service = ServiceBuilder::new()
+            .settings(self.request.clone(), JanitorRetryLogic)
+            .service(DeleteBlobService {
+                client: Arc::clone(&self.client),
+            });
+
+        let mut deleted = 0;
+        for blob in blobs {
+            let result = match service.ready().await {
+                Ok(service) => service.call(blob.clone()).await,
+                Err(error) => Err(error),
+            };
+            match result {
+                Ok(()) => deleted += 1,
+                Err(error) => emit!(AzureBlobJanitorError {
+                    path: blob,
+                    error: error.to_string(),
+                }),
+            }
+        }
+        deleted
+    }
+
+    /// Deletes the blobs in batches, only retrying the batches that failed as a whole.
+    ///
+    /// Blobs whose own delete failed are left for the next sweep, which finds them expired again.
+    async fn delete_batched(&self, blobs: Vec<String>) -> usize {
+        let mut service = ServiceBuilder::new()
+            .settings(self.request.clone(), BatchJanitorRetryLogic)
+            .service(BatchDeleteService {
+                client: Arc::clone(&self.client),
+            });
+
+        let mut deleted = 0;
+        for batch in blobs.chunks(MAX_BATCH_SIZE) {
+            let result = match service.ready().await {
+                Ok(service) => service.call(batch.to_vec()).await,
+                Err(error) => Err(error),
+            };
+            let outcomes = match result {
+                Ok(outcomes) => outcomes,
+                Err(error) => {
+                    for blob in batch {
+                        emit!(AzureBlobJanitorError {
+                            path: blob.clone(),
+                            error: error.to_string(),
+                        });
+                    }
+                    continue;
+                }
+            };
+            for (blob, outcome) in outcomes {
+                match outcome {
+                    DeleteOutcome::Deleted => deleted += 1,
+                    DeleteOutcome::NotFound => {}
+                    DeleteOutcome::Failed(error) => {
+                        emit!(AzureBlobJanitorError { path: blob, error })
+                    }
+                }
+            }
+        }
+        deleted
+    }
+
+    async fn list_blobs(&self, prefix: &str) -> crate::Result<Vec<(String, DateTime<Utc>)>> {
+        let mut blobs = Vec::new();
+        let mut next_marker = None;
+        loop {
+            let request = self
+                .client
+                .list_blobs()
+                .prefix(prefix)
+                .max_results(NonZeroU32::new(LIST_PAGE_SIZE).expect("non-zero page size"));
+            let request = match next_marker.take() {
+                Some(marker) => request.next_marker(marker),
+                None => request,
+            };
+            let response = request.execute().await?;
+
+            blobs.extend(
+                response
+                    .blobs
+                    .blobs
+                    .into_iter()
+                    .map(|blob| (blob.name, blob.properties.last_modified)),
+            );
+
+            match response.next_marker {
+                Some(marker) => next_marker = Some(marker),
+                None => break,
+            }
+        }
+        Ok(blobs)
+    }
+}
+
+struct DeleteBlobService {
+    client: Arc<ContainerClient>,
+}
+
+impl Service<String> for DeleteBlobService {
+    type Response = ();
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, blob: String) -> Self::Future {
+        let client = Arc::clone(&self.client).as_blob_client(blob.as_str());
+        Box::pin(async move { client.delete().execute().await.map(|_| ()) })
+    }
+}
+
+/// Deletes are retried under the same conditions as uploads.
+#[derive(Debug, Clone)]
+struct JanitorRetryLogic;
+
+impl RetryLogic for JanitorRetryLogic {
+    type Error = HttpError;
+    type Response = ();
+
+    fn is_retriable_error(&self, error: &Self::Error) -> bool {
+        AzureBlobRetryLogic.is_retriable_error(error)
+    }
+}
+
+/// Batches are retried as a whole under the same conditions as uploads.
+#[derive(Debug, Clone)]
+struct BatchJanitorRetryLogic;
+
+impl RetryLogic for BatchJanitorRetryLogic {
+    type Error = HttpError;
+    type Response = Vec<(String, DeleteOutcome)>;
+
+    fn is_retriable_error(&self, error: &Self::Error) -> bool {
+        AzureBlobRetryLogic.is_retriable_error(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone as _};
+    use wiremock::MockServer;
+
+    use super::*;
+    use crate::{
+        event::{BatchNotifier, BatchStatus, LogEvent},
+        sinks::{
+            azure_common::test_util::{counter_value, mock_client, mock_request},
+            util::TowerRequestConfig,
+        },
+    };
+
+    #[test]
+    fn only_deletes_expired_reserved_objects() {
+        let now = Utc.ymd(2022, 6, 1).and_hms(12, 0, 0);
+        let old = now - Duration::days(30);
+        let recent = now - Duration::minutes(5);
+        let prefixes = reserved_prefixes(&["probe".into()]).unwrap();
+
+        let expired = expired_blobs(
+            vec![
+                ("_vector/probe/old".to_string(), old),
+                ("_vector/probe/recent".to_string(), recent),
+                ("_vector/manifest/old".to_string(), old),
+                ("blob/_vector/probe/old".to_string(), old),
+                ("blob/2022-05-01/old.log".to_string(), old),
+            ],
+            &prefixes,
+            now,
+            Duration::days(7),
+        );
+
+        assert_eq!(expired, vec!["_vector/probe/old".to_string()]);
+    }
+
+    #[test]
+    fn rejects_prefixes_escaping_reserved_root() {
+        assert_eq!(
+            reserved_prefixes(&[]).unwrap(),
+            vec!["_vector/".to_string()]
+        );
+        assert_eq!(
+            reserved_prefixes(&["/dead_letter/".into()]).unwrap(),
+            vec!["_vector/dead_letter/".to_string()]
+        );
+        assert!(reserved_prefixes(&["".into()]).is_err());
+        assert!(reserved_prefixes(&["../blob".into()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn validates_max_age() {
+        let server = MockServer::start().await;
+        let client = mock_client(&server).await;
+        let build = |max_age_secs| {
+            JanitorConfig {
+                max_age_secs,
+                interval_secs: default_interval_secs(),
+                prefixes: Vec::new(),
+                batch_deletes: false,
+            }
+            .build(
+                Arc::clone(&client),
+                TowerRequestConfig::default().unwrap_with(&TowerRequestConfig::default()),
+            )
+        };
+
+        assert!(build(0).is_err());
+        assert!(build(MIN_MAX_AGE_SECS - 1).is_err());
+        assert!(build(MIN_MAX_AGE_SECS).is_ok());
+        // Would overflow `chrono::Duration` instead of being rejected.
+        assert!(build(u64::MAX).is_err());
+        assert!(build(i64::MAX as u64).is_err());
+    }
+
+    #[tokio::test]
+    async fn guard_rejects_blobs_rendered_under_reserved_prefix() {
+        crate::test_util::components::init_test();
+        let guard = ReservedPrefixGuard::new(vec![String::new(), "_vec".into()]);
+        let check = |partition_key: &str| {
+            let (batch, receiver) = BatchNotifier::new_with_receiver();
+            let mut request = mock_request();
+            request.metadata.partition_key = partition_key.into();
+            request.metadata.finalizers = LogEvent::from("test message")
+                .with_batch_notifier(&batch)
+                .take_finalizers();
+            drop(batch);
+            (guard.check(request), receiver)
+        };
+
+        let (request, receiver) = check("blob/_vector/test.log");
+        let request = request.expect("blob outside of the reserved prefix");
+        request
+            .metadata
+            .finalizers
+            .update_status(EventStatus::Delivered);
+        drop(request);
+        assert_eq!(receiver.await, BatchStatus::Delivered);
+
+        // Directly under the reserved prefix, or through the prefix of a fan-out target.
+        for partition_key in ["_vector/test.log", "tor/test.log"] {
+            let (request, receiver) = check(partition_key);
+            assert!(request.is_none(), "{}", partition_key);
+            assert_eq!(receiver.await, BatchStatus::Rejected);
+        }
+        assert_eq!(
+            counter_value(
+                "component_errors_total",
+                &[("error_code", "reserved_prefix")]
+            ),
+            2.0
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use azure_storage_blobs::prelude::*;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::{select, sync::watch, time::interval};
+
+use crate::{
+    internal_events::azure_blob::{
+        AzureBlobSuccessMarkerError, AzureBlobSuccessMarkerSkipped, AzureBlobSuccessMarkerWritten,
+    },
+    sinks::azure_common::service::blob_exists,
+};
+
+/// Longest time between two checks for closed partitions.
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+fn default_name() -> String {
+    "_SUCCESS".to_string()
+}
+
+const fn default_close_after_secs() -> u64 {
+    300
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SuccessMarkerConfig {
+    /// The name of the marker written into the prefix of each closed partition.
+    #[serde(default = "default_name")]
+    pub name: String,
+    /// How long after its last blob was written a partition is considered closed.
+    #[serde(default = "default_close_after_secs")]
+    pub close_after_secs: u64,
+}
+
+impl SuccessMarkerConfig {
+    pub fn build(&self, client: Arc<ContainerClient>) -> crate::Result<Arc<SuccessMarkers>> {
+        if self.name.is_empty() || self.name.contains('/') {
+            return Err("`success_marker.name` must be a non-empty name without `/`".into());
+        }
+        if self.close_after_secs == 0 {
+            return Err("`success_marker.close_after_secs` must be greater than zero".into());
+        }
+
+        Ok(Arc::new(SuccessMarkers {
+            client,
+            name: self.name.clone(),
+            close_after: Duration::from_secs(self.close_after_secs),
+            partitions: Mutex::new(HashMap::new()),
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct PartitionWrites {
+    in_flight: usize,
+    last_written: Instant,
+    /// Whether the last attempt done with failed, in which case the partition isn't complete.
+    failed: bool,
+}
+
+/// Writes an empty marker into the prefix of every partition once its time window is closed,
+/// Hadoop-style, so that downstream jobs know all of its blobs are in place.
+///
+/// A partition is closed once `close_after` has passed since its last blob was written, with no
+/// request of it in flight. Only the partitions written to since the sink started are tracked, so a
+/// restart never shortens the wait, and the marker isn't written again when it already exists.
+pub struct SuccessMarkers {
+    client: Arc<ContainerClient>,
+    name: String,
+    close_after: Duration,
+    partitions: Mutex<HashMap<String, PartitionWrites>>,
+}
+
+impl SuccessMarkers {
+    /// Records an attempt at writing a blob of the partition.
+    pub fn started(&self, partition: &str) {
+        let mut partitions = self.partitions.lock().expect("marker lock poisoned");
+        partitions
+            .entry(partition.to_string())
+            .or_insert_with(|| PartitionWrites {
+                in_flight: 0,
+                last_written: Instant::now(),
+                failed: false,
+            })
+            .in_flight += 1;
+    }
+
+    /// Records the end of an attempt at writing a blob of the partition.
+    pub fn finished(&self, partition: &str, written: bool) {
+        let mut partitions = self.partitions.lock().expect("marker lock poisoned");
+        if let Some(writes) = partitions.get_mut(partition) {
+            writes.in_flight = writes.in_flight.saturating_sub(1);
+            writes.last_written = Instant::now();
+            writes.failed = !written;
+        }
+    }
+
+    /// The name of the marker of the partition.
+    pub fn marker(&self, partition: &str) -> String {
+        let separator = if partition.is_empty() || partition.ends_with('/') {
+            ""
+        } else {
+            "/"
+        };
+        format!("{}{}{}", partition, separator, self.name)
+    }
+
+    /// Returns the partitions closed by `now`, forgetting the ones that failed to be written.
+    pub(crate) fn closed(&self, now: Instant) -> Vec<String> {
+        let mut partitions = self.partitions.lock().expect("marker lock poisoned");
+        let mut closed = Vec::new();
+        partitions.retain(|partition, writes| {
+            if writes.in_flight > 0
+                || now.saturating_duration_since(writes.last_written) < self.close_after
+            {
+                return true;
+            }
+            if writes.failed {
+                emit!(AzureBlobSuccessMarkerSkipped {
+                    partition: partition.as_str(),
+                });
+                return false;
+            }
+            closed.push(partition.clone());
+            true
+        });
+        closed
+    }
+
+    /// Writes the markers of the partitions closed by `now`.
+    ///
+    /// Partitions whose marker fails to be written are tried again on the next sweep.
+    pub(crate) async fn sweep(&self, now: Instant) {
+        for partition in self.closed(now) {
+            let marker = self.marker(&partition);
+            match self.write(&marker).await {
+                Ok(()) => {
+                    let mut partitions = self.partitions.lock().expect("marker lock poisoned");
+                    // Blobs written to the partition in the meantime reopen it.
+                    if partitions.get(&partition).map_or(false, |writes| {
+                        writes.in_flight == 0 && writes.last_written <= now
+                    }) {
+                        partitions.remove(&partition);
+                    }
+                }
+                Err(error) => emit!(AzureBlobSuccessMarkerError {
+                    marker: marker.as_str(),
+                    error: error.to_string(),
+                }),
+            }
+        }
+    }
+
+    async fn write(&self, marker: &str) -> crate::Result<()> {
+        let client = Arc::clone(&self.client).as_blob_client(marker);
+        // Written before the sink restarted.
+        if blob_exists(&client).await? {
+            return Ok(());
+        }
+        client
+            .put_block_blob(Bytes::new())
+            .content_type("application/octet-stream")
+            .execute()
+            .await?;
+        emit!(AzureBlobSuccessMarkerWritten { marker });
+        Ok(())
+    }
+
+    /// Checks for closed partitions until `shutdown` fires.
+    ///
+    /// The partitions still open then are left without a marker, as their window may not be over.
+    pub async fn run(self: Arc<Self>, mut shutdown: watch::Receiver<()>) {
+        let mut check_interval = interval(self.close_after.min(MAX_CHECK_INTERVAL));
+        loop {
+            select! {
+                _ = check_interval.tick() => self.sweep(Instant::now()).await,
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::Service;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::sinks::azure_common::{
+        service::AzureBlobService,
+        test_util::{mock_client, mock_properties_response, mock_put_response, mock_request},
+    };
+
+    #[tokio::test]
+    async fn writes_one_marker_per_closed_partition() {
+        let marker_path = "/devstoreaccount1/logs/blob/_SUCCESS";
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path(marker_path))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(mock_put_response())
+            .mount(&server)
+            .await;
+        let client = mock_client(&server).await;
+        let marker_config = SuccessMarkerConfig {
+            name: "_SUCCESS".into(),
+            close_after_secs: 60,
+        };
+        let markers = marker_config.build(Arc::clone(&client)).unwrap();
+        let marker_puts = || async {
+            server
+                .received_requests()
+                .await
+                .unwrap()
+                .into_iter()
+                .filter(|request| {
+                    request.method == wiremock::http::Method::Put
+                        && request.url.path() == marker_path
+                })
+                .count()
+        };
+
+        let mut service = AzureBlobService::new(Arc::clone(&client))
+            .with_success_markers(Some(Arc::clone(&markers)));
+        service.call(mock_request()).await.unwrap();
+        service.call(mock_request()).await.unwrap();
+
+        // The window of the partition isn't closed yet.
+        markers.sweep(Instant::now()).await;
+        assert_eq!(marker_puts().await, 0);
+
+        let closed = Instant::now() + Duration::from_secs(60);
+        markers.sweep(closed).await;
+        markers.sweep(closed).await;
+        assert_eq!(marker_puts().await, 1);
+        assert_eq!(markers.marker("blob"), "blob/_SUCCESS");
+
+        // After a restart, the marker already written isn't written again.
+        server.reset().await;
+        Mock::given(method("HEAD"))
+            .and(path(marker_path))
+            .respond_with(mock_properties_response("0"))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(mock_put_response())
+            .mount(&server)
+            .await;
+        let markers = marker_config.build(Arc::clone(&client)).unwrap();
+        let mut service =
+            AzureBlobService::new(client).with_success_markers(Some(Arc::clone(&markers)));
+        service.call(mock_request()).await.unwrap();
+        markers.sweep(closed + Duration::from_secs(60)).await;
+        assert_eq!(marker_puts().await, 0);
+
+        assert!(SuccessMarkerConfig {
+            name: "a/_SUCCESS".into(),
+            close_after_secs: 60,
+        }
+        .build(mock_client(&server).await)
+        .is_err());
+    }
+}
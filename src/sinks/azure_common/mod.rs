@@ -1,3 +1,36 @@
+pub(crate) mod acknowledgements;
+pub(crate) mod batch;
+pub(crate) mod blob_path;
+pub(crate) mod blob_type;
+pub(crate) mod broker;
+pub(crate) mod bucket;
+pub(crate) mod cancellation;
+pub(crate) mod coalesce;
+pub(crate) mod computed_metadata;
+pub(crate) mod concurrency;
 pub(crate) mod config;
+pub(crate) mod container;
+pub(crate) mod content_language;
+pub(crate) mod encoding;
+pub(crate) mod endpoint;
+pub(crate) mod fan_out;
+pub(crate) mod fingerprint;
+pub(crate) mod flush;
+pub(crate) mod imds;
+pub(crate) mod in_flight;
+pub(crate) mod index;
+pub(crate) mod janitor;
+pub(crate) mod marker;
+pub(crate) mod precompressed;
+pub(crate) mod receipt;
+pub(crate) mod retry_after;
+pub(crate) mod schema;
+pub(crate) mod schema_version;
 pub(crate) mod service;
+pub(crate) mod shedding;
 pub(crate) mod sink;
+pub(crate) mod slow_start;
+pub(crate) mod soft_delete;
+pub(crate) mod summary;
+#[cfg(test)]
+pub(crate) mod test_util;
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::log_schema,
+    event::{Event, Value},
+};
+
+/// The leading bytes of the formats detected, along with their content encoding.
+const MAGIC_BYTES: [(&[u8], &str); 2] =
+    [(&[0x1f, 0x8b], "gzip"), (&[0x28, 0xb5, 0x2f, 0xfd], "zstd")];
+
+/// The content encodings the marker field can name, along with the extension of their blobs.
+const CONTENT_ENCODINGS: [(&str, &str); 5] = [
+    ("br", "br"),
+    ("compress", "Z"),
+    ("deflate", "zz"),
+    ("gzip", "gz"),
+    ("zstd", "zst"),
+];
+
+fn default_field() -> String {
+    log_schema().message_key().to_owned()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PrecompressedConfig {
+    /// The field of each event holding the payload checked for the magic bytes of a compression
+    /// format.
+    #[serde(default = "default_field")]
+    pub field: String,
+    /// Whether payloads starting with the magic bytes of gzip or zstd are taken as already
+    /// compressed.
+    #[serde(default = "crate::serde::default_true")]
+    pub detect_magic_bytes: bool,
+    /// The field of each event holding the content encoding its payload is already compressed
+    /// with, if any: one of `br`, `compress`, `deflate`, `gzip` or `zstd`.
+    pub marker_field: Option<String>,
+}
+
+impl PrecompressedConfig {
+    pub fn build(&self) -> crate::Result<Precompressed> {
+        if !self.detect_magic_bytes && self.marker_field.is_none() {
+            return Err("`precompressed` needs either `detect_magic_bytes` or \
+                        `marker_field` to find the compressed payloads"
+                .into());
+        }
+        Ok(Precompressed {
+            field: self.field.clone(),
+            detect_magic_bytes: self.detect_magic_bytes,
+            marker_field: self.marker_field.clone(),
+        })
+    }
+}
+
+/// Finds the events whose payload is already compressed, which are written without being
+/// compressed again.
+#[derive(Clone, Debug)]
+pub struct Precompressed {
+    field: String,
+    detect_magic_bytes: bool,
+    marker_field: Option<String>,
+}
+
+impl Precompressed {
+    /// Returns the content encoding the payload of the event is already compressed with, or
+    /// `None` if it's to be compressed by the sink.
+    ///
+    /// The marker field takes precedence over the magic bytes. Markers naming any other content
+    /// encoding are ignored.
+    pub fn content_encoding(&self, event: &Event) -> Option<&'static str> {
+        let log = event.maybe_as_log()?;
+        let marked = self
+            .marker_field
+            .as_deref()
+            .and_then(|field| log.get(field))
+            .and_then(|marker| {
+                let marker = marker.to_string_lossy();
+                CONTENT_ENCODINGS
+                    .iter()
+                    .find(|(encoding, _)| marker.eq_ignore_ascii_case(encoding))
+                    .map(|(encoding, _)| *encoding)
+            });
+        if marked.is_some() {
+            return marked;
+        }
+
+        match log.get(self.field.as_str()) {
+            Some(Value::Bytes(payload)) if self.detect_magic_bytes => MAGIC_BYTES
+                .iter()
+                .find(|(magic, _)| payload.starts_with(magic))
+                .map(|(_, encoding)| *encoding),
+            _ => None,
+        }
+    }
+
+    /// Splits the batch of a partition into a batch of the events to compress and a batch for
+    /// each content encoding the other events are already compressed with, so that no blob mixes
+    /// them. The events keep their order within each batch.
+    pub fn split(&self, key: String, events: Vec<Event>) -> Vec<(String, Vec<Event>)> {
+        let mut batches: Vec<(Option<&'static str>, Vec<Event>)> = Vec::new();
+        for event in events {
+            let encoding = self.content_encoding(&event);
+            match batches.iter_mut().find(|(seen, _)| *seen == encoding) {
+                Some((_, batch)) => batch.push(event),
+                None => batches.push((encoding, vec![event])),
+            }
+        }
+        // The sort is stable, so the encodings keep the order they were first seen in.
+        batches.sort_by_key(|(encoding, _)| encoding.is_some());
+        batches
+            .into_iter()
+            .map(|(_, events)| (key.clone(), events))
+            .collect()
+    }
+}
+
+/// Returns the extension of the blobs already compressed with `content_encoding`.
+pub fn extension(content_encoding: &str) -> &'static str {
+    CONTENT_ENCODINGS
+        .iter()
+        .find(|(encoding, _)| *encoding == content_encoding)
+        .map_or("bin", |(_, extension)| *extension)
+}
@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+
+/// The version of the fields of the receipts, bumped whenever they change in a way that could
+/// break the consumers of the receipts.
+pub const RECEIPT_VERSION: u32 = 1;
+
+/// Describes a blob once it has been written, for downstream processing to tell exactly which
+/// events were stored where.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WriteReceipt {
+    pub container: String,
+    pub blob: String,
+    /// The ETag the storage account gave the written blob.
+    pub etag: String,
+    /// The hex MD5 digest of the payload of the blob.
+    pub content_md5: String,
+    pub count: usize,
+    pub byte_size: usize,
+    /// The oldest and newest event timestamps of the blob, if its events have any.
+    pub event_time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+/// Where the events of a batch landed, announced before they are acknowledged so that the
+/// consumers of the acknowledgements can tell where each batch was stored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlobProvenance {
+    pub container: String,
+    pub blob: String,
+    /// The ETag the storage account gave the written blob.
+    pub etag: String,
+    /// When the write of the blob completed.
+    pub written_at: DateTime<Utc>,
+}
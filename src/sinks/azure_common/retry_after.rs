@@ -0,0 +1,174 @@
+use std::{cell::Cell, future::Future, time::Duration};
+
+use azure_core::{HttpClient, HttpError};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http::{header::RETRY_AFTER, HeaderMap, Request, Response, StatusCode};
+
+tokio::task_local! {
+    /// The delay asked for by the last throttled response to the requests being captured.
+    static CAPTURED: Cell<Option<Duration>>;
+}
+
+/// Runs `future`, returning along with its output the delay the last throttled response it
+/// received asked for through its `Retry-After` header.
+///
+/// The errors of the SDK only carry the status and body of the responses, so the header is picked
+/// up by the HTTP client of the storage account instead.
+pub(crate) async fn capture_retry_after<F: Future>(future: F) -> (F::Output, Option<Duration>) {
+    CAPTURED
+        .scope(Cell::new(None), async move {
+            let output = future.await;
+            (output, CAPTURED.with(Cell::get))
+        })
+        .await
+}
+
+/// The HTTP client of the storage account, recording the `Retry-After` header of the throttled
+/// responses for [`capture_retry_after`].
+#[derive(Debug)]
+pub(crate) struct RetryAfterClient {
+    inner: reqwest::Client,
+}
+
+impl RetryAfterClient {
+    pub(crate) const fn new(inner: reqwest::Client) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpClient for RetryAfterClient {
+    async fn execute_request(&self, request: Request<Bytes>) -> Result<Response<Bytes>, HttpError> {
+        let response = self.inner.execute_request(request).await?;
+        record(response.status(), response.headers());
+        Ok(response)
+    }
+
+    async fn execute_request2(
+        &self,
+        request: &azure_core::Request,
+    ) -> Result<azure_core::Response, HttpError> {
+        let response = self.inner.execute_request2(request).await?;
+        record(response.status(), response.headers());
+        Ok(response)
+    }
+}
+
+fn record(status: StatusCode, headers: &HeaderMap) {
+    if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+        return;
+    }
+    if let Some(retry_after) = headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, Utc::now()))
+    {
+        // Requests made outside of `capture_retry_after`, such as by the healthcheck, aren't
+        // retried by the sink.
+        let _ = CAPTURED.try_with(|captured| captured.set(Some(retry_after)));
+    }
+}
+
+/// Parses a `Retry-After` header, given either as a number of seconds or as an HTTP date.
+pub(crate) fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    // A date already past asks for an immediate retry.
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use chrono::TimeZone as _;
+    use tower::{Service, ServiceBuilder, ServiceExt};
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::event::MetricValue;
+    use crate::sinks::{
+        azure_common::{
+            config::AzureBlobRetryLogic,
+            service::AzureBlobService,
+            test_util::{metric_values, mock_client, mock_put_response, mock_request},
+        },
+        util::{ServiceBuilderExt, TowerRequestConfig},
+    };
+
+    #[tokio::test]
+    async fn records_server_retry_backoff() {
+        crate::test_util::components::init_test();
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "1"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(mock_put_response())
+            .mount(&server)
+            .await;
+
+        // The computed backoff would have been much longer than the one asked for.
+        let request_settings = TowerRequestConfig::default()
+            .retry_initial_backoff_secs(30)
+            .unwrap_with(&TowerRequestConfig::default());
+        let mut service = ServiceBuilder::new()
+            .settings(request_settings, AzureBlobRetryLogic)
+            .service(AzureBlobService::new(mock_client(&server).await));
+
+        let retry_backoffs = || {
+            metric_values("azure_blob_retry_backoff_seconds", &[("source", "server")])
+                .into_iter()
+                .fold((0, 0.0), |(count, sum), value| match value {
+                    MetricValue::AggregatedHistogram {
+                        count: more,
+                        sum: added,
+                        ..
+                    } => (count + more, sum + added),
+                    _ => (count, sum),
+                })
+        };
+        let (count, sum) = retry_backoffs();
+        let started = Instant::now();
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(mock_request())
+            .await
+            .unwrap();
+
+        assert_eq!(response.attempts, 2);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert!(started.elapsed() < Duration::from_secs(30));
+        let (retried_count, retried_sum) = retry_backoffs();
+        assert_eq!(retried_count, count + 1);
+        assert!((retried_sum - sum - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        let now = Utc.ymd(2022, 9, 1).and_hms(0, 0, 0);
+        assert_eq!(parse_retry_after(" 7 ", now), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after("Thu, 01 Sep 2022 00:00:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        // A date already past asks for an immediate retry.
+        assert_eq!(
+            parse_retry_after("Wed, 31 Aug 2022 23:59:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
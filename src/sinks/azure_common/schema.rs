@@ -0,0 +1,64 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use azure_storage_blobs::prelude::*;
+use bytes::Bytes;
+
+use super::service::write_sidecar;
+
+/// The number of sidecars remembered as written, past which they are all forgotten. Forgotten
+/// sidecars are written again, unchanged, along with the next blob next to them.
+const MAX_WRITTEN: usize = 10_000;
+
+/// Writes a sidecar describing the blobs written, such as the header of CSV tables, into every
+/// directory blobs are written to.
+///
+/// Every sidecar holds the same content, so it's written once for each directory, along with the
+/// first blob written to it since the sink started.
+#[derive(Debug)]
+pub struct SchemaSidecar {
+    name: String,
+    content_type: &'static str,
+    content: Bytes,
+    written: Mutex<HashSet<String>>,
+}
+
+impl SchemaSidecar {
+    pub fn new(name: impl Into<String>, content_type: &'static str, content: Bytes) -> Self {
+        Self {
+            name: name.into(),
+            content_type,
+            content,
+            written: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// The name of the sidecar of the directory `blob` is written to.
+    pub fn sidecar(&self, blob: &str) -> String {
+        let directory = blob.rfind('/').map_or("", |index| &blob[..=index]);
+        format!("{}{}", directory, self.name)
+    }
+
+    /// Writes the sidecar of the directory of `blob`, unless it was already written.
+    pub async fn write(&self, client: Arc<ContainerClient>, blob: &str) {
+        let sidecar = self.sidecar(blob);
+        if self
+            .written
+            .lock()
+            .expect("schema lock poisoned")
+            .contains(&sidecar)
+        {
+            return;
+        }
+        // Concurrent writes of the same sidecar are harmless, as they write the same content.
+        if write_sidecar(client, &sidecar, self.content.clone(), self.content_type).await {
+            let mut written = self.written.lock().expect("schema lock poisoned");
+            if written.len() >= MAX_WRITTEN {
+                written.clear();
+            }
+            written.insert(sidecar);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use crate::event::Event;
+
+/// The version the events lacking the field are grouped by.
+pub const MISSING_SCHEMA_VERSION: &str = "none";
+
+fn default_field() -> String {
+    "schema_version".to_owned()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SchemaVersionConfig {
+    /// The field holding the version of the schema of each event.
+    #[serde(default = "default_field")]
+    pub field: String,
+}
+
+impl SchemaVersionConfig {
+    pub fn build(&self) -> crate::Result<SchemaVersions> {
+        if self.field.is_empty() {
+            return Err("`schema_version.field` must not be empty".into());
+        }
+        Ok(SchemaVersions {
+            field: self.field.clone(),
+        })
+    }
+}
+
+/// Groups events by the version of their schema, so that no blob mixes several versions, even
+/// when its events share every other part of their partition.
+#[derive(Clone, Debug)]
+pub struct SchemaVersions {
+    field: String,
+}
+
+impl SchemaVersions {
+    /// Returns the version of the schema of the event, unless it lacks the field.
+    ///
+    /// The field is looked up among the tags of metrics.
+    pub fn version(&self, event: &Event) -> Option<String> {
+        match event {
+            Event::Log(log) => Some(log.get(self.field.as_str())?.to_string_lossy()),
+            Event::Metric(metric) => metric.tag_value(&self.field),
+            Event::Trace(trace) => Some(trace.get(self.field.as_str())?.to_string_lossy()),
+        }
+    }
+}
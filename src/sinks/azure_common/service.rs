@@ -2,28 +2,226 @@ use std::{
     result::Result as StdResult,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
-use azure_core::HttpError;
-use azure_storage_blobs::prelude::*;
+use azure_core::{headers::add_optional_header, prelude::Metadata, HttpClient, HttpError};
+use azure_storage::prelude::*;
+use azure_storage_blobs::{
+    blob::responses::{AppendBlockResponse, PutBlockBlobResponse},
+    prelude::*,
+};
+use bytes::Bytes;
+use chrono::{SecondsFormat, Utc};
 use futures::{future::BoxFuture, TryFutureExt};
+use http::{
+    header::{
+        CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_TYPE, IF_MATCH, IF_NONE_MATCH,
+    },
+    Method, StatusCode,
+};
+use snafu::Snafu;
+use tokio::sync::Mutex;
 use tower::Service;
 use tracing::Instrument;
 
 use crate::{
-    internal_events::azure_blob::{AzureBlobHttpError, AzureBlobResponseError},
-    sinks::azure_common::config::{AzureBlobRequest, AzureBlobResponse},
+    internal_events::azure_blob::{
+        AzureBlobBatchExpired, AzureBlobChecksumMismatch, AzureBlobHttpError,
+        AzureBlobNameCollision, AzureBlobOversizedBatch, AzureBlobPayloadTooLarge,
+        AzureBlobPriorBlobDeleted, AzureBlobRequestBodySent, AzureBlobRequestDelivered,
+        AzureBlobResponseError, AzureBlobSidecarWriteError, AzureBlobWriteReceipt,
+        AzureBlobWriteVerificationError,
+    },
+    sinks::{
+        azure_common::{
+            blob_type::BlobType,
+            config::{
+                AzureBlobMetadata, AzureBlobRequest, AzureBlobResponse, AzureBlobRetryLogic,
+                BlobWrite, ChecksumAlgorithm, OverwritePolicy,
+            },
+            container::{is_container_not_found, ContainerError, ContainerGuard},
+            index::SealedBatches,
+            marker::SuccessMarkers,
+            receipt::{BlobProvenance, WriteReceipt},
+            retry_after::capture_retry_after,
+            schema::SchemaSidecar,
+            soft_delete::delete_permanently,
+            summary::BatchSummary,
+        },
+        util::retries::RetryLogic,
+    },
 };
 use vector_common::internal_event::BytesSent;
 
+/// Number of attempts made at writing a sidecar once its data blob has been written.
+const SIDECAR_ATTEMPTS: usize = 3;
+const SIDECAR_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Names of the blob metadata holding the oldest and newest event timestamps of the blob.
+///
+/// Metadata names have to be valid C# identifiers, so these use underscores rather than dashes.
+pub const EVENT_TIME_MIN_METADATA: &str = "event_time_min";
+pub const EVENT_TIME_MAX_METADATA: &str = "event_time_max";
+/// Name of the blob metadata holding the source types of the events of the blob.
+pub const SOURCE_TYPE_METADATA: &str = "vector_source_type";
+/// Name of the blob metadata holding the ID of the zstd dictionary the blob is compressed with.
+pub const ZSTD_DICTIONARY_METADATA: &str = "zstd_dictionary_id";
+/// Name of the blob metadata holding the fingerprint of the schema of the events of the blob.
+pub const SCHEMA_FINGERPRINT_METADATA: &str = "schema_fingerprint";
+/// Name of the blob metadata holding the version of the schema of the events of the blob.
+pub const SCHEMA_VERSION_METADATA: &str = "schema_version";
+
+#[derive(Debug, Snafu)]
+pub enum AzureBlobServiceError {
+    #[snafu(display("Blob {:?} already exists and overwriting it is refused", blob))]
+    BlobExists { blob: String },
+    #[snafu(display(
+        "Blob {:?} of {} bytes is larger than the storage account accepts; reduce `batch.max_bytes`",
+        blob,
+        byte_size
+    ))]
+    PayloadTooLarge { blob: String, byte_size: usize },
+    #[snafu(display(
+        "Blob {:?} of {} bytes is larger than the {} bytes Azure accepts in a single write to {} \
+         blobs; reduce `batch.max_bytes`",
+        blob,
+        byte_size,
+        limit,
+        blob_type
+    ))]
+    OversizedBatch {
+        blob: String,
+        byte_size: usize,
+        limit: usize,
+        blob_type: &'static str,
+    },
+    #[snafu(display(
+        "Failed to write the entry of blob {:?} to index {:?}: {}",
+        blob,
+        index,
+        error
+    ))]
+    IndexWriteFailed {
+        blob: String,
+        index: String,
+        error: String,
+    },
+}
+
 #[derive(Clone)]
 pub(crate) struct AzureBlobService {
     pub(self) client: Arc<ContainerClient>,
+    pub(self) read_client: Option<Arc<ContainerClient>>,
+    pub(self) checksum_sidecar: Option<ChecksumAlgorithm>,
+    pub(self) summary: Option<Arc<BatchSummary>>,
+    pub(self) verify_write: bool,
+    pub(self) overwrite: OverwritePolicy,
+    pub(self) write_receipts: bool,
+    pub(self) provenance: bool,
+    pub(self) container_guard: Option<Arc<ContainerGuard>>,
+    pub(self) success_markers: Option<Arc<SuccessMarkers>>,
+    pub(self) max_batch_age: Option<chrono::Duration>,
+    /// Serializes the creations of append blobs, so concurrent appends only create them once.
+    pub(self) creating_append_blob: Arc<Mutex<()>>,
+    pub(self) schema_sidecar: Option<Arc<SchemaSidecar>>,
+    pub(self) sealed_batches: Option<Arc<SealedBatches>>,
 }
 
 impl AzureBlobService {
-    pub const fn new(client: Arc<ContainerClient>) -> AzureBlobService {
-        AzureBlobService { client }
+    pub fn new(client: Arc<ContainerClient>) -> AzureBlobService {
+        AzureBlobService {
+            client,
+            read_client: None,
+            checksum_sidecar: None,
+            summary: None,
+            verify_write: false,
+            overwrite: OverwritePolicy::Allow,
+            write_receipts: false,
+            provenance: false,
+            container_guard: None,
+            success_markers: None,
+            max_batch_age: None,
+            creating_append_blob: Arc::new(Mutex::new(())),
+            schema_sidecar: None,
+            sealed_batches: None,
+        }
+    }
+
+    /// Reads blobs back through the given client rather than the one writing them.
+    pub fn with_read_client(mut self, client: Arc<ContainerClient>) -> Self {
+        self.read_client = Some(client);
+        self
+    }
+
+    /// Writes a `<blob>.<algorithm>` sidecar holding the hex digest of every blob written.
+    pub const fn with_checksum_sidecar(mut self, algorithm: Option<ChecksumAlgorithm>) -> Self {
+        self.checksum_sidecar = algorithm;
+        self
+    }
+
+    /// Records every blob written into the given summary.
+    pub fn with_batch_summary(mut self, summary: Option<Arc<BatchSummary>>) -> Self {
+        self.summary = summary;
+        self
+    }
+
+    /// Reads the properties of every blob back once written, and retries the request unless
+    /// they match what was uploaded.
+    pub const fn with_verify_write(mut self, verify_write: bool) -> Self {
+        self.verify_write = verify_write;
+        self
+    }
+
+    /// Sets what happens when a blob is written under the name of an existing blob.
+    pub const fn with_overwrite(mut self, overwrite: OverwritePolicy) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Emits a receipt describing every blob written.
+    pub const fn with_write_receipts(mut self, write_receipts: bool) -> Self {
+        self.write_receipts = write_receipts;
+        self
+    }
+
+    /// Describes where every blob was written in its response, for its events to be annotated
+    /// with before they are acknowledged.
+    pub const fn with_provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Handles the uploads finding the container gone through the given guard.
+    pub fn with_container_guard(mut self, guard: Arc<ContainerGuard>) -> Self {
+        self.container_guard = Some(guard);
+        self
+    }
+
+    /// Tracks the partitions written to, for their success markers to be written once closed.
+    pub fn with_success_markers(mut self, markers: Option<Arc<SuccessMarkers>>) -> Self {
+        self.success_markers = markers;
+        self
+    }
+
+    /// Drops the batches whose oldest event is older than `max_age` by the time they're sent,
+    /// rather than uploading them.
+    pub const fn with_max_batch_age(mut self, max_age: Option<chrono::Duration>) -> Self {
+        self.max_batch_age = max_age;
+        self
+    }
+
+    /// Writes the given sidecar next to the blobs written, describing them.
+    pub fn with_schema_sidecar(mut self, sidecar: Option<Arc<SchemaSidecar>>) -> Self {
+        self.schema_sidecar = sidecar;
+        self
+    }
+
+    /// Appends an entry for every blob written to the index of its partition, failing the
+    /// request when it can't be.
+    pub fn with_sealed_batches(mut self, sealed_batches: Option<Arc<SealedBatches>>) -> Self {
+        self.sealed_batches = sealed_batches;
+        self
     }
 }
 
@@ -39,43 +237,791 @@ impl Service<AzureBlobRequest> for AzureBlobService {
     fn call(&mut self, request: AzureBlobRequest) -> Self::Future {
         let client =
             Arc::clone(&self.client).as_blob_client(request.metadata.partition_key.as_str());
+        let read_client = Arc::clone(self.read_client.as_ref().unwrap_or(&self.client))
+            .as_blob_client(request.metadata.partition_key.as_str());
+        let container_client = Arc::clone(&self.client);
+        let checksum_sidecar = self.checksum_sidecar;
+        let summary = self.summary.clone();
+        let verify_write = self.verify_write;
+        let overwrite = self.overwrite;
+        let write_receipts = self.write_receipts;
+        let provenance = self.provenance;
+        let container_guard = self.container_guard.clone();
+        let success_markers = self.success_markers.clone();
+        let max_batch_age = self.max_batch_age;
+        let creating_append_blob = Arc::clone(&self.creating_append_blob);
+        let schema_sidecar = self.schema_sidecar.clone();
+        let sealed_batches = self.sealed_batches.clone();
+        let attempt = request.attempts.start();
 
         Box::pin(async move {
+            if let Some(guard) = &container_guard {
+                // Every upload would fail the same way until the sink stops.
+                if guard.is_lost() {
+                    return Err(ContainerError::ContainerNotFound {
+                        container: container_client.container_name().to_owned(),
+                    }
+                    .into());
+                }
+            }
+            // Checked on every attempt, so that retries don't upload a batch gone stale meanwhile.
+            if let (Some(max_age), Some(oldest)) =
+                (max_batch_age, request.metadata.oldest_event_time)
+            {
+                let age = Utc::now() - oldest;
+                if age > max_age {
+                    emit!(AzureBlobBatchExpired {
+                        blob: request.metadata.partition_key.as_str(),
+                        count: request.metadata.count,
+                        age_secs: age.num_seconds(),
+                    });
+                    return Ok(AzureBlobResponse {
+                        inner: None,
+                        count: request.metadata.count,
+                        events_byte_size: request.metadata.byte_size,
+                        byte_size: 0,
+                        attempts: attempt,
+                        elapsed: request.attempts.elapsed(),
+                        verify_error: None,
+                        partition: request.metadata.partition,
+                        receipt: None,
+                        provenance: None,
+                        append_sequence: request.metadata.append_sequence,
+                        outcomes: Vec::new(),
+                    });
+                }
+            }
+            // Azure would refuse the write after the whole payload was sent, on every attempt.
+            let blob_type = request.metadata.blob_type;
+            if request.blob_data.len() > blob_type.max_write_bytes() {
+                emit!(AzureBlobOversizedBatch {
+                    blob: request.metadata.partition_key.as_str(),
+                    count: request.metadata.count,
+                    byte_size: request.blob_data.len(),
+                    limit: blob_type.max_write_bytes(),
+                    blob_type: blob_type.as_str(),
+                });
+                return Err(AzureBlobServiceError::OversizedBatch {
+                    blob: request.metadata.partition_key,
+                    byte_size: request.blob_data.len(),
+                    limit: blob_type.max_write_bytes(),
+                    blob_type: blob_type.as_str(),
+                }
+                .into());
+            }
+            let checksums = checksum_sidecar.map(|algorithm| {
+                (
+                    algorithm,
+                    algorithm.hex_digest(&request.blob_data),
+                    ChecksumAlgorithm::Md5.hex_digest(&request.blob_data),
+                )
+            });
+            let content_md5 = write_receipts.then(|| match &checksums {
+                Some((_, _, md5)) => md5.clone(),
+                None => ChecksumAlgorithm::Md5.hex_digest(&request.blob_data),
+            });
+            if overwrite == OverwritePolicy::DeletePermanently
+                && blob_type == BlobType::Block
+                && delete_permanently(&container_client, &request.metadata.partition_key).await?
+            {
+                emit!(AzureBlobPriorBlobDeleted {
+                    blob: request.metadata.partition_key.as_str(),
+                    policy: overwrite.as_str(),
+                });
+            }
+
             let byte_size = request.blob_data.len();
-            let blob = client
-                .put_block_blob(request.blob_data)
-                .content_type(request.content_type);
-            let blob = match request.content_encoding {
-                Some(encoding) => blob.content_encoding(encoding),
-                None => blob,
+            let metadata = blob_metadata(&request.metadata);
+            if let Some(markers) = &success_markers {
+                markers.started(&request.metadata.partition);
+            }
+            // Blobs are only written if absent when overwriting them is refused or skipped, which
+            // the storage account checks atomically with the write.
+            let if_absent = matches!(overwrite, OverwritePolicy::Refuse | OverwritePolicy::Skip);
+            let write = || BlobWriter {
+                client: &client,
+                container_client: &container_client,
+                request: &request,
+                metadata: metadata.as_ref(),
+                creating_append_blob: &creating_append_blob,
+                if_absent,
             };
+            let result = async {
+                let result = write().write().await;
+                match (&container_guard, result) {
+                    (Some(guard), Err(reason)) if is_container_not_found(reason.as_ref()) => {
+                        guard.not_found(&container_client).await?;
+                        write().write().await
+                    }
+                    (Some(guard), Ok(response)) => {
+                        guard.found();
+                        Ok(response)
+                    }
+                    (_, result) => result,
+                }
+            }
+            .inspect_err(|reason| {
+                match reason.downcast_ref::<HttpError>() {
+                    // Reported as a collision rather than an error.
+                    _ if if_absent && is_blob_exists(reason.as_ref()) => (),
+                    Some(HttpError::StatusCode { status, .. })
+                        if *status == StatusCode::PAYLOAD_TOO_LARGE =>
+                    {
+                        emit!(AzureBlobPayloadTooLarge {
+                            blob: request.metadata.partition_key.as_str(),
+                            count: request.metadata.count,
+                            byte_size,
+                        })
+                    }
+                    Some(HttpError::StatusCode { status, .. }) => {
+                        emit!(AzureBlobResponseError::from(*status))
+                    }
+                    _ => emit!(AzureBlobHttpError {
+                        error: reason.to_string()
+                    }),
+                };
+            })
+            .inspect_ok(|_| {
+                emit!(BytesSent {
+                    byte_size,
+                    protocol: "https",
+                });
+            })
+            .instrument(info_span!("request").or_current());
+            let (result, retry_after) = capture_retry_after(result).await;
+            // Throttled uploads are retried after the delay the server asked for.
+            request.attempts.set_retry_after(retry_after);
+            if let Some(markers) = &success_markers {
+                markers.finished(&request.metadata.partition, result.is_ok());
+            }
 
-            let result = blob
-                .execute()
-                .inspect_err(|reason| {
-                    match reason.downcast_ref::<HttpError>() {
-                        Some(HttpError::StatusCode { status, .. }) => {
-                            emit!(AzureBlobResponseError::from(*status))
+            let result = match result {
+                // The blob may be the one written by an earlier attempt, whose response was lost or
+                // which failed its verification, rather than another writer's.
+                Err(reason) if if_absent && attempt > 1 && is_blob_exists(reason.as_ref()) => {
+                    match written_by_earlier_attempt(&read_client, &request).await {
+                        Some(etag) => Ok(BlobWrite::Existing { etag }),
+                        None => Err(reason),
+                    }
+                }
+                result => result,
+            };
+            let inner = match result {
+                Ok(inner) => inner,
+                Err(reason) if if_absent && is_blob_exists(reason.as_ref()) => {
+                    emit!(AzureBlobNameCollision {
+                        blob: request.metadata.partition_key.as_str(),
+                        policy: overwrite.as_str(),
+                    });
+                    if overwrite == OverwritePolicy::Refuse {
+                        return Err(AzureBlobServiceError::BlobExists {
+                            blob: request.metadata.partition_key,
                         }
-                        _ => emit!(AzureBlobHttpError {
-                            error: reason.to_string()
-                        }),
-                    };
-                })
-                .inspect_ok(|_| {
-                    emit!(BytesSent {
+                        .into());
+                    }
+                    return Ok(AzureBlobResponse {
+                        inner: None,
+                        count: request.metadata.count,
+                        events_byte_size: request.metadata.byte_size,
+                        byte_size: 0,
+                        attempts: attempt,
+                        elapsed: request.attempts.elapsed(),
+                        verify_error: None,
+                        partition: request.metadata.partition,
+                        receipt: None,
+                        provenance: None,
+                        append_sequence: request.metadata.append_sequence,
+                        outcomes: Vec::new(),
+                    });
+                }
+                Err(reason) if is_payload_too_large(reason.as_ref()) => {
+                    return Err(AzureBlobServiceError::PayloadTooLarge {
+                        blob: request.metadata.partition_key,
                         byte_size,
-                        protocol: "https",
+                    }
+                    .into())
+                }
+                Err(reason) => return Err(reason),
+            };
+            let elapsed = request.attempts.elapsed();
+
+            // Append blobs hold more than the payload appended, so only the blobs written as a
+            // whole are verified.
+            if let (true, BlobWrite::Put(put)) = (verify_write, &inner) {
+                if let Err(error) =
+                    verify_blob(&read_client, byte_size, &put.etag.to_string()).await
+                {
+                    emit!(AzureBlobWriteVerificationError {
+                        blob: request.metadata.partition_key.as_str(),
+                        error: error.clone(),
                     });
-                })
-                .instrument(info_span!("request").or_current())
-                .await;
+                    // The blobs written only if absent can't be overwritten by the retry.
+                    if if_absent {
+                        delete_blob_if_match(
+                            &container_client,
+                            &request.metadata.partition_key,
+                            &put.etag.to_string(),
+                        )
+                        .await?;
+                    }
+                    return Ok(AzureBlobResponse {
+                        inner: Some(inner),
+                        count: request.metadata.count,
+                        events_byte_size: request.metadata.byte_size,
+                        byte_size,
+                        attempts: attempt,
+                        elapsed,
+                        verify_error: Some(error),
+                        partition: request.metadata.partition,
+                        receipt: None,
+                        provenance: None,
+                        append_sequence: request.metadata.append_sequence,
+                        outcomes: Vec::new(),
+                    });
+                }
+            }
+
+            if let (
+                Some((algorithm, digest, md5)),
+                BlobWrite::Put(_) | BlobWrite::Existing { .. },
+            ) = (checksums, &inner)
+            {
+                let blob = request.metadata.partition_key.as_str();
+                // The sidecar must describe what Azure actually stored, so a blob whose stored MD5
+                // disagrees with the payload we sent is deleted and written again instead. The
+                // blobs written by an earlier attempt were already found to match it.
+                let stored_md5 = match &inner {
+                    BlobWrite::Put(put) => put.content_md5.as_ref().map(|stored_md5| {
+                        let stored_md5: &[u8] = stored_md5.as_ref();
+                        hex::encode(stored_md5)
+                    }),
+                    _ => None,
+                };
+                if let Some(stored_md5) = stored_md5 {
+                    if stored_md5 != md5 {
+                        emit!(AzureBlobChecksumMismatch {
+                            blob,
+                            stored_md5: stored_md5.clone(),
+                            expected_md5: &md5,
+                        });
+                        delete_blob_if_match(&container_client, blob, &inner.etag()).await?;
+                        return Ok(AzureBlobResponse {
+                            inner: Some(inner),
+                            count: request.metadata.count,
+                            events_byte_size: request.metadata.byte_size,
+                            byte_size,
+                            attempts: attempt,
+                            elapsed,
+                            verify_error: Some(format!(
+                                "Written blob has Content-MD5 {} instead of {}",
+                                stored_md5, md5
+                            )),
+                            partition: request.metadata.partition,
+                            receipt: None,
+                            provenance: None,
+                            append_sequence: request.metadata.append_sequence,
+                            outcomes: Vec::new(),
+                        });
+                    }
+                }
+                write_checksum_sidecar(Arc::clone(&container_client), blob, algorithm, digest)
+                    .await;
+            }
+            if let Some(schema_sidecar) = &schema_sidecar {
+                schema_sidecar
+                    .write(
+                        Arc::clone(&container_client),
+                        &request.metadata.partition_key,
+                    )
+                    .await;
+            }
+            // The entry comes last, so that the readers going through the index only find blobs
+            // along with their sidecars.
+            if let Some(sealed_batches) = &sealed_batches {
+                let blob = request.metadata.partition_key.as_str();
+                let sealed = sealed_batches
+                    .seal(
+                        Arc::clone(&container_client),
+                        &request.metadata.partition,
+                        blob,
+                        &inner.etag(),
+                        request.metadata.count,
+                    )
+                    .await;
+                if let Err(error) = sealed {
+                    let error = error.to_string();
+                    sealed_batches
+                        .orphan(&client, blob, metadata, error.clone())
+                        .await;
+                    return Err(AzureBlobServiceError::IndexWriteFailed {
+                        blob: blob.to_owned(),
+                        index: sealed_batches.index(&request.metadata.partition),
+                        error,
+                    }
+                    .into());
+                }
+            }
+
+            if let Some(summary) = summary {
+                summary.record(
+                    container_client.container_name(),
+                    request.metadata.count,
+                    request.metadata.byte_size,
+                    byte_size,
+                );
+            }
+
+            emit!(AzureBlobRequestDelivered {
+                blob: request.metadata.partition_key.as_str(),
+                attempts: attempt,
+                elapsed,
+            });
+
+            let receipt = content_md5.map(|content_md5| WriteReceipt {
+                container: container_client.container_name().to_owned(),
+                blob: request.metadata.partition_key.clone(),
+                etag: inner.etag(),
+                content_md5,
+                count: request.metadata.count,
+                byte_size,
+                event_time_range: request.metadata.event_time_range,
+            });
+            if let Some(receipt) = &receipt {
+                emit!(AzureBlobWriteReceipt { receipt });
+            }
+            let provenance = provenance.then(|| BlobProvenance {
+                container: container_client.container_name().to_owned(),
+                blob: request.metadata.partition_key.clone(),
+                etag: inner.etag(),
+                written_at: Utc::now(),
+            });
 
-            result.map(|inner| AzureBlobResponse {
-                inner,
+            Ok(AzureBlobResponse {
+                inner: Some(inner),
                 count: request.metadata.count,
                 events_byte_size: request.metadata.byte_size,
+                byte_size,
+                attempts: attempt,
+                elapsed,
+                verify_error: None,
+                partition: request.metadata.partition,
+                receipt,
+                provenance,
+                append_sequence: request.metadata.append_sequence,
+                outcomes: Vec::new(),
             })
         })
     }
 }
+
+/// Writes the payload of a request to its blob.
+struct BlobWriter<'a> {
+    client: &'a BlobClient,
+    container_client: &'a Arc<ContainerClient>,
+    request: &'a AzureBlobRequest,
+    metadata: Option<&'a Metadata>,
+    creating_append_blob: &'a Mutex<()>,
+    /// Whether block blobs are only written when no blob exists under their name yet.
+    if_absent: bool,
+}
+
+impl<'a> BlobWriter<'a> {
+    /// Writes the payload according to the type of the blob.
+    ///
+    /// Append blobs are always appended to, only the block blobs can be written if absent.
+    async fn write(self) -> crate::Result<BlobWrite> {
+        match (self.request.metadata.blob_type, self.if_absent) {
+            (BlobType::Block, false) => put_blob(self.client, self.request, self.metadata)
+                .await
+                .map(BlobWrite::Put),
+            (BlobType::Block, true) => {
+                put_blob_if_absent(self.container_client, self.request, self.metadata)
+                    .await
+                    .map(BlobWrite::Put)
+            }
+            (BlobType::Append, _) => append_blob(
+                self.client,
+                self.request,
+                self.metadata,
+                self.creating_append_blob,
+            )
+            .await
+            .map(BlobWrite::Append),
+        }
+    }
+}
+
+/// Writes the payload of the request as the blob of `client`.
+async fn put_blob(
+    client: &BlobClient,
+    request: &AzureBlobRequest,
+    metadata: Option<&Metadata>,
+) -> crate::Result<PutBlockBlobResponse> {
+    let blob = client
+        .put_block_blob(request.blob_data.clone())
+        .content_type(request.content_type.as_ref());
+    let blob = match request.content_encoding {
+        Some(encoding) => blob.content_encoding(encoding),
+        None => blob,
+    };
+    let blob = match &request.metadata.content_language {
+        Some(language) => blob.content_language(language.as_str()),
+        None => blob,
+    };
+    let blob = match metadata {
+        Some(metadata) => blob.metadata(metadata),
+        None => blob,
+    };
+    let result = blob.execute().await;
+    emit_body_sent(request, &result);
+    result
+}
+
+/// Writes the payload of the request as its blob, unless a blob already exists under its name.
+///
+/// The put is sent with `If-None-Match: *`, so that the storage account refuses it rather than
+/// replacing the existing blob, without racing with the other writers of the blob. The builders of
+/// the client don't take conditions, so the request is prepared the way they would.
+async fn put_blob_if_absent(
+    container_client: &Arc<ContainerClient>,
+    request: &AzureBlobRequest,
+    metadata: Option<&Metadata>,
+) -> crate::Result<PutBlockBlobResponse> {
+    let account = container_client.storage_client().storage_account_client();
+    let url = Arc::clone(container_client)
+        .as_blob_client(request.metadata.partition_key.as_str())
+        .url_with_segments(None)?;
+    let (put, _) = account.prepare_request(
+        url.as_str(),
+        &Method::PUT,
+        ServiceType::Blob,
+        &|builder| {
+            let builder = builder
+                .header("x-ms-blob-type", "BlockBlob")
+                .header(CONTENT_TYPE, request.content_type.as_ref())
+                .header(CONTENT_LENGTH, request.blob_data.len())
+                .header(IF_NONE_MATCH, "*");
+            let builder = match request.content_encoding {
+                Some(encoding) => builder.header(CONTENT_ENCODING, encoding),
+                None => builder,
+            };
+            let builder = match &request.metadata.content_language {
+                Some(language) => builder.header(CONTENT_LANGUAGE, language.as_str()),
+                None => builder,
+            };
+            add_optional_header(&metadata, builder)
+        },
+        Some(request.blob_data.clone()),
+    )?;
+    let result = account
+        .http_client()
+        .execute_request_check_status(put, StatusCode::CREATED)
+        .await
+        .map_err(crate::Error::from)
+        .and_then(|response| {
+            PutBlockBlobResponse::from_headers(response.headers()).map_err(Into::into)
+        });
+    emit_body_sent(request, &result);
+    result
+}
+
+/// Deletes the blob named `blob`, unless it was written again since it had the ETag `etag`.
+///
+/// The delete is sent with `If-Match`, so that it doesn't delete what another writer wrote under
+/// the same name in the meantime. The blobs already gone are left as they are.
+async fn delete_blob_if_match(
+    container_client: &Arc<ContainerClient>,
+    blob: &str,
+    etag: &str,
+) -> crate::Result<()> {
+    let account = container_client.storage_client().storage_account_client();
+    let url = Arc::clone(container_client)
+        .as_blob_client(blob)
+        .url_with_segments(None)?;
+    let (delete, _) = account.prepare_request(
+        url.as_str(),
+        &Method::DELETE,
+        ServiceType::Blob,
+        &|builder| builder.header(IF_MATCH, etag),
+        None,
+    )?;
+    let result = account
+        .http_client()
+        .execute_request_check_status(delete, StatusCode::ACCEPTED)
+        .await
+        .map_err(crate::Error::from);
+    match result {
+        Err(reason)
+            if is_blob_not_found(reason.as_ref())
+                || matches!(
+                    reason.downcast_ref::<HttpError>(),
+                    Some(HttpError::StatusCode { status, .. })
+                        if *status == StatusCode::PRECONDITION_FAILED
+                ) =>
+        {
+            Ok(())
+        }
+        result => result.map(|_| ()),
+    }
+}
+
+/// Reads the blob of the request, which was found existing when writing it again, and returns its
+/// ETag if it holds the payload of the request.
+///
+/// Only the blobs written as a whole are compared, by their size and their Content-MD5.
+async fn written_by_earlier_attempt(
+    client: &BlobClient,
+    request: &AzureBlobRequest,
+) -> Option<String> {
+    let properties = client
+        .get_properties()
+        .execute()
+        .await
+        .ok()?
+        .blob
+        .properties;
+    let stored_md5 = properties.content_md5.as_ref().map(|md5| {
+        let md5: &[u8] = md5.as_ref();
+        hex::encode(md5)
+    })?;
+    (properties.content_length == request.blob_data.len() as u64
+        && stored_md5 == ChecksumAlgorithm::Md5.hex_digest(&request.blob_data))
+    .then(|| properties.etag.to_string())
+}
+
+/// Whether a write was refused because a blob already exists under its name.
+///
+/// The storage account answers a put sent with `If-None-Match: *` with a `409 Conflict`, or with
+/// a `412 Precondition Failed` when the blob is being written concurrently.
+fn is_blob_exists(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        error.downcast_ref::<HttpError>(),
+        Some(HttpError::StatusCode { status, .. })
+            if *status == StatusCode::CONFLICT || *status == StatusCode::PRECONDITION_FAILED
+    )
+}
+
+/// Appends the payload of the request to the append blob of `client`, which is created by the
+/// first batch of its partition.
+///
+/// The content type, the content encoding, the content language and the metadata of the blob are
+/// the ones of the batch that created it.
+async fn append_blob(
+    client: &BlobClient,
+    request: &AzureBlobRequest,
+    metadata: Option<&Metadata>,
+    creating: &Mutex<()>,
+) -> crate::Result<AppendBlockResponse> {
+    let result = append_block(client, request).await;
+    match result {
+        Err(reason) if is_blob_not_found(reason.as_ref()) => {
+            {
+                let _creating = creating.lock().await;
+                // Another append may have created the blob while this one was waiting.
+                if !blob_exists(client).await? {
+                    let blob = client
+                        .put_append_blob()
+                        .content_type(request.content_type.as_ref());
+                    let blob = match request.content_encoding {
+                        Some(encoding) => blob.content_encoding(encoding),
+                        None => blob,
+                    };
+                    let blob = match &request.metadata.content_language {
+                        Some(language) => blob.content_language(language.as_str()),
+                        None => blob,
+                    };
+                    let blob = match metadata {
+                        Some(metadata) => blob.metadata(metadata),
+                        None => blob,
+                    };
+                    blob.execute().await?;
+                }
+            }
+            append_block(client, request).await
+        }
+        result => result,
+    }
+}
+
+async fn append_block(
+    client: &BlobClient,
+    request: &AzureBlobRequest,
+) -> crate::Result<AppendBlockResponse> {
+    let result = client
+        .append_block(request.blob_data.clone())
+        .execute()
+        .await;
+    emit_body_sent(request, &result);
+    result
+}
+
+/// Counts the body of the request towards the bytes sent, once the storage account answered.
+///
+/// Every body the storage account answered counts towards its ingress, including the ones it
+/// refused and the ones retried.
+fn emit_body_sent<T>(request: &AzureBlobRequest, result: &crate::Result<T>) {
+    let answered = match result {
+        Ok(_) => true,
+        Err(reason) => matches!(
+            reason.downcast_ref::<HttpError>(),
+            Some(HttpError::StatusCode { .. })
+        ),
+    };
+    if answered {
+        emit!(AzureBlobRequestBodySent {
+            byte_size: request.blob_data.len(),
+        });
+    }
+}
+
+/// Whether a write failed because the blob doesn't exist, as opposed to its container.
+pub(super) fn is_blob_not_found(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        error.downcast_ref::<HttpError>(),
+        Some(HttpError::StatusCode { status, body })
+            if *status == StatusCode::NOT_FOUND && body.contains("<Code>BlobNotFound</Code>")
+    )
+}
+
+/// Whether a write was refused because the blob is too large to be written in a single request.
+///
+/// Retrying the same blob would fail the same way, so this is reported as a distinct error rather
+/// than the bare status code.
+fn is_payload_too_large(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        error.downcast_ref::<HttpError>(),
+        Some(HttpError::StatusCode { status, .. }) if *status == StatusCode::PAYLOAD_TOO_LARGE
+    )
+}
+
+/// Checks whether a blob already exists under the name of `client`.
+///
+/// The check can race with another writer of the same blob.
+pub async fn blob_exists(client: &BlobClient) -> crate::Result<bool> {
+    match client.get_properties().execute().await {
+        Ok(_) => Ok(true),
+        Err(reason) => match reason.downcast_ref::<HttpError>() {
+            Some(HttpError::StatusCode { status, .. }) if *status == StatusCode::NOT_FOUND => {
+                Ok(false)
+            }
+            _ => Err(reason),
+        },
+    }
+}
+
+/// Checks that the blob stored by Azure has the size and ETag of the blob just written.
+async fn verify_blob(client: &BlobClient, byte_size: usize, etag: &str) -> Result<(), String> {
+    let properties = client
+        .get_properties()
+        .execute()
+        .await
+        .map_err(|error| format!("Failed to read back the written blob: {}", error))?
+        .blob
+        .properties;
+
+    if properties.content_length != byte_size as u64 {
+        return Err(format!(
+            "Written blob is {} bytes long instead of {}",
+            properties.content_length, byte_size
+        ));
+    }
+    let stored_etag = properties.etag.to_string();
+    if stored_etag != etag {
+        return Err(format!(
+            "Written blob has ETag {} instead of {}",
+            stored_etag, etag
+        ));
+    }
+    Ok(())
+}
+
+/// Builds the metadata written along with the blob, if there is any.
+fn blob_metadata(request: &AzureBlobMetadata) -> Option<Metadata> {
+    if request.event_time_range.is_none()
+        && request.source_types.is_none()
+        && request.zstd_dictionary_id.is_none()
+        && request.computed_metadata.is_empty()
+        && request.schema_fingerprint.is_none()
+        && request.schema_version.is_none()
+    {
+        return None;
+    }
+
+    let mut metadata = Metadata::new();
+    if let Some((min, max)) = request.event_time_range {
+        metadata.insert(
+            EVENT_TIME_MIN_METADATA,
+            min.to_rfc3339_opts(SecondsFormat::Millis, true),
+        );
+        metadata.insert(
+            EVENT_TIME_MAX_METADATA,
+            max.to_rfc3339_opts(SecondsFormat::Millis, true),
+        );
+    }
+    if let Some(source_types) = &request.source_types {
+        metadata.insert(SOURCE_TYPE_METADATA, source_types.clone());
+    }
+    if let Some(id) = request.zstd_dictionary_id {
+        metadata.insert(ZSTD_DICTIONARY_METADATA, id.to_string());
+    }
+    if let Some(fingerprint) = &request.schema_fingerprint {
+        metadata.insert(SCHEMA_FINGERPRINT_METADATA, fingerprint.clone());
+    }
+    if let Some(version) = &request.schema_version {
+        metadata.insert(SCHEMA_VERSION_METADATA, version.clone());
+    }
+    for (name, value) in &request.computed_metadata {
+        metadata.insert(name.clone(), value.clone());
+    }
+    Some(metadata)
+}
+
+/// Writes the checksum sidecar of `blob`.
+async fn write_checksum_sidecar(
+    client: Arc<ContainerClient>,
+    blob: &str,
+    algorithm: ChecksumAlgorithm,
+    digest: String,
+) {
+    let sidecar = format!("{}.{}", blob, algorithm.extension());
+    write_sidecar(client, &sidecar, digest.into(), "text/plain").await;
+}
+
+/// Writes a sidecar, retrying retriable errors a few times, and returns whether it was written.
+///
+/// The data blob has already been written at this point, so failing to write its sidecar is
+/// reported but doesn't fail the request.
+pub(super) async fn write_sidecar(
+    client: Arc<ContainerClient>,
+    sidecar: &str,
+    content: Bytes,
+    content_type: &'static str,
+) -> bool {
+    let client = client.as_blob_client(sidecar);
+
+    for attempt in 1..=SIDECAR_ATTEMPTS {
+        let result = client
+            .put_block_blob(content.clone())
+            .content_type(content_type)
+            .execute()
+            .await;
+
+        match result {
+            Ok(_) => return true,
+            Err(reason) => {
+                let retriable = reason
+                    .downcast_ref::<HttpError>()
+                    .map_or(false, |error| AzureBlobRetryLogic.is_retriable_error(error));
+                if !retriable || attempt == SIDECAR_ATTEMPTS {
+                    emit!(AzureBlobSidecarWriteError {
+                        blob: sidecar,
+                        error: reason.to_string(),
+                    });
+                    return false;
+                }
+                tokio::time::sleep(SIDECAR_RETRY_BACKOFF).await;
+            }
+        }
+    }
+    false
+}
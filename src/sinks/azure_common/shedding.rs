@@ -0,0 +1,131 @@
+use futures::{Future, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::{
+    conditions::{AnyCondition, Condition},
+    event::{Event, EventStatus, Finalizable},
+    internal_events::azure_blob::AzureBlobEventsShed,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LoadSheddingConfig {
+    /// The number of events queued in front of the sink above which events are shed.
+    pub high_water_mark: usize,
+    /// The condition matching the low-priority events that may be shed.
+    pub condition: AnyCondition,
+}
+
+impl LoadSheddingConfig {
+    pub fn build(&self) -> crate::Result<LoadShedder> {
+        if self.high_water_mark == 0 {
+            return Err("`load_shedding.high_water_mark` must be greater than zero".into());
+        }
+
+        Ok(LoadShedder {
+            high_water_mark: self.high_water_mark,
+            condition: self.condition.build(&Default::default())?,
+        })
+    }
+}
+
+/// Drops low-priority events rather than blocking the pipeline while the sink can't keep up.
+///
+/// Events are queued in front of the sink. Once `high_water_mark` events are queued, the events
+/// matching `condition` are dropped, while the others wait for room in the queue as usual.
+pub struct LoadShedder {
+    high_water_mark: usize,
+    condition: Condition,
+}
+
+impl LoadShedder {
+    /// Splits `input` into the future feeding the queue and the stream of queued events.
+    ///
+    /// The future has to be polled alongside the stream, and completes once `input` is exhausted
+    /// or the stream is dropped.
+    pub fn shed<'a, S>(
+        self,
+        mut input: S,
+    ) -> (
+        impl Future<Output = ()> + 'a,
+        impl Stream<Item = Event> + 'a,
+    )
+    where
+        S: Stream<Item = Event> + Send + Unpin + 'a,
+    {
+        let (sender, receiver) = mpsc::channel(self.high_water_mark);
+
+        let feed = async move {
+            while let Some(event) = input.next().await {
+                let event = match sender.try_send(event) {
+                    Ok(()) => continue,
+                    Err(TrySendError::Full(event)) => event,
+                    Err(TrySendError::Closed(_)) => break,
+                };
+
+                let (shed, mut event) = self.condition.check(event);
+                if shed {
+                    emit!(AzureBlobEventsShed { count: 1 });
+                    event.take_finalizers().update_status(EventStatus::Dropped);
+                } else if sender.send(event).await.is_err() {
+                    break;
+                }
+            }
+        };
+
+        (feed, ReceiverStream::new(receiver))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::config::log_schema;
+    use crate::event::{BatchNotifier, BatchStatus, LogEvent};
+
+    #[tokio::test]
+    async fn sheds_low_priority_events_under_backpressure() {
+        let (batch, receiver) = BatchNotifier::new_with_receiver();
+        let event = |priority: &str| {
+            let mut log = LogEvent::from(format!("{} priority", priority));
+            log.insert("priority", priority);
+            let log = match priority {
+                "low" => log.with_batch_notifier(&batch),
+                _ => log,
+            };
+            Event::Log(log)
+        };
+        let input = vec![event("high"), event("high"), event("low"), event("high")];
+        drop(batch);
+
+        let shedder = LoadSheddingConfig {
+            high_water_mark: 2,
+            condition: AnyCondition::String(r#".priority == "low""#.into()),
+        }
+        .build()
+        .unwrap();
+        let (feed, queued) = shedder.shed(stream::iter(input));
+
+        // Nothing is read from the queue until the feed waits for room for the last event, by which
+        // time the queue is full.
+        let ((), queued) = futures::join!(feed, queued.collect::<Vec<_>>());
+
+        let messages = queued
+            .iter()
+            .map(|event| event.as_log()[log_schema().message_key()].to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["high priority"; 3]);
+        assert_eq!(receiver.await, BatchStatus::Delivered);
+
+        assert!(LoadSheddingConfig {
+            high_water_mark: 0,
+            condition: AnyCondition::String("true".into()),
+        }
+        .build()
+        .is_err());
+    }
+}
@@ -1,15 +1,38 @@
-use std::{fmt, num::NonZeroUsize};
+use std::{fmt, num::NonZeroUsize, panic, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
-use futures::stream::BoxStream;
+use futures::{
+    future::{self, BoxFuture},
+    stream::{self, BoxStream},
+    FutureExt,
+};
 use futures_util::StreamExt;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
 use tower::Service;
 use vector_core::{
-    event::Finalizable,
     sink::StreamSink,
     stream::{BatcherSettings, DriverResponse},
 };
 
+use super::{
+    blob_path::BlobPathPolicy,
+    blob_type::BlobTypeRouter,
+    bucket::{BucketedPartitioner, HashBucket},
+    coalesce::Coalescer,
+    concurrency::{ByteLimiter, PartitionLimiter},
+    config::{AzureBlobRequest, UnresolvablePartition},
+    container::ContainerGuard,
+    encoding::EncodingErrorHandler,
+    fingerprint::SchemaFingerprints,
+    flush::{FlushListener, FlushTrigger},
+    janitor::{Janitor, ReservedPrefixGuard},
+    marker::SuccessMarkers,
+    precompressed::Precompressed,
+    schema_version::SchemaVersions,
+    shedding::LoadShedder,
+    summary::BatchSummary,
+};
 use crate::{
     event::Event,
     sinks::util::{partitioner::KeyPartitioner, RequestBuilder, SinkBuilderExt},
@@ -20,6 +43,28 @@ pub struct AzureBlobSink<Svc, RB> {
     request_builder: RB,
     partitioner: KeyPartitioner,
     batcher_settings: BatcherSettings,
+    janitor: Option<Janitor>,
+    reserved_prefix_guard: Option<ReservedPrefixGuard>,
+    batch_summary: Option<(Arc<BatchSummary>, Duration)>,
+    coalescer: Option<Coalescer>,
+    unresolvable_partition: UnresolvablePartition,
+    blob_path_policy: BlobPathPolicy,
+    load_shedder: Option<LoadShedder>,
+    encoding_errors: Option<EncodingErrorHandler>,
+    partition_limiter: Option<PartitionLimiter>,
+    append_sequencer: Option<PartitionLimiter>,
+    byte_limiter: Option<ByteLimiter>,
+    hash_bucket: Option<HashBucket>,
+    container_guard: Option<Arc<ContainerGuard>>,
+    flush_trigger: Option<Arc<FlushTrigger>>,
+    flush_listener: Option<FlushListener>,
+    success_markers: Option<Arc<SuccessMarkers>>,
+    blob_type_router: Option<BlobTypeRouter>,
+    precompressed: Option<Precompressed>,
+    cancellation: Option<CancellationToken>,
+    schema_fingerprints: Option<SchemaFingerprints>,
+    schema_versions: Option<SchemaVersions>,
+    encoding_pool_size: Option<NonZeroUsize>,
 }
 
 impl<Svc, RB> AzureBlobSink<Svc, RB> {
@@ -34,56 +79,399 @@ impl<Svc, RB> AzureBlobSink<Svc, RB> {
             request_builder,
             partitioner,
             batcher_settings,
+            janitor: None,
+            reserved_prefix_guard: None,
+            batch_summary: None,
+            coalescer: None,
+            unresolvable_partition: UnresolvablePartition::Drop,
+            blob_path_policy: BlobPathPolicy::Keep,
+            load_shedder: None,
+            encoding_errors: None,
+            partition_limiter: None,
+            append_sequencer: None,
+            byte_limiter: None,
+            hash_bucket: None,
+            container_guard: None,
+            flush_trigger: None,
+            flush_listener: None,
+            success_markers: None,
+            blob_type_router: None,
+            precompressed: None,
+            cancellation: None,
+            schema_fingerprints: None,
+            schema_versions: None,
+            encoding_pool_size: None,
         }
     }
+
+    /// Runs the given janitor alongside the sink for as long as the sink is running.
+    pub fn with_janitor(mut self, janitor: Janitor) -> Self {
+        self.janitor = Some(janitor);
+        self
+    }
+
+    /// Rejects the events whose blob would be written under the prefix reserved for the janitor.
+    pub fn with_reserved_prefix_guard(mut self, guard: ReservedPrefixGuard) -> Self {
+        self.reserved_prefix_guard = Some(guard);
+        self
+    }
+
+    /// Emits the given summary every `period` for as long as the sink is running, and once more
+    /// when it stops.
+    pub fn with_batch_summary(mut self, summary: Arc<BatchSummary>, period: Duration) -> Self {
+        self.batch_summary = Some((summary, period));
+        self
+    }
+
+    /// Merges the batches of low-volume partitions before they are written.
+    pub fn with_coalescer(mut self, coalescer: Coalescer) -> Self {
+        self.coalescer = Some(coalescer);
+        self
+    }
+
+    /// Sets what happens to the events whose partition can't be rendered.
+    pub fn with_unresolvable_partition(mut self, mode: UnresolvablePartition) -> Self {
+        self.unresolvable_partition = mode;
+        self
+    }
+
+    /// Sets what happens to the partitions rendered into malformed paths.
+    pub const fn with_blob_path_policy(mut self, policy: BlobPathPolicy) -> Self {
+        self.blob_path_policy = policy;
+        self
+    }
+
+    /// Sheds low-priority events instead of blocking while too many events are queued.
+    pub fn with_load_shedder(mut self, load_shedder: LoadShedder) -> Self {
+        self.load_shedder = Some(load_shedder);
+        self
+    }
+
+    /// Takes the events that can't be encoded out of the batches before they are written.
+    pub fn with_encoding_errors(mut self, handler: EncodingErrorHandler) -> Self {
+        self.encoding_errors = Some(handler);
+        self
+    }
+
+    /// Limits the requests in flight for each partition.
+    pub fn with_partition_limiter(mut self, limiter: PartitionLimiter) -> Self {
+        self.partition_limiter = Some(limiter);
+        self
+    }
+
+    /// Holds the appends to each blob back until the previous one is done, through the given
+    /// limiter.
+    pub fn with_append_sequencer(mut self, sequencer: PartitionLimiter) -> Self {
+        self.append_sequencer = Some(sequencer);
+        self
+    }
+
+    /// Holds the requests back until the payloads in flight leave room for theirs.
+    pub fn with_byte_limiter(mut self, limiter: ByteLimiter) -> Self {
+        self.byte_limiter = Some(limiter);
+        self
+    }
+
+    /// Splits every partition into the hash buckets of one of the fields of its events.
+    pub fn with_hash_bucket(mut self, hash_bucket: HashBucket) -> Self {
+        self.hash_bucket = Some(hash_bucket);
+        self
+    }
+
+    /// Stops the sink once the guard finds the container lost.
+    pub fn with_container_guard(mut self, guard: Arc<ContainerGuard>) -> Self {
+        self.container_guard = Some(guard);
+        self
+    }
+
+    /// Flushes all the pending batches whenever the trigger is pulled, without stopping the sink.
+    pub fn with_flush_trigger(mut self, trigger: Arc<FlushTrigger>) -> Self {
+        self.flush_trigger = Some(trigger);
+        self
+    }
+
+    /// Pulls the flush trigger of the sink whenever the listener receives its signal.
+    pub fn with_flush_listener(mut self, listener: FlushListener) -> Self {
+        self.flush_trigger.get_or_insert_with(FlushTrigger::new);
+        self.flush_listener = Some(listener);
+        self
+    }
+
+    /// Writes the success markers of the partitions closed for as long as the sink is running.
+    pub fn with_success_markers(mut self, markers: Arc<SuccessMarkers>) -> Self {
+        self.success_markers = Some(markers);
+        self
+    }
+
+    /// Splits every batch into the events of each type of blob before they are written.
+    pub fn with_blob_type_router(mut self, router: BlobTypeRouter) -> Self {
+        self.blob_type_router = Some(router);
+        self
+    }
+
+    /// Splits the events already compressed out of every batch before they are written.
+    pub fn with_precompressed(mut self, precompressed: Precompressed) -> Self {
+        self.precompressed = Some(precompressed);
+        self
+    }
+
+    /// Cancels the token once the input of the sink ends, on reload or shutdown.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Groups the events of every partition by the fingerprint of their schema.
+    pub fn with_schema_fingerprints(mut self, fingerprints: SchemaFingerprints) -> Self {
+        self.schema_fingerprints = Some(fingerprints);
+        self
+    }
+
+    /// Groups the events of every partition by the version of their schema.
+    pub fn with_schema_versions(mut self, versions: SchemaVersions) -> Self {
+        self.schema_versions = Some(versions);
+        self
+    }
+
+    /// Encodes and compresses the batches on up to `size` blocking threads, handing each request
+    /// over as soon as it's built rather than in the order the batches were flushed.
+    pub fn with_encoding_pool(mut self, size: NonZeroUsize) -> Self {
+        self.encoding_pool_size = Some(size);
+        self
+    }
 }
 
 impl<Svc, RB> AzureBlobSink<Svc, RB>
 where
-    Svc: Service<RB::Request> + Send + 'static,
+    Svc: Service<AzureBlobRequest> + Send + 'static,
     Svc::Future: Send + 'static,
     Svc::Response: DriverResponse + Send + 'static,
     Svc::Error: fmt::Debug + Into<crate::Error> + Send,
-    RB: RequestBuilder<(String, Vec<Event>)> + Send + Sync + 'static,
+    RB: RequestBuilder<(String, Vec<Event>), Request = AzureBlobRequest> + Send + Sync + 'static,
     RB::Error: fmt::Debug + Send,
-    RB::Request: Finalizable + Send,
 {
     async fn run_inner(self: Box<Self>, input: BoxStream<'_, Event>) -> Result<(), ()> {
-        let partitioner = self.partitioner;
+        let partitioner = BucketedPartitioner::new(self.partitioner, self.hash_bucket)
+            .with_schema_fingerprints(self.schema_fingerprints)
+            .with_schema_versions(self.schema_versions);
         let settings = self.batcher_settings;
 
         let builder_limit = NonZeroUsize::new(64);
-        let request_builder = self.request_builder;
-
-        let sink = input
-            .batched_partitioned(partitioner, settings)
-            .filter_map(|(key, batch)| async move { key.map(move |k| (k, batch)) })
-            .request_builder(builder_limit, request_builder)
-            .filter_map(|request| async move {
-                match request {
-                    Err(e) => {
-                        error!("Failed to build Azure Blob request: {:?}.", e);
-                        None
+        let unresolvable_partition = self.unresolvable_partition;
+        let blob_path_policy = self.blob_path_policy;
+        let request_builder = Arc::new(self.request_builder);
+
+        // The input ends once the sink is reloaded or shut down, at which point the requests
+        // queued or in flight are cancelled rather than drained.
+        let input = match self.cancellation {
+            Some(token) => input
+                .chain(stream::once(async move { token.cancel() }).filter_map(|()| async { None }))
+                .boxed(),
+            None => input,
+        };
+
+        // The side tasks stop once `_shutdown` is dropped at the end of this function.
+        let (_shutdown, tripwire) = watch::channel(());
+        if let Some(janitor) = self.janitor {
+            tokio::spawn(janitor.run(tripwire.clone()));
+        }
+        if let Some((summary, period)) = self.batch_summary {
+            tokio::spawn(summary.run(period, tripwire.clone()));
+        }
+        if let Some(markers) = self.success_markers {
+            tokio::spawn(markers.run(tripwire.clone()));
+        }
+        if let (Some(listener), Some(trigger)) = (self.flush_listener, &self.flush_trigger) {
+            tokio::spawn(listener.run(Arc::clone(trigger), tripwire));
+        }
+
+        // Once the container is lost no more events are taken in, and the events already taken in
+        // are flushed, to be rejected, before the sink stops.
+        let container_guard = self.container_guard;
+        let input = match &container_guard {
+            Some(guard) => {
+                let guard = Arc::clone(guard);
+                input.take_until(async move { guard.lost().await }).boxed()
+            }
+            None => input,
+        };
+
+        let (feed, input): (BoxFuture<'_, ()>, BoxStream<'_, Event>) = match self.load_shedder {
+            Some(load_shedder) => {
+                let (feed, queued) = load_shedder.shed(input);
+                (feed.boxed(), queued.boxed())
+            }
+            None => (future::ready(()).boxed(), input),
+        };
+
+        // Every flush requested ends the input of the pipeline, which then drains all of its
+        // partitions and waits for the uploads in flight as it does when shutting down, before it
+        // is started over on the rest of the input.
+        let flush_trigger = self.flush_trigger;
+        let coalescer = self.coalescer;
+        let reserved_prefix_guard = self.reserved_prefix_guard;
+        let encoding_errors = self.encoding_errors;
+        let partition_limiter = self.partition_limiter;
+        let append_sequencer = self.append_sequencer;
+        let byte_limiter = self.byte_limiter;
+        let blob_type_router = self.blob_type_router;
+        let precompressed = self.precompressed;
+        let encoding_pool_size = self.encoding_pool_size;
+        let mut service = self.service;
+        let run = async {
+            let mut input = input.fuse();
+            loop {
+                let epoch = match &flush_trigger {
+                    Some(trigger) => {
+                        let seen = trigger.flushing();
+                        (&mut input).take_until(trigger.requested(seen)).boxed()
+                    }
+                    None => (&mut input).boxed(),
+                };
+
+                let unresolvable_partition = unresolvable_partition.clone();
+                let batches = epoch
+                    .batched_partitioned(partitioner.clone(), settings)
+                    .filter_map(move |(key, batch)| {
+                        let resolved = match key {
+                            Some(key) => Some((key, batch)),
+                            None => unresolvable_partition.resolve(batch),
+                        }
+                        .and_then(|(key, batch)| blob_path_policy.apply(key, batch));
+                        async move { resolved }
+                    })
+                    .boxed();
+                let batches = match &coalescer {
+                    Some(coalescer) => coalescer.clone().coalesce(batches).boxed(),
+                    None => batches,
+                };
+                let batches = match &encoding_errors {
+                    Some(handler) => {
+                        let handler = handler.clone();
+                        batches
+                            .filter_map(move |(key, batch)| {
+                                let batch = handler.handle(batch).map(|batch| (key, batch));
+                                async move { batch }
+                            })
+                            .boxed()
                     }
-                    Ok(req) => Some(req),
+                    None => batches,
+                };
+                let batches = match &blob_type_router {
+                    Some(router) => {
+                        let router = router.clone();
+                        batches
+                            .flat_map(move |(key, batch)| stream::iter(router.split(key, batch)))
+                            .boxed()
+                    }
+                    None => batches,
+                };
+                let batches = match &precompressed {
+                    Some(precompressed) => {
+                        let precompressed = precompressed.clone();
+                        batches
+                            .flat_map(move |(key, batch)| {
+                                stream::iter(precompressed.split(key, batch))
+                            })
+                            .boxed()
+                    }
+                    None => batches,
+                };
+
+                let requests = match encoding_pool_size {
+                    // The encoding of a heavy batch holds up neither the upload loop nor the
+                    // batches encoded after it. No more batches are taken in while the pool is
+                    // full.
+                    Some(size) => {
+                        let request_builder = Arc::clone(&request_builder);
+                        batches
+                            .map(move |input| {
+                                let builder = Arc::clone(&request_builder);
+                                tokio::task::spawn_blocking(move || {
+                                    let (metadata, events) = builder.split_input(input);
+                                    let payload = builder.encode_events(events)?;
+                                    Ok::<_, RB::Error>(builder.build_request(metadata, payload))
+                                })
+                                .map(|result| {
+                                    result.unwrap_or_else(|error| {
+                                        panic::resume_unwind(error.into_panic())
+                                    })
+                                })
+                            })
+                            .buffer_unordered(size.get())
+                            .boxed()
+                    }
+                    None => batches
+                        .request_builder(builder_limit, Arc::clone(&request_builder))
+                        .boxed(),
+                };
+                let requests = requests
+                    .filter_map(|request| async move {
+                        match request {
+                            Err(e) => {
+                                error!("Failed to build Azure Blob request: {:?}.", e);
+                                None
+                            }
+                            Ok(req) => Some(req),
+                        }
+                    })
+                    .boxed();
+                // Only the names of the blobs built are final, whichever of the prefix, the
+                // fallback partition or the path normalization they came from.
+                let requests = match &reserved_prefix_guard {
+                    Some(guard) => {
+                        let guard = guard.clone();
+                        requests
+                            .filter_map(move |request| {
+                                let request = guard.check(request);
+                                async move { request }
+                            })
+                            .boxed()
+                    }
+                    None => requests,
+                };
+                let requests = match &partition_limiter {
+                    Some(limiter) => limiter.clone().limit(requests).boxed(),
+                    None => requests,
+                };
+                let requests = match &append_sequencer {
+                    Some(sequencer) => sequencer.clone().limit(requests).boxed(),
+                    None => requests,
+                };
+                let requests = match &byte_limiter {
+                    Some(limiter) => limiter.clone().limit(requests).boxed(),
+                    None => requests,
+                };
+
+                let result = requests.into_driver(&mut service).run().await;
+                match &flush_trigger {
+                    Some(trigger) if result.is_ok() && !input.is_done() => trigger.complete(),
+                    Some(trigger) => {
+                        trigger.close();
+                        return result;
+                    }
+                    None => return result,
                 }
-            })
-            .into_driver(self.service);
+            }
+        };
 
-        sink.run().await
+        let ((), result) = futures::join!(feed, run);
+        match container_guard {
+            Some(guard) if guard.is_lost() => Err(()),
+            _ => result,
+        }
     }
 }
 
 #[async_trait]
 impl<Svc, RB> StreamSink<Event> for AzureBlobSink<Svc, RB>
 where
-    Svc: Service<RB::Request> + Send + 'static,
+    Svc: Service<AzureBlobRequest> + Send + 'static,
     Svc::Future: Send + 'static,
     Svc::Response: DriverResponse + Send + 'static,
     Svc::Error: fmt::Debug + Into<crate::Error> + Send,
-    RB: RequestBuilder<(String, Vec<Event>)> + Send + Sync + 'static,
+    RB: RequestBuilder<(String, Vec<Event>), Request = AzureBlobRequest> + Send + Sync + 'static,
     RB::Error: fmt::Debug + Send,
-    RB::Request: Finalizable + Send,
 {
     async fn run(mut self: Box<Self>, input: BoxStream<'_, Event>) -> Result<(), ()> {
         self.run_inner(input).await
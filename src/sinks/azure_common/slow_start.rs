@@ -0,0 +1,262 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{sleep_until, Instant, Sleep},
+};
+use tokio_util::sync::PollSemaphore;
+use tower::Service;
+
+use super::config::AzureBlobRequest;
+
+const fn default_initial_concurrency() -> usize {
+    1
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SlowStartConfig {
+    /// The requests in flight at once when the sink starts sending.
+    #[serde(default = "default_initial_concurrency")]
+    pub initial_concurrency: usize,
+    /// How long the concurrency takes to ramp up to `request.concurrency`.
+    pub warm_up_secs: u64,
+}
+
+impl SlowStartConfig {
+    /// Builds the ramp up to `concurrency`, the fixed `request.concurrency` of the sink.
+    pub fn build(&self, concurrency: Option<usize>) -> crate::Result<SlowStart> {
+        let max_concurrency = concurrency.ok_or(
+            "`slow_start` needs a fixed `request.concurrency`, adaptive concurrency already \
+             starts from a single request",
+        )?;
+        if self.warm_up_secs == 0 {
+            return Err("`slow_start.warm_up_secs` must be greater than zero".into());
+        }
+        if self.initial_concurrency == 0 || self.initial_concurrency >= max_concurrency {
+            return Err(
+                "`slow_start.initial_concurrency` must be greater than zero and lower than \
+                 `request.concurrency`"
+                    .into(),
+            );
+        }
+
+        Ok(SlowStart {
+            initial_concurrency: self.initial_concurrency,
+            max_concurrency,
+            warm_up: Duration::from_secs(self.warm_up_secs),
+        })
+    }
+}
+
+/// Ramps the concurrency up linearly from `initial_concurrency` to `max_concurrency` over
+/// `warm_up`.
+#[derive(Clone, Copy, Debug)]
+pub struct SlowStart {
+    initial_concurrency: usize,
+    max_concurrency: usize,
+    warm_up: Duration,
+}
+
+impl SlowStart {
+    /// The concurrency allowed once `elapsed` has passed since the sink started sending.
+    pub fn limit(&self, elapsed: Duration) -> usize {
+        if elapsed >= self.warm_up {
+            return self.max_concurrency;
+        }
+        let ramp = (self.max_concurrency - self.initial_concurrency) as u128;
+        self.initial_concurrency + (ramp * elapsed.as_nanos() / self.warm_up.as_nanos()) as usize
+    }
+
+    /// The time since the sink started sending after which the concurrency goes above `limit`.
+    fn next_step(&self, limit: usize) -> Duration {
+        let ramp = (self.max_concurrency - self.initial_concurrency) as u128;
+        let steps = (limit + 1 - self.initial_concurrency) as u128;
+        // Rounded up, so that the limit has gone up by then.
+        let nanos = (steps * self.warm_up.as_nanos() + ramp - 1) / ramp;
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+struct Ramp {
+    slow_start: SlowStart,
+    semaphore: PollSemaphore,
+    /// The permits handed to the semaphore so far.
+    granted: usize,
+    started: Option<Instant>,
+    /// Fires when the concurrency goes up next.
+    step: Option<Pin<Box<Sleep>>>,
+}
+
+/// Holds the requests back while the concurrency ramps up after the sink starts, so that a storage
+/// account that was idle isn't throttled by a burst of requests before it scales out.
+///
+/// Once the ramp is over, the requests are only limited by the services wrapped.
+pub struct SlowStartService<S> {
+    inner: S,
+    ramp: Option<Ramp>,
+    /// The permit of the next call, once the service was polled ready for it.
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl<S> SlowStartService<S> {
+    /// Wraps the service, which isn't held back without a ramp.
+    pub fn new(inner: S, slow_start: Option<SlowStart>) -> Self {
+        Self {
+            inner,
+            ramp: slow_start.map(|slow_start| Ramp {
+                slow_start,
+                semaphore: PollSemaphore::new(Arc::new(Semaphore::new(0))),
+                granted: 0,
+                started: None,
+                step: None,
+            }),
+            permit: None,
+        }
+    }
+
+    fn poll_permit(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            let ramp = match &mut self.ramp {
+                Some(ramp) if self.permit.is_none() => ramp,
+                _ => return Poll::Ready(()),
+            };
+            let now = Instant::now();
+            let started = *ramp.started.get_or_insert(now);
+            let limit = ramp.slow_start.limit(now - started);
+            if limit >= ramp.slow_start.max_concurrency {
+                // The permits still held are released into the dropped semaphore.
+                self.ramp = None;
+                return Poll::Ready(());
+            }
+            if limit > ramp.granted {
+                ramp.semaphore.add_permits(limit - ramp.granted);
+                ramp.granted = limit;
+            }
+            if let Poll::Ready(permit) = ramp.semaphore.poll_acquire(cx) {
+                self.permit = permit;
+                return Poll::Ready(());
+            }
+            let deadline = started + ramp.slow_start.next_step(limit);
+            let step = ramp
+                .step
+                .get_or_insert_with(|| Box::pin(sleep_until(deadline)));
+            step.as_mut().reset(deadline);
+            if step.poll_unpin(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<S> Service<AzureBlobRequest> for SlowStartService<S>
+where
+    S: Service<AzureBlobRequest>,
+    S::Error: Into<crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        futures::ready!(self.poll_permit(cx));
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: AzureBlobRequest) -> Self::Future {
+        let permit = self.permit.take();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let result = future.await;
+            drop(permit);
+            result.map_err(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tower::{ServiceBuilder, ServiceExt};
+
+    use super::*;
+    use crate::sinks::azure_common::test_util::mock_request;
+
+    #[tokio::test]
+    async fn ramps_concurrency_up() {
+        tokio::time::pause();
+        let slow_start = SlowStartConfig {
+            initial_concurrency: 1,
+            warm_up_secs: 4,
+        }
+        .build(Some(4))
+        .unwrap();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let inner = {
+            let in_flight = Arc::clone(&in_flight);
+            tower::service_fn(move |_request: AzureBlobRequest| {
+                let in_flight = Arc::clone(&in_flight);
+                async move {
+                    in_flight.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok::<_, crate::Error>(())
+                }
+            })
+        };
+        // The fixed `request.concurrency` the ramp goes up to.
+        let mut service = SlowStartService::new(
+            ServiceBuilder::new().concurrency_limit(4).service(inner),
+            Some(slow_start),
+        );
+        tokio::spawn(async move {
+            loop {
+                let call = service.ready().await.unwrap().call(mock_request());
+                tokio::spawn(call);
+            }
+        });
+
+        let mut elapsed = Duration::ZERO;
+        for (at, expected) in [
+            (500, 1),
+            (1500, 2),
+            (3000, 3),
+            (3900, 3),
+            (4100, 4),
+            (10_000, 4),
+        ] {
+            let at = Duration::from_millis(at);
+            tokio::time::sleep(at - elapsed).await;
+            elapsed = at;
+            assert_eq!(
+                in_flight.load(Ordering::SeqCst),
+                expected,
+                "in flight after {:?}",
+                at
+            );
+        }
+    }
+
+    #[test]
+    fn validation() {
+        let slow_start = |initial_concurrency, warm_up_secs| SlowStartConfig {
+            initial_concurrency,
+            warm_up_secs,
+        };
+        assert!(slow_start(1, 30).build(Some(16)).is_ok());
+        // Adaptive concurrency already starts from a single request.
+        assert!(slow_start(1, 30).build(None).is_err());
+        assert!(slow_start(16, 30).build(Some(16)).is_err());
+        assert!(slow_start(0, 30).build(Some(16)).is_err());
+        assert!(slow_start(1, 0).build(Some(16)).is_err());
+    }
+}
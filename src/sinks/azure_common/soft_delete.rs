@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use azure_core::{HttpClient, HttpError};
+use azure_storage::prelude::*;
+use azure_storage_blobs::prelude::*;
+use http::{Method, StatusCode};
+
+use crate::{
+    internal_events::azure_blob::{AzureBlobSoftDeleteEnabled, AzureBlobSoftDeleteUnknown},
+    sinks::azure_common::config::OverwritePolicy,
+};
+
+/// Deletes the blob named `blob` for good, along with its snapshots, so that overwriting it
+/// doesn't leave its previous content soft-deleted until the retention period has elapsed.
+///
+/// Azure only deletes blobs permanently once they are soft-deleted, so the blob is soft-deleted
+/// first. The permanent delete is sent even when there was no blob left to soft-delete, which
+/// purges what a previous attempt soft-deleted before failing. Returns whether a blob was deleted.
+pub async fn delete_permanently(
+    container: &Arc<ContainerClient>,
+    blob: &str,
+) -> crate::Result<bool> {
+    let account = container.storage_client().storage_account_client();
+    let mut url = Arc::clone(container)
+        .as_blob_client(blob)
+        .url_with_segments(None)?;
+    let (request, _) = account.prepare_request(
+        url.as_str(),
+        &Method::DELETE,
+        ServiceType::Blob,
+        &|builder| builder.header("x-ms-delete-snapshots", "include"),
+        None,
+    )?;
+    let deleted = account
+        .http_client()
+        .execute_request_check_status(request, StatusCode::ACCEPTED)
+        .await
+        .map_err(crate::Error::from);
+    let deleted = match deleted {
+        Ok(_) => true,
+        Err(reason) if is_not_found(reason.as_ref()) => false,
+        Err(reason) => return Err(reason),
+    };
+
+    url.query_pairs_mut().append_pair("deletetype", "permanent");
+    let (request, _) = account.prepare_request(
+        url.as_str(),
+        &Method::DELETE,
+        ServiceType::Blob,
+        &|builder| builder,
+        None,
+    )?;
+    let purged = account
+        .http_client()
+        .execute_request_check_status(request, StatusCode::ACCEPTED)
+        .await
+        .map_err(crate::Error::from);
+    match purged {
+        // Without soft delete, the blob is already gone for good.
+        Err(reason) if !is_not_found(reason.as_ref()) => Err(reason),
+        _ => Ok(deleted),
+    }
+}
+
+fn is_not_found(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        error.downcast_ref::<HttpError>(),
+        Some(HttpError::StatusCode { status, .. }) if *status == StatusCode::NOT_FOUND
+    )
+}
+
+/// Reads the blob soft delete policy of the storage account of `client`, reporting whether the
+/// blobs overwritten by the sink keep their previous content around.
+///
+/// Only the credentials with access to the properties of the account's blob service can read the
+/// policy, so failing to read it is reported without failing the healthcheck.
+pub async fn check_soft_delete(client: Arc<ContainerClient>, overwrite: OverwritePolicy) {
+    match delete_retention_days(&client).await {
+        Ok(Some(retention_days)) => emit!(AzureBlobSoftDeleteEnabled {
+            retention_days,
+            policy: overwrite.as_str(),
+            retained: overwrite == OverwritePolicy::Allow,
+        }),
+        Ok(None) => (),
+        Err(error) => emit!(AzureBlobSoftDeleteUnknown {
+            error: error.to_string(),
+        }),
+    }
+}
+
+/// The number of days soft-deleted blobs are retained by the storage account of `client`, or
+/// `None` when soft delete is disabled.
+async fn delete_retention_days(client: &ContainerClient) -> crate::Result<Option<u32>> {
+    let account = client.storage_client().storage_account_client();
+    let mut url = client.url_with_segments(None)?;
+    url.path_segments_mut()
+        .map_err(|()| "the URL of the container can't be a base")?
+        .pop();
+    url.query_pairs_mut()
+        .append_pair("restype", "service")
+        .append_pair("comp", "properties");
+    let (request, _) = account.prepare_request(
+        url.as_str(),
+        &Method::GET,
+        ServiceType::Blob,
+        &|builder| builder,
+        None,
+    )?;
+    let response = account
+        .http_client()
+        .execute_request_check_status(request, StatusCode::OK)
+        .await?;
+    parse_delete_retention_days(&String::from_utf8_lossy(response.body()))
+}
+
+/// Parses the retention period out of the `DeleteRetentionPolicy` of the blob service properties.
+pub(crate) fn parse_delete_retention_days(properties: &str) -> crate::Result<Option<u32>> {
+    let policy = element(properties, "DeleteRetentionPolicy")
+        .ok_or("the blob service properties have no `DeleteRetentionPolicy`")?;
+    if element(policy, "Enabled") != Some("true") {
+        return Ok(None);
+    }
+    let days = element(policy, "Days")
+        .and_then(|days| days.parse().ok())
+        .ok_or("the enabled `DeleteRetentionPolicy` has no valid `Days`")?;
+    Ok(Some(days))
+}
+
+/// The content of the first `name` element of `xml`.
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", name))?;
+    Some(xml[start..start + end].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::sinks::azure_common::test_util::mock_client;
+
+    fn service_properties(policy: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?><StorageServiceProperties>\
+             <DeleteRetentionPolicy>{}</DeleteRetentionPolicy>\
+             <ContainerDeleteRetentionPolicy><Enabled>true</Enabled><Days>30</Days>\
+             </ContainerDeleteRetentionPolicy></StorageServiceProperties>",
+            policy
+        )
+    }
+
+    #[test]
+    fn parses_delete_retention_policy() {
+        assert_eq!(
+            parse_delete_retention_days(&service_properties(
+                "<Enabled>true</Enabled><Days>7</Days>"
+            ))
+            .unwrap(),
+            Some(7)
+        );
+        // The retention of the deleted containers doesn't matter to the blobs overwritten.
+        assert_eq!(
+            parse_delete_retention_days(&service_properties("<Enabled>false</Enabled>")).unwrap(),
+            None
+        );
+        assert!(
+            parse_delete_retention_days(&service_properties("<Enabled>true</Enabled>")).is_err()
+        );
+        assert!(parse_delete_retention_days("<StorageServiceProperties/>").is_err());
+    }
+
+    #[tokio::test]
+    async fn reads_retention_from_account_properties() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/devstoreaccount1"))
+            .and(query_param("restype", "service"))
+            .and(query_param("comp", "properties"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(service_properties("<Enabled>true</Enabled><Days>14</Days>")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let retention_days = delete_retention_days(&mock_client(&server).await)
+            .await
+            .unwrap();
+        assert_eq!(retention_days, Some(14));
+    }
+
+    #[tokio::test]
+    async fn deletes_soft_deleted_blob_permanently() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/devstoreaccount1/logs/blob/test.log"))
+            .and(header("x-ms-delete-snapshots", "include"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/devstoreaccount1/logs/blob/test.log"))
+            .and(query_param("deletetype", "permanent"))
+            .respond_with(
+                ResponseTemplate::new(202).insert_header("x-ms-delete-type-permanent", "true"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server).await;
+        assert!(delete_permanently(&client, "blob/test.log").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn deleting_missing_blob_still_purges() {
+        let server = MockServer::start().await;
+        // What a failed attempt soft-deleted is purged by the next one.
+        Mock::given(method("DELETE"))
+            .and(header("x-ms-delete-snapshots", "include"))
+            .respond_with(
+                ResponseTemplate::new(404).insert_header("x-ms-error-code", "BlobNotFound"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(query_param("deletetype", "permanent"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server).await;
+        assert!(!delete_permanently(&client, "blob/test.log").await.unwrap());
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{select, sync::watch, time::interval};
+
+use crate::internal_events::azure_blob::AzureBlobBatchSummary;
+
+/// Totals accumulated for a single container over one summary interval.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BatchTotals {
+    pub events: usize,
+    pub logical_bytes: usize,
+    pub compressed_bytes: usize,
+    pub blobs: usize,
+}
+
+impl BatchTotals {
+    /// The average compressed size of the blobs written, unless none were.
+    pub fn average_blob_bytes(&self) -> Option<f64> {
+        (self.blobs > 0).then(|| self.compressed_bytes as f64 / self.blobs as f64)
+    }
+
+    /// The number of blobs written per event, unless no events were. The closer to 1, the more
+    /// the writes are amplified by small batches.
+    pub fn blobs_per_event(&self) -> Option<f64> {
+        (self.events > 0).then(|| self.blobs as f64 / self.events as f64)
+    }
+}
+
+/// Aggregates the blobs written per container and periodically emits them as an
+/// `AzureBlobBatchSummary` event, which also sets the write amplification gauges of the
+/// container over the interval.
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    totals: Mutex<HashMap<String, BatchTotals>>,
+}
+
+impl BatchSummary {
+    pub fn record(&self, container: &str, events: usize, logical_bytes: usize, bytes: usize) {
+        let mut totals = self.totals.lock().expect("summary lock poisoned");
+        let totals = totals.entry(container.to_string()).or_default();
+        totals.events += events;
+        totals.logical_bytes += logical_bytes;
+        totals.compressed_bytes += bytes;
+        totals.blobs += 1;
+    }
+
+    /// Returns the totals accumulated since the last call, sorted by container.
+    pub fn take(&self) -> Vec<(String, BatchTotals)> {
+        let totals = mem::take(&mut *self.totals.lock().expect("summary lock poisoned"));
+        let mut totals = totals.into_iter().collect::<Vec<_>>();
+        totals.sort_by(|a, b| a.0.cmp(&b.0));
+        totals
+    }
+
+    fn emit(&self, interval: Duration) {
+        for (container, totals) in self.take() {
+            emit!(AzureBlobBatchSummary {
+                container: container.as_str(),
+                totals,
+                interval,
+            });
+        }
+    }
+
+    /// Emits a summary every `period` and a final one once `shutdown` fires.
+    pub async fn run(self: Arc<Self>, period: Duration, mut shutdown: watch::Receiver<()>) {
+        let mut summary_interval = interval(period);
+        // The first tick completes immediately.
+        summary_interval.tick().await;
+        loop {
+            select! {
+                _ = summary_interval.tick() => self.emit(period),
+                _ = shutdown.changed() => break,
+            }
+        }
+        self.emit(period);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::azure_common::test_util::gauge_value;
+
+    #[test]
+    fn aggregates_per_container() {
+        let summary = BatchSummary::default();
+        summary.record("logs", 10, 1000, 120);
+        summary.record("metrics", 1, 50, 40);
+        summary.record("logs", 5, 500, 80);
+
+        assert_eq!(
+            summary.take(),
+            vec![
+                (
+                    "logs".to_string(),
+                    BatchTotals {
+                        events: 15,
+                        logical_bytes: 1500,
+                        compressed_bytes: 200,
+                        blobs: 2,
+                    }
+                ),
+                (
+                    "metrics".to_string(),
+                    BatchTotals {
+                        events: 1,
+                        logical_bytes: 50,
+                        compressed_bytes: 40,
+                        blobs: 1,
+                    }
+                ),
+            ]
+        );
+        // Every interval starts from scratch.
+        assert!(summary.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn sets_write_amplification_gauges() {
+        crate::test_util::components::init_test();
+        let summary = Arc::new(BatchSummary::default());
+        // Four small blobs of a few events each.
+        summary.record("amplification", 2, 300, 100);
+        summary.record("amplification", 1, 200, 60);
+        summary.record("amplification", 3, 600, 180);
+        summary.record("amplification", 2, 200, 60);
+
+        // The final summary is emitted once the shutdown fires, which it does as its sender is gone.
+        let (_, shutdown) = watch::channel(());
+        summary.run(Duration::from_secs(3600), shutdown).await;
+
+        assert_eq!(
+            gauge_value(
+                "azure_blob_average_blob_size_bytes",
+                &[("container", "amplification")]
+            ),
+            Some(100.0)
+        );
+        assert_eq!(
+            gauge_value(
+                "azure_blob_blobs_per_event",
+                &[("container", "amplification")]
+            ),
+            Some(0.5)
+        );
+    }
+}
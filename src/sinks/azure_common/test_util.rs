@@ -0,0 +1,186 @@
+//! Fixtures shared by the tests of the Azure Blob Storage sink and its components.
+
+use std::sync::Arc;
+
+use azure_storage_blobs::prelude::ContainerClient;
+use bytes::Bytes;
+use wiremock::{MockServer, ResponseTemplate};
+
+use super::config::{build_client, AzureBlobMetadata, AzureBlobRequest, ClientOptions};
+use crate::event::MetricValue;
+
+pub(crate) const MOCK_ETAG: &str = "\"0x8DA2B2A2F4F0C8E\"";
+
+/// Response of the mock storage account to writing the blob of `mock_request`.
+pub(crate) fn mock_put_response() -> ResponseTemplate {
+    ResponseTemplate::new(201)
+        .insert_header("etag", MOCK_ETAG)
+        .insert_header("last-modified", "Thu, 01 Sep 2022 00:00:00 GMT")
+        .insert_header("content-md5", "8RpCWQYomr+MzhczYig0yA==")
+        .insert_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+        .insert_header("date", "Thu, 01 Sep 2022 00:00:00 GMT")
+        .insert_header("x-ms-request-server-encrypted", "true")
+}
+
+/// Response of the mock storage account to appending a block to an append blob.
+pub(crate) fn mock_append_response() -> ResponseTemplate {
+    ResponseTemplate::new(201)
+        .insert_header("etag", MOCK_ETAG)
+        .insert_header("last-modified", "Thu, 01 Sep 2022 00:00:00 GMT")
+        .insert_header("content-md5", "8RpCWQYomr+MzhczYig0yA==")
+        .insert_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+        .insert_header("date", "Thu, 01 Sep 2022 00:00:00 GMT")
+        .insert_header("x-ms-blob-append-offset", "0")
+        .insert_header("x-ms-blob-committed-block-count", "1")
+        .insert_header("x-ms-request-server-encrypted", "true")
+}
+
+/// Response of the mock storage account to writing a blob only if absent, when it already exists.
+pub(crate) fn mock_blob_exists_response() -> ResponseTemplate {
+    ResponseTemplate::new(409)
+        .insert_header("x-ms-error-code", "BlobAlreadyExists")
+        .set_body_string(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?><Error><Code>BlobAlreadyExists</Code>\
+             <Message>The specified blob already exists.</Message></Error>",
+        )
+}
+
+/// Response of the mock storage account to reading the properties of an existing blob.
+pub(crate) fn mock_properties_response(content_length: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200)
+        .insert_header("content-length", content_length)
+        .insert_header("content-type", "text/plain")
+        .insert_header("content-md5", "8RpCWQYomr+MzhczYig0yA==")
+        .insert_header("etag", MOCK_ETAG)
+        .insert_header("last-modified", "Thu, 01 Sep 2022 00:00:00 GMT")
+        .insert_header("x-ms-creation-time", "Thu, 01 Sep 2022 00:00:00 GMT")
+        .insert_header("x-ms-blob-type", "BlockBlob")
+        .insert_header("x-ms-lease-state", "available")
+        .insert_header("x-ms-lease-status", "unlocked")
+        .insert_header("x-ms-server-encrypted", "true")
+        .insert_header("x-ms-access-tier", "Hot")
+        .insert_header("x-ms-access-tier-inferred", "true")
+        .insert_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+        .insert_header("date", "Thu, 01 Sep 2022 00:00:00 GMT")
+}
+
+/// Response of the mock storage account to reading the properties of the container.
+pub(crate) fn mock_container_properties_response() -> ResponseTemplate {
+    ResponseTemplate::new(200)
+        .insert_header("etag", MOCK_ETAG)
+        .insert_header("last-modified", "Thu, 01 Sep 2022 00:00:00 GMT")
+        .insert_header("x-ms-lease-status", "unlocked")
+        .insert_header("x-ms-lease-state", "available")
+        .insert_header("x-ms-has-immutability-policy", "false")
+        .insert_header("x-ms-has-legal-hold", "false")
+        .insert_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+        .insert_header("date", "Thu, 01 Sep 2022 00:00:00 GMT")
+}
+
+/// Response of the mock storage account to a request sent to a container that doesn't exist.
+pub(crate) fn mock_not_found_response(error_code: &str) -> ResponseTemplate {
+    ResponseTemplate::new(404)
+        .insert_header("x-ms-error-code", error_code)
+        .set_body_string(format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?><Error><Code>{}</Code>\
+             <Message>The specified resource does not exist.</Message></Error>",
+            error_code
+        ))
+}
+
+/// The connection string of the mock storage account served by `server`.
+pub(crate) fn mock_connection_string(server: &MockServer) -> String {
+    format!(
+        "DefaultEndpointsProtocol=http;AccountName=devstoreaccount1;AccountKey=Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==;BlobEndpoint={}/devstoreaccount1;",
+        server.uri()
+    )
+}
+
+/// A client of the `logs` container of the mock storage account served by `server`.
+pub(crate) async fn mock_client(server: &MockServer) -> Arc<ContainerClient> {
+    build_client(ClientOptions {
+        connection_string: Some(mock_connection_string(server)),
+        container_name: "logs".into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap()
+}
+
+/// A request writing a single event to `blob/test.log`.
+pub(crate) fn mock_request() -> AzureBlobRequest {
+    AzureBlobRequest {
+        blob_data: Bytes::from("test message\n"),
+        content_encoding: None,
+        content_type: "text/plain".into(),
+        metadata: AzureBlobMetadata {
+            partition_key: "blob/test.log".into(),
+            partition: "blob/".into(),
+            count: 1,
+            byte_size: 13,
+            finalizers: Default::default(),
+            event_time_range: None,
+            source_types: None,
+            zstd_dictionary_id: None,
+            computed_metadata: Vec::new(),
+            oldest_event_time: None,
+            blob_type: Default::default(),
+            schema_fingerprint: None,
+            schema_version: None,
+            append_sequence: None,
+            content_language: None,
+        },
+        attempts: Default::default(),
+        partition_permit: Default::default(),
+        batched_finalizers: Default::default(),
+        finalizers_taken: false,
+    }
+}
+
+/// The number of blobs written to the mock storage account so far.
+pub(crate) async fn received_puts(server: &MockServer) -> usize {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.method == wiremock::http::Method::Put)
+        .count()
+}
+
+/// The values captured so far of the metric of the given name, from its series holding all the
+/// given tags.
+pub(crate) fn metric_values(name: &str, tags: &[(&str, &str)]) -> Vec<MetricValue> {
+    vector_core::metrics::Controller::get()
+        .unwrap()
+        .capture_metrics()
+        .into_iter()
+        .filter(|metric| metric.name() == name)
+        .filter(|metric| {
+            tags.iter()
+                .all(|(tag, value)| metric.tag_value(tag).as_deref() == Some(*value))
+        })
+        .map(|metric| metric.value().clone())
+        .collect()
+}
+
+/// The value of the counter of the given name, summed over its series holding the given tags.
+pub(crate) fn counter_value(name: &str, tags: &[(&str, &str)]) -> f64 {
+    metric_values(name, tags)
+        .into_iter()
+        .map(|value| match value {
+            MetricValue::Counter { value } => value,
+            _ => 0.0,
+        })
+        .sum()
+}
+
+/// The value of the gauge of the given name, from its first series holding the given tags.
+pub(crate) fn gauge_value(name: &str, tags: &[(&str, &str)]) -> Option<f64> {
+    metric_values(name, tags)
+        .into_iter()
+        .find_map(|value| match value {
+            MetricValue::Gauge { value } => Some(value),
+            _ => None,
+        })
+}
@@ -0,0 +1,464 @@
+use std::sync::Arc;
+
+use azure_core::auth::TokenCredential;
+use azure_identity::AutoRefreshingTokenCredential;
+use bytes::Bytes;
+use futures::{FutureExt, SinkExt};
+use http::{header, Request, StatusCode, Uri};
+use hyper::Body;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    codecs::Transformer,
+    config::{log_schema, AcknowledgementsConfig, Input, SinkConfig, SinkContext, SinkDescription},
+    event::{Event, Value},
+    http::{HttpClient, HttpError},
+    sinks::{
+        azure_common::{broker::TokenBrokerConfig, config::build_credential, imds},
+        util::{
+            http::{BatchedHttpSink, HttpEventEncoder, HttpSink},
+            retries::{RetryAction, RetryLogic},
+            BatchConfig, BoxedRawValue, JsonArrayBuffer, RealtimeSizeBasedDefaultBatchSettings,
+            TowerRequestConfig,
+        },
+        Healthcheck, VectorSink,
+    },
+    tls::{TlsConfig, TlsSettings},
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AzureLogsIngestionConfig {
+    /// The logs ingestion endpoint of the Data Collection Endpoint.
+    pub endpoint: String,
+    /// The immutable ID of the Data Collection Rule.
+    pub dcr_immutable_id: String,
+    /// The stream of the Data Collection Rule the logs are sent to.
+    pub stream_name: String,
+    pub imds_endpoint: Option<String>,
+    pub token_broker: Option<TokenBrokerConfig>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub encoding: Transformer,
+    #[serde(default)]
+    pub batch: BatchConfig<RealtimeSizeBasedDefaultBatchSettings>,
+    #[serde(default)]
+    pub request: TowerRequestConfig,
+    pub tls: Option<TlsConfig>,
+    #[serde(
+        default,
+        deserialize_with = "crate::serde::bool_or_struct",
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    acknowledgements: AcknowledgementsConfig,
+}
+
+inventory::submit! {
+    SinkDescription::new::<AzureLogsIngestionConfig>("azure_logs_ingestion")
+}
+
+impl_generate_config_from_default!(AzureLogsIngestionConfig);
+
+/// Max number of bytes in request body
+const MAX_BATCH_SIZE: usize = 1024 * 1024;
+/// API version
+const API_VERSION: &str = "2023-01-01";
+/// Resource the bearer tokens are requested for
+const TOKEN_RESOURCE: &str = "https://monitor.azure.com";
+/// JSON content type of logs
+const CONTENT_TYPE: &str = "application/json";
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "azure_logs_ingestion")]
+impl SinkConfig for AzureLogsIngestionConfig {
+    async fn build(&self, cx: SinkContext) -> crate::Result<(VectorSink, Healthcheck)> {
+        let batch_settings = self
+            .batch
+            .validate()?
+            .limit_max_bytes(MAX_BATCH_SIZE)?
+            .into_batch_settings()?;
+
+        let tls_settings = TlsSettings::from_options(&self.tls)?;
+        let client = HttpClient::new(Some(tls_settings), &cx.proxy)?;
+
+        let sink = AzureLogsIngestionSink::new(self)?;
+        let request_settings = self.request.unwrap_with(&TowerRequestConfig::default());
+
+        let healthcheck = healthcheck(sink.clone(), client.clone()).boxed();
+
+        let sink = BatchedHttpSink::with_logic(
+            sink,
+            JsonArrayBuffer::new(batch_settings.size),
+            LogsIngestionRetryLogic,
+            request_settings,
+            batch_settings.timeout,
+            client,
+        )
+        .sink_map_err(|error| error!(message = "Fatal azure_logs_ingestion sink error.", %error));
+
+        Ok((VectorSink::from_event_sink(sink), healthcheck))
+    }
+
+    fn input(&self) -> Input {
+        Input::log()
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "azure_logs_ingestion"
+    }
+
+    fn acknowledgements(&self) -> Option<&AcknowledgementsConfig> {
+        Some(&self.acknowledgements)
+    }
+}
+
+#[derive(Clone)]
+struct AzureLogsIngestionSink {
+    uri: Uri,
+    transformer: Transformer,
+    credential: Arc<dyn TokenCredential>,
+}
+
+struct AzureLogsIngestionEventEncoder {
+    transformer: Transformer,
+}
+
+impl HttpEventEncoder<JsonValue> for AzureLogsIngestionEventEncoder {
+    fn encode_event(&mut self, mut event: Event) -> Option<JsonValue> {
+        self.transformer.transform(&mut event);
+
+        // The ingestion API parses the timestamps of the stream columns as ISO 8601, with at most
+        // millisecond precision.
+        let mut log = event.into_log();
+        let timestamp_key = log_schema().timestamp_key();
+
+        let timestamp = if let Some(Value::Timestamp(ts)) = log.remove(timestamp_key) {
+            ts
+        } else {
+            chrono::Utc::now()
+        };
+
+        let mut entry = serde_json::json!(&log);
+        let object_entry = entry.as_object_mut().unwrap();
+        object_entry.insert(
+            timestamp_key.to_string(),
+            JsonValue::String(timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+        );
+
+        Some(entry)
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpSink for AzureLogsIngestionSink {
+    type Input = JsonValue;
+    type Output = Vec<BoxedRawValue>;
+    type Encoder = AzureLogsIngestionEventEncoder;
+
+    fn build_encoder(&self) -> Self::Encoder {
+        AzureLogsIngestionEventEncoder {
+            transformer: self.transformer.clone(),
+        }
+    }
+
+    async fn build_request(&self, events: Self::Output) -> crate::Result<Request<Bytes>> {
+        let token = self.credential.get_token(TOKEN_RESOURCE).await?;
+        self.build_request_with_token(events, token.token.secret())
+    }
+}
+
+impl AzureLogsIngestionSink {
+    fn new(config: &AzureLogsIngestionConfig) -> crate::Result<AzureLogsIngestionSink> {
+        if config.token_broker.is_some() && config.imds_endpoint.is_some() {
+            return Err("`token_broker` and `imds_endpoint` can't be configured together".into());
+        }
+        let imds_endpoint = config
+            .imds_endpoint
+            .as_deref()
+            .map(imds::parse_endpoint)
+            .transpose()?;
+        let token_broker = config
+            .token_broker
+            .as_ref()
+            .map(TokenBrokerConfig::build)
+            .transpose()?;
+        let credential = Arc::new(AutoRefreshingTokenCredential::new(build_credential(
+            imds_endpoint,
+            token_broker,
+        )));
+
+        Ok(AzureLogsIngestionSink {
+            uri: build_uri(config)?,
+            transformer: config.encoding.clone(),
+            credential,
+        })
+    }
+
+    fn build_request_with_token(
+        &self,
+        events: Vec<BoxedRawValue>,
+        token: &str,
+    ) -> crate::Result<Request<Bytes>> {
+        let body = crate::serde::json::to_bytes(&events)?.freeze();
+
+        Request::post(self.uri.clone())
+            .header(header::CONTENT_TYPE, CONTENT_TYPE)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(body)
+            .map_err(Into::into)
+    }
+}
+
+/// The URL of the stream of the Data Collection Rule on the Data Collection Endpoint.
+fn build_uri(config: &AzureLogsIngestionConfig) -> crate::Result<Uri> {
+    let endpoint = config.endpoint.trim_end_matches('/');
+    if endpoint.is_empty() {
+        return Err("endpoint can't be an empty string".into());
+    }
+    if config.dcr_immutable_id.is_empty() {
+        return Err("dcr_immutable_id can't be an empty string".into());
+    }
+    if config.stream_name.is_empty() {
+        return Err("stream_name can't be an empty string".into());
+    }
+
+    let uri: Uri = format!(
+        "{}/dataCollectionRules/{}/streams/{}?api-version={}",
+        endpoint, config.dcr_immutable_id, config.stream_name, API_VERSION
+    )
+    .parse()?;
+    if uri.scheme().is_none() || uri.host().is_none() {
+        return Err(format!(
+            "invalid endpoint \"{}\": expected an absolute URL",
+            endpoint
+        )
+        .into());
+    }
+    Ok(uri)
+}
+
+/// Retries the throttled requests, the server errors and the requests that failed to be sent, the
+/// other rejections of a batch being permanent.
+///
+/// The requests that timed out are retried by the retry policy itself.
+#[derive(Debug, Default, Clone)]
+struct LogsIngestionRetryLogic;
+
+impl RetryLogic for LogsIngestionRetryLogic {
+    type Error = HttpError;
+    type Response = http::Response<Bytes>;
+
+    fn is_retriable_error(&self, error: &Self::Error) -> bool {
+        // A request that couldn't be built would fail the same way on every attempt.
+        error.is_retriable()
+    }
+
+    fn should_retry_response(&self, response: &Self::Response) -> RetryAction {
+        let status = response.status();
+
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => RetryAction::Retry("too many requests".into()),
+            _ if status.is_server_error() => RetryAction::Retry(
+                format!("{}: {}", status, String::from_utf8_lossy(response.body())).into(),
+            ),
+            _ if status.is_success() => RetryAction::Successful,
+            _ => RetryAction::DontRetry(
+                format!("{}: {}", status, String::from_utf8_lossy(response.body())).into(),
+            ),
+        }
+    }
+}
+
+/// Sends an empty batch, which the Data Collection Endpoint accepts once both it and the stream of
+/// the Data Collection Rule are reachable with the credential.
+async fn healthcheck(sink: AzureLogsIngestionSink, client: HttpClient) -> crate::Result<()> {
+    let request = sink.build_request(vec![]).await?.map(Body::from);
+
+    let res = client.send(request).await?;
+    let status = res.status();
+
+    if status.is_success() {
+        return Ok(());
+    }
+
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err("The service failed to authenticate the request. Verify that the credential is granted the Monitoring Metrics Publisher role on the Data Collection Rule".into());
+    }
+
+    if status == StatusCode::NOT_FOUND {
+        return Err("Either the endpoint provided is incorrect, or the Data Collection Rule or its stream doesn't exist".into());
+    }
+
+    Err(format!("Unexpected status: {}", status).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use azure_core::auth::{AccessToken, TokenResponse};
+    use serde_json::value::RawValue;
+
+    use super::*;
+    use crate::event::LogEvent;
+
+    #[test]
+    fn generate_config() {
+        crate::test_util::test_generate_config::<AzureLogsIngestionConfig>();
+    }
+
+    struct StaticCredential;
+
+    #[async_trait::async_trait]
+    impl TokenCredential for StaticCredential {
+        async fn get_token(&self, resource: &str) -> Result<TokenResponse, azure_core::Error> {
+            assert_eq!(resource, TOKEN_RESOURCE);
+            Ok(TokenResponse::new(
+                AccessToken::new("token".to_owned()),
+                chrono::Utc::now() + chrono::Duration::hours(1),
+            ))
+        }
+    }
+
+    fn test_config() -> AzureLogsIngestionConfig {
+        toml::from_str(
+            r#"
+            endpoint = "https://vector-dce.westeurope-1.ingest.monitor.azure.com/"
+            dcr_immutable_id = "dcr-00000000000000000000000000000000"
+            stream_name = "Custom-Vector_CL"
+        "#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn correct_request() {
+        let config = test_config();
+        let sink = AzureLogsIngestionSink {
+            uri: build_uri(&config).unwrap(),
+            transformer: Default::default(),
+            credential: Arc::new(StaticCredential),
+        };
+        let mut encoder = sink.build_encoder();
+
+        let mut log = [("message", "hello")].iter().copied().collect::<LogEvent>();
+        let timestamp = chrono::Utc::now();
+        log.insert(log_schema().timestamp_key(), timestamp);
+
+        let event = encoder.encode_event(Event::from(log)).unwrap();
+        let raw = RawValue::from_string(serde_json::to_string(&event).unwrap()).unwrap();
+
+        let (parts, body) = sink.build_request(vec![raw]).await.unwrap().into_parts();
+        assert_eq!(&parts.method.to_string(), "POST");
+        assert_eq!(
+            &parts.uri.to_string(),
+            "https://vector-dce.westeurope-1.ingest.monitor.azure.com/dataCollectionRules/dcr-00000000000000000000000000000000/streams/Custom-Vector_CL?api-version=2023-01-01"
+        );
+        assert_eq!(
+            parts
+                .headers
+                .get("authorization")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "Bearer token"
+        );
+        assert_eq!(
+            parts.headers.get("content-type").unwrap().to_str().unwrap(),
+            "application/json"
+        );
+
+        let json: serde_json::Value = serde_json::from_slice(&body[..]).unwrap();
+        let expected_json = serde_json::json!([{
+            log_schema().timestamp_key(): timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "message": "hello"
+        }]);
+        assert_eq!(json, expected_json);
+    }
+
+    #[test]
+    fn fails_invalid_stream() {
+        let mut config = test_config();
+        config.stream_name = String::new();
+        build_uri(&config).expect_err("empty stream_name should error");
+
+        let mut config = test_config();
+        config.endpoint = "vector-dce".to_owned();
+        build_uri(&config).expect_err("relative endpoint should error");
+    }
+
+    #[test]
+    fn fails_imds_endpoint_with_token_broker() {
+        let config: AzureLogsIngestionConfig = toml::from_str(
+            r#"
+            endpoint = "https://vector-dce.westeurope-1.ingest.monitor.azure.com"
+            dcr_immutable_id = "dcr-00000000000000000000000000000000"
+            stream_name = "Custom-Vector_CL"
+            imds_endpoint = "http://169.254.169.254/metadata/identity/oauth2/token"
+            token_broker.type = "command"
+            token_broker.command = ["get-token"]
+        "#,
+        )
+        .unwrap();
+        assert!(AzureLogsIngestionSink::new(&config).is_err());
+    }
+
+    #[test]
+    fn retries_throttling_and_server_errors() {
+        let logic = LogsIngestionRetryLogic;
+        let response = |status: StatusCode| {
+            http::Response::builder()
+                .status(status)
+                .body(Bytes::from_static(b"{}"))
+                .unwrap()
+        };
+
+        for status in [
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ] {
+            assert!(
+                matches!(
+                    logic.should_retry_response(&response(status)),
+                    RetryAction::Retry(_)
+                ),
+                "{} should be retried",
+                status
+            );
+        }
+
+        for status in [
+            StatusCode::BAD_REQUEST,
+            StatusCode::UNAUTHORIZED,
+            StatusCode::FORBIDDEN,
+            StatusCode::NOT_FOUND,
+            StatusCode::PAYLOAD_TOO_LARGE,
+        ] {
+            assert!(
+                matches!(
+                    logic.should_retry_response(&response(status)),
+                    RetryAction::DontRetry(_)
+                ),
+                "{} should not be retried",
+                status
+            );
+        }
+
+        assert!(matches!(
+            logic.should_retry_response(&response(StatusCode::NO_CONTENT)),
+            RetryAction::Successful
+        ));
+    }
+
+    #[test]
+    fn does_not_retry_requests_failing_to_build() {
+        let source = http::Request::builder()
+            .uri("not a uri")
+            .body(())
+            .unwrap_err();
+        assert!(!LogsIngestionRetryLogic.is_retriable_error(&HttpError::BuildRequest { source }));
+    }
+}
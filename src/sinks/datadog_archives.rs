@@ -209,11 +209,13 @@ impl DatadogArchivesSinkConfig {
                     .azure_blob
                     .as_ref()
                     .expect("azure blob config wasn't provided");
-                let client = azure_common::config::build_client(
-                    Some(azure_config.connection_string.clone()),
-                    None,
-                    self.bucket.clone(),
-                )?;
+                let client =
+                    azure_common::config::build_client(azure_common::config::ClientOptions {
+                        connection_string: Some(azure_config.connection_string.clone()),
+                        container_name: self.bucket.clone(),
+                        ..Default::default()
+                    })
+                    .await?;
                 let svc = self
                     .build_azure_sink(Arc::<ContainerClient>::clone(&client))
                     .map_err(|error| error.to_string())?;
@@ -688,10 +690,21 @@ impl RequestBuilder<(String, Vec<Event>)> for DatadogAzureRequestBuilder {
         let (partition_key, mut events) = input;
         let finalizers = events.take_finalizers();
         let metadata = AzureBlobMetadata {
+            partition: partition_key.clone(),
             partition_key,
             count: events.len(),
             byte_size: events.size_of(),
             finalizers,
+            event_time_range: None,
+            source_types: None,
+            zstd_dictionary_id: None,
+            computed_metadata: Vec::new(),
+            oldest_event_time: None,
+            blob_type: Default::default(),
+            schema_fingerprint: None,
+            schema_version: None,
+            append_sequence: None,
+            content_language: None,
         };
 
         (metadata, events)
@@ -718,8 +731,12 @@ impl RequestBuilder<(String, Vec<Event>)> for DatadogAzureRequestBuilder {
         AzureBlobRequest {
             blob_data,
             content_encoding: DEFAULT_COMPRESSION.content_encoding(),
-            content_type: "application/gzip",
+            content_type: "application/gzip".into(),
             metadata,
+            attempts: Default::default(),
+            partition_permit: Default::default(),
+            batched_finalizers: Default::default(),
+            finalizers_taken: false,
         }
     }
 }
@@ -19,8 +19,10 @@ pub mod aws_sqs;
 pub mod axiom;
 #[cfg(feature = "sinks-azure_blob")]
 pub mod azure_blob;
-#[cfg(any(feature = "sinks-azure_blob", feature = "sinks-datadog_archives"))]
+#[cfg(feature = "azure_common")]
 pub mod azure_common;
+#[cfg(feature = "sinks-azure_logs_ingestion")]
+pub mod azure_logs_ingestion;
 #[cfg(feature = "sinks-azure_monitor_logs")]
 pub mod azure_monitor_logs;
 #[cfg(feature = "sinks-blackhole")]
@@ -152,6 +154,10 @@ pub enum Sinks {
     #[cfg(feature = "sinks-azure_blob")]
     AzureBlob(#[configurable(derived)] azure_blob::AzureBlobSinkConfig),
 
+    /// Azure Monitor Logs Ingestion.
+    #[cfg(feature = "sinks-azure_logs_ingestion")]
+    AzureLogsIngestion(#[configurable(derived)] azure_logs_ingestion::AzureLogsIngestionConfig),
+
     /// Azure Monitor Logs.
     #[cfg(feature = "sinks-azure_monitor_logs")]
     AzureMonitorLogs(#[configurable(derived)] azure_monitor_logs::AzureMonitorLogsConfig),
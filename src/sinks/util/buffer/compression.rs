@@ -220,8 +220,8 @@ impl ser::Serialize for Compression {
 pub struct CompressionLevel(flate2::Compression);
 
 impl CompressionLevel {
-    #[cfg(test)]
-    const fn new(level: u32) -> Self {
+    /// Creates a compression level from a number between 0 and 9.
+    pub const fn new(level: u32) -> Self {
         Self(flate2::Compression::new(level))
     }
 
@@ -34,7 +34,7 @@ pub use buffer::{
     json::{BoxedRawValue, JsonArrayBuffer},
     partition::Partition,
     vec::{EncodedLength, VecBuffer},
-    Buffer, Compression, PartitionBuffer, PartitionInnerBuffer,
+    Buffer, Compression, CompressionLevel, PartitionBuffer, PartitionInnerBuffer,
 };
 pub use builder::SinkBuilderExt;
 pub use compressor::Compressor;
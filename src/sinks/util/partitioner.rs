@@ -1,13 +1,132 @@
-use vector_core::{event::Event, partition::Partitioner};
+use chrono::{DateTime, Duration, Utc};
+use vector_common::TimeZone;
+use vector_core::{
+    event::{Event, Value},
+    partition::Partitioner,
+};
 
-use crate::{internal_events::TemplateRenderingError, template::Template};
+use crate::{config::log_schema, internal_events::TemplateRenderingError, template::Template};
+
+/// Bounds the event times used to render the strftime specifiers of a key.
+///
+/// Events timestamped outside of the window are rendered as if they were timestamped at its
+/// nearest edge, under their own prefix, rather than creating partitions far away from the
+/// current ones.
+#[derive(Clone, Debug)]
+pub struct TimeWindow {
+    pub max_future_skew: Option<Duration>,
+    pub max_past_age: Option<Duration>,
+    pub future_prefix: String,
+    pub late_prefix: String,
+}
+
+impl TimeWindow {
+    /// Returns the event retimed to the window edge along with the prefix of its key, if it falls
+    /// outside of the window.
+    fn clamp(&self, event: &Event, now: DateTime<Utc>) -> Option<(Event, &str)> {
+        let timestamp = match event {
+            Event::Log(log) => log
+                .get(log_schema().timestamp_key())?
+                .as_timestamp()
+                .copied(),
+            Event::Metric(metric) => metric.timestamp(),
+            Event::Trace(trace) => trace
+                .get(log_schema().timestamp_key())?
+                .as_timestamp()
+                .copied(),
+        }?;
+        // Windows reaching past the representable times don't bound that side at all.
+        let newest = self
+            .max_future_skew
+            .and_then(|skew| now.checked_add_signed(skew));
+        let oldest = self
+            .max_past_age
+            .and_then(|age| now.checked_sub_signed(age));
+        let (edge, prefix) = match (newest, oldest) {
+            (Some(newest), _) if timestamp > newest => (newest, &self.future_prefix),
+            (_, Some(oldest)) if timestamp < oldest => (oldest, &self.late_prefix),
+            _ => return None,
+        };
+
+        let event = match event.clone() {
+            Event::Log(mut log) => {
+                log.insert(log_schema().timestamp_key(), Value::Timestamp(edge));
+                Event::Log(log)
+            }
+            Event::Metric(metric) => Event::Metric(metric.with_timestamp(Some(edge))),
+            Event::Trace(mut trace) => {
+                trace.insert(log_schema().timestamp_key(), Value::Timestamp(edge));
+                Event::Trace(trace)
+            }
+        };
+        Some((event, prefix.as_str()))
+    }
+}
 
 /// Partitions items based on the generated key for the given event.
-pub struct KeyPartitioner(Template);
+#[derive(Clone)]
+pub struct KeyPartitioner {
+    template: Template,
+    timezone: Option<TimeZone>,
+    drop_on_error: bool,
+    time_window: Option<TimeWindow>,
+}
 
 impl KeyPartitioner {
     pub const fn new(template: Template) -> Self {
-        Self(template)
+        Self {
+            template,
+            timezone: None,
+            drop_on_error: true,
+            time_window: None,
+        }
+    }
+
+    /// Renders the strftime specifiers of the key in the given time zone instead of UTC.
+    pub const fn with_timezone(mut self, timezone: Option<TimeZone>) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Sets whether events whose key fails to render are reported as discarded.
+    ///
+    /// Set this to `false` when the caller still delivers those events somewhere.
+    pub const fn with_drop_on_error(mut self, drop_on_error: bool) -> Self {
+        self.drop_on_error = drop_on_error;
+        self
+    }
+
+    /// Bounds the event times used to render the key to the given window around the current time.
+    #[allow(clippy::missing_const_for_fn)] // const cannot run destructor
+    pub fn with_time_window(mut self, time_window: Option<TimeWindow>) -> Self {
+        self.time_window = time_window;
+        self
+    }
+
+    /// Generates the key of the event as of `now`, which the time window is relative to.
+    pub fn partition_at(&self, item: &Event, now: DateTime<Utc>) -> Option<String> {
+        let clamped = self
+            .time_window
+            .as_ref()
+            .and_then(|time_window| time_window.clamp(item, now));
+        let (item, prefix) = match &clamped {
+            Some((event, prefix)) => (event, *prefix),
+            None => (item, ""),
+        };
+
+        match self.timezone {
+            Some(timezone) => self.template.render_string_in_timezone(item, timezone),
+            None => self.template.render_string(item),
+        }
+        .map(|key| format!("{}{}", prefix, key))
+        .map_err(|error| {
+            emit!(TemplateRenderingError {
+                error,
+                field: Some("key_prefix"),
+                drop_event: self.drop_on_error,
+            });
+        })
+        .ok()
     }
 }
 
@@ -16,15 +135,6 @@ impl Partitioner for KeyPartitioner {
     type Key = Option<String>;
 
     fn partition(&self, item: &Self::Item) -> Self::Key {
-        self.0
-            .render_string(item)
-            .map_err(|error| {
-                emit!(TemplateRenderingError {
-                    error,
-                    field: Some("key_prefix"),
-                    drop_event: true,
-                });
-            })
-            .ok()
+        self.partition_at(item, Utc::now())
     }
 }
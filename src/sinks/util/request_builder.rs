@@ -1,4 +1,4 @@
-use std::io;
+use std::{io, sync::Arc};
 
 use bytes::Bytes;
 
@@ -94,6 +94,46 @@ pub trait RequestBuilder<Input> {
     ) -> Self::Request;
 }
 
+/// Shares a request builder between several streams of batches.
+impl<Input, B> RequestBuilder<Input> for Arc<B>
+where
+    B: RequestBuilder<Input>,
+{
+    type Metadata = B::Metadata;
+    type Events = B::Events;
+    type Encoder = B::Encoder;
+    type Payload = B::Payload;
+    type Request = B::Request;
+    type Error = B::Error;
+
+    fn compression(&self) -> Compression {
+        (**self).compression()
+    }
+
+    fn encoder(&self) -> &Self::Encoder {
+        (**self).encoder()
+    }
+
+    fn split_input(&self, input: Input) -> (Self::Metadata, Self::Events) {
+        (**self).split_input(input)
+    }
+
+    fn encode_events(
+        &self,
+        events: Self::Events,
+    ) -> Result<EncodeResult<Self::Payload>, Self::Error> {
+        (**self).encode_events(events)
+    }
+
+    fn build_request(
+        &self,
+        metadata: Self::Metadata,
+        payload: EncodeResult<Self::Payload>,
+    ) -> Self::Request {
+        (**self).build_request(metadata, payload)
+    }
+}
+
 /// Generalized interface for defining how a batch of events will incrementally be turned into requests.
 ///
 /// As opposed to `RequestBuilder`, this trait provides the means to incrementally build requests
@@ -1,4 +1,5 @@
 use std::{
+    any::Any,
     borrow::Cow,
     cmp,
     future::Future,
@@ -32,6 +33,33 @@ pub trait RetryLogic: Clone + Send + Sync + 'static {
         // Treat the default as the request is successful
         RetryAction::Successful
     }
+
+    /// The delay the server asked for, through a `Retry-After` header, before the request is
+    /// retried, which replaces the computed backoff.
+    fn retry_after(&self, _request: &dyn Any) -> Option<Duration> {
+        None
+    }
+
+    /// Called with every backoff delay applied before retrying a request.
+    fn on_backoff(&self, _delay: Duration, _source: BackoffSource) {}
+}
+
+/// Where a backoff delay applied before retrying a request comes from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BackoffSource {
+    /// The delay computed by the retry policy.
+    Computed,
+    /// The delay asked for by the server through a `Retry-After` header.
+    Server,
+}
+
+impl BackoffSource {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            BackoffSource::Computed => "computed",
+            BackoffSource::Server => "server",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -80,23 +108,35 @@ impl<L: RetryLogic> FixedRetryPolicy<L> {
         self.current_duration
     }
 
-    fn build_retry(&self) -> RetryPolicyFuture<L> {
+    fn build_retry(&self, request: &dyn Any) -> RetryPolicyFuture<L> {
         let policy = self.advance();
-        let delay = Box::pin(sleep(self.backoff()));
+        let (backoff, source) = match self.logic.retry_after(request) {
+            Some(retry_after) => (
+                cmp::min(retry_after, self.max_duration),
+                BackoffSource::Server,
+            ),
+            None => (self.backoff(), BackoffSource::Computed),
+        };
+        self.logic.on_backoff(backoff, source);
+        let delay = Box::pin(sleep(backoff));
 
-        debug!(message = "Retrying request.", delay_ms = %self.backoff().as_millis());
+        debug!(
+            message = "Retrying request.",
+            delay_ms = %backoff.as_millis(),
+            source = source.as_str()
+        );
         RetryPolicyFuture { delay, policy }
     }
 }
 
 impl<Req, Res, L> Policy<Req, Res, Error> for FixedRetryPolicy<L>
 where
-    Req: Clone,
+    Req: Clone + 'static,
     L: RetryLogic<Response = Res>,
 {
     type Future = RetryPolicyFuture<L>;
 
-    fn retry(&self, _: &Req, result: Result<&Res, &Error>) -> Option<Self::Future> {
+    fn retry(&self, request: &Req, result: Result<&Res, &Error>) -> Option<Self::Future> {
         match result {
             Ok(response) => match self.logic.should_retry_response(response) {
                 RetryAction::Retry(reason) => {
@@ -109,7 +149,7 @@ where
                     }
 
                     warn!(message = "Retrying after response.", reason = %reason);
-                    Some(self.build_retry())
+                    Some(self.build_retry(request))
                 }
 
                 RetryAction::DontRetry(reason) => {
@@ -128,7 +168,7 @@ where
                 if let Some(expected) = error.downcast_ref::<L::Error>() {
                     if self.logic.is_retriable_error(expected) {
                         warn!(message = "Retrying after error.", error = %expected);
-                        Some(self.build_retry())
+                        Some(self.build_retry(request))
                     } else {
                         error!(
                             message = "Non-retriable error; dropping the request.",
@@ -138,7 +178,7 @@ where
                     }
                 } else if error.downcast_ref::<Elapsed>().is_some() {
                     warn!("Request timed out. If this happens often while the events are actually reaching their destination, try decreasing `batch.max_bytes` and/or using `compression` if applicable. Alternatively `request.timeout_secs` can be increased.");
-                    Some(self.build_retry())
+                    Some(self.build_retry(request))
                 } else {
                     error!(
                         message = "Unexpected error type; dropping the request.",
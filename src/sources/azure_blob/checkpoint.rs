@@ -0,0 +1,222 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    ops::Bound,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+const CHECKPOINT_FILENAME: &str = "checkpoint.json";
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// The number of changes made to the checkpoint between two writes of it.
+const FLUSH_INTERVAL: usize = 100;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CheckpointFile {
+    version: u32,
+    container: String,
+    blobs: BTreeSet<String>,
+}
+
+/// The blobs of the container already read, persisted in the data directory of the source so that
+/// they aren't read again after a restart.
+///
+/// The names of the blobs aren't expected to grow, so every blob read is kept, until it is found
+/// deleted by a listing of the container.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    file: CheckpointFile,
+    /// The number of changes made since the checkpoint was last written.
+    unflushed: usize,
+    /// The prefix of the blobs listed.
+    prefix: String,
+    /// The last blob listed, the blobs read named before it and not listed being deleted.
+    last_listed: Option<String>,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint kept in `data_dir`, of the blobs listed under `prefix`.
+    ///
+    /// The blobs read from another container are forgotten.
+    pub fn load(data_dir: &Path, container: &str, prefix: &str) -> io::Result<Self> {
+        let path = data_dir.join(CHECKPOINT_FILENAME);
+        let file = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice::<CheckpointFile>(&contents)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => CheckpointFile::default(),
+            Err(error) => return Err(error),
+        };
+        let file = if file.container == container {
+            file
+        } else {
+            CheckpointFile {
+                version: CHECKPOINT_VERSION,
+                container: container.to_owned(),
+                blobs: BTreeSet::new(),
+            }
+        };
+        Ok(Self {
+            path,
+            file,
+            unflushed: 0,
+            prefix: prefix.to_owned(),
+            last_listed: None,
+        })
+    }
+
+    pub fn contains(&self, blob: &str) -> bool {
+        self.file.blobs.contains(blob)
+    }
+
+    /// Records `blob` as listed, forgetting the blobs read that were named between it and the
+    /// previous blob listed.
+    ///
+    /// The blobs are expected in the order they are listed in, which is the order of their names.
+    pub fn listed(&mut self, blob: &str) {
+        let after = match &self.last_listed {
+            Some(last) if last.as_str() < blob => Bound::Excluded(last.as_str()),
+            Some(_) => return,
+            None if self.prefix.as_str() <= blob => Bound::Included(self.prefix.as_str()),
+            None => return,
+        };
+        let deleted = self
+            .file
+            .blobs
+            .range::<str, _>((after, Bound::Excluded(blob)))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.forget(deleted);
+        self.last_listed = Some(blob.to_owned());
+    }
+
+    /// Forgets the blobs read that were named after the last blob listed, once the whole prefix
+    /// was listed.
+    pub fn listing_complete(&mut self) {
+        let after = match &self.last_listed {
+            Some(last) => Bound::Excluded(last.as_str()),
+            None => Bound::Included(self.prefix.as_str()),
+        };
+        let deleted = self
+            .file
+            .blobs
+            .range::<str, _>((after, Bound::Unbounded))
+            .take_while(|blob| blob.starts_with(&self.prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.forget(deleted);
+    }
+
+    fn forget(&mut self, blobs: Vec<String>) {
+        self.unflushed += blobs.len();
+        for blob in blobs {
+            self.file.blobs.remove(&blob);
+        }
+    }
+
+    /// Records `blob` as read, writing the checkpoint every `FLUSH_INTERVAL` changes.
+    pub async fn insert(&mut self, blob: String) -> io::Result<()> {
+        self.file.blobs.insert(blob);
+        self.unflushed += 1;
+        if self.unflushed >= FLUSH_INTERVAL {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the changes made since the checkpoint was last written.
+    ///
+    /// The checkpoint is written to a temporary file first, so that a crash doesn't leave it
+    /// truncated.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        if self.unflushed == 0 {
+            return Ok(());
+        }
+        let contents = serde_json::to_vec(&self.file)?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, contents).await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+        self.unflushed = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn persists_read_blobs() {
+        let data_dir = tempfile::tempdir().unwrap();
+
+        let mut checkpoint = Checkpoint::load(data_dir.path(), "logs", "blob/").unwrap();
+        assert!(!checkpoint.contains("blob/1.log"));
+        checkpoint.insert("blob/1.log".to_owned()).await.unwrap();
+        checkpoint.insert("blob/3.log".to_owned()).await.unwrap();
+        checkpoint.flush().await.unwrap();
+
+        let checkpoint = Checkpoint::load(data_dir.path(), "logs", "blob/").unwrap();
+        assert!(checkpoint.contains("blob/1.log"));
+        assert!(checkpoint.contains("blob/3.log"));
+        // The blobs named before the blobs read are still read, whenever they were written.
+        assert!(!checkpoint.contains("blob/0.log"));
+        assert!(!checkpoint.contains("blob/2.log"));
+
+        // The blobs read from another container are forgotten.
+        let checkpoint = Checkpoint::load(data_dir.path(), "other", "blob/").unwrap();
+        assert!(!checkpoint.contains("blob/1.log"));
+    }
+
+    #[tokio::test]
+    async fn batches_writes() {
+        let data_dir = tempfile::tempdir().unwrap();
+
+        let mut checkpoint = Checkpoint::load(data_dir.path(), "logs", "blob/").unwrap();
+        for index in 0..FLUSH_INTERVAL - 1 {
+            checkpoint
+                .insert(format!("blob/{:03}.log", index))
+                .await
+                .unwrap();
+        }
+        assert!(!data_dir.path().join(CHECKPOINT_FILENAME).exists());
+
+        checkpoint
+            .insert(format!("blob/{:03}.log", FLUSH_INTERVAL))
+            .await
+            .unwrap();
+        let checkpoint = Checkpoint::load(data_dir.path(), "logs", "blob/").unwrap();
+        assert!(checkpoint.contains("blob/000.log"));
+        assert!(checkpoint.contains(&format!("blob/{:03}.log", FLUSH_INTERVAL)));
+    }
+
+    #[tokio::test]
+    async fn forgets_deleted_blobs() {
+        let data_dir = tempfile::tempdir().unwrap();
+
+        let mut checkpoint = Checkpoint::load(data_dir.path(), "logs", "blob/").unwrap();
+        for blob in [
+            "a.log",
+            "blob/1.log",
+            "blob/2.log",
+            "blob/3.log",
+            "blob/4.log",
+        ] {
+            checkpoint.insert(blob.to_owned()).await.unwrap();
+        }
+
+        // `blob/1.log` and `blob/4.log` were deleted since they were read.
+        checkpoint.listed("blob/2.log");
+        checkpoint.listed("blob/3.log");
+        checkpoint.listing_complete();
+        checkpoint.flush().await.unwrap();
+
+        let checkpoint = Checkpoint::load(data_dir.path(), "logs", "blob/").unwrap();
+        assert!(!checkpoint.contains("blob/1.log"));
+        assert!(checkpoint.contains("blob/2.log"));
+        assert!(checkpoint.contains("blob/3.log"));
+        assert!(!checkpoint.contains("blob/4.log"));
+        // The blobs outside of the prefix weren't listed.
+        assert!(checkpoint.contains("a.log"));
+    }
+}
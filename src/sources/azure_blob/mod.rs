@@ -0,0 +1,798 @@
+use std::{io, num::NonZeroU32, path::PathBuf, sync::Arc};
+
+use async_compression::tokio::bufread::{GzipDecoder, ZlibDecoder, ZstdDecoder};
+use azure_core::prelude::Range;
+use azure_storage_blobs::prelude::{AccessTier, ContainerClient};
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use codecs::{
+    decoding::{DeserializerConfig, FramingConfig},
+    StreamDecodingError,
+};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use tokio::io::{AsyncRead, BufReader};
+use tokio_util::{codec::FramedRead, io::StreamReader};
+use vector_config::configurable_component;
+use vector_core::{config::LogNamespace, ByteSizeOf};
+
+use crate::{
+    codecs::{Decoder, DecodingConfig},
+    config::{
+        log_schema, AcknowledgementsConfig, Output, SourceConfig, SourceContext, SourceDescription,
+    },
+    event::{BatchNotifier, BatchStatus, Event},
+    internal_events::{
+        azure_blob::{
+            AzureBlobSourceArchivedBlob, AzureBlobSourceCheckpointError, AzureBlobSourceListError,
+            AzureBlobSourceReadError,
+        },
+        BytesReceived, OldEventsReceived, StreamClosedError,
+    },
+    serde::{bool_or_struct, default_decoding},
+    shutdown::ShutdownSignal,
+    sinks::azure_common::{
+        config::{build_client, ClientOptions},
+        imds,
+    },
+    SourceSender,
+};
+
+mod checkpoint;
+
+use checkpoint::Checkpoint;
+
+const LIST_PAGE_SIZE: u32 = 1000;
+
+/// The size of the ranges the blobs are downloaded in.
+const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The number of decoded events sent at once.
+const MAX_EVENTS_SENT: usize = 1000;
+
+/// Compression of the blobs read.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Derivative, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// Picked for each blob from its `Content-Encoding`, or else from the extension of its name.
+    #[derivative(Default)]
+    Auto,
+    /// Uncompressed.
+    None,
+    /// Gzip.
+    Gzip,
+    /// Zlib.
+    Zlib,
+    /// Zstandard.
+    ///
+    /// Blobs compressed with a dictionary, as written by the `zstd.dictionaries` of the
+    /// `azure_blob` sink, are rejected.
+    Zstd,
+}
+
+/// What happens to the blobs in the archive tier, which can't be read until they are rehydrated.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Derivative, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveTier {
+    /// The blobs are skipped.
+    #[derivative(Default)]
+    Skip,
+    /// The blobs are moved to the hot tier, to be read once rehydrated.
+    Rehydrate,
+}
+
+/// Configuration for the `azure_blob` source.
+#[configurable_component(source)]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AzureBlobSourceConfig {
+    /// The connection string of the storage account.
+    pub connection_string: Option<String>,
+
+    /// The name of the storage account, authenticated with the same credentials as the
+    /// `azure_blob` sink.
+    pub storage_account: Option<String>,
+
+    /// The token endpoint of the instance metadata service used to authenticate with the managed
+    /// identity of `storage_account`.
+    pub imds_endpoint: Option<String>,
+
+    /// The container the blobs are read from.
+    pub container_name: String,
+
+    /// The prefix of the names of the blobs read.
+    #[serde(default)]
+    pub blob_prefix: String,
+
+    /// The format of the time the names of the blobs hold right after `blob_prefix`, used to only
+    /// read the blobs between `start_time` and `end_time`.
+    pub blob_time_format: Option<String>,
+
+    /// The time from which the blobs are read, as an RFC 3339 timestamp.
+    pub start_time: Option<String>,
+
+    /// The time until which the blobs are read, as an RFC 3339 timestamp.
+    pub end_time: Option<String>,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub compression: Compression,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub archive_tier: ArchiveTier,
+
+    #[configurable(derived)]
+    pub framing: Option<FramingConfig>,
+
+    #[configurable(derived)]
+    #[serde(default = "default_decoding")]
+    pub decoding: DeserializerConfig,
+
+    /// The directory used to persist the names of the blobs already read.
+    ///
+    /// By default, the global `data_dir` option is used.
+    pub data_dir: Option<PathBuf>,
+
+    #[configurable(derived)]
+    #[serde(default, deserialize_with = "bool_or_struct")]
+    pub acknowledgements: AcknowledgementsConfig,
+}
+
+impl Default for AzureBlobSourceConfig {
+    fn default() -> Self {
+        Self {
+            connection_string: Some("DefaultEndpointsProtocol=https;AccountName=some-account-name;AccountKey=some-account-key;".to_owned()),
+            storage_account: None,
+            imds_endpoint: None,
+            container_name: "logs".to_owned(),
+            blob_prefix: "blob/".to_owned(),
+            blob_time_format: None,
+            start_time: None,
+            end_time: None,
+            compression: Compression::default(),
+            archive_tier: ArchiveTier::default(),
+            framing: None,
+            decoding: default_decoding(),
+            data_dir: None,
+            acknowledgements: Default::default(),
+        }
+    }
+}
+
+inventory::submit! {
+    SourceDescription::new::<AzureBlobSourceConfig>("azure_blob")
+}
+
+impl_generate_config_from_default!(AzureBlobSourceConfig);
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "azure_blob")]
+impl SourceConfig for AzureBlobSourceConfig {
+    async fn build(&self, cx: SourceContext) -> crate::Result<super::Source> {
+        let time_range = self.time_range()?;
+        let data_dir = cx
+            .globals
+            .resolve_and_make_data_subdir(self.data_dir.as_ref(), cx.key.id())?;
+        let checkpoint = Checkpoint::load(&data_dir, &self.container_name, &self.blob_prefix)?;
+        let imds_endpoint = self
+            .imds_endpoint
+            .as_deref()
+            .map(imds::parse_endpoint)
+            .transpose()?;
+        let client = build_client(ClientOptions {
+            connection_string: self.connection_string.clone(),
+            storage_account: self.storage_account.clone(),
+            imds_endpoint,
+            container_name: self.container_name.clone(),
+            ..Default::default()
+        })
+        .await?;
+
+        let framing = self
+            .framing
+            .clone()
+            .unwrap_or_else(|| self.decoding.default_stream_framing());
+        let decoder =
+            DecodingConfig::new(framing, self.decoding.clone(), LogNamespace::Legacy).build();
+
+        let replay = Replay {
+            client,
+            container_name: self.container_name.clone(),
+            blob_prefix: self.blob_prefix.clone(),
+            time_range,
+            compression: self.compression,
+            archive_tier: self.archive_tier,
+            decoder,
+            acknowledgements: cx.do_acknowledgements(&self.acknowledgements),
+            checkpoint,
+        };
+        Ok(Box::pin(replay.run(cx.shutdown, cx.out)))
+    }
+
+    fn outputs(&self, _global_log_namespace: LogNamespace) -> Vec<Output> {
+        vec![Output::default(self.decoding.output_type())]
+    }
+
+    fn source_type(&self) -> &'static str {
+        "azure_blob"
+    }
+
+    fn can_acknowledge(&self) -> bool {
+        true
+    }
+}
+
+impl AzureBlobSourceConfig {
+    fn time_range(&self) -> crate::Result<Option<TimeRange>> {
+        let parse = |time: &Option<String>, option: &str| {
+            time.as_deref()
+                .map(|time| {
+                    DateTime::parse_from_rfc3339(time)
+                        .map(|time| time.with_timezone(&Utc))
+                        .map_err(|error| format!("invalid `{}`: {}", option, error))
+                })
+                .transpose()
+        };
+        let start = parse(&self.start_time, "start_time")?;
+        let end = parse(&self.end_time, "end_time")?;
+        match (&self.blob_time_format, start, end) {
+            (_, None, None) => Ok(None),
+            (None, _, _) => {
+                Err("`start_time` and `end_time` need the `blob_time_format` of the blobs".into())
+            }
+            (Some(_), Some(start), Some(end)) if start >= end => {
+                Err("`start_time` must be before `end_time`".into())
+            }
+            (Some(format), start, end) => Ok(Some(TimeRange {
+                format: format.clone(),
+                start,
+                end,
+            })),
+        }
+    }
+}
+
+/// The times the names of the blobs read must hold.
+#[derive(Clone, Debug)]
+struct TimeRange {
+    format: String,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+}
+
+impl TimeRange {
+    /// Whether `time`, if the name of the blob held one, is within the range.
+    fn contains(&self, time: Option<DateTime<Utc>>) -> bool {
+        match time {
+            Some(time) => {
+                self.start.map_or(true, |start| time >= start)
+                    && self.end.map_or(true, |end| time < end)
+            }
+            None => false,
+        }
+    }
+}
+
+/// The time held by the name of `blob` right after `prefix`, formatted with `format`.
+///
+/// The name usually goes on after the time, so the longest part of it that parses is used.
+pub(crate) fn blob_time(blob: &str, prefix: &str, format: &str) -> Option<DateTime<Utc>> {
+    let rest = blob.strip_prefix(prefix)?;
+    rest.char_indices()
+        .map(|(index, c)| index + c.len_utf8())
+        .rev()
+        .find_map(|end| {
+            let head = &rest[..end];
+            NaiveDateTime::parse_from_str(head, format)
+                .ok()
+                .or_else(|| {
+                    NaiveDate::parse_from_str(head, format)
+                        .ok()
+                        .map(|date| date.and_hms(0, 0, 0))
+                })
+        })
+        .map(|time| Utc.from_utc_datetime(&time))
+}
+
+/// A blob listed in the container.
+struct ListedBlob {
+    name: String,
+    content_encoding: Option<String>,
+    content_length: u64,
+    archived: bool,
+}
+
+struct Replay {
+    client: Arc<ContainerClient>,
+    container_name: String,
+    blob_prefix: String,
+    time_range: Option<TimeRange>,
+    compression: Compression,
+    archive_tier: ArchiveTier,
+    decoder: Decoder,
+    acknowledgements: bool,
+    checkpoint: Checkpoint,
+}
+
+impl Replay {
+    /// Reads the blobs not read yet, in the order of their names, then stops.
+    async fn run(mut self, mut shutdown: ShutdownSignal, mut out: SourceSender) -> Result<(), ()> {
+        let result = self.read_blobs(&mut shutdown, &mut out).await;
+        if let Err(error) = self.checkpoint.flush().await {
+            emit!(AzureBlobSourceCheckpointError { error });
+        }
+        if result.is_ok() {
+            info!(message = "Finished reading blobs.", container = %self.container_name);
+        }
+        result
+    }
+
+    /// Reads the blobs one page of the listing at a time. The blobs are listed in the order of
+    /// their names, which the checkpoint relies on to forget the deleted blobs.
+    async fn read_blobs(
+        &mut self,
+        shutdown: &mut ShutdownSignal,
+        out: &mut SourceSender,
+    ) -> Result<(), ()> {
+        let mut next_marker = None;
+        loop {
+            let request = self
+                .client
+                .list_blobs()
+                .prefix(self.blob_prefix.as_str())
+                .max_results(NonZeroU32::new(LIST_PAGE_SIZE).expect("non-zero page size"));
+            let request = match next_marker.take() {
+                Some(marker) => request.next_marker(marker),
+                None => request,
+            };
+            let response = match request.execute().await {
+                Ok(response) => response,
+                Err(error) => {
+                    emit!(AzureBlobSourceListError {
+                        error: error.to_string()
+                    });
+                    return Err(());
+                }
+            };
+
+            for blob in response.blobs.blobs {
+                let blob = ListedBlob {
+                    archived: matches!(blob.properties.access_tier, Some(AccessTier::Archive)),
+                    content_encoding: blob.properties.content_encoding,
+                    content_length: blob.properties.content_length,
+                    name: blob.name,
+                };
+                self.checkpoint.listed(&blob.name);
+                if self.checkpoint.contains(&blob.name) {
+                    continue;
+                }
+                if let Some(time_range) = &self.time_range {
+                    let time = blob_time(&blob.name, &self.blob_prefix, &time_range.format);
+                    if !time_range.contains(time) {
+                        continue;
+                    }
+                }
+
+                if blob.archived {
+                    self.archived(&blob.name).await;
+                    continue;
+                }
+
+                let read = tokio::select! {
+                    _ = &mut *shutdown => return Ok(()),
+                    read = self.read(&blob, out) => read?,
+                };
+                if read {
+                    if let Err(error) = self.checkpoint.insert(blob.name).await {
+                        emit!(AzureBlobSourceCheckpointError { error });
+                    }
+                }
+            }
+
+            match response.next_marker {
+                Some(marker) => next_marker = Some(marker),
+                None => {
+                    self.checkpoint.listing_complete();
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    async fn archived(&self, blob: &str) {
+        let rehydrating = self.archive_tier == ArchiveTier::Rehydrate;
+        if rehydrating {
+            // A blob already being rehydrated refuses to have its tier set again.
+            if let Err(error) = self
+                .client
+                .as_blob_client(blob)
+                .set_blob_tier(AccessTier::Hot)
+                .execute()
+                .await
+            {
+                debug!(message = "Failed to rehydrate blob.", blob, %error);
+            }
+        }
+        emit!(AzureBlobSourceArchivedBlob { blob, rehydrating });
+    }
+
+    /// Reads `blob` and sends its events as they are decoded, returning whether it can be
+    /// checkpointed.
+    async fn read(&self, blob: &ListedBlob, out: &mut SourceSender) -> Result<bool, ()> {
+        let chunks = blob_chunks(
+            Arc::clone(&self.client),
+            blob.name.clone(),
+            blob.content_length,
+        );
+        let reader = match blob_reader(
+            self.compression,
+            blob.content_encoding.as_deref(),
+            &blob.name,
+            chunks,
+        )
+        .await
+        {
+            Ok(reader) => reader,
+            Err(error) => {
+                emit!(AzureBlobSourceReadError {
+                    blob: &blob.name,
+                    error: error.to_string(),
+                });
+                return Ok(false);
+            }
+        };
+
+        let (batch, receiver) = BatchNotifier::maybe_new_with_receiver(self.acknowledgements);
+        let now = Utc::now();
+        let enrich = |mut event: Event| {
+            if let Event::Log(log) = &mut event {
+                log.try_insert(log_schema().source_type_key(), Bytes::from("azure_blob"));
+                log.try_insert(log_schema().timestamp_key(), now);
+                log.try_insert("container", self.container_name.clone());
+                log.try_insert("blob", blob.name.clone());
+            }
+            event.with_batch_notifier_option(&batch)
+        };
+
+        let mut frames = FramedRead::new(reader, self.decoder.clone());
+        let mut events = Vec::new();
+        let mut failed = false;
+        while let Some(frame) = frames.next().await {
+            match frame {
+                Ok((decoded, _)) => events.extend(decoded.into_iter().map(enrich)),
+                // Decoding errors are logged by `crate::codecs::Decoder`, but not the errors of
+                // downloading or decompressing the blob.
+                Err(error) if !error.can_continue() => {
+                    emit!(AzureBlobSourceReadError {
+                        blob: &blob.name,
+                        error: error.to_string(),
+                    });
+                    failed = true;
+                    break;
+                }
+                Err(_) => {}
+            }
+            if events.len() >= MAX_EVENTS_SENT {
+                send(std::mem::take(&mut events), out).await?;
+            }
+        }
+        if !events.is_empty() {
+            send(events, out).await?;
+        }
+        drop(batch);
+
+        // The events sent before the blob failed to be read are sent again by a later run.
+        if failed {
+            return Ok(false);
+        }
+        Ok(match receiver {
+            None => true,
+            Some(receiver) => match receiver.await {
+                BatchStatus::Delivered => true,
+                // The blob is read again after a restart.
+                BatchStatus::Errored => false,
+                BatchStatus::Rejected => {
+                    error!(
+                        message = "Sink reported events were rejected.",
+                        blob = %blob.name,
+                        internal_log_rate_secs = 5,
+                    );
+                    // Rejected events can't be retried, so the blob isn't read again.
+                    true
+                }
+            },
+        })
+    }
+}
+
+async fn send(events: Vec<Event>, out: &mut SourceSender) -> Result<(), ()> {
+    emit!(OldEventsReceived {
+        count: events.len(),
+        byte_size: events.size_of(),
+    });
+    let count = events.len();
+    out.send_batch(events)
+        .await
+        .map_err(|error| emit!(StreamClosedError { error, count }))
+}
+
+/// The content of `blob`, downloaded in ranges of `CHUNK_SIZE` bytes so that it's never held in
+/// memory whole.
+fn blob_chunks(
+    client: Arc<ContainerClient>,
+    blob: String,
+    content_length: u64,
+) -> impl Stream<Item = crate::Result<Bytes>> + Send + Unpin {
+    Box::pin(stream::try_unfold(0, move |start| {
+        let client = Arc::clone(&client);
+        let blob = blob.clone();
+        async move {
+            if start >= content_length {
+                return Ok(None);
+            }
+            let end = (start + CHUNK_SIZE).min(content_length);
+            let response = client
+                .as_blob_client(blob)
+                .get()
+                .range(Range::new(start, end))
+                .execute()
+                .await?;
+            emit!(BytesReceived {
+                byte_size: response.data.len(),
+                protocol: "https",
+            });
+            Ok::<_, crate::Error>(Some((response.data, end)))
+        }
+    }))
+}
+
+/// The compression of a blob, from its `Content-Encoding` or else from the extension of its name,
+/// as written by the `azure_blob` sink.
+fn detect_compression(content_encoding: Option<&str>, blob: &str) -> Compression {
+    match content_encoding {
+        Some("gzip") => Compression::Gzip,
+        Some("deflate") => Compression::Zlib,
+        Some("zstd") => Compression::Zstd,
+        _ if blob.ends_with(".gz") => Compression::Gzip,
+        _ if blob.ends_with(".zz") => Compression::Zlib,
+        _ if blob.ends_with(".zst") => Compression::Zstd,
+        _ => Compression::None,
+    }
+}
+
+/// Decompresses the content of `blob` as it is read from `chunks`.
+///
+/// Blobs compressed with a Zstandard dictionary are rejected, the dictionaries of the sink that
+/// wrote them being unknown to the source.
+pub(crate) async fn blob_reader<S>(
+    compression: Compression,
+    content_encoding: Option<&str>,
+    blob: &str,
+    chunks: S,
+) -> io::Result<Box<dyn AsyncRead + Send + Unpin>>
+where
+    S: Stream<Item = crate::Result<Bytes>> + Send + Unpin + 'static,
+{
+    let mut chunks = chunks.map_err(|error| io::Error::new(io::ErrorKind::Other, error));
+    let first = match chunks.next().await {
+        Some(first) => first?,
+        None => return Ok(Box::new(tokio::io::empty())),
+    };
+
+    let compression = match compression {
+        Compression::Auto => detect_compression(content_encoding, blob),
+        compression => compression,
+    };
+    if compression == Compression::Zstd {
+        let dictionary_id = zstd::zstd_safe::get_dict_id_from_frame(&first);
+        if dictionary_id != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the blob is compressed with the Zstandard dictionary {}, which isn't supported",
+                    dictionary_id
+                ),
+            ));
+        }
+    }
+
+    let reader = BufReader::new(StreamReader::new(
+        stream::iter(Some(Ok(first))).chain(chunks),
+    ));
+    Ok(match compression {
+        Compression::Auto | Compression::None => Box::new(reader),
+        // Blobs may be made of several concatenated gzip members.
+        Compression::Gzip => Box::new({
+            let mut decoder = GzipDecoder::new(reader);
+            decoder.multiple_members(true);
+            decoder
+        }),
+        Compression::Zlib => Box::new(ZlibDecoder::new(reader)),
+        Compression::Zstd => Box::new({
+            let mut decoder = ZstdDecoder::new(reader);
+            decoder.multiple_members(true);
+            decoder
+        }),
+    })
+}
+
+/// Decompresses and decodes the events of `blob`, made of `data`.
+#[cfg(test)]
+pub(crate) async fn decode_blob(
+    decoder: Decoder,
+    compression: Compression,
+    content_encoding: Option<&str>,
+    blob: &str,
+    data: Bytes,
+) -> io::Result<Vec<Event>> {
+    let chunks = stream::iter(Some(Ok(data)));
+    let reader = blob_reader(compression, content_encoding, blob, chunks).await?;
+    let mut frames = FramedRead::new(reader, decoder);
+    let mut events = Vec::new();
+    while let Some(frame) = frames.next().await {
+        match frame {
+            Ok((decoded, _)) => events.extend(decoded),
+            Err(error) if !error.can_continue() => {
+                return Err(io::Error::new(io::ErrorKind::Other, error.to_string()))
+            }
+            Err(_) => {}
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn generate_config() {
+        crate::test_util::test_generate_config::<AzureBlobSourceConfig>();
+    }
+
+    fn json_decoder() -> Decoder {
+        DecodingConfig::new(
+            FramingConfig::NewlineDelimited {
+                newline_delimited: Default::default(),
+            },
+            DeserializerConfig::Json,
+            LogNamespace::Legacy,
+        )
+        .build()
+    }
+
+    #[test]
+    fn parses_time_of_blob_names() {
+        assert_eq!(
+            blob_time("blob/1662026400-1f8b2c2e.log.gz", "blob/", "%s"),
+            Some(Utc.ymd(2022, 9, 1).and_hms(10, 0, 0))
+        );
+        assert_eq!(
+            blob_time("blob/2022/09/01/1662026400.log", "blob/", "%Y/%m/%d/"),
+            Some(Utc.ymd(2022, 9, 1).and_hms(0, 0, 0))
+        );
+        assert_eq!(
+            blob_time(
+                "blob/2022-09-01T10:00:00-1f8b2c2e.log",
+                "blob/",
+                "%Y-%m-%dT%H:%M:%S"
+            ),
+            Some(Utc.ymd(2022, 9, 1).and_hms(10, 0, 0))
+        );
+        assert_eq!(blob_time("blob/latest.log", "blob/", "%s"), None);
+        assert_eq!(blob_time("other/1662026400.log", "blob/", "%s"), None);
+    }
+
+    #[test]
+    fn filters_blobs_by_time_range() {
+        let config = AzureBlobSourceConfig {
+            blob_time_format: Some("%s".to_owned()),
+            start_time: Some("2022-09-01T00:00:00Z".to_owned()),
+            end_time: Some("2022-09-02T00:00:00Z".to_owned()),
+            ..Default::default()
+        };
+        let range = config.time_range().unwrap().unwrap();
+        assert!(range.contains(Some(Utc.ymd(2022, 9, 1).and_hms(0, 0, 0))));
+        assert!(range.contains(Some(Utc.ymd(2022, 9, 1).and_hms(23, 59, 59))));
+        assert!(!range.contains(Some(Utc.ymd(2022, 9, 2).and_hms(0, 0, 0))));
+        assert!(!range.contains(None));
+
+        let config = AzureBlobSourceConfig {
+            blob_time_format: None,
+            ..config
+        };
+        assert!(config.time_range().is_err());
+    }
+
+    #[tokio::test]
+    async fn decodes_compressed_blobs() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder
+            .write_all(b"{\"message\":\"one\"}\n{\"message\":\"two\"}\n")
+            .unwrap();
+        let data = Bytes::from(encoder.finish().unwrap());
+
+        let events = decode_blob(
+            json_decoder(),
+            Compression::Auto,
+            None,
+            "blob/1662026400.log.gz",
+            data,
+        )
+        .await
+        .unwrap();
+        let messages = events
+            .iter()
+            .map(|event| event.as_log()["message"].to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["one", "two"]);
+
+        assert_eq!(
+            detect_compression(None, "blob/1662026400.log.gz"),
+            Compression::Gzip
+        );
+        assert_eq!(
+            detect_compression(Some("zstd"), "blob/1662026400.log"),
+            Compression::Zstd
+        );
+        assert_eq!(
+            detect_compression(None, "blob/1662026400.log"),
+            Compression::None
+        );
+    }
+
+    #[tokio::test]
+    async fn decodes_blobs_as_they_are_downloaded() {
+        let lines = (0..1000)
+            .map(|index| format!("{{\"message\":\"{}\"}}\n", index))
+            .collect::<String>();
+        let data = zstd::stream::encode_all(lines.as_bytes(), 3).unwrap();
+        // Events and compressed frames are split across the chunks.
+        let chunks = data
+            .chunks(100)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+
+        let reader = blob_reader(
+            Compression::Auto,
+            Some("zstd"),
+            "blob/1662026400.log",
+            stream::iter(chunks),
+        )
+        .await
+        .unwrap();
+        let events = FramedRead::new(reader, json_decoder())
+            .map(|frame| frame.unwrap().0.len())
+            .fold(0, |count, decoded| async move { count + decoded })
+            .await;
+        assert_eq!(events, 1000);
+    }
+
+    #[tokio::test]
+    async fn rejects_blobs_compressed_with_zstd_dictionary() {
+        let samples = (0..1000)
+            .map(|index| format!("{{\"message\":\"{}\",\"app\":\"nginx\"}}\n", index))
+            .collect::<Vec<_>>();
+        let dictionary = zstd::dict::from_samples(&samples, 4096).unwrap();
+        let mut compressor = zstd::bulk::Compressor::with_dictionary(3, &dictionary).unwrap();
+        let data = compressor.compress(samples[0].as_bytes()).unwrap();
+
+        let error = decode_blob(
+            json_decoder(),
+            Compression::Auto,
+            None,
+            "blob/1662026400.log.zst",
+            data.into(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("dictionary"), "{}", error);
+    }
+}
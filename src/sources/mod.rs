@@ -10,6 +10,8 @@ pub mod aws_kinesis_firehose;
 pub mod aws_s3;
 #[cfg(feature = "sources-aws_sqs")]
 pub mod aws_sqs;
+#[cfg(feature = "sources-azure_blob")]
+pub mod azure_blob;
 #[cfg(any(feature = "sources-datadog_agent"))]
 pub mod datadog;
 #[cfg(feature = "sources-demo_logs")]
@@ -110,6 +112,10 @@ pub enum Sources {
     #[cfg(feature = "sources-aws_sqs")]
     AwsSqs(#[configurable(derived)] aws_sqs::AwsSqsConfig),
 
+    /// Azure Blob Storage.
+    #[cfg(feature = "sources-azure_blob")]
+    AzureBlob(#[configurable(derived)] azure_blob::AzureBlobSourceConfig),
+
     /// Datadog Agent.
     #[cfg(feature = "sources-datadog_agent")]
     DatadogAgent(#[configurable(derived)] datadog::agent::DatadogAgentConfig),
@@ -3,11 +3,12 @@ use std::{borrow::Cow, convert::TryFrom, hash::Hash, path::PathBuf};
 use bytes::Bytes;
 use chrono::{
     format::{strftime::StrftimeItems, Item},
-    Utc,
+    Local, Utc,
 };
 use once_cell::sync::Lazy;
 use regex::{Captures, Regex};
 use snafu::Snafu;
+use vector_common::TimeZone;
 use vector_config::configurable_component;
 
 use crate::{
@@ -129,14 +130,31 @@ impl Template {
         &self,
         event: impl Into<EventRef<'a>>,
     ) -> Result<String, TemplateRenderingError> {
-        let event = event.into();
+        self.render_string_in(event.into(), None)
+    }
+
+    /// Renders the template like [`Template::render_string`], but with the strftime specifiers
+    /// formatted in the given time zone instead of UTC.
+    pub fn render_string_in_timezone<'a>(
+        &self,
+        event: impl Into<EventRef<'a>>,
+        timezone: TimeZone,
+    ) -> Result<String, TemplateRenderingError> {
+        self.render_string_in(event.into(), Some(timezone))
+    }
+
+    fn render_string_in(
+        &self,
+        event: EventRef<'_>,
+        timezone: Option<TimeZone>,
+    ) -> Result<String, TemplateRenderingError> {
         match (self.has_fields, self.has_ts) {
             (false, false) => Ok(self.src.clone()),
             (true, false) => render_fields(&self.src, event),
-            (false, true) => Ok(render_timestamp(&self.src, event)),
+            (false, true) => Ok(render_timestamp(&self.src, event, timezone)),
             (true, true) => {
                 let tmp = render_fields(&self.src, event)?;
-                Ok(render_timestamp(&tmp, event))
+                Ok(render_timestamp(&tmp, event, timezone))
             }
         }
     }
@@ -202,7 +220,7 @@ fn render_metric_field(key: &str, metric: &Metric) -> Option<String> {
     }
 }
 
-fn render_timestamp(src: &str, event: EventRef<'_>) -> String {
+fn render_timestamp(src: &str, event: EventRef<'_>, timezone: Option<TimeZone>) -> String {
     let timestamp = match event {
         EventRef::Log(log) => log
             .get(log_schema().timestamp_key())
@@ -214,10 +232,13 @@ fn render_timestamp(src: &str, event: EventRef<'_>) -> String {
             .and_then(Value::as_timestamp)
             .copied(),
     };
-    if let Some(ts) = timestamp {
-        ts.format(src).to_string()
-    } else {
-        Utc::now().format(src).to_string()
+    let ts = timestamp.unwrap_or_else(Utc::now);
+    // Converting an instant to a time zone is never ambiguous, so the repeated hour of a DST
+    // transition is rendered correctly; `%z` can be used to tell both occurrences apart.
+    match timezone {
+        None => ts.format(src).to_string(),
+        Some(TimeZone::Local) => ts.with_timezone(&Local).format(src).to_string(),
+        Some(TimeZone::Named(tz)) => ts.with_timezone(&tz).format(src).to_string(),
     }
 }
 
@@ -225,7 +246,7 @@ fn render_timestamp(src: &str, event: EventRef<'_>) -> String {
 mod tests {
     use std::collections::BTreeMap;
 
-    use chrono::TimeZone;
+    use chrono::TimeZone as _;
 
     use super::*;
     use crate::event::{Event, LogEvent, MetricKind, MetricValue};
@@ -347,6 +368,54 @@ mod tests {
         assert_eq!(Ok(Bytes::from("abcd-2001-02-03")), template.render(&event))
     }
 
+    #[test]
+    fn render_log_timestamp_in_fixed_offset_timezone() {
+        let ts = Utc.ymd(2001, 2, 3).and_hms(22, 5, 6);
+
+        let mut event = Event::Log(LogEvent::from("hello world"));
+        event.as_mut_log().insert(log_schema().timestamp_key(), ts);
+
+        let template = Template::try_from("abcd-%F-%H%M").unwrap();
+        let timezone = TimeZone::parse("Asia/Kolkata").unwrap();
+
+        assert_eq!(
+            Ok("abcd-2001-02-04-0335".to_string()),
+            template.render_string_in_timezone(&event, timezone)
+        )
+    }
+
+    #[test]
+    fn render_log_timestamp_in_timezone_around_dst_fall_back() {
+        let template = Template::try_from("%F/%H%z").unwrap();
+        let timezone = TimeZone::parse("America/New_York").unwrap();
+
+        // 2022-11-06 06:00 UTC is when New York falls back from EDT to EST, so local 01:xx
+        // happens twice.
+        let rendered = [(4, 30), (5, 30), (6, 30), (7, 30)]
+            .iter()
+            .map(|&(hour, minute)| {
+                let mut event = Event::Log(LogEvent::from("hello world"));
+                event.as_mut_log().insert(
+                    log_schema().timestamp_key(),
+                    Utc.ymd(2022, 11, 6).and_hms(hour, minute, 0),
+                );
+                template
+                    .render_string_in_timezone(&event, timezone)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rendered,
+            vec![
+                "2022-11-06/00-0400",
+                "2022-11-06/01-0400",
+                "2022-11-06/01-0500",
+                "2022-11-06/02-0500",
+            ]
+        );
+    }
+
     #[test]
     fn render_log_timestamp_multiple_strftime_style() {
         let ts = Utc.ymd(2001, 2, 3).and_hms(4, 5, 6);
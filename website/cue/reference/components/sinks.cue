@@ -187,6 +187,9 @@ components: sinks: [Name=string]: {
 											if codec == "text" {
 												text: "The message field from the event."
 											}
+											if codec == "line_protocol" {
+												line_protocol: "[InfluxDB line protocol](\(urls.influxdb_line_protocol)) encoded metric."
+											}
 											if codec == "logfmt" {
 												logfmt: "[logfmt](\(urls.logfmt)) encoded event."
 											}
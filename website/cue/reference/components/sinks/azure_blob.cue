@@ -33,7 +33,7 @@ components: sinks: azure_blob: {
 				codec: {
 					enabled: true
 					framing: true
-					enum: ["json", "text"]
+					enum: ["json", "line_protocol", "text"]
 				}
 			}
 			request: {
@@ -68,7 +68,7 @@ components: sinks: azure_blob: {
 
 	configuration: {
 		connection_string: {
-			description: "The Azure Blob Storage Account connection string. Only authentication with access key supported. This or storage_account has to be provided."
+			description: "The Azure Blob Storage Account connection string. Only authentication with access key supported. This or storage_account has to be provided. When both are provided, the credentials of `storage_account` take precedence and the connection string is only used if no token can be acquired for the storage account when the sink starts."
 			required:    false
 			common:      true
 			type: string: {
@@ -77,7 +77,7 @@ components: sinks: azure_blob: {
 			}
 		}
 		storage_account: {
-			description: "The Azure Blob Storage Account name. Credentials are read in this order: [EnvironmentCredential](https://docs.rs/azure_identity/latest/azure_identity/struct.DefaultAzureCredential.html), ManagedIdentityCredential, AzureCliCredential. This or connection_string has to be provided."
+			description: "The Azure Blob Storage Account name. Credentials are read in this order: [EnvironmentCredential](https://docs.rs/azure_identity/latest/azure_identity/struct.DefaultAzureCredential.html), ManagedIdentityCredential, AzureCliCredential. This or connection_string has to be provided. When both are provided, this takes precedence over `connection_string`, which is used as a fallback if no token can be acquired when the sink starts."
 			required:    false
 			common:      true
 			type: string: {
@@ -120,12 +120,1156 @@ components: sinks: azure_blob: {
 				syntax:  "strftime"
 			}
 		}
+		ordering_key: {
+			category:    "File Naming"
+			common:      false
+			description: "Starts the names of the blobs with a key sorting in the order the blobs were named, in place of the time formatted with [`blob_time_format`](#blob_time_format), which can't be set along with it, so that consumers merging the blobs in order can sort them by name even when several are written within the same second. The key is a sequence zero-padded to 19 digits followed by the time the blob was named, to the microsecond, such as `0001664625600123456-20221001T120000.123456Z`. The sequence is the number of microseconds since the Unix epoch, bumped past the previous one when several blobs are named within the same microsecond, so it keeps going up across restarts as long as the clock doesn't go back. Blobs are named before being uploaded, so to have them written in the order of their names too, limit the requests of each partition to one at a time with [`concurrency_mode`](#concurrency_mode). It can't be enabled along with appending to blobs through [`blob_type`](#blob_type)."
+			required:    false
+			type: bool: default: false
+		}
+		janitor: {
+			common:      false
+			description: "Periodically deletes old objects that Vector created under the reserved `_vector/` prefix of the container. Objects outside of `_vector/` are never listed nor deleted, and the events whose blob would be written under `_vector/` are rejected while the janitor is enabled."
+			required:    false
+			type: object: {
+				examples: [{"max_age_secs": 604800, "prefixes": ["dead_letter"]}]
+				options: {
+					max_age_secs: {
+						description: "Objects whose last modification is older than this are deleted. Must be at least 60 seconds."
+						required:    true
+						type: uint: {
+							examples: [604800]
+							unit: "seconds"
+						}
+					}
+					interval_secs: {
+						common:      false
+						description: "The number of seconds between two sweeps of the reserved prefixes."
+						required:    false
+						type: uint: {
+							default: 3600
+							unit:    "seconds"
+						}
+					}
+					prefixes: {
+						common:      false
+						description: "The sub-prefixes of `_vector/` to prune. When empty, the whole of `_vector/` is pruned."
+						required:    false
+						type: array: {
+							default: []
+							items: type: string: {
+								examples: ["dead_letter", "manifest"]
+							}
+						}
+					}
+					batch_deletes: {
+						common:      false
+						description: "Deletes the expired objects through the [Blob Batch API](https://learn.microsoft.com/en-us/rest/api/storageservices/blob-batch), bundling up to 256 deletes per request instead of sending one request per object. Only batches failing as a whole are retried; objects whose own delete fails are logged and left for the next sweep, and objects already gone are skipped."
+						required:    false
+						type: bool: default: false
+					}
+				}
+			}
+		}
+		sidecar_checksum: {
+			common:      false
+			description: "When set, every blob written is followed by a `<blob name>.<algorithm>` sidecar blob containing the hex digest of the stored blob. The payload is checked against the `Content-MD5` returned by Azure before the sidecar is written; a blob whose `Content-MD5` doesn't match is deleted and written again. A failure to write the sidecar is logged but doesn't fail the delivery of the data blob."
+			required:    false
+			type: string: {
+				default: null
+				enum: {
+					md5:    "MD5 digest."
+					sha256: "SHA-256 digest."
+				}
+			}
+		}
+		timezone: {
+			common:      false
+			description: "The name of the time zone in which the [`strftime` specifiers](\(urls.strptime_specifiers)) of `blob_prefix` are rendered. The name may be any name in the [TZ database](\(urls.tz_time_zones)), or `local` to indicate system local time. Converting the event timestamp to a time zone is never ambiguous, so events around a daylight saving time transition are filed under their actual local hour; add `%z` to the prefix to tell apart the two occurrences of the repeated hour."
+			required:    false
+			type: string: {
+				default: "UTC"
+				examples: ["local", "America/New_York", "EST5EDT"]
+			}
+		}
+		batch_summary_interval_secs: {
+			common:      false
+			description: "When set, an `AzureBlobBatchSummary` event is logged every interval for each container written to, with the number of events, logical bytes, compressed bytes and blobs written during that interval. A final summary is logged when the sink stops. Each summary also sets the `azure_blob_average_blob_size_bytes` and `azure_blob_blobs_per_event` gauges of its container, which show how much small batches amplify the writes over the interval."
+			required:    false
+			type: uint: {
+				default: null
+				examples: [3600]
+				unit: "seconds"
+			}
+		}
+		slow_start: {
+			common:      false
+			description: "Ramps the concurrency up after Vector starts, rather than opening `request.concurrency` requests against the storage account at once, which may throttle the burst with `429 Too Many Requests` responses until it scales out after being idle. The requests in flight start at [`slow_start.initial_concurrency`](#slow_start.initial_concurrency) and go up linearly to `request.concurrency` over [`slow_start.warm_up_secs`](#slow_start.warm_up_secs), counted from the first request. Needs a fixed `request.concurrency`: adaptive concurrency already starts from a single request."
+			required:    false
+			type: object: {
+				examples: [{initial_concurrency: 2, warm_up_secs: 60}]
+				options: {
+					initial_concurrency: {
+						common:      false
+						description: "The requests in flight at once when the sink starts sending. Must be lower than `request.concurrency`."
+						required:    false
+						type: uint: {
+							default: 1
+						}
+					}
+					warm_up_secs: {
+						common:      false
+						description: "How long the concurrency takes to ramp up to `request.concurrency`."
+						required:    true
+						type: uint: {
+							examples: [60]
+							unit: "seconds"
+						}
+					}
+				}
+			}
+		}
+		envelope: {
+			common:      false
+			description: "Wraps each event into an envelope holding ingestion metadata next to the event, as in `{\"meta\": {...}, \"event\": {...}}`, when encoding the blobs. The event is nested as is, after the `only_fields` and `except_fields` of the encoding are applied to it. Can only be enabled with the `json` codec, and not along with [`avro_ocf`](#avro_ocf)."
+			required:    false
+			type: object: {
+				examples: [{fields: {received_at: "received_at", partition: "partition"}}]
+				options: {
+					meta_key: {
+						common:      false
+						description: "The key of the metadata in the envelope."
+						required:    false
+						type: string: {
+							default: "meta"
+						}
+					}
+					event_key: {
+						common:      false
+						description: "The key of the event in the envelope."
+						required:    false
+						type: string: {
+							default: "event"
+						}
+					}
+					fields: {
+						common:      false
+						description: "The metadata written into each envelope, by name. Metadata an event has no value for, such as the source type of a metric, is left out of its envelope."
+						required:    false
+						type: object: {
+							examples: [{received_at: "received_at", source_type: "source_type", partition: "partition"}]
+							options: {
+								"*": {
+									common:      false
+									description: "The metadata written under this name."
+									required:    true
+									type: string: {
+										enum: {
+											received_at: "The timestamp of the event, set when it was received."
+											source_type: "The type of the source the event was received by."
+											partition:   "The partition of the blob the event is written to, rendered from [`blob_prefix`](#blob_prefix)."
+											encoded_at:  "The time the blob holding the event was encoded at."
+										}
+									}
+								}
+							}
+						}
+					}
+				}
+			}
+		}
+		gzip_level: {
+			common:      false
+			description: "The gzip compression level, from 0 (no compression) to 9 (best compression). Only valid when `compression` is `gzip`. Every blob is a single complete gzip member, so blobs can be concatenated into one valid gzip stream."
+			required:    false
+			type: uint: {
+				default: 6
+				examples: [1, 9]
+				unit: null
+			}
+		}
+		coalesce: {
+			common:      false
+			description: "Merges the batches of low-volume partitions into shared blobs to avoid writing many tiny blobs. A batch with fewer than `min_events` events isn't written to its own partition; instead each of its events gets the partition it came from in `partition_field` and is written under `blob_prefix` together with the events of other low-volume partitions. Batches with at least `min_events` events are written to their own partition as usual."
+			required:    false
+			type: object: {
+				examples: []
+				options: {
+					min_events: {
+						description: "The number of events below which the batch of a partition is coalesced."
+						required:    true
+						type: uint: {
+							examples: [100]
+							unit: "events"
+						}
+					}
+					blob_prefix: {
+						common:      false
+						description: "The prefix of the shared blobs. It isn't templated."
+						required:    false
+						type: string: {
+							default: "coalesced/"
+						}
+					}
+					partition_field: {
+						common:      false
+						description: "The field set to the original partition of each coalesced event."
+						required:    false
+						type: string: {
+							default: "partition"
+						}
+					}
+				}
+			}
+		}
+		imds_endpoint: {
+			common:      false
+			description: "The token endpoint of the instance metadata service used to authenticate with the managed identity of `storage_account`, for environments where it isn't reachable at the default `169.254.169.254`. When set, tokens are fetched from this endpoint only, instead of going through the chain of credentials described for `storage_account`. When both `storage_account` and `connection_string` are provided and no token is acquired within 10 seconds at startup, the connection string is used."
+			required:    false
+			type: string: {
+				default: null
+				examples: ["http://169.254.169.254/metadata/identity/oauth2/token"]
+			}
+		}
+		unresolvable_partition: {
+			common:      false
+			description: "What happens to events for which `blob_prefix` can't be rendered, for example because they lack a field it references. The number of such events is counted in `azure_blob_unresolvable_partition_events_total`, tagged with the mode."
+			required:    false
+			type: object: {
+				examples: [{mode: "fallback", partition: "unresolved/"}]
+				options: {
+					mode: {
+						description: "How the events are handled."
+						required:    true
+						type: string: {
+							enum: {
+								drop:     "The events are discarded."
+								fallback: "The events are written under `partition`."
+								reject:   "The events are rejected, so their end-to-end acknowledgement fails."
+							}
+						}
+					}
+					partition: {
+						description: "The partition the events are written under, when `mode` is `fallback`."
+						required:    false
+						common:      false
+						type: string: {
+							default: null
+							examples: ["unresolved/"]
+						}
+					}
+				}
+			}
+		}
+		force_http1: {
+			common:      false
+			description: "By default HTTP/2 is negotiated through ALPN when the storage endpoint supports it, so concurrent requests are multiplexed over a single connection. Set this to `true` to always use HTTP/1.1, for compatibility with proxies or emulators that don't handle HTTP/2."
+			required:    false
+			type: bool: default: false
+		}
+		event_time_metadata: {
+			common:      false
+			description: "When `true`, the oldest and newest event timestamps of each blob are written to its `event_time_min` and `event_time_max` metadata (`x-ms-meta-event_time_min`/`x-ms-meta-event_time_max`) as RFC 3339 timestamps, so the blob can be skipped by time range queries without being read. Events without a timestamp are ignored, and blobs where no event has a timestamp get no such metadata. Azure requires metadata names to be valid C# identifiers, hence the underscores."
+			required:    false
+			type: bool: default: false
+		}
+		content_type_sniffing: {
+			common:      false
+			description: "Guesses the `Content-Type` of each blob from the magic bytes at its start instead of deriving it from the `compression`, which helps browsers render blobs served through a CDN. Only available with the `raw_message` codec. Only the message of the first event in the blob is inspected, and at most `max_bytes` bytes of it. Blobs whose type can't be identified keep the default content type. With compression enabled the sniffed type describes the uncompressed payload, and `Content-Encoding` is still set."
+			required:    false
+			type: object: {
+				examples: []
+				options: {
+					max_bytes: {
+						common:      false
+						description: "The maximum number of leading bytes inspected."
+						required:    false
+						type: uint: {
+							default: 512
+							unit:    "bytes"
+						}
+					}
+				}
+			}
+		}
+		load_shedding: {
+			common:      false
+			description: "Drops low-priority events instead of blocking the pipeline while the sink can't keep up, for example when Azure is persistently slow. Events are queued in front of the sink; once `high_water_mark` events are queued, incoming events matching `condition` are dropped and counted in `azure_blob_shed_events_total`, while all other events wait for room in the queue as usual. Shed events are acknowledged as dropped, so they aren't redelivered by sources supporting end-to-end acknowledgements."
+			required:    false
+			type: object: {
+				examples: []
+				options: {
+					high_water_mark: {
+						description: "The number of queued events above which events matching `condition` are shed."
+						required:    true
+						type: uint: {
+							examples: [10000]
+							unit: "events"
+						}
+					}
+					condition: {
+						description: "The condition matching the low-priority events that may be shed."
+						required:    true
+						type: condition: {}
+					}
+				}
+			}
+		}
+		source_type_metadata: {
+			common:      false
+			description: "When `true`, the source types of the events of each blob are written to its `vector_source_type` metadata (`x-ms-meta-vector_source_type`), as a sorted, comma separated list, to tell which sources produced it for cost allocation and governance. Events without a source type are ignored. To keep within the 8KiB Azure allows for the metadata of a blob, blobs whose list of source types would be longer than 256 bytes get no such metadata. The id of the originating source component isn't carried by the events, so it can't be recorded."
+			required:    false
+			type: bool: default: true
+		}
+		verify_write: {
+			common:      false
+			description: "When `true`, the properties of every blob are read back once it's written, and the events are only acknowledged as delivered if the size and ETag of the stored blob match the uploaded one. A mismatch, or a blob that can't be found, is retried like a failed request, and the events are marked as errored once the retries are exhausted. When `overwrite` is `refuse` or `skip`, the mismatched blob is deleted before the retry, so that it can be written again. This trades an extra request, and its latency, per blob for the certainty that the blob is retrievable."
+			required:    false
+			type: bool: default: false
+		}
+		encoding_on_error: {
+			common:      false
+			description: "What happens to events that can't be encoded with the configured codec, for example events missing a field the `gelf` codec requires. Every event failing to encode is counted in `component_discarded_events_total`."
+			required:    false
+			type: string: {
+				default: "drop_batch"
+				enum: {
+					drop_event: "Only the events that can't be encoded are left out of the blob and acknowledged as dropped. This encodes each event on its own before writing the blob, at some CPU cost."
+					drop_batch: "The whole batch is discarded and acknowledged as dropped."
+					reject:     "Only the events that can't be encoded are left out of the blob, and they are rejected, failing their acknowledgement. This encodes each event on its own before writing the blob, at some CPU cost."
+				}
+			}
+		}
+		overwrite: {
+			common:      false
+			description: "What happens when a blob is written under the name of an existing blob, which can only happen when `blob_append_uuid` is disabled or events are replayed. Unless overwrites are allowed, every such collision is counted in `azure_blob_name_collisions_total`, tagged with the policy. A retried write finding the blob written by an earlier attempt, with the size and `Content-MD5` of its payload, counts as written rather than as a collision. When soft delete is enabled on the storage account, overwriting a blob keeps its previous content as a soft-deleted snapshot until the retention period elapses, which adds to the storage costs of sinks repeatedly overwriting the same blobs. The healthcheck reads the soft delete policy of the storage account and logs whether it is enabled, warning when overwrites are allowed."
+			required:    false
+			type: string: {
+				default: "allow"
+				enum: {
+					allow:              "The existing blob is overwritten."
+					delete_permanently: "The existing blob and its snapshots are permanently deleted before being overwritten, so soft delete doesn't retain their content. This takes two extra requests before each write, and requires permanent delete to be allowed on the storage account. Append blobs are appended to as usual."
+					refuse:             "The existing blob is left untouched and the request fails, so its events are rejected. Blobs are written with `If-None-Match: *`, so the storage account refuses the write itself without racing with other writers of the same blob. Append blobs are appended to as usual."
+					skip:               "The existing blob is left untouched and its events are dropped. Blobs are written conditionally as with `refuse`."
+				}
+			}
+		}
+		user_agent: {
+			common:      false
+			description: "The `User-Agent` header sent with every request to the storage account, which lets storage request logs and firewall rules tell this Vector instance apart from other clients. It has to be a valid HTTP header value."
+			required:    false
+			type: string: {
+				default:  "Vector/<version>"
+				examples: ["my-pipeline/1.2 (team-a)"]
+			}
+		}
+		extra_headers: {
+			common:      false
+			description: "Headers sent with every request to the storage account, keyed by the name of the header, such as a cost center tag required by a policy of the network. Names and values have to be valid HTTP headers. `Authorization`, `User-Agent`, `Host`, the `Content-*`, `Date`, `If-*` and `Range` headers and every `x-ms-` header, `x-ms-date` and `x-ms-version` included, are set by the sink or covered by the signature of the request, so they can't be configured."
+			required:    false
+			type: object: {
+				examples: [{"x-cost-center": "cc-1234"}]
+				options: {
+					"*": {
+						common:      false
+						description: "The value of the header."
+						required:    false
+						type: string: {
+							default: null
+							examples: ["cc-1234"]
+						}
+					}
+				}
+			}
+		}
+		concurrency_mode: {
+			common:      false
+			description: "How the requests in flight are limited. By default the requests of every partition share the [`request.concurrency`](#request.concurrency) limit, so several blobs of the same partition can be written at once and land in any order. Limiting the requests per partition keeps a busy partition from taking up the concurrency of the others, and with a `limit` of `1` makes the blobs of each partition be written one after the other, in the order their batches were built."
+			required:    false
+			type: object: {
+				examples: [{mode: "per_partition", limit: 1}]
+				options: {
+					mode: {
+						description: "How the requests are limited."
+						required:    true
+						type: string: {
+							enum: {
+								global:        "The requests of every partition share the `request.concurrency` limit."
+								per_partition: "At most `limit` requests of each partition are in flight at once, within the `request.concurrency` limit. The requests of a busy partition are held back while the requests of other partitions go ahead of them."
+							}
+						}
+					}
+					limit: {
+						description: "The number of requests of each partition that can be in flight at once, when `mode` is `per_partition`."
+						required:    false
+						common:      false
+						type: uint: {
+							default: null
+							examples: [1]
+							unit: "requests"
+						}
+					}
+				}
+			}
+		}
+		passthrough: {
+			common:      false
+			description: "Writes the bytes of a field of each event as is, as a blob of its own, instead of encoding the events. This is meant for events carrying whole files, such as images or archives, that must be stored unchanged. It requires the `raw_message` codec and the `drop_batch` [`encoding_on_error`](#encoding_on_error) policy, can't be combined with [`coalesce`](#coalesce) or [`content_type_sniffing`](#content_type_sniffing), and limits [`batch.max_events`](#batch.max_events) to `1`. The blobs are still compressed according to [`compression`](#compression), unless their payload is already compressed and [`precompressed`](#precompressed) is enabled."
+			required:    false
+			type: object: {
+				examples: [{field: "payload", content_type_field: "mime_type"}]
+				options: {
+					field: {
+						description: "The field of each event holding the bytes written as its blob. Events whose field is missing or doesn't hold bytes are dropped."
+						required:    false
+						common:      true
+						type: string: {
+							default: "message"
+							examples: ["payload"]
+						}
+					}
+					content_type_field: {
+						description: "The field of each event holding the content type of its blob."
+						required:    false
+						common:      false
+						type: string: {
+							default: null
+							examples: ["mime_type"]
+						}
+					}
+					content_type: {
+						description: "The content type of the blobs whose event has no `content_type_field`, or one that isn't a valid header value."
+						required:    false
+						common:      false
+						type: string: {
+							default: "application/octet-stream"
+							examples: ["image/png"]
+						}
+					}
+				}
+			}
+		}
+		time_window: {
+			common:      false
+			description: "Bounds the event times used to render the [`strftime` specifiers](\(urls.strptime_specifiers)) of [`blob_prefix`](#blob_prefix) to a window around the current time. Events timestamped outside of the window, such as events of edge devices whose clock is skewed or backfills of old data, are filed as if they were timestamped at the nearest edge of the window, under `future_prefix` or `late_prefix`, rather than creating partitions far from the current ones. Their own timestamp is left untouched. Events without a timestamp are always filed at the current time."
+			required:    false
+			type: object: {
+				examples: [{max_future_skew_secs: 300, max_past_age_secs: 86400}]
+				options: {
+					max_future_skew_secs: {
+						description: "How far ahead of the current time events can be timestamped before being filed under `future_prefix`. Events aren't bounded in the future when unset."
+						required:    false
+						common:      true
+						type: uint: {
+							default: null
+							examples: [300]
+							unit: "seconds"
+						}
+					}
+					max_past_age_secs: {
+						description: "How far behind the current time events can be timestamped before being filed under `late_prefix`. Events aren't bounded in the past when unset."
+						required:    false
+						common:      true
+						type: uint: {
+							default: null
+							examples: [86400]
+							unit: "seconds"
+						}
+					}
+					future_prefix: {
+						description: "Prepended to the blob names of the events timestamped too far ahead. It can't be a template."
+						required:    false
+						common:      false
+						type: string: {
+							default: "_future/"
+						}
+					}
+					late_prefix: {
+						description: "Prepended to the blob names of the events timestamped too far behind. It can't be a template."
+						required:    false
+						common:      false
+						type: string: {
+							default: "_late/"
+						}
+					}
+				}
+			}
+		}
+		token_warm_up: {
+			common:      false
+			description: "Acquires the Azure AD token of [`storage_account`](#storage_account) while the sink starts, so the first upload doesn't wait for the token exchange. The sink fails to start if no token can be acquired within `timeout_secs`. It has no effect when the sink falls back to [`connection_string`](#connection_string)."
+			required:    false
+			type: object: {
+				examples: [{timeout_secs: 30, keep_fresh: true}]
+				options: {
+					timeout_secs: {
+						description: "How long to wait for the token at startup."
+						required:    false
+						common:      true
+						type: uint: {
+							default: 30
+							unit:    "seconds"
+						}
+					}
+					keep_fresh: {
+						description: "Whether the token is renewed in the background ahead of its expiry, instead of by the next upload once it expires. Failures to renew it are logged and retried shortly after."
+						required:    false
+						common:      false
+						type: bool: default: false
+					}
+				}
+			}
+		}
+		avro_ocf: {
+			common:      false
+			description: "Writes each batch as an [Avro Object Container File](\(urls.avro_ocf)) embedding the schema of its records, with a `.avro` extension and the `application/avro` content type. The files compress their data blocks according to `codec`, so [`compression`](#compression) must be set to `none`. The payloads take the place of the configured codec, while its `only_fields`, `except_fields` and `timestamp_format` options still apply. Events that don't conform to `schema` are handled according to [`encoding_on_error`](#encoding_on_error). It can't be combined with the `line_protocol` codec, [`passthrough`](#passthrough), [`coalesce`](#coalesce) or [`content_type_sniffing`](#content_type_sniffing)."
+			required:    false
+			type: object: {
+				examples: [{codec: "snappy"}]
+				options: {
+					schema: {
+						description: "The Avro schema of the records, as JSON. When unset, a record schema is inferred from the events of each batch: every top level field becomes an optional field, named after the event field with the characters not allowed in Avro names replaced by `_`. Booleans, integers, floats, strings and timestamps keep their type, integers mixed with floats are written as doubles, and objects, arrays and fields holding values of different types are written as JSON strings."
+						required:    false
+						common:      true
+						type: string: {
+							default: null
+							examples: [#"{"type": "record", "name": "Log", "fields": [{"name": "message", "type": "string"}]}"#]
+						}
+					}
+					record_name: {
+						description: "The name of the records of inferred schemas."
+						required:    false
+						common:      false
+						type: string: {
+							default: "Event"
+						}
+					}
+					codec: {
+						description: "The compression of the data blocks of the files."
+						required:    false
+						common:      true
+						type: string: {
+							default: "deflate"
+							enum: {
+								none:    "The data blocks aren't compressed."
+								deflate: "The data blocks are compressed with Deflate."
+								snappy:  "The data blocks are compressed with Snappy."
+							}
+						}
+					}
+				}
+			}
+		}
+		hash_bucket: {
+			common:      false
+			description: "Splits every partition into a fixed number of buckets by the hash of a field of its events, appending `bucket=<n>/` to the blob prefix, so that a high-cardinality field can spread the load over several blobs without creating a partition per value. The events lacking the field are put under `bucket=none/`. The hash is stable across restarts, so the events holding the same value always land in the same bucket."
+			required:    false
+			type: object: {
+				examples: [{field: "user_id", buckets: 16}]
+				options: {
+					field: {
+						description: "The field whose value picks the bucket of each event. It is looked up among the tags of metrics."
+						required:    true
+						type: string: {
+							examples: ["user_id", "host"]
+						}
+					}
+					buckets: {
+						description: "The number of buckets each partition is split into."
+						required:    true
+						type: uint: {
+							examples: [16]
+							unit: null
+						}
+					}
+				}
+			}
+		}
+		token_broker: {
+			common:      false
+			description: "A token broker fronting Azure Active Directory, through which the tokens of `storage_account` are acquired instead of going through the chain of credentials described for `storage_account`. The broker answers with a JSON object holding the token as `access_token` (or `token`) and its expiry, either as `expires_on`, a Unix timestamp or an RFC 3339 date, or as `expires_in`, a number of seconds. Tokens are cached until they get close to their expiry. It can't be combined with [`imds_endpoint`](#imds_endpoint)."
+			required:    false
+			type: object: {
+				examples: [{type: "command", command: ["/usr/local/bin/token-broker", "--json"]}, {type: "http", endpoint: "http://127.0.0.1:8900/token"}]
+				options: {
+					type: {
+						description: "How the broker is reached."
+						required:    true
+						type: string: {
+							enum: {
+								command: "The broker is a command printing the token to its standard output. It runs without a shell, with no standard input and an environment holding only `PATH` and `AZURE_TOKEN_RESOURCE`, the resource the token is requested for."
+								http:    "The broker is an HTTP endpoint, requested with a `GET` whose `resource` query parameter is the resource the token is requested for."
+							}
+						}
+					}
+					command: {
+						description:   "The program to run followed by its arguments."
+						relevant_when: "type = \"command\""
+						required:      true
+						type: array: {
+							items: type: string: {
+								examples: ["/usr/local/bin/token-broker", "--json"]
+							}
+						}
+					}
+					endpoint: {
+						description:   "The URL of the endpoint."
+						relevant_when: "type = \"http\""
+						required:      true
+						type: string: {
+							examples: ["http://127.0.0.1:8900/token"]
+						}
+					}
+					timeout_secs: {
+						description: "How long to wait for the broker to answer, after which the command is killed."
+						required:    false
+						common:      false
+						type: uint: {
+							default: 10
+							unit:    "seconds"
+						}
+					}
+				}
+			}
+		}
+		write_receipts: {
+			common:      false
+			description: "Logs a receipt for every blob written, with the `Blob write receipt.` message, at the `info` level and without rate limiting. Receipts can be captured with the [`internal_logs`](\(urls.vector_internal_logs_source)) source and routed to another sink, for example to keep a durable record of the blobs for exactly-once downstream processing. Each receipt holds the `receipt_version` of its fields, currently `1`, the `container` and `blob` written, the `etag` the storage account gave the blob, the hex `content_md5` digest of its payload, the `count` of its events, its `byte_size`, and the `event_time_min` and `event_time_max` timestamps of its events, which are empty when its events have no timestamp."
+			required:    false
+			type: bool: default: false
+		}
+		create_missing_container: {
+			common:      false
+			description: "Whether the container is recreated when an upload finds it gone, for example because it was deleted while Vector is running. The upload is then tried again in the recreated container. When disabled, the sink stops with an error once 3 uploads in a row found the container gone, rejecting the events it already took in, instead of failing every upload that follows. Uploads answered with a missing blob rather than a missing container, as told by the `ContainerNotFound` error code, don't count towards it."
+			required:    false
+			type: bool: default: false
+		}
+		fan_out: {
+			common:      false
+			description: "Writes every batch to further containers of the same storage account on top of [`container_name`](#container_name), through the credentials and connections of the sink. Each container is written to independently, with retries and concurrency of its own, and the events of a batch are acknowledged once it was written to as many containers as [`fan_out.quorum`](#fan_out.quorum) requires. Per-container access tiers aren't supported, and the healthcheck only covers `container_name`."
+			required:    false
+			type: object: {
+				examples: [{containers: [{container_name: "redacted-logs"}, {container_name: "archive", blob_prefix: "copy/"}], quorum: 2}]
+				options: {
+					containers: {
+						description: "The containers every batch is written to on top of `container_name`. The same container can't be written to twice with the same prefix."
+						required:    true
+						type: array: items: type: object: {
+							examples: []
+							options: {
+								container_name: {
+									description: "The name of the container."
+									required:    true
+									type: string: {
+										examples: ["redacted-logs"]
+									}
+								}
+								blob_prefix: {
+									description: "Prepended to the name of every blob written to the container, after [`blob_prefix`](#blob_prefix)."
+									required:    false
+									type: string: {
+										default: ""
+										examples: ["copy/"]
+									}
+								}
+							}
+						}
+					}
+					quorum: {
+						description: "The number of containers, `container_name` included, a batch has to be written to for its events to be acknowledged. Defaults to all of them. The events of batches written to fewer containers are rejected, although the batch stays in the containers it was written to."
+						required:    false
+						type: uint: {
+							examples: [1, 2]
+							unit: null
+						}
+					}
+				}
+			}
+		}
+		zstd: {
+			common:      false
+			description: "Compresses every blob as a single zstd frame instead of applying [`compression`](#compression), which must then be `none`. The blobs are written with the `.zst` extension and a `zstd` content encoding. A dictionary can be used for the partitions matching a pattern, which compresses small blobs of similar events far better; the ID of the dictionary a blob was compressed with is recorded in its `zstd_dictionary_id` metadata, so that it can be decompressed with the right dictionary. Blobs of partitions no dictionary matches are compressed without one. It can't be combined with [`avro_ocf`](#avro_ocf)."
+			required:    false
+			type: object: {
+				examples: [{level: 3, dictionaries: [{partitions: "logs/*/app=nginx/", path: "/etc/vector/nginx.dict"}, {partitions: "logs/*/app=redis/", train_from: "/etc/vector/redis-sample.log"}]}]
+				options: {
+					level: {
+						common:      false
+						description: "The zstd compression level, from 1 to 22."
+						required:    false
+						type: uint: {
+							default: 3
+							unit:    null
+						}
+					}
+					dictionaries: {
+						common:      false
+						description: "The dictionaries to compress with. The first dictionary whose pattern matches the partition of a batch is used for it."
+						required:    false
+						type: array: {
+							default: []
+							items: type: object: {
+								examples: []
+								options: {
+									partitions: {
+										description: "A glob pattern matched against the partition of each batch, that is its rendered `blob_prefix`. `*` matches across `/`."
+										required:    true
+										type: string: {
+											examples: ["logs/*/app=nginx/"]
+										}
+									}
+									path: {
+										description: "A dictionary file, as trained by `zstd --train`. Exactly one of `path` and `train_from` must be set."
+										required:    false
+										type: string: {
+											default: null
+											examples: ["/etc/vector/nginx.dict"]
+										}
+									}
+									train_from: {
+										description: "A sample of the data of the partitions, one sample per line, from which the dictionary is trained when the sink starts. Exactly one of `path` and `train_from` must be set."
+										required:    false
+										type: string: {
+											default: null
+											examples: ["/etc/vector/redis-sample.log"]
+										}
+									}
+									max_size: {
+										description: "The largest dictionary trained from `train_from`, in bytes."
+										required:    false
+										type: uint: {
+											default: 112640
+											unit:    "bytes"
+										}
+									}
+								}
+							}
+						}
+					}
+				}
+			}
+		}
+		in_flight_limit: {
+			common:      false
+			description: "Caps the requests held in flight by the sink, including the ones waiting to be retried, so that an extended outage of the storage account doesn't back up the buffer in front of the sink and the components sharing it. While the requests in flight are over the limit, new batches are shed rather than queued: their events are rejected and counted by the `azure_blob_in_flight_shed_events_total` metric. Unlike [`load_shedding`](#load_shedding), which drops low-priority events queued in front of the sink, this bounds the data held by the retries regardless of its priority."
+			required:    false
+			type: object: {
+				examples: [{max_bytes: 100000000}, {max_events: 500000}]
+				options: {
+					max_bytes: {
+						common:      false
+						description: "The payload bytes of the requests in flight above which batches are shed."
+						required:    false
+						type: uint: {
+							default: null
+							unit:    "bytes"
+						}
+					}
+					max_events: {
+						common:      false
+						description: "The events of the requests in flight above which batches are shed."
+						required:    false
+						type: uint: {
+							default: null
+							unit:    "events"
+						}
+					}
+				}
+			}
+		}
+		batch_metadata: {
+			common:      false
+			description: "Blob metadata computed from each batch as a whole once it is flushed, keyed by the name of the metadata. The values are templates in which `{{ batch.count }}`, `{{ batch.count_bucket }}` (the power of ten range of the count, such as `1k-10k`), `{{ batch.byte_size }}`, `{{ batch.time_min }}`, `{{ batch.time_max }}` and `{{ batch.dominant.<field> }}` (the most frequent value of the field) refer to statistics of the batch, while `{{ <field> }}` refers to a field of its first event. As for blob index tags, at most 10 entries are allowed, names are identifiers of at most 128 characters, and values longer than 256 characters are left out, as are values referring to missing fields or timestamps. Blob index tags themselves aren't supported."
+			required:    false
+			type: object: {
+				examples: [{count_bucket: "{{ batch.count_bucket }}", level: "{{ batch.dominant.level }}", first_event: "{{ batch.time_min }}"}]
+				options: {
+					"*": {
+						common:      false
+						description: "The template of the value of the metadata."
+						required:    false
+						type: string: {
+							default: null
+							examples: ["{{ batch.count_bucket }}"]
+							syntax: "literal"
+						}
+					}
+				}
+			}
+		}
+		flush_signal: {
+			common:      false
+			description: "A signal on which all the pending batches are flushed without stopping Vector, for example ahead of a maintenance cutover. The sink stops taking in events, writes the batches of every partition and waits for the uploads in flight as it does when shutting down, then carries on with the events that followed. Each flush is counted by the `azure_blob_flushes_total` metric. Only supported on Unix."
+			required:    false
+			type: string: {
+				default: null
+				enum: {
+					sigusr1: "Flush on `SIGUSR1`."
+					sigusr2: "Flush on `SIGUSR2`."
+				}
+			}
+		}
+		success_marker: {
+			common:      false
+			description: "Writes an empty marker blob into the prefix of every partition once its time window is closed and all of its blobs were written, Hadoop-style, for downstream jobs waiting on it before reading the partition. A partition is considered closed once no blob was written to it for [`success_marker.close_after_secs`](#success_marker.close_after_secs) and none is in flight; partitions whose last blob failed to be written get no marker. Only the partitions written to since Vector started are tracked, so a restart never closes a partition early, and a marker that already exists isn't written again. With [`fan_out`](#fan_out), markers are only written to [`container_name`](#container_name)."
+			required:    false
+			type: object: {
+				examples: [{name: "_SUCCESS", close_after_secs: 900}]
+				options: {
+					name: {
+						common:      false
+						description: "The name of the marker, appended to the prefix of the partition."
+						required:    false
+						type: string: {
+							default: "_SUCCESS"
+						}
+					}
+					close_after_secs: {
+						common:      false
+						description: "How long after its last blob was written a partition is considered closed. Should exceed the batch timeout and the time late events take to arrive."
+						required:    false
+						type: uint: {
+							default: 300
+							unit:    "seconds"
+						}
+					}
+				}
+			}
+		}
+		write_endpoint: {
+			common:      false
+			description: "Routes the uploads to this endpoint, such as the private link of the storage account, rather than to the address the host of the storage account resolves to. Given as a host name or an http(s) URL without a port or path. Requests keep the host of the storage account in their `Host` header and TLS server name, as private endpoints expect, and are sent to its port. Reads, such as the ones of [`verify_write`](#verify_write), [`overwrite`](#overwrite) and the healthcheck, keep going to the storage account's own endpoint unless [`read_endpoint`](#read_endpoint) is set."
+			required:    false
+			type: string: {
+				default: null
+				examples: ["mylogstorage.privatelink.blob.core.windows.net"]
+			}
+		}
+		read_endpoint: {
+			common:      false
+			description: "Routes the reads of blobs, such as the ones of [`verify_write`](#verify_write), [`overwrite`](#overwrite) and the healthcheck, to this endpoint rather than to the address the host of the storage account resolves to, in the same way as [`write_endpoint`](#write_endpoint)."
+			required:    false
+			type: string: {
+				default: null
+				examples: ["https://logs-cdn.example.com"]
+			}
+		}
+		max_batch_age_before_send_secs: {
+			common:      false
+			description: "Drops the batches whose oldest event is older than this by the time they're about to be uploaded, such as after a long outage, rather than writing data that's no longer useful downstream. The age is checked before every attempt, retries included. Dropped batches are acknowledged as dropped rather than delivered, and counted in `azure_blob_expired_events_total`. Events without a timestamp don't count towards the age of their batch."
+			required:    false
+			type: uint: {
+				default: null
+				examples: [3600]
+				unit: "seconds"
+			}
+		}
+		blob_type: {
+			common:      false
+			description: "Picks the type of blob each event is written to from one of its fields, so that a single sink can write some events as block blobs, one per batch as usual, and append the others to a rolling append blob. The events of each batch are split by blob type before being written. The batches appended are written to a blob named from the partition and [`blob_time_format`](#blob_time_format) alone, without the UUID of [`blob_append_uuid`](#blob_append_uuid), so that the batches of a partition keep being appended to the same blob until the time format rolls it over. See [append blobs](#append-blobs)."
+			required:    false
+			type: object: {
+				examples: [{field: "kind", values: {snapshot: "block", stream: "append"}}]
+				options: {
+					field: {
+						description: "The field whose value picks the type of blob of each event. It is looked up among the tags of metrics."
+						required:    true
+						type: string: {
+							examples: ["kind"]
+						}
+					}
+					values: {
+						description: "The type of blob of the events holding each value of the field."
+						required:    true
+						type: object: {
+							examples: [{snapshot: "block", stream: "append"}]
+							options: {
+								"*": {
+									description: "The type of blob of the events holding the value."
+									required:    true
+									type: string: {
+										enum: {
+											block:  "Each batch is written as a block blob of its own."
+											append: "The batches are appended to the append blob of their partition."
+										}
+									}
+								}
+							}
+						}
+					}
+					default: {
+						common:      false
+						description: "The type of blob of the events lacking the field or holding a value missing from `values`."
+						required:    false
+						type: string: {
+							default: "block"
+							enum: {
+								block:  "Each batch is written as a block blob of its own."
+								append: "The batches are appended to the append blob of their partition."
+							}
+						}
+					}
+					ordering: {
+						common:      false
+						description: "The order the batches appended to a blob are written in. See [append blobs](#append-blobs)."
+						required:    false
+						type: string: {
+							default: "best_effort"
+							enum: {
+								best_effort: "The appends to a blob are sent concurrently, up to the request concurrency, so they may be written out of order, for example when one of them is retried."
+								strict:      "The appends to a blob are sent one at a time, each one once the previous one is done, retries included, so they are written in the order their batches were flushed. The appends to other blobs and the block blobs aren't held back. It can't be set along with `encoding_pool_size`."
+							}
+						}
+					}
+				}
+			}
+		}
+		batch_acknowledgements: {
+			common:      false
+			description: "Merges the finalizers of the events of each batch into one for each batch they were received in, when the batch is built, and resolves them in a single pass once its blob is written, rather than one by one for each event. This reduces the overhead of acknowledging events at very high rates. Events are still only acknowledged once their blob is written, retries included, or rejected once it can't be. The time taken to resolve them is reported by the `azure_blob_finalizer_resolution_seconds` metric."
+			required:    false
+			type: bool: default: false
+		}
+		precompressed: {
+			common:      false
+			description: "Writes the payloads that are already compressed as is, instead of compressing them a second time according to [`compression`](#compression) or [`zstd`](#zstd). The events of each batch are split into a blob of the events to compress and a blob for each content encoding the other events are compressed with, which is set as the content encoding of their blob, along with the `application/octet-stream` content type unless [`passthrough`](#passthrough) picks one, and gives them their extension, such as `.gz` for `gzip`. Their payloads have to be written unchanged, so it requires the `raw_message` codec, along with the `bytes` framing unless [`passthrough`](#passthrough) is enabled, and can't be combined with [`avro_ocf`](#avro_ocf)."
+			required:    false
+			type: object: {
+				examples: [{marker_field: "content_encoding"}]
+				options: {
+					field: {
+						description: "The field of each event holding the payload checked for magic bytes."
+						required:    false
+						common:      false
+						type: string: {
+							default: "message"
+							examples: ["payload"]
+						}
+					}
+					detect_magic_bytes: {
+						description: "Whether payloads starting with the magic bytes of gzip (`1f 8b`) or zstd (`28 b5 2f fd`) are taken as already compressed, with the `gzip` or `zstd` content encoding."
+						required:    false
+						common:      true
+						type: bool: default: true
+					}
+					marker_field: {
+						description: "The field of each event holding the content encoding its payload is already compressed with, one of `br`, `compress`, `deflate`, `gzip` or `zstd`. It takes precedence over the magic bytes, and other values are ignored."
+						required:    false
+						common:      true
+						type: string: {
+							default: null
+							examples: ["content_encoding"]
+						}
+					}
+				}
+			}
+		}
+		csv: {
+			common:      false
+			description: "Writes each batch as a CSV table, with a row for each log and a column for each of the fields listed, instead of encoding its events with the codec. Values holding the delimiter, quotes or line breaks are quoted, with their quotes doubled. The blobs are given the `text/csv` content type and the `.csv` extension, followed by the extension of their compression. Metrics can't be written as CSV. It can't be combined with [`passthrough`](#passthrough), [`avro_ocf`](#avro_ocf), [`envelope`](#envelope), [`precompressed`](#precompressed) or [`content_type_sniffing`](#content_type_sniffing)."
+			required:    false
+			type: object: {
+				examples: [{columns: ["timestamp", "host", "message"], header: "sidecar"}]
+				options: {
+					columns: {
+						description: "The fields written as the columns of each row, in order. Nested fields are listed by path. It can only be empty when `unlisted_fields` is `append`."
+						required:    false
+						common:      true
+						type: array: {
+							default: []
+							items: type: string: examples: ["timestamp", "host", "message"]
+						}
+					}
+					unlisted_fields: {
+						description: "What happens to the fields of the events missing from `columns`."
+						required:    false
+						common:      false
+						type: string: {
+							default: "drop"
+							enum: {
+								drop:   "The fields are left out."
+								append: "The top-level fields of the batch missing from `columns` are written as columns of their own after the listed ones, sorted by name, so that every row of a blob has the same columns."
+							}
+						}
+					}
+					header: {
+						description: "Where the header row naming the columns is written."
+						required:    false
+						common:      true
+						type: string: {
+							default: "first_line"
+							enum: {
+								first_line: "As the first line of each blob. It can't be used when [`blob_type`](#blob_type) appends to blobs, as every batch appended would repeat it."
+								sidecar:    "As a `_schema.csv` blob written next to the blobs of each directory, along with the first blob written to it since the sink started. It can't be used when `unlisted_fields` is `append`, as the columns of the blobs would vary."
+								none:       "Nowhere."
+							}
+						}
+					}
+					null_placeholder: {
+						description: "The value written for null fields and fields missing from an event."
+						required:    false
+						common:      false
+						type: string: {
+							default: ""
+							examples: ["NULL", "\\N"]
+						}
+					}
+					delimiter: {
+						description: "The character separating the values of each row. It has to be an ASCII character other than a quote or a line break."
+						required:    false
+						common:      false
+						type: string: {
+							default: ","
+							examples: [";", "\t"]
+						}
+					}
+				}
+			}
+		}
+		drain_on_reload: {
+			common:      false
+			description: "Whether the requests queued or in flight when the sink is reloaded or shut down are written before it stops. When disabled, the sink stops as soon as its input ends: the requests that haven't started yet, including the ones for the batches still pending, are cancelled right away, and the requests in flight are cancelled along with their retries, closing their connections. The events of the cancelled requests are rejected rather than dropped, so that sources supporting end-to-end acknowledgements deliver them again, and are counted by the `azure_blob_cancelled_events_total` metric."
+			required:    false
+			type: bool: default: true
+		}
+		schema_fingerprint: {
+			common:      false
+			description: "Groups the events of every partition by the fingerprint of their schema, appending `schema=<fingerprint>/` to the blob prefix after any [`hash_bucket`](#hash_bucket), so that every blob holds events of a single shape for columnar readers. The fingerprint is a stable hash of the paths and types of the fields of each event, or of the value type and tag names of metrics, regardless of their values, of the order the fields were inserted in and of the length of arrays. It is also written to the `schema_fingerprint` metadata of the blobs."
+			required:    false
+			type: object: {
+				examples: [{max_fingerprints: 50}]
+				options: {
+					max_fingerprints: {
+						description: "The number of distinct schemas the events are grouped by, past which the events of any other schema are grouped together under `schema=mixed/`. The schemas are counted from the start of the sink, in the order they are first seen."
+						required:    false
+						common:      true
+						type: uint: default: 100
+					}
+				}
+			}
+		}
+		schema_version: {
+			common:      false
+			description: "Groups the events of every partition by the version of their schema, appending `version=<version>/` to the blob prefix before any [`hash_bucket`](#hash_bucket), so that no blob mixes several versions, even when its events share every other part of their partition, such as a time window. The events lacking the field are grouped under `version=none/`. The version is also written to the `schema_version` metadata of the blobs whose events hold one. The [`blob_prefix`](#blob_prefix) can reference the field as well, for example to put the version first."
+			required:    false
+			type: object: {
+				examples: [{field: "schema_version"}]
+				options: {
+					field: {
+						description: "The field holding the version of the schema of each event, looked up among the tags of metrics."
+						required:    false
+						common:      true
+						type: string: default: "schema_version"
+					}
+				}
+			}
+		}
+		encoding_pool_size: {
+			common:      false
+			description: "Encodes and compresses the batches on up to this many blocking threads, off the threads uploading the blobs, so that CPU-heavy encodings, such as high `zstd` levels, stall neither the uploads nor the batches encoded after them. Each request is handed over for upload as soon as it is built, so the batches of a partition can be uploaded in a different order than they were flushed. No more batches are taken in while every thread is busy. By default, the batches are encoded alongside the uploads and handed over in the order they were flushed."
+			required:    false
+			type: uint: {
+				default: null
+				examples: [4]
+			}
+		}
+		acknowledgement_provenance: {
+			common:      false
+			description: "Logs where the events of every batch were written right before they are acknowledged, with the `Acknowledging events written to blob.` message, at the `info` level and without rate limiting, so that the consumers of the acknowledgements can tell where each batch was stored. The records can be captured with the [`internal_logs`](\(urls.vector_internal_logs_source)) source. Each record holds the `container` and `blob` written, the `etag` the storage account gave the blob, the `written_at` time the write completed, and the `count` of its events. With `fan_out`, the record describes the first container the batch was written to."
+			required:    false
+			type: bool: default: false
+		}
+		content_language: {
+			common:      false
+			description: "Sets the `Content-Language` of every blob, which is served back by the storage account and the CDNs in front of it, from a language rendered from its events, such as a detected-language field. The rendered language must be a well-formed [BCP 47](https://www.rfc-editor.org/info/bcp47) language tag, such as `en`, `pt-BR` or `zh-Hant-TW`. The batches aren't split by language, so the blob only gets the language when all of its events render the same valid tag; otherwise it gets `default`, or no language at all. Reference the same field in [`blob_prefix`](#blob_prefix) for the events of every batch to share their language. The language of append blobs is the one of the batch that created them."
+			required:    false
+			type: object: {
+				examples: [{template: "{{ detected_language }}", default: "en"}]
+				options: {
+					template: {
+						description: "The template rendering the language of each event."
+						required:    true
+						type: string: {
+							examples: ["{{ detected_language }}", "{{ metadata.language }}"]
+							syntax: "template"
+						}
+					}
+					default: {
+						description: "The language of the blobs whose events don't all render the same valid language tag, which must be a well-formed BCP 47 language tag itself. By default, such blobs have no language."
+						required:    false
+						common:      true
+						type: string: {
+							default: null
+							examples: ["en", "en-US"]
+						}
+					}
+				}
+			}
+		}
+		sealed_batch: {
+			common:      false
+			description: "Seals every batch by appending an entry pointing to its data blob, with the `blob` name, its `etag`, the `count` of its events and the `sealed_at` time, to an index blob in the prefix of its partition once the data blob and its sidecars are written, so that the readers going through the index never see a data blob before it is complete. As the partitions hold the time window of [`blob_prefix`](#blob_prefix), each window gets an index of its own, written as an append blob of JSON lines. The events of a batch are only acknowledged once both its data blob and its index entry are written. When the entry can't be, the request fails, its events are rejected, and the data blob is handled according to `orphans`; each such blob is counted by the `azure_blob_orphaned_blobs_total` metric. Can't be used along with a `blob_type` appending to blobs."
+			required:    false
+			type: object: {
+				examples: [{index_name: "_index.jsonl", orphans: "delete"}]
+				options: {
+					index_name: {
+						description: "The name of the index blob written into the prefix of each partition."
+						required:    false
+						common:      true
+						type: string: default: "_index.jsonl"
+					}
+					orphans: {
+						description: "What happens to a data blob once its index entry fails to be written."
+						required:    false
+						common:      true
+						type: string: {
+							default: "delete"
+							enum: {
+								delete: "The data blob is deleted."
+								mark:   "The data blob is kept, with its `orphaned` metadata set to `true`, along with the metadata it was written with."
+							}
+						}
+					}
+				}
+			}
+		}
+		max_inflight_bytes: {
+			common:      false
+			description: "Caps the sum of the payload sizes of the requests in flight, retries included, on top of the number of requests limited by `request.concurrency`, so that bursts of large batches don't take up more memory than this. A batch is held back, along with the batches built after it, until the requests in flight leave room for its payload; the batches larger than the limit wait for the room of the whole limit, and so are sent alone. Unlike [`in_flight_limit`](#in_flight_limit), which sheds batches once over its limit, this blocks, applying backpressure to the pipeline in front of the sink."
+			required:    false
+			type: uint: {
+				default: null
+				examples: [104857600]
+				unit: "bytes"
+			}
+		}
+		blob_path_policy: {
+			common:      false
+			description: "What happens to the batches whose partition, as rendered from [`blob_prefix`](#blob_prefix), is a malformed blob path, holding double slashes, leading slashes, `.` segments or backslashes, which some consumers of the blobs, such as the ones mounting the container as a file system, handle differently. Under either `normalize` or `reject`, the paths that no normalization can make valid per Azure's blob naming rules, holding `..` segments or control characters, longer than 1024 characters, or leaving no room for the name of the blob within 254 segments, are rejected along with their events. Every path normalized or rejected is counted by the `azure_blob_malformed_paths_total` metric."
+			required:    false
+			type: string: {
+				default: "keep"
+				enum: {
+					keep:      "The paths are written as rendered, without being checked."
+					normalize: "The backslashes are turned into slashes, and the leading slashes, redundant slashes and `.` segments are removed."
+					reject:    "The events of the batches whose paths are malformed are rejected."
+				}
+			}
+		}
 	}
 
 	input: {
-		logs:    true
-		metrics: null
-		traces:  false
+		logs: true
+		metrics: {
+			counter:      true
+			distribution: true
+			gauge:        true
+			histogram:    true
+			set:          true
+			summary:      true
+		}
+		traces: false
 	}
 
 	how_it_works: {
@@ -164,17 +1308,150 @@ components: sinks: azure_blob: {
 
 				You can control the resulting name via the [`blob_prefix`](#blob_prefix),
 				[`blob_time_format`](#blob_time_format), and [`blob_append_uuid`](#blob_append_uuid) options.
+				With [`ordering_key`](#ordering_key), the timestamp is replaced by a key sorting in
+				the order the blobs were named:
+
+				```text
+				blob/2022-10-01/0001664625600123456-20221001T120000.123456Z-fddd7a0e-fad9-4f7e-9bce-00ae5debc563.log.gz
+				```
+				"""
+		}
+		line_protocol: {
+			title: "InfluxDB line protocol"
+			body:  """
+				With the `line_protocol` codec, the sink accepts metrics instead of logs and writes
+				each of them as a point in [InfluxDB line protocol](\(urls.influxdb_line_protocol)),
+				laid out as the [`influxdb_metrics`](\(urls.vector_influxdb_metrics_sink)) sink writes
+				it, so the blobs can be replayed into InfluxDB later. The measurement is the namespace
+				and name of the metric joined by a dot, and the tags of the metric are completed by a
+				`metric_type` tag.
+
+				The blobs are named with a `.lp` extension instead of `.log`, and are written with the
+				`text/plain; charset=utf-8` content type unless they are compressed. Events that
+				can't be written as line protocol, such as metrics with a line break in their name or
+				tags, are handled according to [`encoding_on_error`](#encoding_on_error).
+				"""
+		}
+		sent_events_per_partition: {
+			title: "Sent events per partition"
+			body:  """
+				The `component_sent_events_total` and `component_sent_event_bytes_total` metrics
+				are tagged with the partition each blob was written for, rendered from
+				[`blob_prefix`](#blob_prefix), in their `output` tag. This lets the GraphQL API, and the
+				tools built on it, break the throughput of the sink down by destination prefix.
+
+				Each rendered partition is a separate series, so prefixes templated with fine-grained
+				dates or high cardinality fields produce many of them.
+				"""
+		}
+		retry_after: {
+			title: "Throttling"
+			body:  """
+				When an upload is throttled with a `429 Too Many Requests` or `503 Server Busy`
+				response carrying a `Retry-After` header, the upload is retried after the delay the
+				header asks for, capped at [`request.retry_max_duration_secs`](#request.retry_max_duration_secs),
+				rather than after the computed backoff. Every delay applied before a retry is recorded
+				in the `azure_blob_retry_backoff_seconds` histogram, tagged with the `source` of the
+				delay.
+
+				A healthcheck throttled with a `429 Too Many Requests` response is retried too, up to
+				4 attempts in all, after the delay its `Retry-After` header asks for, or after a
+				backoff starting at one second otherwise, capped at 10 seconds. Vector only fails to
+				start once every attempt was throttled.
+				"""
+		}
+		byte_accounting: {
+			title: "Byte accounting"
+			body:  """
+				All the byte counts of the sink are in raw bytes, neither in decimal (MB, 1,000,000
+				bytes) nor in binary (MiB, 1,048,576 bytes) units, which are left to the dashboards
+				built on them:
+
+				* `component_sent_event_bytes_total` is the size of the events written, in memory,
+				  before they were encoded.
+				* `component_sent_bytes_total` is the length of the bodies of the blobs written,
+				  once encoded and compressed, each counted once when its upload succeeded.
+				* `azure_blob_request_body_bytes_total` is the length of the bodies of all the
+				  uploads the storage account answered, including the ones it refused and the
+				  ones retried.
+
+				The last one is the one to reconcile with the `Ingress` metric of the storage account
+				in the Azure portal, which also counts the headers of the requests, and the requests
+				of the healthcheck and of the options reading or writing other blobs, so it runs
+				slightly above.
+				"""
+		}
+		append_blobs: {
+			title: "Append blobs"
+			body:  """
+				With [`blob_type`](#blob_type), the batches of the events routed to append blobs are
+				appended as blocks to the blob of their partition. The blob is created by the first
+				batch finding it missing, with the content type, content encoding and metadata of
+				that batch, which aren't updated by the batches appended afterwards.
+
+				Appended payloads are concatenated as they are, so the blobs are only readable as a
+				whole with framings and compressions that can be concatenated: newline delimited
+				events, and `gzip` or `zstd`, whose members or frames follow each other. Appending
+				isn't supported along with `avro_ocf` or `zlib`, nor with `verify_write`,
+				`sidecar_checksum` or `overwrite`, which describe blobs written as a whole.
+
+				An append blob holds at most 50,000 blocks of at most 4 MiB each, so keep
+				`batch.max_bytes` under 4 MiB and let `blob_time_format` roll the blobs over before
+				they fill up. Creations are only coordinated within a Vector instance: when several
+				instances write to the same container, make their partitions distinct, such as by
+				including the host in `blob_prefix`, so that they don't append to the same blobs.
+
+				By default, the batches appended to a blob are sent as concurrently as any other
+				request, so a batch can be appended before a batch flushed earlier, such as while the
+				earlier one is retried, and the blob no longer holds the events in order. With
+				`blob_type.ordering` set to `strict`, the appends to each blob are sent one at a time,
+				in the order their batches were flushed, at the cost of the throughput of each blob.
+				An append that fails for good is skipped, and the next one is sent in its place.
+				"""
+		}
+		size_limits: {
+			title: "Size limits"
+			body:  """
+				Azure refuses block blobs larger than 256 MiB written in a single request, and
+				blocks larger than 4 MiB appended to append blobs. Blobs over these limits once
+				encoded and compressed aren't sent: their events are rejected right away, rather
+				than once the whole payload has been uploaded, and they are counted by the
+				`azure_blob_oversized_batch_total` metric. Keep `batch.max_bytes` under the limit
+				of the type of blob written.
 				"""
 		}
 	}
 
 	telemetry: metrics: {
-		component_sent_events_total:      components.sources.internal_metrics.output.metrics.component_sent_events_total
-		component_sent_event_bytes_total: components.sources.internal_metrics.output.metrics.component_sent_event_bytes_total
-		events_discarded_total:           components.sources.internal_metrics.output.metrics.events_discarded_total
-		processing_errors_total:          components.sources.internal_metrics.output.metrics.processing_errors_total
-		http_error_response_total:        components.sources.internal_metrics.output.metrics.http_error_response_total
-		http_request_errors_total:        components.sources.internal_metrics.output.metrics.http_request_errors_total
-		processed_bytes_total:            components.sources.internal_metrics.output.metrics.processed_bytes_total
+		azure_blob_average_blob_size_bytes:             components.sources.internal_metrics.output.metrics.azure_blob_average_blob_size_bytes
+		azure_blob_blobs_per_event:                     components.sources.internal_metrics.output.metrics.azure_blob_blobs_per_event
+		azure_blob_cancelled_events_total:              components.sources.internal_metrics.output.metrics.azure_blob_cancelled_events_total
+		azure_blob_delivery_attempts:                   components.sources.internal_metrics.output.metrics.azure_blob_delivery_attempts
+		azure_blob_delivery_duration_seconds:           components.sources.internal_metrics.output.metrics.azure_blob_delivery_duration_seconds
+		azure_blob_expired_events_total:                components.sources.internal_metrics.output.metrics.azure_blob_expired_events_total
+		azure_blob_fan_out_target_errors_total:         components.sources.internal_metrics.output.metrics.azure_blob_fan_out_target_errors_total
+		azure_blob_finalizer_double_take_total:         components.sources.internal_metrics.output.metrics.azure_blob_finalizer_double_take_total
+		azure_blob_finalizer_resolution_seconds:        components.sources.internal_metrics.output.metrics.azure_blob_finalizer_resolution_seconds
+		azure_blob_flushes_total:                       components.sources.internal_metrics.output.metrics.azure_blob_flushes_total
+		azure_blob_in_flight_shed_bytes_total:          components.sources.internal_metrics.output.metrics.azure_blob_in_flight_shed_bytes_total
+		azure_blob_in_flight_shed_events_total:         components.sources.internal_metrics.output.metrics.azure_blob_in_flight_shed_events_total
+		azure_blob_malformed_paths_total:               components.sources.internal_metrics.output.metrics.azure_blob_malformed_paths_total
+		azure_blob_name_collisions_total:               components.sources.internal_metrics.output.metrics.azure_blob_name_collisions_total
+		azure_blob_objects_written_total:               components.sources.internal_metrics.output.metrics.azure_blob_objects_written_total
+		azure_blob_orphaned_blobs_total:                components.sources.internal_metrics.output.metrics.azure_blob_orphaned_blobs_total
+		azure_blob_oversized_batch_total:               components.sources.internal_metrics.output.metrics.azure_blob_oversized_batch_total
+		azure_blob_request_body_bytes_total:            components.sources.internal_metrics.output.metrics.azure_blob_request_body_bytes_total
+		azure_blob_retry_backoff_seconds:               components.sources.internal_metrics.output.metrics.azure_blob_retry_backoff_seconds
+		azure_blob_shed_events_total:                   components.sources.internal_metrics.output.metrics.azure_blob_shed_events_total
+		azure_blob_success_markers_total:               components.sources.internal_metrics.output.metrics.azure_blob_success_markers_total
+		azure_blob_unresolvable_partition_events_total: components.sources.internal_metrics.output.metrics.azure_blob_unresolvable_partition_events_total
+		component_sent_bytes_total:                     components.sources.internal_metrics.output.metrics.component_sent_bytes_total
+		component_sent_events_total:                    components.sources.internal_metrics.output.metrics.component_sent_events_total
+		component_sent_event_bytes_total:               components.sources.internal_metrics.output.metrics.component_sent_event_bytes_total
+		events_discarded_total:                         components.sources.internal_metrics.output.metrics.events_discarded_total
+		processing_errors_total:                        components.sources.internal_metrics.output.metrics.processing_errors_total
+		http_error_response_total:                      components.sources.internal_metrics.output.metrics.http_error_response_total
+		http_request_errors_total:                      components.sources.internal_metrics.output.metrics.http_request_errors_total
+		processed_bytes_total:                          components.sources.internal_metrics.output.metrics.processed_bytes_total
 	}
 }
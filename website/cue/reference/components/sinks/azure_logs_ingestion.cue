@@ -0,0 +1,132 @@
+package metadata
+
+components: sinks: azure_logs_ingestion: {
+	title: "Azure Monitor Logs Ingestion"
+
+	classes: {
+		commonly_used: false
+		delivery:      "at_least_once"
+		development:   "beta"
+		egress_method: "batch"
+		service_providers: ["Azure"]
+		stateful: false
+	}
+
+	features: {
+		acknowledgements: true
+		healthcheck: enabled: true
+		send: {
+			batch: {
+				enabled:      true
+				common:       false
+				max_bytes:    1_000_000
+				timeout_secs: 1.0
+			}
+			compression: enabled: false
+			encoding: {
+				enabled: true
+				codec: enabled: false
+			}
+			proxy: enabled:   true
+			request: enabled: false
+			tls: {
+				enabled:                true
+				can_verify_certificate: true
+				can_verify_hostname:    true
+				enabled_default:        false
+			}
+			to: {
+				service: services.azure_monitor_logs
+
+				interface: {
+					socket: {
+						api: {
+							title: "Azure Monitor Logs Ingestion API"
+							url:   urls.azure_logs_ingestion_api
+						}
+						direction: "outgoing"
+						protocols: ["http"]
+						ssl: "required"
+					}
+				}
+			}
+		}
+	}
+
+	support: {
+		requirements: [
+			"""
+				The credential Vector authenticates with must be granted the `Monitoring Metrics Publisher`
+				role on the Data Collection Rule.
+				""",
+		]
+		warnings: []
+		notices: []
+	}
+
+	configuration: {
+		dcr_immutable_id: {
+			description: "The immutable ID of the Data Collection Rule the logs are sent through."
+			required:    true
+			type: string: {
+				examples: ["dcr-00000000000000000000000000000000"]
+			}
+		}
+		endpoint: {
+			description: "The logs ingestion endpoint of the Data Collection Endpoint, or of the Data Collection Rule when it has one of its own."
+			required:    true
+			type: string: {
+				examples: ["https://my-dce-5kyl.westeurope-1.ingest.monitor.azure.com"]
+			}
+		}
+		imds_endpoint: {
+			common:      false
+			description: "The token endpoint of the instance metadata service used to authenticate with the managed identity, for environments where it isn't reachable at the default `169.254.169.254`. When set, tokens are fetched from this endpoint only, instead of going through the chain of credentials of the [`azure_blob`](\(urls.vector_sinks)/azure_blob/#storage_account) sink: the `AZURE_CLIENT_ID`, `AZURE_CLIENT_SECRET` and `AZURE_TENANT_ID` environment variables, the managed identity, then the Azure CLI."
+			required:    false
+			type: string: {
+				default: null
+				examples: ["http://169.254.169.254/metadata/identity/oauth2/token"]
+			}
+		}
+		stream_name: {
+			description: "The stream of the Data Collection Rule the logs are sent to, which maps them onto the columns of its table."
+			required:    true
+			type: string: {
+				examples: ["Custom-MyTable_CL"]
+			}
+		}
+		token_broker: {
+			common:      false
+			description: "A token broker fronting Azure Active Directory, through which the tokens are acquired, configured as for the [`azure_blob`](\(urls.vector_sinks)/azure_blob/#token_broker) sink. It can't be combined with [`imds_endpoint`](#imds_endpoint)."
+			required:    false
+			type: object: {
+				examples: [{type: "command", command: ["/usr/local/bin/token-broker", "--json"]}, {type: "http", endpoint: "http://127.0.0.1:8900/token"}]
+				options: {}
+			}
+		}
+	}
+
+	input: {
+		logs:    true
+		metrics: null
+		traces:  false
+	}
+
+	how_it_works: {
+		retries: {
+			title: "Retries"
+			body: """
+				Requests throttled with a `429 Too Many Requests` or failing with a server error are
+				retried. Any other rejection, for example of events that don't match the stream
+				declaration, is permanent and the events are dropped.
+				"""
+		}
+	}
+
+	telemetry: metrics: {
+		component_sent_bytes_total:       components.sources.internal_metrics.output.metrics.component_sent_bytes_total
+		component_sent_events_total:      components.sources.internal_metrics.output.metrics.component_sent_events_total
+		component_sent_event_bytes_total: components.sources.internal_metrics.output.metrics.component_sent_event_bytes_total
+		events_out_total:                 components.sources.internal_metrics.output.metrics.events_out_total
+	}
+}
@@ -0,0 +1,211 @@
+package metadata
+
+components: sources: azure_blob: {
+	title: "Azure Blob Storage"
+
+	features: {
+		acknowledgements: true
+		multiline: enabled: false
+		codecs: {
+			enabled:         true
+			default_framing: "`newline_delimited` for codecs other than `native`, which defaults to `length_delimited`"
+		}
+		collect: {
+			tls: enabled:        false
+			checkpoint: enabled: true
+			proxy: enabled:      false
+			from: service:       services.azure_blob
+		}
+	}
+
+	classes: {
+		commonly_used: false
+		deployment_roles: ["aggregator"]
+		delivery:      "at_least_once"
+		development:   "beta"
+		egress_method: "batch"
+		stateful:      false
+	}
+
+	support: {
+		requirements: []
+		warnings: []
+		notices: []
+	}
+
+	installation: {
+		platform_name: null
+	}
+
+	configuration: {
+		acknowledgements: configuration._source_acknowledgements
+		archive_tier: {
+			common:      false
+			description: "What happens to the blobs in the archive tier, which can't be read until they are rehydrated. The number of such blobs is counted in `azure_blob_source_archived_blobs_total`, tagged with the `action` taken."
+			required:    false
+			type: string: {
+				default: "skip"
+				enum: {
+					skip:      "The blobs are skipped, to be read by a later run once rehydrated."
+					rehydrate: "The blobs are moved to the hot tier, which takes up to several hours, to be read by a later run."
+				}
+			}
+		}
+		blob_prefix: {
+			common:      true
+			description: "The prefix of the names of the blobs read."
+			required:    false
+			type: string: {
+				default: ""
+				examples: ["date/2022-09-01/"]
+			}
+		}
+		blob_time_format: {
+			common:      false
+			description: "The [`strftime` format](\(urls.strptime_specifiers)) of the time the names of the blobs hold right after `blob_prefix`, as set with the `blob_time_format` of the `azure_blob` sink, possibly preceded by the date directories of its `blob_prefix`. It is needed by `start_time` and `end_time`. The blobs whose names hold no time are left out when they are set."
+			required:    false
+			type: string: {
+				default: null
+				examples: ["%s", "%Y/%m/%d/%s"]
+			}
+		}
+		compression: {
+			common:      false
+			description: "The compression of the blobs."
+			required:    false
+			type: string: {
+				default: "auto"
+				enum: {
+					auto: "Picked for each blob from its `Content-Encoding`, or else from the extension of its name, as written by the `azure_blob` sink."
+					none: "Uncompressed."
+					gzip: "Gzip, possibly made of several concatenated members."
+					zlib: "Zlib."
+					zstd: "Zstandard. Blobs compressed with a dictionary, as written by the `zstd.dictionaries` of the `azure_blob` sink, are rejected."
+				}
+			}
+		}
+		connection_string: {
+			description: "The Azure Blob Storage Account connection string. This or `storage_account` has to be provided."
+			required:    false
+			common:      true
+			type: string: {
+				default: null
+				examples: ["DefaultEndpointsProtocol=https;AccountName=mylogstorage;AccountKey=storageaccountkeybase64encoded;EndpointSuffix=core.windows.net"]
+			}
+		}
+		container_name: {
+			description: "The Azure Blob Storage Account container name."
+			required:    true
+			type: string: {
+				examples: ["my-logs"]
+			}
+		}
+		data_dir: {
+			common:      false
+			description: "The directory used to persist the names of the blobs already read, so that they aren't read again after a restart. By default, the [global `data_dir` option](\(urls.vector_configuration)/global-options#data_dir) is used."
+			required:    false
+			type: string: {
+				default: null
+				examples: ["/var/lib/vector"]
+			}
+		}
+		end_time: {
+			common:      false
+			description: "The time until which the blobs are read, excluded, as an RFC 3339 timestamp compared with the time the names of the blobs hold."
+			required:    false
+			type: string: {
+				default: null
+				examples: ["2022-09-02T00:00:00Z"]
+			}
+		}
+		imds_endpoint: {
+			common:      false
+			description: "The token endpoint of the instance metadata service used to authenticate with the managed identity of `storage_account`, for environments where it isn't reachable at the default `169.254.169.254`."
+			required:    false
+			type: string: {
+				default: null
+				examples: ["http://169.254.169.254/metadata/identity/oauth2/token"]
+			}
+		}
+		start_time: {
+			common:      false
+			description: "The time from which the blobs are read, as an RFC 3339 timestamp compared with the time the names of the blobs hold."
+			required:    false
+			type: string: {
+				default: null
+				examples: ["2022-09-01T00:00:00Z"]
+			}
+		}
+		storage_account: {
+			description: "The Azure Blob Storage Account name, authenticated with the same chain of credentials as the [`azure_blob` sink](\(urls.vector_sinks)/azure_blob/#storage_account). This or `connection_string` has to be provided."
+			required:    false
+			common:      true
+			type: string: {
+				default: null
+				examples: ["mylogstorage"]
+			}
+		}
+	}
+
+	output: logs: object: {
+		description: "An event read from a blob."
+		fields: {
+			message: {
+				description: "The message of the event, unless the codec decoded it into other fields."
+				required:    false
+				type: string: {
+					examples: ["53.126.150.246 - - [01/Oct/2020:11:25:58 -0400] \"GET /disintermediate HTTP/2.0\" 401 20308"]
+				}
+			}
+			timestamp: fields._current_timestamp & {
+				description: "The time the event was read at, unless the event holds one."
+			}
+			container: {
+				description: "The container of the blob the event came from."
+				required:    true
+				type: string: {
+					examples: ["my-logs"]
+				}
+			}
+			blob: {
+				description: "The blob the event came from."
+				required:    true
+				type: string: {
+					examples: ["date/2022-09-01/1662026400-1f8b2c2e-2e4a-4b1c-9f36-0f3e1a6e2d4b.log.gz"]
+				}
+			}
+		}
+	}
+
+	how_it_works: {
+		replay: {
+			title: "Replaying blobs"
+			body:  """
+				The source lists the blobs under `blob_prefix` when it starts, reads those it
+				hasn't read yet in the order of their names, one page of the listing at a time,
+				then stops. Each blob is downloaded in ranges, decompressed and decoded with the
+				configured codec as it is downloaded, so blobs written by the `azure_blob` sink can
+				be read back by configuring the same framing and codec without being held in memory
+				whole. Blobs written as Avro Object Container Files can't be read, and blobs
+				compressed with a zstd dictionary are rejected with an error.
+
+				The blobs read are recorded in the data directory once their events were sent, or
+				acknowledged when `acknowledgements` are enabled, so that restarting Vector doesn't
+				read them again, whatever the order their names were written in. The checkpoint is
+				written every 100 changes and when the source stops. A blob whose events weren't
+				delivered, or that was in the archive tier, is read again by a later run. The blobs
+				read that a complete listing no longer finds are forgotten, so that the checkpoint
+				only grows with the blobs kept in the container.
+				"""
+		}
+	}
+
+	telemetry: metrics: {
+		azure_blob_source_archived_blobs_total: components.sources.internal_metrics.output.metrics.azure_blob_source_archived_blobs_total
+		component_errors_total:                 components.sources.internal_metrics.output.metrics.component_errors_total
+		component_received_bytes_total:         components.sources.internal_metrics.output.metrics.component_received_bytes_total
+		component_received_events_total:        components.sources.internal_metrics.output.metrics.component_received_events_total
+		component_received_event_bytes_total:   components.sources.internal_metrics.output.metrics.component_received_event_bytes_total
+		events_in_total:                        components.sources.internal_metrics.output.metrics.events_in_total
+	}
+}
@@ -129,6 +129,233 @@ components: sources: internal_metrics: {
 			default_namespace: "vector"
 			tags:              _internal_metrics_tags
 		}
+		azure_blob_average_blob_size_bytes: {
+			description:       "The average compressed size of the blobs the `azure_blob` sink wrote to each container over the last `batch_summary_interval_secs`. Small averages point to batches flushed before they fill up, which multiplies the writes and their costs."
+			type:              "gauge"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				container: {
+					description: "The container the blobs were written to."
+					required:    true
+				}
+			}
+		}
+		azure_blob_blobs_per_event: {
+			description:       "The number of blobs the `azure_blob` sink wrote to each container per event over the last `batch_summary_interval_secs`. The closer to 1, the fewer events each blob holds."
+			type:              "gauge"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				container: {
+					description: "The container the blobs were written to."
+					required:    true
+				}
+			}
+		}
+		azure_blob_cancelled_events_total: {
+			description:       "The number of events the `azure_blob` sink rejected as it cancelled their requests on reload or shutdown, when `drain_on_reload` is disabled."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				state: {
+					description: "The state of the request when it was cancelled."
+					required:    true
+					enum: {
+						queued:    "The request hadn't started yet."
+						in_flight: "The request was being written."
+					}
+				}
+			}
+		}
+		azure_blob_delivery_attempts: {
+			description:       "The number of attempts it took to write each blob, including the successful one."
+			type:              "histogram"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_delivery_duration_seconds: {
+			description:       "The time between the first attempt at writing each blob and its successful write."
+			type:              "histogram"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_expired_events_total: {
+			description:       "The number of events the `azure_blob` sink dropped because their batch was older than `max_batch_age_before_send_secs` when it was about to be sent."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_fan_out_target_errors_total: {
+			description:       "The number of batches the `azure_blob` sink failed to write to one of the containers it fans out to."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				container: {
+					description: "The container the batch couldn't be written to."
+					required:    true
+				}
+			}
+		}
+		azure_blob_finalizer_double_take_total: {
+			description:       "The number of times the finalizers of a request of the `azure_blob` sink were taken more than once, which would leave its events unacknowledged. This is a bug, and it panics in debug builds."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_finalizer_resolution_seconds: {
+			description:       "The time the `azure_blob` sink took to resolve the finalizers of each batch once written, when `batch_acknowledgements` is enabled."
+			type:              "histogram"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_flushes_total: {
+			description:       "The number of times the `azure_blob` sink flushed all of its pending batches on `flush_signal`."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_in_flight_shed_bytes_total: {
+			description:       "The payload bytes of the batches the `azure_blob` sink shed as its requests in flight were over `in_flight_limit`."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_in_flight_shed_events_total: {
+			description:       "The number of events the `azure_blob` sink shed as its requests in flight were over `in_flight_limit`."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_malformed_paths_total: {
+			description:       "The number of batches whose rendered blob path the `azure_blob` sink normalized or rejected according to `blob_path_policy`."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				outcome: {
+					description: "What happened to the path."
+					required:    true
+					enum: {
+						normalized: "The path was rewritten into the one it stands for."
+						rejected:   "The events of the batch were rejected."
+					}
+				}
+			}
+		}
+		azure_blob_name_collisions_total: {
+			description:       "The number of blobs the `azure_blob` sink was about to write under the name of an existing blob."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				policy: {
+					description: "The `overwrite` policy the collision was handled with."
+					required:    true
+					enum: {
+						delete_permanently: "The existing blob was permanently deleted before being overwritten."
+						refuse:             "The request failed and its events were rejected."
+						skip:               "The blob was skipped and its events were dropped."
+					}
+				}
+			}
+		}
+		azure_blob_objects_written_total: {
+			description:       "The number of blobs the `azure_blob` sink wrote, retries excluded."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_orphaned_blobs_total: {
+			description:       "The number of data blobs the `azure_blob` sink deleted or marked as orphaned because their entry failed to be appended to the index, when `sealed_batch` is set."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				handling: {
+					description: "What happened to the data blob."
+					required:    true
+					enum: {
+						delete: "The data blob was deleted."
+						mark:   "The data blob was marked as orphaned."
+					}
+				}
+			}
+		}
+		azure_blob_oversized_batch_total: {
+			description:       "The number of blobs the `azure_blob` sink refused to send as they were larger than Azure accepts in a single write."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				blob_type: {
+					description: "The type of blob the batch was to be written to."
+					required:    true
+					enum: {
+						block:  "The blob was larger than 256 MiB."
+						append: "The block appended was larger than 4 MiB."
+					}
+				}
+			}
+		}
+		azure_blob_request_body_bytes_total: {
+			description:       "The number of bytes in the bodies of the uploads the storage account answered for the `azure_blob` sink, once encoded and compressed, including the uploads it refused and the ones retried."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_retry_backoff_seconds: {
+			description:       "The delays the `azure_blob` sink waited for before retrying its requests."
+			type:              "histogram"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				source: {
+					description: "Where the delay comes from."
+					required:    true
+					enum: {
+						computed: "The backoff computed from the `request` retry settings."
+						server:   "The `Retry-After` header of a throttled response."
+					}
+				}
+			}
+		}
+		azure_blob_shed_events_total: {
+			description:       "The number of low-priority events dropped by the load shedding of the `azure_blob` sink."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_source_archived_blobs_total: {
+			description:       "The number of blobs in the archive tier the `azure_blob` source didn't read."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				action: {
+					description: "What was done with the blob, as set by `archive_tier`."
+					required:    true
+					enum: {
+						rehydrating: "The blob was moved to the hot tier, to be read by a later run."
+						skipped:     "The blob was skipped."
+					}
+				}
+			}
+		}
+		azure_blob_success_markers_total: {
+			description:       "The number of success markers the `azure_blob` sink wrote into the prefixes of closed partitions."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		azure_blob_unresolvable_partition_events_total: {
+			description:       "The number of events whose partition couldn't be rendered by the `azure_blob` sink."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags & {
+				mode: {
+					description: "How the events were handled."
+					required:    true
+					enum: {
+						drop:     "The events were discarded."
+						fallback: "The events were written under the fallback partition."
+						reject:   "The events were rejected."
+					}
+				}
+			}
+		}
 		config_load_errors_total: {
 			description:       "The total number of errors loading the Vector configuration."
 			type:              "counter"
@@ -1021,7 +1248,7 @@ components: sources: internal_metrics: {
 			type:              "counter"
 			default_namespace: "vector"
 
-			tags: _component_tags & {
+			tags:              _component_tags & {
 				ignore_type: {
 					description: "The reason for ignoring the S3 record"
 					required:    true
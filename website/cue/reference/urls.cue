@@ -15,6 +15,7 @@ urls: {
 	apache_mod_status:                          "http://httpd.apache.org/docs/current/mod/mod_status.html"
 	apt:                                        "\(wikipedia)/wiki/APT_(software)"
 	arm:                                        "\(wikipedia)/wiki/ARM_architecture"
+	avro_ocf:                                   "https://avro.apache.org/docs/current/spec.html#Object+Container+Files"
 	aws_access_keys:                            "\(aws_docs)/IAM/latest/UserGuide/id_credentials_access-keys.html"
 	aws_arm_g2_announcement:                    "https://aws.amazon.com/about-aws/whats-new/2019/12/announcing-new-amazon-ec2-m6g-c6g-and-r6g-instances-powered-by-next-generation-arm-based-aws-graviton2-processors/"
 	aws_athena:                                 "https://aws.amazon.com/athena/"
@@ -85,6 +86,7 @@ urls: {
 	axiom_cloud:                                "https://cloud.axiom.co"
 	azure_blob:                                 "https://azure.microsoft.com/en-us/services/storage/blobs/"
 	azure_blob_endpoints:                       "https://docs.microsoft.com/en-us/rest/api/storageservices/blob-service-rest-api"
+	azure_logs_ingestion_api:                   "https://learn.microsoft.com/en-us/azure/azure-monitor/logs/logs-ingestion-api-overview"
 	azure_monitor:                              "https://azure.microsoft.com/en-us/services/monitor/"
 	azure_monitor_logs_endpoints:               "https://docs.microsoft.com/en-us/rest/api/monitor/"
 	base64:                                     "\(wikipedia)/wiki/Base64"
@@ -536,6 +538,7 @@ urls: {
 	vector_http_source:                         "/docs/reference/configuration/sources/http/"
 	vector_initd_service:                       "\(vector_repo)/blob/master/distribution/init.d/vector"
 	vector_installer:                           "https://sh.vector.dev"
+	vector_internal_logs_source:                "/docs/reference/configuration/sources/internal_logs/"
 	vector_issues:                              "\(vector_repo)/issues"
 	vector_journald_source:                     "/docs/reference/configuration/sources/journald/"
 	vector_kubernetes_logs_source:              "/docs/reference/configuration/sources/kubernetes_logs/"
@@ -578,6 +581,7 @@ urls: {
 	vector_splunk_hec_metrics_sink:             "/docs/reference/configuration/sinks/splunk_hec_metrics"
 	vector_datadog_events_sink:                 "/docs/reference/configuration/sinks/datadog_events"
 	vector_azure_blob_sink:                     "/docs/reference/configuration/sinks/azure_blob"
+	vector_influxdb_metrics_sink:               "/docs/reference/configuration/sinks/influxdb_metrics"
 	vector_redis_sink:                          "/docs/reference/configuration/sinks/redis"
 	vector_dnstap_source:                       "/docs/reference/configuration/sources/dnstap"
 	vector_eventstoredb_metrics_source:         "/docs/reference/configuration/sources/eventstoredb_metrics"